@@ -180,6 +180,12 @@ fn main() {
         Some("tim8") => "TIM8",
         Some("tim9") => "TIM9",
         Some("tim10") => "TIM10",
+        Some("rtc") => {
+            if !singletons.contains(&"RTC".to_string()) {
+                panic!("time-driver-rtc requested, but the chip doesn't have an RTC");
+            }
+            "RTC"
+        }
         Some("any") => {
             [
                 "TIM5", "TIM4", "TIM3", "TIM2", // GP16 / GP32
@@ -194,7 +200,9 @@ fn main() {
 
     if !time_driver_singleton.is_empty() {
         println!("cargo:rustc-cfg=time_driver_{}", time_driver_singleton.to_lowercase());
-        println!("cargo:rustc-cfg=time_driver_timer");
+        if time_driver_singleton != "RTC" {
+            println!("cargo:rustc-cfg=time_driver_timer");
+        }
     }
 
     // ========
@@ -293,6 +301,7 @@ fn main() {
 
         if let Some(remap) = &p.remap {
             let remap_reg = format_ident!("{}", remap.register.to_ascii_lowercase());
+            let remap_field = format_ident!("{}", remap.field.to_ascii_lowercase());
             let set_remap_field = format_ident!("set_{}", remap.field.to_ascii_lowercase());
 
             g.extend(quote! {
@@ -300,6 +309,9 @@ fn main() {
                     fn set_remap(remap: u8) {
                         crate::pac::AFIO.#remap_reg().modify(|w| w.#set_remap_field(unsafe { core::mem::transmute(remap) }));
                     }
+                    fn get_remap() -> u8 {
+                        unsafe { core::mem::transmute::<_, u8>(crate::pac::AFIO.#remap_reg().read().#remap_field()) }
+                    }
                 }
 
                 impl crate::peripheral::RemapPeripheral for peripherals::#pname {}
@@ -318,6 +330,84 @@ fn main() {
         }
     }
 
+    // ========
+    // Generate typed remap enums, one per peripheral with remappable pins
+    let mut remaps = TokenStream::new();
+    for p in METADATA.peripherals {
+        if !singletons.contains(&p.name.to_string()) || p.registers.is_none() {
+            continue;
+        }
+        if p.pins.iter().all(|pin| pin.remap.is_none()) {
+            continue;
+        }
+
+        // remap value => pin assignments
+        let mut mappings: BTreeMap<u8, Vec<String>> = BTreeMap::new();
+        for pin in p.pins {
+            mappings
+                .entry(pin.remap.unwrap_or(0))
+                .or_default()
+                .push(format!("{}: {}", pin.signal, pin.pin));
+        }
+
+        let pname = format_ident!("{}", p.name);
+        let camel_name = p.name[..1].to_string() + &p.name[1..].to_ascii_lowercase();
+        let enum_name = format_ident!("{}Remap", camel_name);
+        let enum_doc = format!("Pin mappings supported by {}.", p.name);
+
+        let mut variants = TokenStream::new();
+        let mut from_bits = TokenStream::new();
+        for (remap, pins) in &mappings {
+            let variant = format_ident!("Remap{}", remap);
+            let doc = pins.join(", ");
+            variants.extend(quote! {
+                #[doc = #doc]
+                #variant = #remap,
+            });
+            from_bits.extend(quote! {
+                #remap => Some(Self::#variant),
+            });
+        }
+
+        remaps.extend(quote! {
+            #[doc = #enum_doc]
+            #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+            #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+            #[repr(u8)]
+            pub enum #enum_name {
+                #variants
+            }
+
+            impl #enum_name {
+                /// The raw remap value, usable as a driver's `REMAP` const parameter.
+                pub const fn bits(self) -> u8 {
+                    self as u8
+                }
+            }
+
+            impl crate::afio::Remap for #enum_name {
+                type Peripheral = crate::peripherals::#pname;
+
+                fn bits(self) -> u8 {
+                    self as u8
+                }
+
+                fn from_bits(bits: u8) -> Option<Self> {
+                    match bits {
+                        #from_bits
+                        _ => None,
+                    }
+                }
+            }
+        });
+    }
+
+    g.extend(quote! {
+        pub mod remaps {
+            #remaps
+        }
+    });
+
     // ========
     // Generate fns to enable GPIO, DMA in RCC
     for kind in ["dma", "gpio"] {
@@ -466,11 +556,15 @@ fn main() {
         (("spi", "TX"), quote!(crate::spi::TxDma)),
         (("i2c", "RX"), quote!(crate::i2c::RxDma)),
         (("i2c", "TX"), quote!(crate::i2c::TxDma)),
+        (("timer", "UP"), quote!(crate::timer::UpDma)),
         (("timer", "CH1"), quote!(crate::timer::Ch1Dma)),
         (("timer", "CH2"), quote!(crate::timer::Ch2Dma)),
         (("timer", "CH3"), quote!(crate::timer::Ch3Dma)),
         (("timer", "CH4"), quote!(crate::timer::Ch4Dma)),
         (("sdio", "SDIO"), quote!(crate::sdio::SdioDma)),
+        (("adc", "ADC"), quote!(crate::adc::RxDma)),
+        (("dac", "CH1"), quote!(crate::dac::DacDma1)),
+        (("dac", "CH2"), quote!(crate::dac::DacDma2)),
     ]
     .into();
 
@@ -538,6 +632,14 @@ fn main() {
                 let port_letter = p.name.chars().nth(4).unwrap();
                 assert_eq!(0, (p.address as u32 - gpio_base) % gpio_stride);
                 let port_num = (p.address as u32 - gpio_base) / gpio_stride;
+                // EXTI routing and AnyPin use the port number, it must follow the letter,
+                // including high ports such as GPIOF/GPIOG on large packages.
+                assert_eq!(
+                    port_num,
+                    port_letter as u32 - 'A' as u32,
+                    "{} is not at the expected address",
+                    p.name
+                );
 
                 for pin_num in 0..gpio_lines {
                     let pin_name = format!("P{}{}", port_letter, pin_num);
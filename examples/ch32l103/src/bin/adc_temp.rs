@@ -33,10 +33,9 @@ async fn main(_spawner: Spawner) -> ! {
         Timer::after(Duration::from_millis(500)).await;
 
         println!("Starting conversion!");
-        let raw = adc.convert(&mut temp, SampleTime::CYCLES239_5);
-        let val = convert_temp(raw);
+        let val = adc.convert_temperature(&mut temp, 3300);
 
-        println!("raw: {}, val: {}C", raw, val);
+        println!("temperature: {}C", val);
 
         let raw = adc.convert(&mut vref, SampleTime::CYCLES55_5);
         let vref = raw as f32 * 3.3 / 4096.0;
@@ -49,17 +48,6 @@ async fn main(_spawner: Spawner) -> ! {
     }
 }
 
-fn convert_temp(raw: u16) -> f32 {
-    // V_25 and Avg_Slope from datasheet
-    let v25 = 1.45;
-    let avg_slope = 4.2e-3;
-
-    let v = raw as f32 * 3.3 / 4096.0;
-    let temp = (v - v25) / avg_slope + 25.0;
-
-    temp
-}
-
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     let _ = println!("\n\n\n{}", info);
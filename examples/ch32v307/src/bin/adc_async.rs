@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+#![feature(impl_trait_in_assoc_type)]
+
+use ch32_hal as hal;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use hal::adc::{self, Adc, SampleTime};
+use hal::{bind_interrupts, peripherals, println};
+
+bind_interrupts!(struct Irqs {
+    ADC1_2 => adc::InterruptHandler<peripherals::ADC1>;
+});
+
+#[embassy_executor::main(entry = "ch32_hal::entry")]
+async fn main(_spawner: Spawner) -> ! {
+    hal::debug::SDIPrint::enable();
+    let mut config = hal::Config::default();
+    config.rcc = hal::rcc::Config::SYSCLK_FREQ_96MHZ_HSI;
+    let p = hal::init(config);
+
+    let mut adc = Adc::new_async(p.ADC1, Irqs, Default::default());
+
+    let mut ch = p.PA1;
+
+    loop {
+        let val = adc.read(&mut ch, SampleTime::CYCLES239_5).await;
+        println!("val => {}", val);
+
+        Timer::after_millis(500).await;
+    }
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let _ = println!("\n\n\n{}", info);
+
+    loop {}
+}
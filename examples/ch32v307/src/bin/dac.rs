@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+#![feature(impl_trait_in_assoc_type)]
+
+use ch32_hal as hal;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use hal::dac::Dac;
+use hal::dma::NoDma;
+use hal::println;
+
+#[embassy_executor::main(entry = "ch32_hal::entry")]
+async fn main(_spawner: Spawner) -> ! {
+    hal::debug::SDIPrint::enable();
+    let mut config = hal::Config::default();
+    config.rcc = hal::rcc::Config::SYSCLK_FREQ_96MHZ_HSI;
+    let p = hal::init(config);
+
+    let (mut ch1, mut ch2) = Dac::new(p.DAC, NoDma, NoDma, p.PA4, p.PA5).split();
+
+    let mut value = 0;
+    loop {
+        ch1.set_value(value);
+        ch2.set_value(4095 - value);
+        println!("ch1 => {}, ch2 => {}", ch1.read(), ch2.read());
+
+        value = (value + 256) % 4096;
+        Timer::after_millis(100).await;
+    }
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let _ = println!("\n\n\n{}", info);
+
+    loop {}
+}
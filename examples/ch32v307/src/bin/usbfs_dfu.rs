@@ -54,7 +54,7 @@ async fn main(spawner: Spawner) -> ! {
     let mut config = embassy_usb::Config::new(0x6666, 0xcafe);
     config.manufacturer = Some("Embassy");
     config.product = Some("USB DFU Demo");
-    config.serial_number = Some("12345678");
+    config.serial_number = Some(hal::usb::serial_number());
     config.max_power = 100;
     config.max_packet_size_0 = 64;
 
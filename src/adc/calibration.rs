@@ -0,0 +1,74 @@
+//! Self-calibration and offset control.
+
+use super::{apply_offset, Adc, Instance, ADC_MAX};
+use crate::mode::Mode;
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Run the self-calibration sequence.
+    ///
+    /// The ADC must be powered on and idle. It's recommended to calibrate once after power-up.
+    ///
+    /// Returns the calibration factor, the signed offset in LSB added to every result,
+    /// including those of the ring-buffered, ping-pong and dual drivers created afterwards.
+    /// Injected conversions are returned raw.
+    pub fn calibrate(&mut self) -> i16 {
+        #[cfg(adc_v3)]
+        {
+            let regs = T::regs();
+
+            // The residual offset is left in RDATAR after each calibration run,
+            // average the middle of a few runs, as done in the vendor library.
+            let mut buf = [0u16; 10];
+            for v in buf.iter_mut() {
+                Self::run_calibration();
+                *v = regs.rdatar().read().data();
+            }
+            buf.sort_unstable();
+            let avg = buf[2..8].iter().map(|&v| v as u32).sum::<u32>() / 6;
+
+            self.calibration = ((ADC_MAX + 1) / 2) as i16 - avg as i16;
+        }
+
+        #[cfg(not(adc_v3))]
+        {
+            Self::run_calibration();
+            self.calibration = 0;
+        }
+
+        self.calibration
+    }
+
+    fn run_calibration() {
+        let regs = T::regs();
+
+        regs.ctlr2().modify(|w| w.set_rstcal(true));
+        while regs.ctlr2().read().rstcal() {}
+
+        regs.ctlr2().modify(|w| w.set_cal(true));
+        while regs.ctlr2().read().cal() {}
+    }
+
+    /// The calibration factor found by the last [`calibrate`](Self::calibrate) call.
+    pub fn calibration(&self) -> i16 {
+        self.calibration
+    }
+
+    /// Override the calibration factor, e.g. with a value stored from a previous calibration.
+    pub fn set_calibration(&mut self, factor: i16) {
+        self.calibration = factor;
+    }
+
+    /// Apply the calibration factor to a raw conversion result, saturating to the valid range.
+    pub fn apply_calibration(&self, raw: u16) -> u16 {
+        apply_offset(raw, self.calibration)
+    }
+
+    /// Set the offset subtracted from the results of an injected rank (0..4).
+    ///
+    /// The injected data register then holds a signed value.
+    pub fn set_injected_offset(&mut self, rank: usize, offset: u16) {
+        assert!(rank < 4 && offset as u32 <= ADC_MAX);
+
+        T::regs().iofr(rank).write(|w| w.set_joffset(offset));
+    }
+}
@@ -0,0 +1,36 @@
+//! Free-running continuous conversion of a single channel.
+
+use super::{Adc, AdcChannel, Instance, SampleTime};
+use crate::mode::Mode;
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Start converting a single channel continuously in the background.
+    ///
+    /// Use [`latest`](Self::latest) to get the most recent result at any time.
+    /// Other conversion methods must not be used until [`stop_continuous`](Self::stop_continuous).
+    pub fn start_continuous(&mut self, channel: &mut impl AdcChannel<T>, sample_time: SampleTime) {
+        self.configure_channel(channel, 1, sample_time);
+        Self::set_sequence_len(1);
+
+        T::regs().ctlr2().modify(|w| w.set_cont(true));
+        T::regs().ctlr2().modify(|w| w.set_swstart(true));
+
+        // Wait for the first result so `latest` never returns a stale value.
+        while !T::regs().statr().read().eoc() {}
+    }
+
+    /// Stop continuous conversion, the ADC stays powered on.
+    pub fn stop_continuous(&mut self) {
+        T::regs().ctlr2().modify(|w| w.set_cont(false));
+    }
+
+    /// Whether continuous conversion is running.
+    pub fn is_continuous(&self) -> bool {
+        T::regs().ctlr2().read().cont()
+    }
+
+    /// Return the most recent conversion result without waiting.
+    pub fn latest(&self) -> u16 {
+        self.read_result()
+    }
+}
@@ -0,0 +1,140 @@
+//! Dual ADC modes, ADC1 as master and ADC2 as slave.
+//!
+//! In simultaneous modes both ADCs sample at the exact same instant, e.g. two motor
+//! phase currents. In interleaved modes they alternate on the same channel to double
+//! the effective sample rate. Results of both ADCs are packed into the 32-bit ADC1
+//! data register, ADC1 in the low and ADC2 in the high half-word.
+
+use super::{apply_offset, Adc, AnyAdcChannel, RxDma, SampleTime, SealedInstance};
+use crate::mode::Mode;
+use crate::peripherals::{ADC1, ADC2};
+use crate::{into_ref, Peripheral};
+
+/// Dual ADC mode (DUALMOD).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum DualMode {
+    /// Combined regular simultaneous and injected simultaneous mode.
+    RegularInjectedSimultaneous = 0b0001,
+    /// Combined regular simultaneous and alternate trigger mode.
+    RegularSimultaneousAlternateTrigger = 0b0010,
+    /// Combined injected simultaneous and fast interleaved mode.
+    InjectedSimultaneousFastInterleaved = 0b0011,
+    /// Combined injected simultaneous and slow interleaved mode.
+    InjectedSimultaneousSlowInterleaved = 0b0100,
+    /// Injected simultaneous mode only.
+    InjectedSimultaneous = 0b0101,
+    /// Regular simultaneous mode only.
+    RegularSimultaneous = 0b0110,
+    /// Fast interleaved mode only, the slave starts 7 ADC clock cycles after the master.
+    FastInterleaved = 0b0111,
+    /// Slow interleaved mode only, the slave starts 14 ADC clock cycles after the master.
+    SlowInterleaved = 0b1000,
+    /// Alternate trigger mode only.
+    AlternateTrigger = 0b1001,
+}
+
+/// Dual ADC driver.
+pub struct DualAdc<'d, M: Mode> {
+    master: Adc<'d, ADC1, M>,
+    slave: Adc<'d, ADC2, M>,
+}
+
+impl<'d, M: Mode> DualAdc<'d, M> {
+    /// Combine ADC1 and ADC2 in the given dual mode.
+    ///
+    /// Conversions are started on the master only, configure triggers on `master`
+    /// before combining. The slave is left on the software trigger.
+    pub fn new(master: Adc<'d, ADC1, M>, slave: Adc<'d, ADC2, M>, mode: DualMode) -> Self {
+        ADC1::regs().ctlr1().modify(|w| w.set_dualmod(mode as u8));
+
+        Self { master, slave }
+    }
+
+    /// Configure the regular sequences of both ADCs.
+    ///
+    /// Both sequences must have the same length. In interleaved modes both should
+    /// contain the same single channel.
+    pub fn set_sequence(
+        &mut self,
+        master: &mut [(&mut AnyAdcChannel<ADC1>, SampleTime)],
+        slave: &mut [(&mut AnyAdcChannel<ADC2>, SampleTime)],
+    ) {
+        assert_eq!(master.len(), slave.len());
+
+        self.master.set_sequence(master);
+        self.slave.set_sequence(slave);
+    }
+
+    /// Convert one pair of samples without DMA, for single-rank sequences.
+    ///
+    /// Returns `(adc1, adc2)`.
+    pub fn blocking_read_pair(&mut self) -> (u16, u16) {
+        ADC1::regs().ctlr2().modify(|w| w.set_swstart(true));
+        while !ADC1::regs().statr().read().eoc() {}
+        while !ADC2::regs().statr().read().eoc() {}
+
+        let combined = ADC1::regs().rdatar().read().0;
+        // Reading the slave clears its EOC flag.
+        let _ = ADC2::regs().rdatar().read();
+
+        let (adc1, adc2) = split_result(combined);
+        (
+            apply_offset(adc1, self.master.calibration),
+            apply_offset(adc2, self.slave.calibration),
+        )
+    }
+
+    /// Convert the configured sequence once, collecting both results with DMA.
+    ///
+    /// Each word holds ADC1 in the low and ADC2 in the high half-word, see [`split_result`].
+    pub async fn read(
+        &mut self,
+        dma: impl Peripheral<P = impl RxDma<ADC1>>,
+        readings: &mut [u32],
+    ) -> Result<(), crate::dma::Error> {
+        assert_eq!(readings.len(), self.master.sequence_len());
+
+        into_ref!(dma);
+        let request = dma.request();
+
+        ADC1::regs().ctlr2().modify(|w| w.set_dma(true));
+
+        let transfer = unsafe {
+            crate::dma::Transfer::new_read(
+                dma,
+                request,
+                ADC1::regs().rdatar().as_ptr() as *mut u32,
+                readings,
+                Default::default(),
+            )
+        };
+
+        ADC1::regs().ctlr2().modify(|w| w.set_swstart(true));
+
+        let result = transfer.await;
+
+        ADC1::regs().ctlr2().modify(|w| w.set_dma(false));
+
+        for v in readings.iter_mut() {
+            let (adc1, adc2) = split_result(*v);
+            let adc1 = apply_offset(adc1, self.master.calibration);
+            let adc2 = apply_offset(adc2, self.slave.calibration);
+            *v = adc1 as u32 | (adc2 as u32) << 16;
+        }
+        result
+    }
+
+    /// Go back to independent mode, returning both ADCs.
+    pub fn split(self) -> (Adc<'d, ADC1, M>, Adc<'d, ADC2, M>) {
+        ADC1::regs().ctlr1().modify(|w| w.set_dualmod(0));
+
+        (self.master, self.slave)
+    }
+}
+
+/// Split a combined dual mode result into `(adc1, adc2)`.
+pub fn split_result(combined: u32) -> (u16, u16) {
+    (combined as u16, (combined >> 16) as u16)
+}
@@ -0,0 +1,135 @@
+//! Injected conversion group.
+//!
+//! Up to 4 injected channels can be converted on their own trigger, interrupting
+//! any regular conversion in progress. Results go to dedicated IDATARx registers,
+//! so a background regular scan is not disturbed.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use super::{Adc, AnyAdcChannel, Instance, SampleTime};
+use crate::mode::{Async, Mode};
+use crate::pac::adc::vals;
+
+/// External trigger source for the injected group (JEXTSEL).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum InjectedTrigger {
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim1Trgo = 0b000,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim1Cc3 = 0b000,
+    Tim1Cc4 = 0b001,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim2Trgo = 0b010,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim2Cc1 = 0b011,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim2Cc3 = 0b010,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim2Cc4 = 0b011,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim3Cc4 = 0b100,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim4Trgo = 0b101,
+    /// EXTI line 15, or TIM8 CC4 when remapped.
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Exti15 = 0b110,
+    /// PD1 / PA2 external pin.
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Exti = 0b110,
+    /// Started by software with [`Adc::start_injected`].
+    Software = 0b111,
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Configure the injected sequence, up to 4 channels in rank order.
+    ///
+    /// Results of rank N are read back from injected data register N.
+    pub fn set_injected_sequence(&mut self, sequence: &mut [(&mut AnyAdcChannel<T>, SampleTime)]) {
+        let len = sequence.len();
+        assert!(len > 0 && len <= 4);
+
+        // When JL < 3 the sequence starts at JSQ(4 - JL - 1) and runs to JSQ4.
+        let first = 4 - len;
+
+        T::regs().isqr().modify(|w| {
+            w.set_jl((len - 1) as u8);
+            for (i, (channel, sample_time)) in sequence.iter_mut().enumerate() {
+                Self::set_channel_sample_time(channel.channel, *sample_time);
+                w.set_jsq(first + i, channel.channel & 0b11111);
+            }
+        });
+    }
+
+    /// Select what starts the injected group.
+    pub fn set_injected_trigger(&mut self, trigger: InjectedTrigger) {
+        T::regs().ctlr2().modify(|w| {
+            w.set_jexttrig(true);
+            w.set_jextsel(vals::Jextsel::from_bits(trigger as u8));
+        });
+    }
+
+    /// Automatically convert the injected group after every regular group (JAUTO).
+    ///
+    /// The injected trigger must be [`InjectedTrigger::Software`] in this mode.
+    pub fn set_injected_auto(&mut self, enable: bool) {
+        T::regs().ctlr1().modify(|w| w.set_jauto(enable));
+    }
+
+    /// Start the injected group by software.
+    pub fn start_injected(&mut self) {
+        T::regs().statr().modify(|w| w.set_jeoc(false));
+        T::regs().ctlr2().modify(|w| w.set_jswstart(true));
+    }
+
+    /// Whether the injected group finished converting since the last read.
+    pub fn is_injected_done(&self) -> bool {
+        T::regs().statr().read().jeoc()
+    }
+
+    /// Wait for the injected group to finish and read its results.
+    ///
+    /// `readings` receives one value per configured rank, at most 4.
+    pub fn blocking_read_injected(&mut self, readings: &mut [u16]) {
+        while !T::regs().statr().read().jeoc() {}
+
+        self.read_injected_results(readings);
+    }
+
+    fn read_injected_results(&mut self, readings: &mut [u16]) {
+        assert!(readings.len() <= 4);
+
+        T::regs().statr().modify(|w| w.set_jeoc(false));
+        for (i, reading) in readings.iter_mut().enumerate() {
+            *reading = T::regs().idatar(i).read().jdata();
+        }
+    }
+}
+
+impl<'d, T: Instance> Adc<'d, T, Async> {
+    /// Wait for the injected group to finish, using the JEOC interrupt, and read its results.
+    ///
+    /// The conversion must be started either by [`start_injected`](Self::start_injected)
+    /// or by the configured trigger.
+    pub async fn read_injected(&mut self, readings: &mut [u16]) {
+        T::regs().ctlr1().modify(|w| w.set_jeocie(true));
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if T::regs().statr().read().jeoc() {
+                Poll::Ready(())
+            } else {
+                T::regs().ctlr1().modify(|w| w.set_jeocie(true));
+                Poll::Pending
+            }
+        })
+        .await;
+
+        T::regs().ctlr1().modify(|w| w.set_jeocie(false));
+
+        self.read_injected_results(readings);
+    }
+}
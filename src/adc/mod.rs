@@ -0,0 +1,693 @@
+//! ADC, Analog to Digital Converter
+
+#![macro_use]
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::internal::drop::OnDrop;
+use crate::interrupt::typelevel::Interrupt;
+use crate::mode::{Async, Blocking, Mode};
+use crate::pac::adc::vals;
+pub use crate::pac::adc::vals::SampleTime;
+use crate::{interrupt, into_ref, peripherals, Peripheral};
+
+#[cfg(not(any(adc_x0, adc_ch641)))]
+mod calibration;
+mod continuous;
+#[cfg(all(adc_v3, peri_adc2))]
+mod dual;
+#[cfg(all(adc_v3, peri_adc2))]
+pub use dual::{split_result, DualAdc, DualMode};
+mod injected;
+pub use injected::InjectedTrigger;
+mod oversampling;
+mod ping_pong;
+pub use ping_pong::PingPongAdc;
+mod power;
+pub use power::LowPowerGuard;
+mod ringbuffered;
+pub use ringbuffered::RingBufferedAdc;
+#[cfg(any(adc_l1, adc_v1, adc_v3))]
+mod temperature;
+#[cfg(any(adc_v0, adc_x0))]
+mod touch_key;
+#[cfg(any(adc_v0, adc_x0))]
+pub use touch_key::{TouchKey, TouchKeyConfig};
+mod trigger;
+pub use trigger::RegularTrigger;
+#[cfg(not(adc_ch641))]
+mod vrefint;
+mod watchdog;
+pub use watchdog::WatchdogGroup;
+
+/// ADC bit resolution
+#[cfg(any(adc_v0, adc_ch641))]
+pub const ADC_MAX: u32 = (1 << 10) - 1;
+#[cfg(not(any(adc_v0, adc_ch641)))]
+pub const ADC_MAX: u32 = (1 << 12) - 1;
+
+/// Nominal internal reference voltage in millivolts.
+///
+/// No calibration data, voltage should be 1.2V (1.16 to 1.24)
+pub const VREF_INT: u32 = 1200;
+
+pub struct Config {
+    /// Div1 to Div16
+    // raw values are 0 to 0b111
+    pub clkdiv: u8,
+    // TODO: handle "-1"
+    pub channel_count: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            // Use Divide by 8 as default, maximum is 14 MHz
+            // Up to 144 MHz system clock can be reached
+            // Power on default is Divide by 2,
+            clkdiv: 0b11,
+            channel_count: 1,
+        }
+    }
+}
+
+pub struct State {
+    pub waker: AtomicWaker,
+}
+impl State {
+    pub const fn new() -> Self {
+        Self {
+            waker: AtomicWaker::new(),
+        }
+    }
+}
+
+/// ADC interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let regs = T::regs();
+        let statr = regs.statr().read();
+        let ctlr1 = regs.ctlr1().read();
+
+        // ADC1 and ADC2 share one interrupt line on some chips, only wake on our own events.
+        if statr.eoc() && ctlr1.eocie() {
+            // Disable the interrupt, EOC itself is cleared by reading RDATAR.
+            regs.ctlr1().modify(|w| w.set_eocie(false));
+            T::state().waker.wake();
+        }
+        if statr.jeoc() && ctlr1.jeocie() {
+            regs.ctlr1().modify(|w| w.set_jeocie(false));
+            T::state().waker.wake();
+        }
+        if statr.awd() && ctlr1.awdie() {
+            regs.ctlr1().modify(|w| w.set_awdie(false));
+            T::state().waker.wake();
+        }
+    }
+}
+
+/// Analog to Digital driver.
+pub struct Adc<'d, T: Instance, M: Mode = Blocking> {
+    #[allow(unused)]
+    adc: crate::PeripheralRef<'d, T>,
+    calibration: i16,
+    _phantom: PhantomData<M>,
+}
+
+impl<'d, T: Instance> Adc<'d, T, Blocking> {
+    /// Create a new blocking ADC driver.
+    pub fn new(adc: impl Peripheral<P = T> + 'd, config: Config) -> Self {
+        Self::new_inner(adc, config)
+    }
+}
+
+impl<'d, T: Instance> Adc<'d, T, Async> {
+    /// Create a new ADC driver, using the end-of-conversion interrupt for async reads.
+    pub fn new_async(
+        adc: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Self {
+        let this = Self::new_inner(adc, config);
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        this
+    }
+
+    /// Perform a single conversion, waiting for the end-of-conversion interrupt.
+    ///
+    /// A sequence configured with [`set_sequence`](Adc::set_sequence) is kept.
+    pub async fn read(&mut self, channel: &mut impl AdcChannel<T>, sample_time: SampleTime) -> u16 {
+        let sequence = Self::save_sequence();
+        self.configure_channel(channel, 1, sample_time);
+        Self::set_sequence_len(1);
+
+        let regs = T::regs();
+        regs.statr().modify(|w| w.set_eoc(false));
+        regs.ctlr1().modify(|w| w.set_eocie(true));
+
+        // Also runs when the future is dropped before the end of conversion.
+        let on_drop = OnDrop::new(|| {
+            regs.ctlr1().modify(|w| w.set_eocie(false));
+            Self::restore_sequence(sequence);
+        });
+
+        regs.ctlr2().modify(|w| w.set_swstart(true));
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if regs.statr().read().eoc() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        drop(on_drop);
+        self.read_result()
+    }
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    fn new_inner(adc: impl Peripheral<P = T> + 'd, config: Config) -> Self {
+        into_ref!(adc);
+        T::enable_and_reset();
+
+        // TODO: ADCPRE
+        // CTLR3 not avaiable to CH3V0, CH32V1
+        #[cfg(any(adc_v3, adc_x0))]
+        T::regs().ctlr3().modify(|w| w.set_clk_div(config.clkdiv));
+
+        // clear, only independent mode is supported
+        T::regs().ctlr1().modify(|w| {
+            w.0 = 0;
+        });
+
+        // no external trigger
+        // no continuous mode
+        T::regs().ctlr2().modify(|w| {
+            w.set_align(false); // right aligned
+            w.set_exttrig(true); // external trigger
+            w.set_extsel(vals::Extsel::SWSTART); //  SWSTART Software trigger
+            w.set_cont(false); // single conversion
+        });
+
+        Self::set_sequence_len(1);
+
+        // ADC ON
+        T::regs().ctlr2().modify(|w| w.set_adon(true));
+
+        Self {
+            adc,
+            calibration: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Set the number of conversions in the regular sequence, 1 to 16.
+    ///
+    /// Scan mode is enabled automatically for sequences longer than one channel.
+    fn set_sequence_len(len: u8) {
+        assert!(len > 0 && len <= 16);
+
+        T::regs().rsqr1().modify(|w| w.set_l(len - 1));
+        T::regs().ctlr1().modify(|w| w.set_scan(len > 1));
+    }
+
+    /// The rank 1 and length part of the regular sequence, which single conversions overwrite.
+    fn save_sequence() -> (u32, u32) {
+        (T::regs().rsqr1().read().0, T::regs().rsqr3().read().0)
+    }
+
+    fn restore_sequence((rsqr1, rsqr3): (u32, u32)) {
+        T::regs().rsqr1().write(|w| w.0 = rsqr1);
+        T::regs().rsqr3().write(|w| w.0 = rsqr3);
+        Self::set_sequence_len(T::regs().rsqr1().read().l() + 1);
+    }
+
+    fn set_channel_sample_time(channel: u8, sample_time: SampleTime) {
+        if channel < 10 {
+            T::regs().samptr2().modify(|w| w.set_smp(channel as usize, sample_time));
+        } else {
+            T::regs()
+                .samptr1()
+                .modify(|w| w.set_smp((channel - 10) as usize, sample_time));
+        }
+    }
+
+    fn channel_sample_time(channel: u8) -> SampleTime {
+        if channel < 10 {
+            T::regs().samptr2().read().smp(channel as usize)
+        } else {
+            T::regs().samptr1().read().smp((channel - 10) as usize)
+        }
+    }
+
+    /// Set the sample time of a channel.
+    ///
+    /// Sample times are kept per channel, so high-impedance sources can use a long
+    /// sample time without slowing down the other channels of a scan.
+    pub fn set_sample_time(&mut self, channel: &impl AdcChannel<T>, sample_time: SampleTime) {
+        Self::set_channel_sample_time(channel.channel(), sample_time);
+    }
+
+    /// Get the sample time currently configured for a channel.
+    pub fn sample_time(&self, channel: &impl AdcChannel<T>) -> SampleTime {
+        Self::channel_sample_time(channel.channel())
+    }
+
+    /// Perform a single conversion using the sample time already configured for the channel.
+    ///
+    /// Pins and internal channels can be turned into an [`AnyAdcChannel`] with
+    /// [`AdcChannel::degrade_adc`], so channels of different types can be read uniformly.
+    pub fn blocking_read(&mut self, channel: &mut impl AdcChannel<T>) -> u16 {
+        let sample_time = Self::channel_sample_time(channel.channel());
+        self.convert(channel, sample_time)
+    }
+
+    /// Convert each channel once, in order, using their configured sample times.
+    ///
+    /// `readings` must have the same length as `channels`.
+    pub fn blocking_read_channels(&mut self, channels: &mut [AnyAdcChannel<T>], readings: &mut [u16]) {
+        assert_eq!(channels.len(), readings.len());
+
+        for (channel, reading) in channels.iter_mut().zip(readings.iter_mut()) {
+            *reading = self.blocking_read(channel);
+        }
+    }
+
+    // regular conversion
+    pub fn configure_channel(&mut self, channel: &mut impl AdcChannel<T>, rank: u8, sample_time: SampleTime) {
+        channel.set_as_analog();
+
+        let channel = channel.channel();
+
+        // sample time config
+        Self::set_channel_sample_time(channel, sample_time);
+
+        // regular sequence config
+        assert!(rank > 0 && rank < 17);
+        if rank < 7 {
+            T::regs()
+                .rsqr3()
+                .modify(|w| w.set_sq((rank - 1) as usize, channel & 0b11111));
+        } else if rank < 13 {
+            T::regs()
+                .rsqr2()
+                .modify(|w| w.set_sq((rank - 7) as usize, channel & 0b11111));
+        } else {
+            T::regs()
+                .rsqr1()
+                .modify(|w| w.set_sq((rank - 13) as usize, channel & 0b11111));
+        }
+    }
+
+    /// Configure the regular sequence, up to 16 channels in rank order.
+    ///
+    /// Each channel gets its own sample time. The same channel may appear more than once.
+    pub fn set_sequence(&mut self, sequence: &mut [(&mut AnyAdcChannel<T>, SampleTime)]) {
+        assert!(!sequence.is_empty() && sequence.len() <= 16);
+
+        for (i, (channel, sample_time)) in sequence.iter_mut().enumerate() {
+            self.configure_channel(&mut **channel, i as u8 + 1, *sample_time);
+        }
+        Self::set_sequence_len(sequence.len() as u8);
+    }
+
+    /// Length of the currently configured regular sequence.
+    pub fn sequence_len(&self) -> usize {
+        T::regs().rsqr1().read().l() as usize + 1
+    }
+
+    /// Convert the configured regular sequence once, one result per rank.
+    ///
+    /// `readings` must have the same length as the sequence.
+    pub fn blocking_read_sequence(&mut self, readings: &mut [u16]) {
+        assert_eq!(readings.len(), self.sequence_len());
+
+        // Without DMA only the last result of a scan survives in RDATAR,
+        // so step through the sequence one rank per trigger.
+        let discontinuous = self.discontinuous();
+        self.set_discontinuous(Some(1));
+
+        for reading in readings.iter_mut() {
+            T::regs().ctlr2().modify(|w| w.set_swstart(true));
+            while !T::regs().statr().read().eoc() {}
+            *reading = self.read_result();
+        }
+
+        self.set_discontinuous(discontinuous);
+    }
+
+    /// Convert the configured regular sequence once, collecting the results with DMA.
+    ///
+    /// `readings` must have the same length as the sequence.
+    pub async fn read_sequence(
+        &mut self,
+        dma: impl Peripheral<P = impl RxDma<T>>,
+        readings: &mut [u16],
+    ) -> Result<(), crate::dma::Error> {
+        assert_eq!(readings.len(), self.sequence_len());
+
+        into_ref!(dma);
+        let request = dma.request();
+
+        T::regs().ctlr2().modify(|w| w.set_dma(true));
+
+        let transfer = unsafe {
+            crate::dma::Transfer::new_read(
+                dma,
+                request,
+                T::regs().rdatar().as_ptr() as *mut u16,
+                readings,
+                Default::default(),
+            )
+        };
+
+        T::regs().ctlr2().modify(|w| w.set_swstart(true));
+
+        let result = transfer.await;
+
+        T::regs().ctlr2().modify(|w| w.set_dma(false));
+
+        apply_offsets(readings, self.calibration);
+        result
+    }
+
+    /// Convert a single channel, a sequence configured with [`set_sequence`](Self::set_sequence) is kept.
+    // Get_ADC_Val
+    pub fn convert(&mut self, channel: &mut impl AdcChannel<T>, sample_time: SampleTime) -> u16 {
+        let sequence = Self::save_sequence();
+        self.configure_channel(channel, 1, sample_time);
+        Self::set_sequence_len(1);
+
+        T::regs().ctlr2().modify(|w| w.set_swstart(true));
+
+        // while not end of conversion
+        while !T::regs().statr().read().eoc() {}
+
+        Self::restore_sequence(sequence);
+        self.read_result()
+    }
+
+    fn read_result(&self) -> u16 {
+        apply_offset(T::regs().rdatar().read().data(), self.calibration)
+    }
+}
+
+/// Add the calibration `offset` to a raw result, saturating to the valid range.
+fn apply_offset(raw: u16, offset: i16) -> u16 {
+    (raw as i32 + offset as i32).clamp(0, ADC_MAX as i32) as u16
+}
+
+/// Add the calibration `offset` to results transferred by DMA.
+fn apply_offsets(results: &mut [u16], offset: i16) {
+    if offset != 0 {
+        for v in results.iter_mut() {
+            *v = apply_offset(*v, offset);
+        }
+    }
+}
+
+#[allow(unused)]
+trait SealedInstance {
+    fn regs() -> crate::pac::adc::Adc;
+    fn state() -> &'static State;
+}
+
+pub(crate) trait SealedAdcChannel<T: Instance> {
+    fn set_as_analog(&mut self) {}
+
+    #[allow(unused)]
+    fn channel(&self) -> u8;
+}
+
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + crate::Peripheral<P = Self> + crate::peripheral::RccPeripheral {
+    type Interrupt: crate::interrupt::typelevel::Interrupt;
+}
+
+dma_trait!(RxDma, Instance);
+
+/// ADC pin.
+#[allow(private_bounds)]
+pub trait AdcChannel<T: Instance>: SealedAdcChannel<T> + Sized {
+    fn degrade_adc(mut self) -> AnyAdcChannel<T> {
+        self.set_as_analog();
+
+        AnyAdcChannel {
+            channel: self.channel(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// A type-erased channel for a given ADC instance.
+///
+/// This is useful in scenarios where you need the ADC channels to have the same type, such as
+/// storing them in an array.
+pub struct AnyAdcChannel<T> {
+    channel: u8,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> AnyAdcChannel<T> {
+    /// The ADC channel number.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+}
+
+impl<T: Instance> AdcChannel<T> for AnyAdcChannel<T> {}
+impl<T: Instance> SealedAdcChannel<T> for AnyAdcChannel<T> {
+    fn channel(&self) -> u8 {
+        self.channel
+    }
+}
+
+foreach_peripheral!(
+    (adc, $inst:ident) => {
+        impl crate::adc::SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::adc::Adc {
+                crate::pac::$inst
+            }
+
+            fn state() -> &'static State {
+                static STATE: State = State::new();
+                &STATE
+            }
+        }
+
+        impl crate::adc::Instance for peripherals::$inst {
+            type Interrupt = crate::_generated::peripheral_interrupts::$inst::GLOBAL;
+        }
+    };
+);
+
+macro_rules! impl_adc_pin {
+    ($inst:ident, $pin:ident, $ch:expr) => {
+        impl crate::adc::AdcChannel<peripherals::$inst> for crate::peripherals::$pin {}
+
+        impl crate::adc::SealedAdcChannel<peripherals::$inst> for crate::peripherals::$pin {
+            fn set_as_analog(&mut self) {
+                <Self as crate::gpio::SealedPin>::set_as_analog(self);
+            }
+
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
+
+#[cfg(adc_l1)]
+mod ch_internal {
+    use super::*;
+
+    pub struct VddaDiv2;
+    impl<T: Instance> AdcChannel<T> for VddaDiv2 {}
+    impl<T: Instance> SealedAdcChannel<T> for VddaDiv2 {
+        fn channel(&self) -> u8 {
+            18
+        }
+    }
+
+    pub struct VrefInt;
+    impl<T: Instance> AdcChannel<T> for VrefInt {}
+    impl<T: Instance> SealedAdcChannel<T> for VrefInt {
+        fn set_as_analog(&mut self) {
+            T::regs().ctlr2().modify(|w| w.set_tsvrefe(true));
+        }
+        fn channel(&self) -> u8 {
+            17
+        }
+    }
+
+    pub struct Temperature;
+    impl<T: Instance> AdcChannel<T> for Temperature {}
+    impl<T: Instance> SealedAdcChannel<T> for Temperature {
+        fn set_as_analog(&mut self) {
+            T::regs().ctlr2().modify(|w| w.set_tsvrefe(true));
+        }
+        fn channel(&self) -> u8 {
+            16
+        }
+    }
+}
+#[cfg(adc_x0)]
+mod ch_internal {
+    use super::*;
+
+    pub struct VrefInt;
+    impl<T: Instance> AdcChannel<T> for VrefInt {}
+    impl<T: Instance> SealedAdcChannel<T> for VrefInt {
+        fn channel(&self) -> u8 {
+            15
+        }
+    }
+}
+#[cfg(any(adc_v1, adc_v3))]
+mod ch_internal {
+    use super::*;
+
+    pub struct VrefInt;
+    impl<T: Instance> AdcChannel<T> for VrefInt {}
+    impl<T: Instance> SealedAdcChannel<T> for VrefInt {
+        fn set_as_analog(&mut self) {
+            T::regs().ctlr2().modify(|w| w.set_tsvrefe(true));
+        }
+        fn channel(&self) -> u8 {
+            17
+        }
+    }
+
+    pub struct Temperature;
+    impl<T: Instance> AdcChannel<T> for Temperature {}
+    impl<T: Instance> SealedAdcChannel<T> for Temperature {
+        fn set_as_analog(&mut self) {
+            T::regs().ctlr2().modify(|w| w.set_tsvrefe(true));
+        }
+        fn channel(&self) -> u8 {
+            16
+        }
+    }
+}
+
+#[cfg(adc_v0)]
+mod ch_internal {
+    use super::*;
+
+    pub struct Vref;
+    impl<T: Instance> AdcChannel<T> for Vref {}
+    impl<T: Instance> SealedAdcChannel<T> for Vref {
+        fn channel(&self) -> u8 {
+            8
+        }
+    }
+
+    pub struct Vcal;
+    impl<T: Instance> AdcChannel<T> for Vcal {}
+    impl<T: Instance> SealedAdcChannel<T> for Vcal {
+        fn channel(&self) -> u8 {
+            9
+        }
+    }
+}
+
+#[cfg(adc_ch641)]
+mod ch_internal {
+    use super::*;
+    use crate::pac::EXTEND;
+
+    // FIXME: move peripherals and pins
+
+    /// Differential input current sampling
+    pub struct Isp {
+        _marker: core::marker::PhantomData<()>,
+    }
+
+    #[derive(Default)]
+    pub struct IspConfig {
+        /// DC bias is about 400 in raw ADC sample value.
+        pub dc_bias: bool,
+    }
+
+    impl Isp {
+        pub fn new_non_inverting(in_positive: impl NonInvertingPin, config: IspConfig) -> Self {
+            in_positive.set_as_isp_pin();
+            EXTEND.ctlr2().modify(|w| {
+                w.set_isp_ps(in_positive.pin_sel());
+                w.set_isp_ns(true); // use GND
+                w.set_isp_ae(true);
+                w.set_isp_be(config.dc_bias);
+            });
+            Isp {
+                _marker: core::marker::PhantomData,
+            }
+        }
+
+        // PB6/PA6, PB7
+        pub fn new(in_positive: impl NonInvertingPin, in_negative: impl InvertingPin, config: IspConfig) -> Self {
+            in_positive.set_as_isp_pin();
+            in_negative.set_as_isp_pin();
+            EXTEND.ctlr2().modify(|w| {
+                w.set_isp_ps(in_positive.pin_sel());
+                w.set_isp_ns(false); // true: use GND; false: use PB7
+                w.set_isp_ae(true);
+                w.set_isp_be(config.dc_bias);
+            });
+            Isp {
+                _marker: core::marker::PhantomData,
+            }
+        }
+    }
+
+    pub trait NonInvertingPin {
+        fn set_as_isp_pin(&self) {}
+        fn pin_sel(&self) -> bool;
+    }
+    pub trait InvertingPin {
+        fn set_as_isp_pin(&self) {}
+    }
+    impl NonInvertingPin for peripherals::PA6 {
+        fn pin_sel(&self) -> bool {
+            true
+        }
+    }
+    // aka. ISP pin
+    impl NonInvertingPin for peripherals::PB6 {
+        fn pin_sel(&self) -> bool {
+            false
+        }
+    }
+    impl InvertingPin for peripherals::PB7 {}
+
+    impl<T: Instance> AdcChannel<T> for Isp {}
+    impl<T: Instance> SealedAdcChannel<T> for Isp {
+        fn channel(&self) -> u8 {
+            8
+        }
+    }
+
+    pub struct VhvDiv5;
+    impl<T: Instance> AdcChannel<T> for VhvDiv5 {}
+    impl<T: Instance> SealedAdcChannel<T> for VhvDiv5 {
+        fn channel(&self) -> u8 {
+            15
+        }
+    }
+}
+
+pub use ch_internal::*;
@@ -0,0 +1,47 @@
+//! Software oversampling.
+//!
+//! Accumulating `4^n` samples and shifting right by `n` yields `n` extra bits of resolution
+//! for slow signals with some noise. Shifting by `log2(samples)` gives a plain average.
+
+use super::{Adc, AdcChannel, Instance, SampleTime};
+use crate::mode::{Async, Mode};
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Accumulate `samples` conversions of a channel and return the sum shifted right by `shift`.
+    pub fn blocking_read_averaged(
+        &mut self,
+        channel: &mut impl AdcChannel<T>,
+        sample_time: SampleTime,
+        samples: u16,
+        shift: u8,
+    ) -> u32 {
+        assert!(samples > 0);
+
+        let mut acc = 0u32;
+        for _ in 0..samples {
+            acc += self.convert(channel, sample_time) as u32;
+        }
+        acc >> shift
+    }
+}
+
+impl<'d, T: Instance> Adc<'d, T, Async> {
+    /// Accumulate `samples` conversions of a channel and return the sum shifted right by `shift`.
+    ///
+    /// Each conversion waits for the end-of-conversion interrupt.
+    pub async fn read_averaged(
+        &mut self,
+        channel: &mut impl AdcChannel<T>,
+        sample_time: SampleTime,
+        samples: u16,
+        shift: u8,
+    ) -> u32 {
+        assert!(samples > 0);
+
+        let mut acc = 0u32;
+        for _ in 0..samples {
+            acc += self.read(channel, sample_time).await as u32;
+        }
+        acc >> shift
+    }
+}
@@ -0,0 +1,136 @@
+//! Gap-free ADC sampling into two alternating DMA buffer halves.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use super::trigger::regular_trigger;
+use super::{apply_offsets, Adc, AnyAdcChannel, Instance, RegularTrigger, RxDma, SampleTime};
+use crate::dma::{OverrunError, Transfer, TransferOptions};
+use crate::mode::Mode;
+use crate::{into_ref, Peripheral};
+
+/// ADC driver recording into one half of a DMA buffer while the other half is processed.
+///
+/// Unlike [`RingBufferedAdc`](super::RingBufferedAdc), samples are not copied: each filled
+/// half is lent out in place. The DMA keeps running meanwhile, so a half must be processed
+/// before the DMA wraps around to it again. Falling further behind is reported as an
+/// [`OverrunError`].
+pub struct PingPongAdc<'d, T: Instance> {
+    _phantom: PhantomData<T>,
+    transfer: Transfer<'d>,
+    buf: *mut u16,
+    half_len: usize,
+    /// Halves lent out so far
+    taken: usize,
+    calibration: i16,
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Turn this ADC into a ping-pong sampler and start it.
+    ///
+    /// `dma_buf` is split into two halves that are filled alternately. Its length must be
+    /// even, and a multiple of twice the sequence length to keep each half aligned to full scans.
+    pub fn into_ping_pong(
+        mut self,
+        dma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        sequence: &mut [(&mut AnyAdcChannel<T>, SampleTime)],
+    ) -> PingPongAdc<'d, T> {
+        assert!(!dma_buf.is_empty() && dma_buf.len() % 2 == 0 && dma_buf.len() <= 0xFFFF);
+
+        into_ref!(dma);
+
+        self.set_sequence(sequence);
+
+        let calibration = self.calibration;
+
+        let half_len = dma_buf.len() / 2;
+        let buf = dma_buf.as_mut_ptr();
+
+        let request = dma.request();
+        let opts = TransferOptions {
+            circular: true,
+            half_transfer_ir: true,
+            complete_transfer_ir: true,
+            ..Default::default()
+        };
+        let rdatar = T::regs().rdatar().as_ptr() as *mut u16;
+        let transfer = unsafe { Transfer::new_read(dma, request, rdatar, dma_buf, opts) };
+
+        if regular_trigger::<T>() == RegularTrigger::Software {
+            T::regs().ctlr2().modify(|w| {
+                w.set_dma(true);
+                w.set_cont(true);
+            });
+            T::regs().ctlr2().modify(|w| w.set_swstart(true));
+        } else {
+            T::regs().ctlr2().modify(|w| w.set_dma(true));
+        }
+
+        PingPongAdc {
+            _phantom: PhantomData,
+            transfer,
+            buf,
+            half_len,
+            taken: 0,
+            calibration,
+        }
+    }
+}
+
+impl<'d, T: Instance> PingPongAdc<'d, T> {
+    /// Number of samples in each half.
+    pub fn half_len(&self) -> usize {
+        self.half_len
+    }
+
+    /// Wait for the next half to fill and lend it out.
+    ///
+    /// Halves are returned alternately, first then second. If the DMA already filled the
+    /// other half too, and wrapped around into this one, [`OverrunError`] is returned and
+    /// the next call continues with the latest half.
+    pub async fn next(&mut self) -> Result<&[u16], OverrunError> {
+        let filled = poll_fn(|cx| {
+            self.transfer.set_waker(cx.waker());
+
+            let filled = self.transfer.event_count();
+            if filled > self.taken {
+                Poll::Ready(filled)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        if filled - self.taken > 1 {
+            self.taken = filled - 1;
+            return Err(OverrunError);
+        }
+
+        let half = self.taken % 2;
+        self.taken += 1;
+
+        // The DMA is writing into the other half meanwhile.
+        let samples = unsafe { core::slice::from_raw_parts_mut(self.buf.add(half * self.half_len), self.half_len) };
+        apply_offsets(samples, self.calibration);
+        Ok(samples)
+    }
+
+    /// Call `f` with each half as it fills, until it returns `false` or an overrun.
+    pub async fn run(&mut self, mut f: impl FnMut(&[u16]) -> bool) -> Result<(), OverrunError> {
+        while f(self.next().await?) {}
+        Ok(())
+    }
+}
+
+impl<'d, T: Instance> Drop for PingPongAdc<'d, T> {
+    fn drop(&mut self) {
+        T::regs().ctlr2().modify(|w| {
+            w.set_cont(false);
+            w.set_dma(false);
+            w.set_adon(false);
+        });
+        T::disable();
+    }
+}
@@ -0,0 +1,56 @@
+//! Powering the ADC down between conversions.
+
+use super::{Adc, Instance};
+use crate::mode::Mode;
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Power down the converter, and the temperature sensor and reference where present.
+    ///
+    /// Configuration is kept, [`enable`](Self::enable) powers it back up.
+    pub fn disable(&mut self) {
+        T::regs().ctlr2().modify(|w| {
+            w.set_cont(false);
+            w.set_adon(false);
+            #[cfg(not(any(adc_v0, adc_ch641)))]
+            w.set_tsvrefe(false);
+        });
+    }
+
+    /// Power up the converter after [`disable`](Self::disable).
+    ///
+    /// Internal channels re-enable the temperature sensor and reference when they are next converted.
+    pub fn enable(&mut self) {
+        if T::regs().ctlr2().read().adon() {
+            return;
+        }
+
+        T::regs().ctlr2().modify(|w| w.set_adon(true));
+
+        // Wait for the converter to stabilize (tSTAB, about 1us).
+        qingke::riscv::asm::delay(crate::rcc::clocks().hclk.0 / 1_000_000 + 1);
+    }
+
+    /// Whether the converter is powered up.
+    pub fn is_enabled(&self) -> bool {
+        T::regs().ctlr2().read().adon()
+    }
+
+    /// Power down the ADC until the returned guard is dropped.
+    ///
+    /// Useful for battery-powered designs that only sample in short bursts.
+    pub fn low_power(&mut self) -> LowPowerGuard<'_, 'd, T, M> {
+        self.disable();
+        LowPowerGuard { adc: self }
+    }
+}
+
+/// Keeps the ADC powered down, powering it back up when dropped.
+pub struct LowPowerGuard<'a, 'd, T: Instance, M: Mode> {
+    adc: &'a mut Adc<'d, T, M>,
+}
+
+impl<'a, 'd, T: Instance, M: Mode> Drop for LowPowerGuard<'a, 'd, T, M> {
+    fn drop(&mut self) {
+        self.adc.enable();
+    }
+}
@@ -0,0 +1,148 @@
+//! Continuous ADC sampling into a DMA ring buffer.
+
+use core::marker::PhantomData;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::trigger::regular_trigger;
+use super::{apply_offsets, Adc, AnyAdcChannel, Instance, RegularTrigger, RxDma, SampleTime};
+use crate::dma::{OverrunError, ReadableRingBuffer, TransferOptions};
+use crate::mode::Mode;
+use crate::{into_ref, Peripheral};
+
+/// ADC driver that continuously scans a regular sequence into a circular DMA buffer.
+///
+/// The DMA wakes the reading task when the buffer is half full and when it wraps around,
+/// so reading half of the buffer capacity at a time yields one notification per half.
+pub struct RingBufferedAdc<'d, T: Instance> {
+    _phantom: PhantomData<T>,
+    ring_buf: ReadableRingBuffer<'d, u16>,
+    calibration: i16,
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Turn this ADC into a ring-buffered sampler.
+    ///
+    /// `sequence` lists the channels of the regular sequence in rank order (up to 16).
+    /// Results are written to `dma_buf` interleaved in that order, so its length should be
+    /// a multiple of twice the sequence length to keep both halves aligned to a full scan.
+    ///
+    /// Call [`RingBufferedAdc::start`] to begin sampling.
+    pub fn into_ring_buffered(
+        mut self,
+        dma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        sequence: &mut [(&mut AnyAdcChannel<T>, SampleTime)],
+    ) -> RingBufferedAdc<'d, T> {
+        assert!(!dma_buf.is_empty() && dma_buf.len() <= 0xFFFF);
+
+        into_ref!(dma);
+
+        self.set_sequence(sequence);
+
+        let request = dma.request();
+        let opts = TransferOptions {
+            half_transfer_ir: true,
+            ..Default::default()
+        };
+        let rdatar = T::regs().rdatar().as_ptr() as *mut u16;
+        let ring_buf = unsafe { ReadableRingBuffer::new(dma, request, rdatar, dma_buf, opts) };
+
+        RingBufferedAdc {
+            _phantom: PhantomData,
+            ring_buf,
+            calibration: self.calibration,
+        }
+    }
+}
+
+impl<'d, T: Instance> RingBufferedAdc<'d, T> {
+    /// Start the DMA and conversions.
+    ///
+    /// With the [`RegularTrigger::Software`] trigger the ADC converts continuously,
+    /// otherwise one scan is done per trigger event.
+    pub fn start(&mut self) {
+        compiler_fence(Ordering::SeqCst);
+
+        self.ring_buf.clear();
+        self.ring_buf.start();
+
+        if regular_trigger::<T>() == RegularTrigger::Software {
+            T::regs().ctlr2().modify(|w| {
+                w.set_dma(true);
+                w.set_cont(true);
+            });
+            T::regs().ctlr2().modify(|w| w.set_swstart(true));
+        } else {
+            T::regs().ctlr2().modify(|w| w.set_dma(true));
+        }
+    }
+
+    /// Stop continuous conversions and the DMA.
+    pub fn stop(&mut self) {
+        T::regs().ctlr2().modify(|w| {
+            w.set_cont(false);
+            w.set_dma(false);
+        });
+
+        self.ring_buf.request_stop();
+
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    /// Clear any samples currently held in the ring buffer.
+    pub fn clear(&mut self) {
+        self.ring_buf.clear();
+    }
+
+    /// The capacity of the DMA buffer, in samples.
+    pub const fn capacity(&self) -> usize {
+        self.ring_buf.capacity()
+    }
+
+    /// Read exactly `measurements.len()` samples, waiting for the DMA as needed.
+    ///
+    /// Returns the number of samples left available for immediate reading.
+    /// If the DMA has overwritten samples that were not read yet, conversions are stopped
+    /// and [`OverrunError`] is returned; call [`start`](Self::start) again to recover.
+    pub async fn read(&mut self, measurements: &mut [u16]) -> Result<usize, OverrunError> {
+        if !self.ring_buf.is_running() {
+            self.start();
+        }
+
+        match self.ring_buf.read_exact(measurements).await {
+            Ok(len) => {
+                apply_offsets(measurements, self.calibration);
+                Ok(len)
+            }
+            Err(e) => {
+                self.stop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Read whatever samples are currently available, without waiting.
+    ///
+    /// Returns the number of samples copied into `buf`.
+    pub fn blocking_read(&mut self, buf: &mut [u16]) -> Result<usize, OverrunError> {
+        match self.ring_buf.read(buf) {
+            Ok((len, _)) => {
+                apply_offsets(&mut buf[..len], self.calibration);
+                Ok(len)
+            }
+            Err(e) => {
+                self.stop();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'d, T: Instance> Drop for RingBufferedAdc<'d, T> {
+    fn drop(&mut self) {
+        self.stop();
+
+        T::regs().ctlr2().modify(|w| w.set_adon(false));
+        T::disable();
+    }
+}
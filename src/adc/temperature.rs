@@ -0,0 +1,38 @@
+//! Internal temperature sensor.
+
+use super::{Adc, Instance, SampleTime, Temperature, ADC_MAX};
+use crate::mode::Mode;
+
+// Typical values from the datasheet.
+#[cfg(adc_l1)]
+const V25_MV: f32 = 1450.0;
+#[cfg(adc_l1)]
+const AVG_SLOPE_MV: f32 = 4.2;
+#[cfg(any(adc_v1, adc_v3))]
+const V25_MV: f32 = 1400.0;
+#[cfg(any(adc_v1, adc_v3))]
+const AVG_SLOPE_MV: f32 = -4.3;
+
+impl Temperature {
+    /// Convert a raw reading of the temperature channel to degrees Celsius.
+    ///
+    /// `vdda_mv` is the ADC supply/reference voltage the reading was taken with. This uses
+    /// the typical V25 and average slope from the datasheet, the sensor isn't calibrated, so
+    /// expect several degrees of offset between chips.
+    pub fn to_celsius(raw: u16, vdda_mv: u32) -> f32 {
+        let mv = raw as f32 * vdda_mv as f32 / ADC_MAX as f32;
+
+        (mv - V25_MV) / AVG_SLOPE_MV + 25.0
+    }
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Measure the chip temperature in degrees Celsius.
+    ///
+    /// See [`Temperature::to_celsius`] for the meaning of `vdda_mv`.
+    pub fn convert_temperature(&mut self, temp: &mut Temperature, vdda_mv: u32) -> f32 {
+        // The sensor needs a sample time of at least ~17us.
+        let raw = self.convert(temp, SampleTime::CYCLES239_5);
+        Temperature::to_celsius(raw, vdda_mv)
+    }
+}
@@ -0,0 +1,85 @@
+//! Touch key (TKEY), capacitive sensing using the ADC.
+//!
+//! The TKEY unit charges the electrode for a programmable time, discharges it,
+//! then samples the remaining voltage with the ADC. A finger on the electrode adds
+//! capacitance and lowers the result, so raw counts drop when a key is touched.
+
+use super::{Adc, AdcChannel, Instance, SampleTime};
+use crate::mode::{Blocking, Mode};
+
+/// CTLR1.TKENABLE
+const CTLR1_TKENABLE: u32 = 1 << 24;
+
+/// Touch key measurement configuration.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TouchKeyConfig {
+    /// Charge time, in ADC clock cycles, 12 bits.
+    pub charge_time: u16,
+    /// Discharge time, in ADC clock cycles, 12 bits.
+    pub discharge_time: u16,
+    /// ADC sample time of the touch channel.
+    pub sample_time: SampleTime,
+}
+
+impl Default for TouchKeyConfig {
+    fn default() -> Self {
+        // Values from the vendor example
+        Self {
+            charge_time: 0x10,
+            discharge_time: 0x08,
+            sample_time: SampleTime::from_bits(0b111),
+        }
+    }
+}
+
+/// Touch key driver, owns the ADC while touch sensing is enabled.
+pub struct TouchKey<'d, T: Instance, M: Mode = Blocking> {
+    adc: Adc<'d, T, M>,
+    config: TouchKeyConfig,
+}
+
+impl<'d, T: Instance, M: Mode> TouchKey<'d, T, M> {
+    /// Enable touch sensing on an ADC.
+    pub fn new(adc: Adc<'d, T, M>, config: TouchKeyConfig) -> Self {
+        assert!(config.charge_time <= 0xFFF && config.discharge_time <= 0xFFF);
+
+        T::regs().ctlr1().modify(|w| w.0 |= CTLR1_TKENABLE);
+
+        Self { adc, config }
+    }
+
+    /// Change the measurement configuration.
+    pub fn set_config(&mut self, config: TouchKeyConfig) {
+        assert!(config.charge_time <= 0xFFF && config.discharge_time <= 0xFFF);
+
+        self.config = config;
+    }
+
+    /// Perform one charge-transfer measurement on a channel, returning the raw count.
+    ///
+    /// Lower values mean more capacitance on the electrode.
+    pub fn measure(&mut self, channel: &mut impl AdcChannel<T>) -> u16 {
+        self.adc.configure_channel(channel, 1, self.config.sample_time);
+        Adc::<'d, T, M>::set_sequence_len(1);
+
+        let regs = T::regs();
+        unsafe {
+            // In touch key mode, IDATAR1 is the charge time, and writing the discharge
+            // time to RDATAR starts the measurement.
+            core::ptr::write_volatile(regs.idatar(0).as_ptr() as *mut u32, self.config.charge_time as u32);
+            core::ptr::write_volatile(regs.rdatar().as_ptr() as *mut u32, self.config.discharge_time as u32);
+        }
+
+        while !regs.statr().read().eoc() {}
+
+        regs.rdatar().read().data()
+    }
+
+    /// Disable touch sensing, returning the ADC.
+    pub fn release(self) -> Adc<'d, T, M> {
+        T::regs().ctlr1().modify(|w| w.0 &= !CTLR1_TKENABLE);
+
+        self.adc
+    }
+}
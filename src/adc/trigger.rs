@@ -0,0 +1,100 @@
+//! External triggers for regular conversions.
+//!
+//! Pair with [`MasterMode`](crate::timer::low_level::MasterMode) on the timer side to start
+//! one conversion (or one scan) per timer event, giving an exact sample rate.
+
+use super::{Adc, Instance};
+use crate::mode::Mode;
+use crate::pac::adc::vals;
+
+/// External trigger source for the regular group (EXTSEL).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum RegularTrigger {
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim1Cc1 = 0b000,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim1Cc2 = 0b001,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim1Cc3 = 0b010,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim2Cc2 = 0b011,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim3Trgo = 0b100,
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Tim4Cc4 = 0b101,
+    /// EXTI line 11, or TIM8 TRGO when remapped.
+    #[cfg(not(any(adc_v0, adc_x0, adc_ch641)))]
+    Exti11 = 0b110,
+
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim1Trgo = 0b000,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim1Cc1 = 0b001,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim1Cc2 = 0b010,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim2Trgo = 0b011,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim2Cc1 = 0b100,
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Tim2Cc2 = 0b101,
+    /// External pin (PD3 / PC2).
+    #[cfg(any(adc_v0, adc_x0, adc_ch641))]
+    Exti = 0b110,
+
+    /// Started by software, the power-on default of this driver.
+    Software = 0b111,
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Select what starts a regular conversion (or scan).
+    ///
+    /// With a hardware trigger, results are usually collected with DMA,
+    /// see [`RingBufferedAdc`](super::RingBufferedAdc).
+    pub fn set_regular_trigger(&mut self, trigger: RegularTrigger) {
+        T::regs().ctlr2().modify(|w| {
+            w.set_exttrig(true);
+            w.set_extsel(vals::Extsel::from_bits(trigger as u8));
+        });
+    }
+
+    /// The currently selected regular trigger.
+    pub fn regular_trigger(&self) -> RegularTrigger {
+        regular_trigger::<T>()
+    }
+
+    /// Discontinuous mode, convert only `count` channels of the regular sequence per trigger.
+    ///
+    /// `count` is 1 to 8, `None` converts the whole sequence on each trigger. The next
+    /// trigger resumes where the previous burst stopped, wrapping around at the end of
+    /// the sequence, so a timer can pace a long scan in short bursts.
+    pub fn set_discontinuous(&mut self, count: Option<u8>) {
+        T::regs().ctlr1().modify(|w| match count {
+            Some(count) => {
+                assert!(count > 0 && count <= 8);
+                w.set_discen(true);
+                w.set_discnum(count - 1);
+            }
+            None => w.set_discen(false),
+        });
+    }
+
+    /// Number of channels converted per trigger in discontinuous mode, if enabled.
+    pub fn discontinuous(&self) -> Option<u8> {
+        let ctlr1 = T::regs().ctlr1().read();
+        ctlr1.discen().then(|| ctlr1.discnum() + 1)
+    }
+
+    /// Discontinuous mode for the injected group, one channel per trigger.
+    pub fn set_injected_discontinuous(&mut self, enable: bool) {
+        T::regs().ctlr1().modify(|w| w.set_jdiscen(enable));
+    }
+}
+
+pub(super) fn regular_trigger<T: Instance>() -> RegularTrigger {
+    let bits = T::regs().ctlr2().read().extsel().to_bits();
+    // SAFETY: EXTSEL is 3 bits wide and every value has a variant.
+    unsafe { core::mem::transmute::<u8, RegularTrigger>(bits & 0b111) }
+}
@@ -0,0 +1,38 @@
+//! Internal voltage reference and supply voltage measurement.
+
+#[cfg(adc_v0)]
+use super::Vref as VrefInt;
+#[cfg(not(adc_v0))]
+use super::VrefInt;
+use super::{Adc, Instance, SampleTime, ADC_MAX, VREF_INT};
+use crate::mode::Mode;
+
+impl VrefInt {
+    /// Back-compute VDDA in millivolts from a raw reading of the internal reference.
+    ///
+    /// The ADC uses VDDA as its reference, so a lower supply yields a higher reading of the
+    /// fixed internal reference.
+    pub fn vdda_mv(raw: u16) -> u32 {
+        if raw == 0 {
+            return 0;
+        }
+        VREF_INT * ADC_MAX / raw as u32
+    }
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Measure the internal reference voltage, returning the raw reading.
+    pub fn convert_vrefint(&mut self, vref: &mut VrefInt) -> u16 {
+        // Longest sample time, the reference has a high output impedance.
+        self.convert(vref, SampleTime::from_bits(0b111))
+    }
+
+    /// Measure the analog supply voltage (VDDA) in millivolts using the internal reference.
+    ///
+    /// The nominal reference is [`VREF_INT`], it is not factory calibrated so expect a
+    /// few percent of error.
+    pub fn measure_vdda_mv(&mut self, vref: &mut VrefInt) -> u32 {
+        let raw = self.convert_vrefint(vref);
+        VrefInt::vdda_mv(raw)
+    }
+}
@@ -0,0 +1,93 @@
+//! Analog watchdog.
+//!
+//! The watchdog compares every conversion result (regular and/or injected) against a
+//! low/high threshold pair and raises the AWD flag when a result falls outside of it.
+//! Conversions still have to be running, e.g. in continuous or timer triggered mode.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use super::{Adc, AnyAdcChannel, Instance, ADC_MAX};
+use crate::mode::{Async, Mode};
+
+/// Which conversion groups are guarded by the analog watchdog.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WatchdogGroup {
+    /// Regular conversions only
+    Regular,
+    /// Injected conversions only
+    Injected,
+    /// Both regular and injected conversions
+    Both,
+}
+
+impl<'d, T: Instance, M: Mode> Adc<'d, T, M> {
+    /// Enable the analog watchdog with the given thresholds.
+    ///
+    /// If `channel` is `None` all channels are guarded, otherwise only the given one.
+    /// Thresholds are raw conversion values, inclusive, and must not exceed [`ADC_MAX`].
+    pub fn enable_watchdog(&mut self, channel: Option<&AnyAdcChannel<T>>, group: WatchdogGroup, low: u16, high: u16) {
+        assert!(low <= high && high as u32 <= ADC_MAX);
+
+        T::regs().wdltr().write(|w| w.set_lt(low));
+        T::regs().wdhtr().write(|w| w.set_ht(high));
+
+        T::regs().statr().modify(|w| w.set_awd(false));
+        T::regs().ctlr1().modify(|w| {
+            match channel {
+                Some(ch) => {
+                    w.set_awdsgl(true);
+                    w.set_awdch(ch.channel & 0b11111);
+                }
+                None => w.set_awdsgl(false),
+            }
+            w.set_awden(matches!(group, WatchdogGroup::Regular | WatchdogGroup::Both));
+            w.set_jawden(matches!(group, WatchdogGroup::Injected | WatchdogGroup::Both));
+        });
+    }
+
+    /// Disable the analog watchdog.
+    pub fn disable_watchdog(&mut self) {
+        T::regs().ctlr1().modify(|w| {
+            w.set_awden(false);
+            w.set_jawden(false);
+            w.set_awdie(false);
+        });
+        T::regs().statr().modify(|w| w.set_awd(false));
+    }
+
+    /// Whether a conversion result fell outside the watchdog thresholds.
+    ///
+    /// The flag stays set until [`clear_out_of_range`](Self::clear_out_of_range) is called.
+    pub fn is_out_of_range(&self) -> bool {
+        T::regs().statr().read().awd()
+    }
+
+    /// Clear the analog watchdog flag.
+    pub fn clear_out_of_range(&mut self) {
+        T::regs().statr().modify(|w| w.set_awd(false));
+    }
+}
+
+impl<'d, T: Instance> Adc<'d, T, Async> {
+    /// Wait until a guarded conversion result falls outside the watchdog thresholds.
+    ///
+    /// The flag is cleared before returning, so the next call waits for a new event.
+    pub async fn wait_for_out_of_range(&mut self) {
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if T::regs().statr().read().awd() {
+                Poll::Ready(())
+            } else {
+                T::regs().ctlr1().modify(|w| w.set_awdie(true));
+                Poll::Pending
+            }
+        })
+        .await;
+
+        T::regs().ctlr1().modify(|w| w.set_awdie(false));
+        T::regs().statr().modify(|w| w.set_awd(false));
+    }
+}
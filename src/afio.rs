@@ -0,0 +1,57 @@
+//! Alternate function I/O remapping.
+//!
+//! Drivers select their pin mapping with a raw `const REMAP: u8` parameter. This module
+//! provides a typed enum per remappable peripheral, generated from the chip metadata, so
+//! only mappings that actually exist on the chip can be named:
+//!
+//! ```ignore
+//! use ch32_hal::afio::{self, Usart1Remap};
+//!
+//! afio::set_remap(Usart1Remap::Remap1);
+//! assert_eq!(afio::get_remap::<Usart1Remap>(), Some(Usart1Remap::Remap1));
+//!
+//! // Also usable where drivers expect the raw value
+//! let tx = UartTx::new_blocking::<{ Usart1Remap::Remap1.bits() }>(p.USART1, p.PD6, Default::default());
+//! ```
+
+use crate::peripheral::RemapPeripheral;
+
+pub use crate::_generated::remaps::*;
+
+/// A pin mapping of one peripheral.
+pub trait Remap: Copy + Sized + 'static {
+    /// The peripheral this mapping applies to.
+    type Peripheral;
+
+    /// The raw remap value.
+    fn bits(self) -> u8;
+
+    /// Convert a raw remap value, returning `None` if the chip has no such mapping.
+    fn from_bits(bits: u8) -> Option<Self>;
+}
+
+/// Apply a pin mapping.
+///
+/// Drivers apply their `REMAP` parameter themselves when created; this is for pins driven
+/// by other means, or to move a peripheral's pins at runtime.
+pub fn set_remap<R: Remap>(remap: R)
+where
+    R::Peripheral: RemapPeripheral,
+{
+    R::Peripheral::set_remap(remap.bits());
+}
+
+/// The currently applied pin mapping.
+///
+/// Returns `None` if the AFIO holds a value with no known mapping.
+pub fn get_remap<R: Remap>() -> Option<R>
+where
+    R::Peripheral: RemapPeripheral,
+{
+    R::from_bits(get_remap_bits::<R::Peripheral>())
+}
+
+/// The currently applied raw remap value of a peripheral.
+pub fn get_remap_bits<T: RemapPeripheral>() -> u8 {
+    T::get_remap()
+}
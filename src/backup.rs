@@ -0,0 +1,307 @@
+//! Backup registers (BKP)
+//!
+//! The backup data registers are 16-bit wide and kept by VBAT when the main supply is off,
+//! and across resets, for boot counters, RTC epoch offsets or the reason for a reboot.
+//! They are cleared by a backup domain reset and by a tamper event.
+//!
+//! Values are stored through typed [`Register`] handles, declared once:
+//!
+//! ```rust,ignore
+//! const BOOT_COUNT: Register<u32> = Register::new(0);
+//!
+//! let mut backup = Backup::new(p.BKP);
+//! let count = backup.get(BOOT_COUNT);
+//! backup.set(BOOT_COUNT, count + 1);
+//! ```
+//!
+//! The TAMPER pin, PC13, can clear them all on an enclosure-open switch, see [`Backup::enable_tamper`].
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::{into_ref, pac, peripherals, Peripheral, PeripheralRef};
+
+static TAMPER_WAKER: AtomicWaker = AtomicWaker::new();
+
+/// TAMPER interrupt handler.
+///
+/// Called by the `TAMPER` interrupt handler of the HAL with the `rt` feature, call it from your
+/// own handler otherwise.
+pub unsafe fn on_irq() {
+    let bkp = pac::BKP;
+    if bkp.tpcsr().read().tif() {
+        // The flag stays set until `clear_tamper`, stop the interrupt from firing again.
+        bkp.tpcsr().modify(|w| w.set_tpie(false));
+        TAMPER_WAKER.wake();
+    }
+}
+
+/// Active level of the TAMPER pin.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TamperLevel {
+    /// A high level on the pin is a tamper event, for a switch to VDD.
+    High,
+    /// A low level on the pin is a tamper event, for a switch to ground.
+    Low,
+}
+
+/// Number of 16-bit backup data registers.
+#[cfg(any(d8, d8c, d8w))]
+pub const REGISTER_COUNT: usize = 42;
+/// Number of 16-bit backup data registers.
+#[cfg(not(any(d8, d8c, d8w)))]
+pub const REGISTER_COUNT: usize = 10;
+
+/// A value that can be stored in consecutive backup registers.
+pub trait Value: Sized {
+    /// Number of 16-bit registers used.
+    const WORDS: usize;
+
+    /// Build the value from `Self::WORDS` registers, lowest first.
+    fn from_words(words: &[u16]) -> Self;
+
+    /// Split the value into `Self::WORDS` registers, lowest first.
+    fn to_words(&self, words: &mut [u16]);
+}
+
+impl Value for u16 {
+    const WORDS: usize = 1;
+
+    fn from_words(words: &[u16]) -> Self {
+        words[0]
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        words[0] = *self;
+    }
+}
+
+impl Value for u8 {
+    const WORDS: usize = 1;
+
+    fn from_words(words: &[u16]) -> Self {
+        words[0] as u8
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        words[0] = *self as u16;
+    }
+}
+
+impl Value for bool {
+    const WORDS: usize = 1;
+
+    fn from_words(words: &[u16]) -> Self {
+        words[0] != 0
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        words[0] = *self as u16;
+    }
+}
+
+impl Value for u32 {
+    const WORDS: usize = 2;
+
+    fn from_words(words: &[u16]) -> Self {
+        (words[0] as u32) | ((words[1] as u32) << 16)
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        words[0] = *self as u16;
+        words[1] = (*self >> 16) as u16;
+    }
+}
+
+impl Value for i32 {
+    const WORDS: usize = 2;
+
+    fn from_words(words: &[u16]) -> Self {
+        u32::from_words(words) as i32
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        (*self as u32).to_words(words)
+    }
+}
+
+impl Value for u64 {
+    const WORDS: usize = 4;
+
+    fn from_words(words: &[u16]) -> Self {
+        (u32::from_words(&words[..2]) as u64) | ((u32::from_words(&words[2..]) as u64) << 32)
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        (*self as u32).to_words(&mut words[..2]);
+        ((*self >> 32) as u32).to_words(&mut words[2..]);
+    }
+}
+
+/// A typed value stored from backup register `index` on, see [`Backup::get`].
+pub struct Register<T: Value> {
+    index: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Value> Register<T> {
+    /// A value starting at register `index`, 0 being BKP_DATAR1.
+    ///
+    /// Fails to compile in a `const` when the value doesn't fit.
+    pub const fn new(index: usize) -> Self {
+        assert!(index + T::WORDS <= REGISTER_COUNT);
+        Self {
+            index,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Index of the first register used.
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T: Value> Clone for Register<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Value> Copy for Register<T> {}
+
+/// Backup registers driver.
+pub struct Backup<'d> {
+    _bkp: PeripheralRef<'d, peripherals::BKP>,
+}
+
+impl<'d> Backup<'d> {
+    /// Enable the backup registers, keeping their content.
+    pub fn new(bkp: impl Peripheral<P = peripherals::BKP> + 'd) -> Self {
+        into_ref!(bkp);
+
+        crate::rcc::enable_backup_domain();
+
+        Self { _bkp: bkp }
+    }
+
+    /// Read register `index`, 0 being BKP_DATAR1.
+    pub fn read(&self, index: usize) -> u16 {
+        unsafe { register(index).read_volatile() as u16 }
+    }
+
+    /// Write register `index`, 0 being BKP_DATAR1.
+    pub fn write(&mut self, index: usize, value: u16) {
+        unsafe { register(index).write_volatile(value as u32) }
+    }
+
+    /// Read a typed value.
+    pub fn get<T: Value>(&self, register: Register<T>) -> T {
+        let mut words = [0; REGISTER_COUNT];
+        let words = &mut words[..T::WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = self.read(register.index + i);
+        }
+        T::from_words(words)
+    }
+
+    /// Write a typed value.
+    pub fn set<T: Value>(&mut self, register: Register<T>, value: T) {
+        let mut words = [0; REGISTER_COUNT];
+        let words = &mut words[..T::WORDS];
+        value.to_words(words);
+        for (i, word) in words.iter().enumerate() {
+            self.write(register.index + i, *word);
+        }
+    }
+
+    /// Clear all backup registers.
+    pub fn clear(&mut self) {
+        for index in 0..REGISTER_COUNT {
+            self.write(index, 0);
+        }
+    }
+
+    /// Turn PC13 into the TAMPER input. When it reaches `level`, all backup registers are
+    /// cleared by hardware, even with the main supply off.
+    ///
+    /// The pin must not be used as a GPIO or as the RTC calibration output meanwhile.
+    pub fn enable_tamper(&mut self, level: TamperLevel) {
+        let bkp = pac::BKP;
+        bkp.tpctlr().modify(|w| w.set_tpe(false));
+        bkp.tpcsr().modify(|w| {
+            w.set_cte(true);
+            w.set_cti(true);
+        });
+        bkp.tpctlr().modify(|w| w.set_tpal(level == TamperLevel::Low));
+        bkp.tpctlr().modify(|w| w.set_tpe(true));
+    }
+
+    /// Release the TAMPER pin.
+    pub fn disable_tamper(&mut self) {
+        let bkp = pac::BKP;
+        bkp.tpcsr().modify(|w| w.set_tpie(false));
+        bkp.tpctlr().modify(|w| w.set_tpe(false));
+    }
+
+    /// Whether a tamper event occurred since the last [`clear_tamper`](Self::clear_tamper).
+    pub fn is_tampered(&self) -> bool {
+        pac::BKP.tpcsr().read().tef()
+    }
+
+    /// Acknowledge a tamper event.
+    ///
+    /// The backup registers are held cleared until then, and the next tamper event is only
+    /// detected afterwards.
+    pub fn clear_tamper(&mut self) {
+        pac::BKP.tpcsr().modify(|w| {
+            w.set_cte(true);
+            w.set_cti(true);
+        });
+    }
+
+    /// Wait for a tamper event, using the TAMPER interrupt.
+    ///
+    /// Returns immediately if an event is pending, call [`clear_tamper`](Self::clear_tamper)
+    /// before waiting for the next one.
+    pub async fn wait_for_tamper(&mut self) {
+        let bkp = pac::BKP;
+
+        bkp.tpcsr().modify(|w| w.set_tpie(true));
+        unsafe { qingke::pfic::enable_interrupt(pac::Interrupt::TAMPER as u8) };
+
+        poll_fn(|cx| {
+            TAMPER_WAKER.register(cx.waker());
+
+            if bkp.tpcsr().read().tef() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        bkp.tpcsr().modify(|w| w.set_tpie(false));
+    }
+}
+
+/// Address of BKP_DATARx, DATAR1 to DATAR10 start at 0x04, DATAR11 to DATAR42 at 0x40.
+fn register(index: usize) -> *mut u32 {
+    assert!(index < REGISTER_COUNT);
+    let offset = if index < 10 {
+        0x04 + index * 4
+    } else {
+        0x40 + (index - 10) * 4
+    };
+    unsafe { (pac::BKP.as_ptr() as *mut u8).add(offset) as *mut u32 }
+}
+
+#[cfg(feature = "rt")]
+#[qingke_rt::interrupt]
+unsafe fn TAMPER() {
+    on_irq();
+}
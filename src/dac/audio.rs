@@ -0,0 +1,178 @@
+//! PCM audio playback on a DAC channel.
+//!
+//! A timer paces the DAC at the sample rate, and a circular DMA transfer feeds it from
+//! a ring buffer. While the DMA plays one half of the buffer, [`Player::feed`] fills the other.
+
+use core::marker::PhantomData;
+
+use super::{DacDma1, DacDma2, DacPin, Instance, TriggerSel};
+use crate::dma::{OverrunError, TransferOptions, WritableRingBuffer};
+use crate::time::Hertz;
+use crate::timer::low_level::{MasterMode, Timer};
+use crate::timer::GeneralInstance16bit;
+use crate::{into_ref, Peripheral};
+
+/// DAC value output between samples, mid-scale.
+const SILENCE: u16 = 0x800;
+
+/// A PCM sample format that can be played.
+pub trait Sample: Copy {
+    /// Convert to a 12 bit right-aligned DAC value.
+    fn to_dac(self) -> u16;
+}
+
+/// Unsigned 8 bit PCM, 0x80 is silence.
+impl Sample for u8 {
+    fn to_dac(self) -> u16 {
+        (self as u16) << 4
+    }
+}
+
+/// Signed 16 bit PCM.
+impl Sample for i16 {
+    fn to_dac(self) -> u16 {
+        ((self as i32 + 0x8000) >> 4) as u16
+    }
+}
+
+/// Audio player streaming PCM samples to one DAC channel.
+pub struct Player<'d, T: Instance, TIM: GeneralInstance16bit> {
+    _phantom: PhantomData<&'d mut T>,
+    idx: usize,
+    timer: Timer<'d, TIM>,
+    ring_buf: WritableRingBuffer<'d, u16>,
+}
+
+impl<'d, T: Instance, TIM: GeneralInstance16bit> Player<'d, T, TIM> {
+    /// Create a player on DAC channel 1.
+    ///
+    /// `trigger` must be the TRGO of `tim`. `dma_buf` holds the samples in flight, larger
+    /// buffers tolerate more latency in calling [`feed`](Self::feed) at the cost of delay.
+    pub fn new_ch1(
+        dac: impl Peripheral<P = T> + 'd,
+        dma: impl Peripheral<P = impl DacDma1<T>> + 'd,
+        pin: impl Peripheral<P = impl DacPin<T, 1> + crate::gpio::Pin> + 'd,
+        tim: impl Peripheral<P = TIM> + 'd,
+        trigger: TriggerSel,
+        sample_rate: Hertz,
+        dma_buf: &'d mut [u16],
+    ) -> Self {
+        into_ref!(dma);
+        let request = dma.request();
+        Self::new_inner(dac, dma, request, pin, 0, tim, trigger, sample_rate, dma_buf)
+    }
+
+    /// Create a player on DAC channel 2.
+    ///
+    /// See [`new_ch1`](Self::new_ch1).
+    pub fn new_ch2(
+        dac: impl Peripheral<P = T> + 'd,
+        dma: impl Peripheral<P = impl DacDma2<T>> + 'd,
+        pin: impl Peripheral<P = impl DacPin<T, 2> + crate::gpio::Pin> + 'd,
+        tim: impl Peripheral<P = TIM> + 'd,
+        trigger: TriggerSel,
+        sample_rate: Hertz,
+        dma_buf: &'d mut [u16],
+    ) -> Self {
+        into_ref!(dma);
+        let request = dma.request();
+        Self::new_inner(dac, dma, request, pin, 1, tim, trigger, sample_rate, dma_buf)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_inner(
+        _dac: impl Peripheral<P = T> + 'd,
+        dma: impl Peripheral<P = impl crate::dma::Channel> + 'd,
+        request: crate::dma::Request,
+        pin: impl Peripheral<P = impl crate::gpio::Pin> + 'd,
+        idx: usize,
+        tim: impl Peripheral<P = TIM> + 'd,
+        trigger: TriggerSel,
+        sample_rate: Hertz,
+        dma_buf: &'d mut [u16],
+    ) -> Self {
+        into_ref!(pin);
+        pin.set_as_analog();
+
+        T::enable_and_reset();
+        T::regs().cr().modify(|w| {
+            w.set_tsel(idx, trigger);
+            w.set_ten(idx, true);
+            w.set_dmaen(idx, true);
+            w.set_en(idx, true);
+        });
+        T::regs().dhr12r(idx).write(|w| w.set_dhr(SILENCE));
+
+        // Start from silence, so a late first feed doesn't play garbage.
+        dma_buf.fill(SILENCE);
+
+        let opts = TransferOptions {
+            half_transfer_ir: true,
+            ..Default::default()
+        };
+        let dhr = T::regs().dhr12r(idx).as_ptr() as *mut u16;
+        let ring_buf = unsafe { WritableRingBuffer::new(dma, request, dhr, dma_buf, opts) };
+
+        let timer = Timer::new(tim);
+        timer.set_frequency(sample_rate);
+        timer.set_master_mode(MasterMode::Update);
+
+        Self {
+            _phantom: PhantomData,
+            idx,
+            timer,
+            ring_buf,
+        }
+    }
+
+    /// Pause playback, the output holds the last sample.
+    pub fn pause(&mut self) {
+        self.timer.stop();
+    }
+
+    /// Resume playback after [`pause`](Self::pause).
+    pub fn resume(&mut self) {
+        self.timer.start();
+    }
+
+    /// Queue samples for playback, waiting for room in the buffer as needed.
+    ///
+    /// Playback starts on the first call, after one buffer of silence. If samples were not
+    /// fed in time, the DMA replays stale samples and [`OverrunError`] is returned. The queue
+    /// is then reset, and feeding again resumes playback after a short glitch.
+    pub async fn feed<S: Sample>(&mut self, samples: &[S]) -> Result<(), OverrunError> {
+        let mut chunk = [SILENCE; 32];
+
+        if !self.ring_buf.is_running() {
+            self.ring_buf.start();
+            self.timer.start();
+        }
+
+        for samples in samples.chunks(chunk.len()) {
+            for (value, sample) in chunk.iter_mut().zip(samples) {
+                *value = sample.to_dac();
+            }
+
+            if let Err(e) = self.ring_buf.write_exact(&chunk[..samples.len()]).await {
+                self.ring_buf.clear();
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'d, T: Instance, TIM: GeneralInstance16bit> Drop for Player<'d, T, TIM> {
+    fn drop(&mut self) {
+        self.timer.stop();
+        self.ring_buf.request_stop();
+
+        T::regs().cr().modify(|w| {
+            w.set_en(self.idx, false);
+            w.set_dmaen(self.idx, false);
+            w.set_ten(self.idx, false);
+        });
+        T::disable();
+    }
+}
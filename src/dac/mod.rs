@@ -8,6 +8,8 @@ pub use crate::pac::dac::vals::TrigSel as TriggerSel;
 use crate::peripheral::RccPeripheral;
 use crate::{into_ref, peripherals, Peripheral, PeripheralRef};
 
+pub mod audio;
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Single 8 or 12 bit value that can be output by the DAC.
@@ -48,6 +50,46 @@ pub enum ValueArray<'a> {
     Bit12Right(&'a [u16]),
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+/// Array variant of [`DualValue`], with both channel values packed in each word.
+///
+/// Use [`DualValue::packed`] to build the words, channel 1 is in the low half.
+pub enum DualValueArray<'a> {
+    /// 8 bit values
+    Bit8(&'a [u16]),
+    /// 12 bit values, left-aligned
+    Bit12Left(&'a [u32]),
+    /// 12 bit values, right-aligned
+    Bit12Right(&'a [u32]),
+}
+
+impl DualValue {
+    /// Both values packed as written to the dual data holding registers.
+    pub const fn packed(self) -> u32 {
+        match self {
+            DualValue::Bit8(v1, v2) => v1 as u32 | ((v2 as u32) << 8),
+            DualValue::Bit12Left(v1, v2) => (v1 & 0xFFF0) as u32 | (((v2 & 0xFFF0) as u32) << 16),
+            DualValue::Bit12Right(v1, v2) => (v1 & 0xFFF) as u32 | (((v2 & 0xFFF) as u32) << 16),
+        }
+    }
+}
+
+/// Hardware wave generation.
+///
+/// The generated wave is added to the value in the data holding register and advances
+/// by one step on each trigger, so triggering must be enabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Wave {
+    /// No wave generation.
+    Disabled,
+    /// Pseudo-random noise from an LFSR, keeping bits `0..=bits` of the LFSR (0 to 11).
+    Noise { bits: u8 },
+    /// Triangle counting up and down with an amplitude of `2^(bits + 1) - 1` (`bits` 0 to 11).
+    Triangle { bits: u8 },
+}
+
 /// Driver for a single DAC channel.
 ///
 /// If you want to use both channels, either together or independently,
@@ -132,6 +174,38 @@ impl<'d, T: Instance, const N: u8, DMA> DacChannel<'d, T, N, DMA> {
         });
     }
 
+    /// Enable or disable the output buffer of this channel.
+    ///
+    /// The buffer lowers the output impedance so the pin can drive a load directly,
+    /// at the cost of not reaching the supply rails. It is enabled on reset.
+    pub fn set_output_buffer(&mut self, on: bool) {
+        critical_section::with(|_| {
+            T::regs().cr().modify(|reg| {
+                reg.set_boff(Self::IDX, !on);
+            });
+        });
+    }
+
+    /// Select the hardware wave generator of this channel.
+    ///
+    /// This method disables the channel, so you may need to re-enable afterwards.
+    pub fn set_wave(&mut self, wave: Wave) {
+        let (mode, bits) = match wave {
+            Wave::Disabled => (0b00, 0),
+            Wave::Noise { bits } => (0b01, bits),
+            Wave::Triangle { bits } => (0b10, bits),
+        };
+        assert!(bits <= 11);
+
+        critical_section::with(|_| {
+            T::regs().cr().modify(|reg| {
+                reg.set_en(Self::IDX, false);
+                reg.set_wave(Self::IDX, crate::pac::dac::vals::Wave::from_bits(mode));
+                reg.set_mamp(Self::IDX, bits);
+            });
+        });
+    }
+
     /// Software trigger this channel.
     pub fn trigger(&mut self) {
         T::regs().swtrigr().write(|reg| {
@@ -151,6 +225,25 @@ impl<'d, T: Instance, const N: u8, DMA> DacChannel<'d, T, N, DMA> {
         }
     }
 
+    /// Write a new 12 bit, right-aligned value to this channel.
+    ///
+    /// Shorthand for `set(Value::Bit12Right(value))`.
+    pub fn set_value(&mut self, value: u16) {
+        self.set(Value::Bit12Right(value))
+    }
+
+    /// Output a voltage, given the DAC reference voltage (usually VDDA), both in millivolts.
+    ///
+    /// Voltages above the reference are clamped to full scale. For a reference output
+    /// driving a comparator or a high-impedance input, the output buffer can be turned
+    /// off with [`set_output_buffer`](Self::set_output_buffer) to reach closer to the rails.
+    pub fn set_millivolts(&mut self, mv: u32, vref_mv: u32) {
+        assert!(vref_mv > 0);
+
+        let value = (mv.min(vref_mv) * 4095 + vref_mv / 2) / vref_mv;
+        self.set_value(value as u16)
+    }
+
     /// Read the current output value of the DAC.
     pub fn read(&self) -> u16 {
         T::regs().dor(Self::IDX).read().dor()
@@ -170,7 +263,7 @@ macro_rules! impl_dma_methods {
             /// `data`. Note that for performance reasons in circular mode the transfer-complete
             /// interrupt is disabled.
             #[cfg(not(gpdma))]
-            pub async fn write(&mut self, data: ValueArray<'_>, circular: bool) {
+            pub async fn write(&mut self, data: ValueArray<'_>, circular: bool) -> Result<(), crate::dma::Error> {
                 // Enable DAC and DMA
                 T::regs().cr().modify(|w| {
                     w.set_en(Self::IDX, true);
@@ -218,12 +311,36 @@ macro_rules! impl_dma_methods {
                     },
                 };
 
-                tx_f.await;
+                let result = tx_f.await;
 
                 T::regs().cr().modify(|w| {
                     w.set_en(Self::IDX, false);
                     w.set_dmaen(Self::IDX, false);
                 });
+
+                result
+            }
+
+            /// Play `data` on this channel via DMA, outputting one sample per `trigger` event.
+            ///
+            /// This is the usual way to generate waveforms: select a timer TRGO as trigger,
+            /// with the timer master mode set to update events, so the timer frequency sets
+            /// the sample rate. With `circular` set, `data` is repeated until the future is dropped.
+            #[cfg(not(gpdma))]
+            pub async fn play(
+                &mut self,
+                data: ValueArray<'_>,
+                trigger: TriggerSel,
+                circular: bool,
+            ) -> Result<(), crate::dma::Error> {
+                self.set_trigger(trigger);
+                self.set_triggering(true);
+
+                let result = self.write(data, circular).await;
+
+                self.set_triggering(false);
+
+                result
             }
         }
     };
@@ -238,6 +355,70 @@ impl<'d, T: Instance, const N: u8, DMA> Drop for DacChannel<'d, T, N, DMA> {
     }
 }
 
+impl<'d, T: Instance, DMACh1, DMACh2> Dac<'d, T, DMACh1, DMACh2>
+where
+    DMACh1: DacDma1<T>,
+{
+    /// Write `data` to both channels via the channel 1 DMA, updating them on the same trigger.
+    ///
+    /// Both channels must use the same trigger source, see [`Dac::set_trigger`].
+    /// With `circular` set, `data` is repeated until the future is dropped.
+    #[cfg(not(gpdma))]
+    pub async fn write_dual(&mut self, data: DualValueArray<'_>, circular: bool) -> Result<(), crate::dma::Error> {
+        T::regs().cr().modify(|w| {
+            w.set_en(0, true);
+            w.set_en(1, true);
+            w.set_dmaen(0, true);
+        });
+
+        let tx_request = self.ch1.dma.request();
+        let dma_channel = &mut self.ch1.dma;
+
+        let tx_options = crate::dma::TransferOptions {
+            circular,
+            half_transfer_ir: false,
+            complete_transfer_ir: !circular,
+            ..Default::default()
+        };
+
+        let tx_f = match data {
+            DualValueArray::Bit8(buf) => unsafe {
+                crate::dma::Transfer::new_write(
+                    dma_channel,
+                    tx_request,
+                    buf,
+                    T::regs().dhr8rd().as_ptr() as *mut u16,
+                    tx_options,
+                )
+            },
+            DualValueArray::Bit12Left(buf) => unsafe {
+                crate::dma::Transfer::new_write(
+                    dma_channel,
+                    tx_request,
+                    buf,
+                    T::regs().dhr12ld().as_ptr() as *mut u32,
+                    tx_options,
+                )
+            },
+            DualValueArray::Bit12Right(buf) => unsafe {
+                crate::dma::Transfer::new_write(
+                    dma_channel,
+                    tx_request,
+                    buf,
+                    T::regs().dhr12rd().as_ptr() as *mut u32,
+                    tx_options,
+                )
+            },
+        };
+
+        let result = tx_f.await;
+
+        T::regs().cr().modify(|w| w.set_dmaen(0, false));
+
+        result
+    }
+}
+
 /// DAC driver.
 ///
 /// Use this struct when you want to use both channels, either together or independently.
@@ -246,7 +427,7 @@ impl<'d, T: Instance, const N: u8, DMA> Drop for DacChannel<'d, T, N, DMA> {
 ///
 /// ```ignore
 /// // Pins may need to be changed for your specific device.
-/// let (dac_ch1, dac_ch2) = embassy_stm32::dac::Dac::new(p.DAC1, NoDma, NoDma, p.PA4, p.PA5).split();
+/// let (dac_ch1, dac_ch2) = ch32_hal::dac::Dac::new(p.DAC, NoDma, NoDma, p.PA4, p.PA5).split();
 /// ```
 pub struct Dac<'d, T: Instance, DMACh1 = NoDma, DMACh2 = NoDma> {
     ch1: DacChannel<'d, T, 1, DMACh1>,
@@ -317,10 +498,57 @@ impl<'d, T: Instance, DMACh1, DMACh2> Dac<'d, T, DMACh1, DMACh2> {
         &mut self.ch2
     }
 
+    /// Enable or disable the output buffer of both channels.
+    pub fn set_output_buffer(&mut self, on: bool) {
+        self.ch1.set_output_buffer(on);
+        self.ch2.set_output_buffer(on);
+    }
+
+    /// Set the same trigger source on both channels and enable triggering.
+    ///
+    /// Both outputs then change on the same trigger event. This method disables the
+    /// channels, so you may need to re-enable them afterwards.
+    pub fn set_trigger(&mut self, source: TriggerSel) {
+        critical_section::with(|_| {
+            T::regs().cr().modify(|reg| {
+                for idx in 0..2 {
+                    reg.set_en(idx, false);
+                    reg.set_tsel(idx, source);
+                    reg.set_ten(idx, true);
+                }
+            });
+        });
+    }
+
+    /// Disable triggering on both channels, values are then output as soon as written.
+    pub fn disable_triggering(&mut self) {
+        critical_section::with(|_| {
+            T::regs().cr().modify(|reg| {
+                reg.set_ten(0, false);
+                reg.set_ten(1, false);
+            });
+        });
+    }
+
+    /// Enable both channels.
+    pub fn enable(&mut self) {
+        self.ch1.enable();
+        self.ch2.enable();
+    }
+
+    /// Software trigger both channels at once.
+    pub fn trigger(&mut self) {
+        T::regs().swtrigr().write(|reg| {
+            reg.set_swtrig(0, true);
+            reg.set_swtrig(1, true);
+        });
+    }
+
     /// Simultaneously update channels 1 and 2 with a new value.
     ///
     /// If triggering is not enabled, the new values are immediately output;
-    /// otherwise, they will be output after the next trigger.
+    /// otherwise, they will be output after the next trigger. Use [`Dac::set_trigger`] so
+    /// both outputs change on the same clock edge.
     pub fn set(&mut self, values: DualValue) {
         match values {
             DualValue::Bit8(v1, v2) => T::regs().dhr8rd().write(|reg| {
@@ -369,3 +597,15 @@ macro_rules! impl_dac_pin {
         impl crate::dac::DacPin<peripherals::$inst, $ch> for crate::peripherals::$pin {}
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dual_value_packed() {
+        assert_eq!(DualValue::Bit8(0x12, 0x34).packed(), 0x3412);
+        assert_eq!(DualValue::Bit12Right(0xFABC, 0x123).packed(), 0x0123_0ABC);
+        assert_eq!(DualValue::Bit12Left(0xABCF, 0x1230).packed(), 0x1230_ABC0);
+    }
+}
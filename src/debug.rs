@@ -27,6 +27,7 @@ impl SDIPrint {
         }
     }
 
+    #[cfg(not(feature = "usb-log"))]
     #[inline]
     fn is_busy() -> bool {
         unsafe { core::ptr::read_volatile(regs::DEBUG_DATA0_ADDRESS) != 0 }
@@ -35,20 +36,27 @@ impl SDIPrint {
 
 impl core::fmt::Write for SDIPrint {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        let mut data = [0u8; 8];
-        for chunk in s.as_bytes().chunks(7) {
-            data[1..chunk.len() + 1].copy_from_slice(chunk);
-            data[0] = chunk.len() as u8;
+        // Without a debugger attached, SDI would block forever.
+        #[cfg(feature = "usb-log")]
+        crate::usb::logger::write(s.as_bytes());
 
-            // data1 is the last 4 bytes of data
-            let data1 = u32::from_le_bytes(data[4..].try_into().unwrap());
-            let data0 = u32::from_le_bytes(data[..4].try_into().unwrap());
+        #[cfg(not(feature = "usb-log"))]
+        {
+            let mut data = [0u8; 8];
+            for chunk in s.as_bytes().chunks(7) {
+                data[1..chunk.len() + 1].copy_from_slice(chunk);
+                data[0] = chunk.len() as u8;
+
+                // data1 is the last 4 bytes of data
+                let data1 = u32::from_le_bytes(data[4..].try_into().unwrap());
+                let data0 = u32::from_le_bytes(data[..4].try_into().unwrap());
 
-            while SDIPrint::is_busy() {}
+                while SDIPrint::is_busy() {}
 
-            unsafe {
-                core::ptr::write_volatile(regs::DEBUG_DATA1_ADDRESS, data1);
-                core::ptr::write_volatile(regs::DEBUG_DATA0_ADDRESS, data0);
+                unsafe {
+                    core::ptr::write_volatile(regs::DEBUG_DATA1_ADDRESS, data1);
+                    core::ptr::write_volatile(regs::DEBUG_DATA0_ADDRESS, data0);
+                }
             }
         }
 
@@ -0,0 +1,81 @@
+//! DMA buffers.
+//!
+//! A DMA transfer keeps running when the future driving it is leaked, so buffers handed to
+//! long running transfers, circular ones in particular, should live for `'static`. The
+//! [`dma_buffer!`](crate::dma_buffer) macro allocates such a buffer once, word aligned so
+//! every transfer size can be used, optionally in a given linker section:
+//!
+//! ```ignore
+//! let rx: &'static mut DmaBuf<[u16; 256]> = ch32_hal::dma_buffer!([u16; 256] = [0; 256]).unwrap();
+//!
+//! // Parts with RAM regions the DMA can't reach: place it explicitly
+//! let tx = ch32_hal::dma_buffer!(#[link_section = ".data.dma"] [u8; 64] = [0; 64]).unwrap();
+//! ```
+//!
+//! The section must exist in the linker script of the application.
+
+use core::ops::{Deref, DerefMut};
+
+/// A buffer aligned to 4 bytes, suitable for DMA transfers of any word size.
+#[repr(C, align(4))]
+pub struct DmaBuf<T: ?Sized>(T);
+
+impl<T> DmaBuf<T> {
+    /// Wrap a buffer.
+    pub const fn new(buf: T) -> Self {
+        Self(buf)
+    }
+
+    /// Unwrap the buffer.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: ?Sized> Deref for DmaBuf<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> DerefMut for DmaBuf<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+#[doc(hidden)]
+pub fn take(taken: &core::sync::atomic::AtomicBool) -> bool {
+    use core::sync::atomic::Ordering;
+
+    // No atomic swap on the RV32EC cores
+    critical_section::with(|_| {
+        let was_taken = taken.load(Ordering::Relaxed);
+        taken.store(true, Ordering::Relaxed);
+        !was_taken
+    })
+}
+
+/// Allocate a `&'static mut DmaBuf<T>`, the first time the invocation is executed.
+///
+/// Returns `None` when the same invocation runs again, since the buffer is then already
+/// handed out. Attributes such as `#[link_section = "..."]` are applied to the static.
+#[macro_export]
+macro_rules! dma_buffer {
+    ($(#[$attr:meta])* $t:ty = $init:expr) => {{
+        const _: () = assert!(core::mem::size_of::<$t>() > 0, "DMA buffers can't be empty");
+
+        $(#[$attr])*
+        static mut BUF: $crate::dma::buffer::DmaBuf<$t> = $crate::dma::buffer::DmaBuf::new($init);
+        static TAKEN: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+        if $crate::dma::buffer::take(&TAKEN) {
+            // Safety: handed out only once
+            Some(unsafe { &mut *core::ptr::addr_of_mut!(BUF) })
+        } else {
+            None
+        }
+    }};
+}
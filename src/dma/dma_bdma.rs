@@ -1,7 +1,9 @@
 use core::cell::Cell;
 use core::future::{poll_fn, Future};
+#[cfg(debug_assertions)]
+use core::panic::Location;
 use core::pin::Pin;
-use core::sync::atomic::{compiler_fence, fence, AtomicUsize, Ordering};
+use core::sync::atomic::{compiler_fence, fence, AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use core::task::{Context, Poll, Waker};
 
 use embassy_sync::blocking_mutex::CriticalSectionMutex;
@@ -24,6 +26,8 @@ pub(crate) enum DmaInfo {
 }
 
 /// DMA transfer options.
+///
+/// The request priority is ignored on channels given a fixed one with [`Channel::set_priority`].
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
@@ -53,7 +57,27 @@ impl Default for TransferOptions {
     }
 }
 
+/// DMA transfer error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A bus error occurred while accessing the peripheral or memory address (TEIF),
+    /// usually because of an invalid address. The channel was disabled by the hardware.
+    TransferError,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TransferError => f.write_str("DMA transfer error"),
+        }
+    }
+}
+
 /// DMA request priority
+///
+/// When several channels of a controller have pending requests, the one with the highest
+/// priority is served first, and the lowest channel number among equal priorities.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Priority {
@@ -106,13 +130,59 @@ mod bdma_only {
 pub(crate) struct ChannelState {
     waker: AtomicWaker,
     complete_count: AtomicUsize,
+    half_count: AtomicUsize,
+    /// Length of the current transfer, as last written to CNTR
+    len: AtomicUsize,
+    paused: AtomicBool,
+    /// Set by a transfer error, until the channel is configured again
+    error: AtomicBool,
+    /// Remaining buffers of a [`TransferChain`]
+    chain: CriticalSectionMutex<Cell<Option<Chain>>>,
+    /// Fixed priority overriding the transfer options, 0 when unset
+    priority: AtomicU8,
+    /// Where the last transfer on the channel was created, to name both parties on a conflict
+    #[cfg(debug_assertions)]
+    owner: CriticalSectionMutex<Cell<Option<&'static Location<'static>>>>,
 }
 
 impl ChannelState {
     pub(crate) const NEW: Self = Self {
         waker: AtomicWaker::new(),
         complete_count: AtomicUsize::new(0),
+        half_count: AtomicUsize::new(0),
+        len: AtomicUsize::new(0),
+        paused: AtomicBool::new(false),
+        error: AtomicBool::new(false),
+        chain: CriticalSectionMutex::new(Cell::new(None)),
+        priority: AtomicU8::new(0),
+        #[cfg(debug_assertions)]
+        owner: CriticalSectionMutex::new(Cell::new(None)),
     };
+
+    pub(crate) fn set_priority(&self, priority: Option<Priority>) {
+        let raw = match priority {
+            None => 0,
+            Some(Priority::Low) => 1,
+            Some(Priority::Medium) => 2,
+            Some(Priority::High) => 3,
+            Some(Priority::VeryHigh) => 4,
+        };
+        self.priority.store(raw, Ordering::Relaxed);
+    }
+
+    pub(crate) fn priority(&self) -> Option<Priority> {
+        match self.priority.load(Ordering::Relaxed) {
+            1 => Some(Priority::Low),
+            2 => Some(Priority::Medium),
+            3 => Some(Priority::High),
+            4 => Some(Priority::VeryHigh),
+            _ => None,
+        }
+    }
+
+    fn priority_or(&self, priority: Priority) -> pac::dma::vals::Pl {
+        self.priority().unwrap_or(priority).into()
+    }
 }
 
 /// safety: must be called only once
@@ -136,16 +206,24 @@ impl AnyChannel {
                 let isr = r.isr().read();
                 let cr = r.ch(info.num).cr();
 
-                if isr.teif(info.num) {
-                    panic!("DMA: error on BDMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
-                }
-
-                if isr.htif(info.num) && cr.read().htie() {
+                if isr.teif(info.num) && cr.read().teie() {
+                    // The hardware already cleared EN, report the error to the transfer
+                    r.ifcr().write(|w| w.set_teif(info.num, true));
+                    cr.modify(|w| w.set_en(false));
+                    state.error.store(true, Ordering::Release);
+                } else if isr.htif(info.num) && cr.read().htie() {
                     // Acknowledge half transfer complete interrupt
                     r.ifcr().write(|w| w.set_htif(info.num, true));
+                    critical_section::with(|_| {
+                        let cnt = state.half_count.load(Ordering::Acquire);
+                        state.half_count.store(cnt + 1, Ordering::Release);
+                    });
                 } else if isr.tcif(info.num) && cr.read().tcie() {
                     // Acknowledge transfer complete interrupt
                     r.ifcr().write(|w| w.set_tcif(info.num, true));
+                    if self.start_next_segment() {
+                        return;
+                    }
                     critical_section::with(|_| {
                         // #safty this is okay because critical section ensures
                         // no interruption
@@ -161,6 +239,35 @@ impl AnyChannel {
         }
     }
 
+    /// Record the caller as the user of the channel.
+    ///
+    /// Two drivers wired to the same channel would otherwise silently overwrite each other's
+    /// transfer, so debug builds panic when a transfer is set up while another one is still
+    /// running, naming the code that started each of them.
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn claim(&self) {
+        #[cfg(debug_assertions)]
+        {
+            let state = &STATE[self.id as usize];
+            let caller = Location::caller();
+            if self.is_running() {
+                if let Some(owner) = state.owner.lock(|owner| owner.get()) {
+                    panic!(
+                        "DMA channel {} (index {}): transfer at {}:{} conflicts with the one still running from {}:{}",
+                        self.number(),
+                        self.index(),
+                        caller.file(),
+                        caller.line(),
+                        owner.file(),
+                        owner.line()
+                    );
+                }
+            }
+            state.owner.lock(|owner| owner.set(Some(caller)));
+        }
+    }
+
+    #[cfg_attr(debug_assertions, track_caller)]
     unsafe fn configure(
         &self,
         _request: Request,
@@ -172,6 +279,8 @@ impl AnyChannel {
         data_size: WordSize,
         options: TransferOptions,
     ) {
+        self.claim();
+
         let info = self.info();
 
         assert!(mem_len > 0 && mem_len <= 0xFFFF);
@@ -182,11 +291,16 @@ impl AnyChannel {
                 let ch = r.ch(info.num);
 
                 state.complete_count.store(0, Ordering::Release);
+                state.error.store(false, Ordering::Release);
+                state.half_count.store(0, Ordering::Release);
+                state.paused.store(false, Ordering::Release);
+                state.chain.lock(|chain| chain.set(None));
                 self.clear_irqs();
 
                 ch.par().write_value(peri_addr as u32); // PADDR
                 ch.mar().write_value(mem_addr as u32); // MADDR
                 ch.ndtr().write(|w| w.set_ndt(mem_len as u16)); // CNTR
+                state.len.store(mem_len, Ordering::Relaxed);
                 ch.cr().write(|w| {
                     w.set_psize(data_size.into());
                     w.set_msize(data_size.into());
@@ -196,13 +310,62 @@ impl AnyChannel {
                     w.set_tcie(options.complete_transfer_ir); // tx complete
                     w.set_htie(options.half_transfer_ir); // half
                     w.set_circ(options.circular); // circular
-                    w.set_pl(options.priority.into()); // priority
+                    w.set_pl(state.priority_or(options.priority)); // priority
                     w.set_en(false); // don't start yet
                 });
             }
         }
     }
 
+    #[cfg_attr(debug_assertions, track_caller)]
+    unsafe fn configure_copy(
+        &self,
+        src_addr: *const u32,
+        dst_addr: *mut u32,
+        len: usize,
+        incr_dst: bool,
+        data_size: WordSize,
+        options: TransferOptions,
+    ) {
+        self.claim();
+
+        let info = self.info();
+
+        assert!(len > 0 && len <= 0xFFFF);
+
+        match self.info().dma {
+            DmaInfo::Dma(r) => {
+                let state: &ChannelState = &STATE[self.id as usize];
+                let ch = r.ch(info.num);
+
+                state.complete_count.store(0, Ordering::Release);
+                state.error.store(false, Ordering::Release);
+                state.half_count.store(0, Ordering::Release);
+                state.paused.store(false, Ordering::Release);
+                state.chain.lock(|chain| chain.set(None));
+                self.clear_irqs();
+
+                // MEM2MEM reads from PADDR and writes to MADDR, starting as soon as enabled
+                ch.par().write_value(src_addr as u32);
+                ch.mar().write_value(dst_addr as u32);
+                ch.ndtr().write(|w| w.set_ndt(len as u16));
+                state.len.store(len, Ordering::Relaxed);
+                ch.cr().write(|w| {
+                    w.set_psize(data_size.into());
+                    w.set_msize(data_size.into());
+                    w.set_pinc(true);
+                    w.set_minc(incr_dst);
+                    w.set_dir(Dir::PeripheralToMemory.into());
+                    w.set_mem2mem(true);
+                    w.set_teie(true);
+                    w.set_tcie(true);
+                    w.set_pl(state.priority_or(options.priority));
+                    w.set_en(false);
+                });
+            }
+        }
+    }
+
     fn start(&self) {
         let info = self.info();
         match self.info().dma {
@@ -228,6 +391,8 @@ impl AnyChannel {
 
     fn request_stop(&self) {
         let info = self.info();
+        STATE[self.id as usize].chain.lock(|chain| chain.set(None));
+        STATE[self.id as usize].paused.store(false, Ordering::Release);
         match self.info().dma {
             DmaInfo::Dma(r) => {
                 // Disable the channel. Keep the IEs enabled so the irqs still fire.
@@ -248,11 +413,104 @@ impl AnyChannel {
                 let en = ch.cr().read().en();
                 let circular = ch.cr().read().circ();
                 let tcif = state.complete_count.load(Ordering::Acquire) != 0;
-                en && (circular || !tcif)
+                let paused = state.paused.load(Ordering::Acquire);
+                paused || (en && (circular || !tcif))
+            }
+        }
+    }
+
+    fn pause(&self) {
+        let info = self.info();
+        match self.info().dma {
+            DmaInfo::Dma(r) => {
+                // Resuming moves the addresses, which a circular transfer would reload with
+                // its whole length on the next wrap around.
+                assert!(
+                    !r.ch(info.num).cr().read().circ(),
+                    "circular DMA transfers can't be paused"
+                );
+                STATE[self.id as usize].paused.store(true, Ordering::Release);
+                r.ch(info.num).cr().modify(|w| w.set_en(false));
             }
         }
     }
 
+    fn resume(&self) {
+        let info = self.info();
+        let state: &ChannelState = &STATE[self.id as usize];
+        let paused = critical_section::with(|_| {
+            let paused = state.paused.load(Ordering::Acquire);
+            state.paused.store(false, Ordering::Release);
+            paused
+        });
+        if !paused {
+            return;
+        }
+
+        match self.info().dma {
+            DmaInfo::Dma(r) => {
+                let ch = r.ch(info.num);
+                let cr = ch.cr().read();
+                let remaining = ch.ndtr().read().ndt() as usize;
+                if remaining == 0 {
+                    return;
+                }
+
+                // Re-enabling restarts from the programmed addresses, move them past the
+                // words already transferred.
+                let done = (state.len.load(Ordering::Relaxed) - remaining) as u32;
+                if cr.minc() {
+                    let mar = ch.mar().read();
+                    ch.mar().write_value(mar + done * (1 << cr.msize().to_bits()));
+                }
+                if cr.pinc() {
+                    let par = ch.par().read();
+                    ch.par().write_value(par + done * (1 << cr.psize().to_bits()));
+                }
+                state.len.store(remaining, Ordering::Relaxed);
+                ch.cr().modify(|w| w.set_en(true));
+            }
+        }
+    }
+
+    /// Continue a [`TransferChain`] with its next buffer, returns false at the end of the chain.
+    unsafe fn start_next_segment(&self) -> bool {
+        let info = self.info();
+        STATE[self.id as usize].chain.lock(|chain| {
+            let Some(mut c) = chain.get() else {
+                return false;
+            };
+            if c.next == c.count {
+                chain.set(None);
+                return false;
+            }
+            let (addr, len) = (c.locate)(c.segments, c.next);
+            c.next += 1;
+            chain.set(Some(c));
+
+            match info.dma {
+                DmaInfo::Dma(r) => {
+                    // MADDR and CNTR can only be written while the channel is disabled
+                    let ch = r.ch(info.num);
+                    ch.cr().modify(|w| w.set_en(false));
+                    ch.mar().write_value(addr);
+                    ch.ndtr().write(|w| w.set_ndt(len as u16));
+                    STATE[self.id as usize].len.store(len, Ordering::Relaxed);
+                    ch.cr().modify(|w| w.set_en(true));
+                }
+            }
+            true
+        })
+    }
+
+    fn result(&self) -> Result<(), Error> {
+        if STATE[self.id as usize].error.load(Ordering::Acquire) {
+            Err(Error::TransferError)
+        } else {
+            Ok(())
+        }
+    }
+
     fn get_remaining_transfers(&self) -> u16 {
         let info = self.info();
         match self.info().dma {
@@ -280,14 +538,44 @@ impl AnyChannel {
     }
 }
 
+/// A peripheral register the DMA can transfer to or from.
+///
+/// Out of tree drivers create one for their data register, and can then start transfers
+/// with [`Transfer::new_read_from`] and [`Transfer::new_write_to`] without raw pointers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PeripheralRegister<W: Word> {
+    addr: *mut W,
+}
+
+impl<W: Word> PeripheralRegister<W> {
+    /// Create from the register address.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must be a memory mapped register accepting accesses of the size of `W`, that
+    /// can be read or written by the DMA at any time without breaking memory safety.
+    pub const unsafe fn new(addr: *mut W) -> Self {
+        Self { addr }
+    }
+
+    /// The register address.
+    pub fn addr(&self) -> *mut W {
+        self.addr
+    }
+}
+
 /// DMA transfer.
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Transfer<'a> {
     channel: PeripheralRef<'a, AnyChannel>,
+    /// Half transfer and transfer complete events already waited for
+    seen_half: usize,
+    seen_complete: usize,
 }
 
 impl<'a> Transfer<'a> {
     /// Create a new read DMA transfer (peripheral to memory).
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new_read<W: Word>(
         channel: impl Peripheral<P = impl Channel> + 'a,
         request: Request,
@@ -299,6 +587,7 @@ impl<'a> Transfer<'a> {
     }
 
     /// Create a new read DMA transfer (peripheral to memory), using raw pointers.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new_read_raw<W: Word>(
         channel: impl Peripheral<P = impl Channel> + 'a,
         request: Request,
@@ -325,6 +614,7 @@ impl<'a> Transfer<'a> {
     }
 
     /// Create a new write DMA transfer (memory to peripheral).
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new_write<W: Word>(
         channel: impl Peripheral<P = impl Channel> + 'a,
         request: Request,
@@ -336,6 +626,7 @@ impl<'a> Transfer<'a> {
     }
 
     /// Create a new write DMA transfer (memory to peripheral), using raw pointers.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new_write_raw<W: Word>(
         channel: impl Peripheral<P = impl Channel> + 'a,
         request: Request,
@@ -362,6 +653,7 @@ impl<'a> Transfer<'a> {
     }
 
     /// Create a new write DMA transfer (memory to peripheral), writing the same value repeatedly.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new_write_repeated<W: Word>(
         channel: impl Peripheral<P = impl Channel> + 'a,
         request: Request,
@@ -385,6 +677,115 @@ impl<'a> Transfer<'a> {
         )
     }
 
+    /// Create a new memory to memory DMA transfer.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_copy<W: Word>(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        src: &'a [W],
+        dst: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        assert_eq!(src.len(), dst.len());
+
+        Self::new_copy_raw(
+            channel,
+            src.as_ptr() as *const u32,
+            dst.as_mut_ptr() as *mut u32,
+            src.len(),
+            W::size(),
+            options,
+        )
+    }
+
+    /// Create a new memory to memory DMA transfer of `len` words of `data_size`, using raw pointers.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_copy_raw(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        src: *const u32,
+        dst: *mut u32,
+        len: usize,
+        data_size: WordSize,
+        options: TransferOptions,
+    ) -> Self {
+        into_ref!(channel);
+        let channel: PeripheralRef<'a, AnyChannel> = channel.map_into();
+
+        channel.configure_copy(src, dst, len, true, data_size, options);
+        channel.start();
+
+        Self::started(channel)
+    }
+
+    /// Create a new read DMA transfer from a peripheral register, paced by the peripheral's DMA request.
+    ///
+    /// The request line of each peripheral is wired to fixed channels, see the reference manual.
+    ///
+    /// # Safety
+    ///
+    /// The transfer must not be leaked with [`core::mem::forget`], the DMA would keep
+    /// accessing `buf` after its borrow ends.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_read_from<W: Word>(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        register: PeripheralRegister<W>,
+        buf: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_read(channel, (), register.addr, buf, options)
+    }
+
+    /// Create a new write DMA transfer to a peripheral register, paced by the peripheral's DMA request.
+    ///
+    /// The request line of each peripheral is wired to fixed channels, see the reference manual.
+    ///
+    /// # Safety
+    ///
+    /// The transfer must not be leaked with [`core::mem::forget`], the DMA would keep
+    /// accessing `buf` after its borrow ends.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_write_to<W: Word>(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        buf: &'a [W],
+        register: PeripheralRegister<W>,
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_write(channel, (), buf, register.addr, options)
+    }
+
+    /// Create a new write DMA transfer to a memory mapped register, as fast as the bus allows.
+    ///
+    /// This uses a memory to memory transfer without incrementing the destination, for
+    /// registers without a DMA request such as the data register of an LCD on the FSMC.
+    /// Any channel can be used.
+    ///
+    /// # Safety
+    ///
+    /// The transfer must not be leaked with [`core::mem::forget`], the DMA would keep
+    /// accessing `buf` after its borrow ends.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_write_to_unpaced<W: Word>(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        buf: &'a [W],
+        register: PeripheralRegister<W>,
+        options: TransferOptions,
+    ) -> Self {
+        into_ref!(channel);
+        let channel: PeripheralRef<'a, AnyChannel> = channel.map_into();
+
+        channel.configure_copy(
+            buf.as_ptr() as *const u32,
+            register.addr as *mut u32,
+            buf.len(),
+            false,
+            W::size(),
+            options,
+        );
+        channel.start();
+
+        Self::started(channel)
+    }
+
+    #[cfg_attr(debug_assertions, track_caller)]
     unsafe fn new_inner(
         channel: PeripheralRef<'a, AnyChannel>,
         _request: Request,
@@ -401,7 +802,15 @@ impl<'a> Transfer<'a> {
         );
         channel.start();
 
-        Self { channel }
+        Self::started(channel)
+    }
+
+    fn started(channel: PeripheralRef<'a, AnyChannel>) -> Self {
+        Self {
+            channel,
+            seen_half: 0,
+            seen_complete: 0,
+        }
     }
 
     /// Request the transfer to stop.
@@ -411,6 +820,77 @@ impl<'a> Transfer<'a> {
         self.channel.request_stop()
     }
 
+    /// Pause the transfer after the current word, keeping its position.
+    ///
+    /// A paused transfer still counts as running. Panics on circular transfers, which can
+    /// only be stopped.
+    pub fn pause(&mut self) {
+        self.channel.pause()
+    }
+
+    /// Resume a transfer paused with [`pause`](Self::pause).
+    pub fn resume(&mut self) {
+        self.channel.resume()
+    }
+
+    /// Wait until the first half of the buffer was transferred.
+    ///
+    /// Each call waits for the next half transfer event not waited for yet, so with a circular
+    /// transfer the first half can be processed while the DMA fills the second one. Enables
+    /// the half transfer interrupt if it was not requested in the options.
+    pub async fn wait_half(&mut self) -> Result<(), Error> {
+        let info = self.channel.info();
+        match info.dma {
+            DmaInfo::Dma(r) => r.ch(info.num).cr().modify(|w| w.set_htie(true)),
+        }
+
+        poll_fn(|cx| {
+            let state: &ChannelState = &STATE[self.channel.id as usize];
+            state.waker.register(cx.waker());
+
+            self.channel.result()?;
+            let count = state.half_count.load(Ordering::Acquire);
+            if count != self.seen_half {
+                self.seen_half = count;
+                Poll::Ready(Ok(()))
+            } else if !self.channel.is_running() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Wait until the whole buffer was transferred.
+    ///
+    /// Unlike awaiting the transfer itself, this also works with circular transfers, where
+    /// each call waits for the next wrap around not waited for yet.
+    pub async fn wait_complete(&mut self) -> Result<(), Error> {
+        poll_fn(|cx| {
+            let state: &ChannelState = &STATE[self.channel.id as usize];
+            state.waker.register(cx.waker());
+
+            self.channel.result()?;
+            let count = state.complete_count.load(Ordering::Acquire);
+            if count != self.seen_complete {
+                self.seen_complete = count;
+                Poll::Ready(Ok(()))
+            } else if !self.channel.is_running() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Number of half transfer and transfer complete interrupts since the transfer started.
+    pub(crate) fn event_count(&self) -> usize {
+        let state: &ChannelState = &STATE[self.channel.id as usize];
+        state.half_count.load(Ordering::Acquire) + state.complete_count.load(Ordering::Acquire)
+    }
+
     /// Return whether this transfer is still running.
     ///
     /// If this returns `false`, it can be because either the transfer finished, or
@@ -425,14 +905,28 @@ impl<'a> Transfer<'a> {
         self.channel.get_remaining_transfers()
     }
 
+    /// Set a waker to be woken on the next transfer interrupt.
+    ///
+    /// Useful with circular transfers, which never complete on their own.
+    pub fn set_waker(&mut self, waker: &Waker) {
+        STATE[self.channel.id as usize].waker.register(waker);
+    }
+
+    /// Whether the transfer was stopped by a transfer error.
+    pub fn is_error(&self) -> bool {
+        self.channel.result().is_err()
+    }
+
     /// Blocking wait until the transfer finishes.
-    pub fn blocking_wait(mut self) {
+    pub fn blocking_wait(mut self) -> Result<(), Error> {
         while self.is_running() {}
 
         // "Subsequent reads and writes cannot be moved ahead of preceding reads."
         fence(Ordering::SeqCst);
 
+        let result = self.channel.result();
         core::mem::forget(self);
+        result
     }
 }
 
@@ -448,7 +942,7 @@ impl<'a> Drop for Transfer<'a> {
 
 impl<'a> Unpin for Transfer<'a> {}
 impl<'a> Future for Transfer<'a> {
-    type Output = ();
+    type Output = Result<(), Error>;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let state: &ChannelState = &STATE[self.channel.id as usize];
 
@@ -457,11 +951,210 @@ impl<'a> Future for Transfer<'a> {
         if self.is_running() {
             Poll::Pending
         } else {
-            Poll::Ready(())
+            Poll::Ready(self.channel.result())
         }
     }
 }
 
+/// Copy `src` into `dst` with a memory to memory DMA transfer, without CPU load.
+///
+/// Any channel can copy memory. Panics if the lengths differ, are zero or exceed 65535 words.
+pub async fn copy<W: Word>(channel: impl Peripheral<P = impl Channel>, src: &[W], dst: &mut [W]) -> Result<(), Error> {
+    unsafe { Transfer::new_copy(channel, src, dst, TransferOptions::default()) }.await
+}
+
+/// Copy `src` into `dst` with a memory to memory DMA transfer, without CPU load.
+///
+/// The widest word size both buffers and their length are aligned to is used, so aligned
+/// buffers are copied four bytes per transfer. Panics if the lengths differ, are zero or
+/// need more than 65535 transfers.
+pub async fn copy_bytes(channel: impl Peripheral<P = impl Channel>, src: &[u8], dst: &mut [u8]) -> Result<(), Error> {
+    assert_eq!(src.len(), dst.len());
+
+    let align = src.as_ptr() as usize | dst.as_mut_ptr() as usize | src.len();
+    let data_size = if align & 0b11 == 0 {
+        WordSize::FourBytes
+    } else if align & 0b1 == 0 {
+        WordSize::TwoBytes
+    } else {
+        WordSize::OneByte
+    };
+
+    unsafe {
+        Transfer::new_copy_raw(
+            channel,
+            src.as_ptr() as *const u32,
+            dst.as_mut_ptr() as *mut u32,
+            src.len() / data_size.bytes(),
+            data_size,
+            TransferOptions::default(),
+        )
+    }
+    .await
+}
+
+#[derive(Clone, Copy)]
+struct Chain {
+    /// Pointer to the `&[W]` or `&mut [W]` buffers
+    segments: *const (),
+    count: usize,
+    next: usize,
+    /// Address and length of buffer `n`
+    locate: unsafe fn(*const (), usize) -> (u32, usize),
+}
+
+// Only dereferenced while the owning `TransferChain` is alive.
+unsafe impl Send for Chain {}
+
+unsafe fn locate_segment<W: Word>(segments: *const (), n: usize) -> (u32, usize) {
+    let segment: &[W] = *(segments as *const &[W]).add(n);
+    (segment.as_ptr() as u32, segment.len())
+}
+
+unsafe fn locate_segment_mut<W: Word>(segments: *const (), n: usize) -> (u32, usize) {
+    let segment: &&mut [W] = &*(segments as *const &mut [W]).add(n);
+    (segment.as_ptr() as u32, segment.len())
+}
+
+/// DMA transfer of several buffers one after the other, as one logical transfer.
+///
+/// The next buffer is started from the transfer complete interrupt, so a protocol header
+/// and its payload, or any list of non-contiguous buffers, can be sent to or received from
+/// a peripheral without CPU involvement in between. There is a short gap between two buffers
+/// while the channel is reprogrammed, the peripheral request just waits for it.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct TransferChain<'a> {
+    transfer: Transfer<'a>,
+    count: usize,
+}
+
+impl<'a> TransferChain<'a> {
+    /// Create a new chained write DMA transfer (memory to peripheral).
+    ///
+    /// Panics if `segments` is empty, or any buffer is empty or longer than 65535 words.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_write<W: Word>(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        request: Request,
+        segments: &'a [&'a [W]],
+        peri_addr: *mut W,
+        options: TransferOptions,
+    ) -> Self {
+        assert!(!segments.is_empty());
+        assert!(segments.iter().all(|s| !s.is_empty() && s.len() <= 0xFFFF));
+
+        Self::new_inner(
+            channel,
+            request,
+            Dir::MemoryToPeripheral,
+            peri_addr as *const u32,
+            segments.as_ptr() as *const (),
+            segments.len(),
+            locate_segment::<W>,
+            W::size(),
+            options,
+        )
+    }
+
+    /// Create a new chained read DMA transfer (peripheral to memory).
+    ///
+    /// Panics if `segments` is empty, or any buffer is empty or longer than 65535 words.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn new_read<W: Word>(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        request: Request,
+        peri_addr: *mut W,
+        segments: &'a mut [&'a mut [W]],
+        options: TransferOptions,
+    ) -> Self {
+        assert!(!segments.is_empty());
+        assert!(segments.iter().all(|s| !s.is_empty() && s.len() <= 0xFFFF));
+
+        Self::new_inner(
+            channel,
+            request,
+            Dir::PeripheralToMemory,
+            peri_addr as *const u32,
+            segments.as_ptr() as *const (),
+            segments.len(),
+            locate_segment_mut::<W>,
+            W::size(),
+            options,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(debug_assertions, track_caller)]
+    unsafe fn new_inner(
+        channel: impl Peripheral<P = impl Channel> + 'a,
+        request: Request,
+        dir: Dir,
+        peri_addr: *const u32,
+        segments: *const (),
+        count: usize,
+        locate: unsafe fn(*const (), usize) -> (u32, usize),
+        data_size: WordSize,
+        mut options: TransferOptions,
+    ) -> Self {
+        into_ref!(channel);
+        let channel: PeripheralRef<'a, AnyChannel> = channel.map_into();
+
+        // The chain is driven by the transfer complete interrupt
+        options.circular = false;
+        options.complete_transfer_ir = true;
+
+        let (addr, len) = locate(segments, 0);
+        channel.configure(request, dir, peri_addr, addr as *mut u32, len, true, data_size, options);
+        STATE[channel.id as usize].chain.lock(|chain| {
+            chain.set(Some(Chain {
+                segments,
+                count,
+                next: 1,
+                locate,
+            }))
+        });
+        channel.start();
+
+        Self {
+            transfer: Transfer::started(channel),
+            count,
+        }
+    }
+
+    /// Index of the buffer being transferred, equal to the number of buffers once done.
+    pub fn current_segment(&self) -> usize {
+        let chain = STATE[self.transfer.channel.id as usize].chain.lock(|chain| chain.get());
+        match chain {
+            Some(chain) => chain.next - 1,
+            None if self.transfer.channel.is_running() => self.count - 1,
+            None => self.count,
+        }
+    }
+
+    /// Request the transfer to stop, after the current word.
+    pub fn request_stop(&mut self) {
+        self.transfer.request_stop()
+    }
+
+    /// Return whether this transfer is still running.
+    pub fn is_running(&mut self) -> bool {
+        self.transfer.is_running()
+    }
+
+    /// Blocking wait until all buffers were transferred.
+    pub fn blocking_wait(self) -> Result<(), Error> {
+        self.transfer.blocking_wait()
+    }
+}
+
+impl<'a> Unpin for TransferChain<'a> {}
+impl<'a> Future for TransferChain<'a> {
+    type Output = Result<(), Error>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.transfer).poll(cx)
+    }
+}
+
 // ==============================
 
 struct DmaCtrlImpl<'a>(PeripheralRef<'a, AnyChannel>);
@@ -490,6 +1183,17 @@ impl<'a> DmaCtrl for DmaCtrlImpl<'a> {
 }
 
 /// Ringbuffer for receiving data using DMA circular mode.
+///
+/// The DMA channel keeps writing `peri_addr` into the buffer in circular mode, this keeps
+/// track of what was read and reports an [`OverrunError`] when unread data got overwritten.
+/// Custom peripheral drivers only need the data register address and a channel.
+///
+/// ```ignore
+/// let mut ring = unsafe { ReadableRingBuffer::new(p.DMA1_CH1, (), peri_addr, &mut BUF, Default::default()) };
+/// ring.start();
+/// let mut chunk = [0u16; 64];
+/// ring.read_exact(&mut chunk).await?;
+/// ```
 pub struct ReadableRingBuffer<'a, W: Word> {
     channel: PeripheralRef<'a, AnyChannel>,
     ringbuf: ReadableDmaRingBuffer<'a, W>,
@@ -497,6 +1201,7 @@ pub struct ReadableRingBuffer<'a, W: Word> {
 
 impl<'a, W: Word> ReadableRingBuffer<'a, W> {
     /// Create a new ring buffer.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new(
         channel: impl Peripheral<P = impl Channel> + 'a,
         _request: Request,
@@ -575,6 +1280,16 @@ impl<'a, W: Word> ReadableRingBuffer<'a, W> {
         self.ringbuf.cap()
     }
 
+    /// The number of elements available for immediate reading.
+    pub fn len(&mut self) -> Result<usize, OverrunError> {
+        self.ringbuf.len(&mut DmaCtrlImpl(self.channel.reborrow()))
+    }
+
+    /// Whether no elements are available for reading.
+    pub fn is_empty(&mut self) -> Result<bool, OverrunError> {
+        Ok(self.len()? == 0)
+    }
+
     /// Set a waker to be woken when at least one byte is received.
     pub fn set_waker(&mut self, waker: &Waker) {
         DmaCtrlImpl(self.channel.reborrow()).set_waker(waker);
@@ -626,6 +1341,10 @@ impl<'a, W: Word> Drop for ReadableRingBuffer<'a, W> {
 }
 
 /// Ringbuffer for writing data using DMA circular mode.
+///
+/// The DMA channel keeps sending the buffer to `peri_addr` in circular mode, this keeps
+/// track of the free space and reports an [`OverrunError`] when the DMA caught up with
+/// the written data.
 pub struct WritableRingBuffer<'a, W: Word> {
     channel: PeripheralRef<'a, AnyChannel>,
     ringbuf: WritableDmaRingBuffer<'a, W>,
@@ -633,6 +1352,7 @@ pub struct WritableRingBuffer<'a, W: Word> {
 
 impl<'a, W: Word> WritableRingBuffer<'a, W> {
     /// Create a new ring buffer.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub unsafe fn new(
         channel: impl Peripheral<P = impl Channel> + 'a,
         _request: Request,
@@ -12,12 +12,17 @@ mod dma_bdma;
 #[cfg(any(bdma, dma))]
 pub use dma_bdma::*;
 
+pub mod buffer;
 pub mod word;
 
 mod util;
 pub(crate) use util::*;
 
-pub(crate) mod ringbuffer;
+pub mod ringbuffer;
+pub use ringbuffer::OverrunError;
+
+mod pool;
+pub use pool::ChannelPool;
 
 /// "No DMA" placeholder.
 ///
@@ -60,6 +65,23 @@ pub trait Channel: SealedChannel + Peripheral<P = Self> + Into<AnyChannel> + 'st
     fn degrade(self) -> AnyChannel {
         AnyChannel { id: self.id() }
     }
+
+    /// Give all transfers on this channel a fixed request priority.
+    ///
+    /// Drivers pick the priority of their transfers themselves, usually the highest. Setting
+    /// it here, before handing the channel to a driver, lets a latency sensitive stream such
+    /// as audio win the arbitration against bulk transfers like logging. `None` restores
+    /// the priority chosen by the driver.
+    #[inline]
+    fn set_priority(&mut self, priority: Option<dma_bdma::Priority>) {
+        STATE[self.id() as usize].set_priority(priority);
+    }
+
+    /// The fixed request priority of this channel, if any.
+    #[inline]
+    fn priority(&self) -> Option<dma_bdma::Priority> {
+        STATE[self.id() as usize].priority()
+    }
 }
 
 macro_rules! dma_channel_impl {
@@ -94,6 +116,16 @@ impl AnyChannel {
     fn info(&self) -> &ChannelInfo {
         &crate::_generated::DMA_CHANNELS[self.id as usize]
     }
+
+    /// Index of the channel among all DMA channels of the chip, starting at 0 with `DMA1_CH1`.
+    pub fn index(&self) -> u8 {
+        self.id
+    }
+
+    /// Number of the channel within its DMA controller, starting at 1 as in the reference manual.
+    pub fn number(&self) -> u8 {
+        self.info().num as u8 + 1
+    }
 }
 
 impl SealedChannel for AnyChannel {
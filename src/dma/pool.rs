@@ -0,0 +1,52 @@
+//! Runtime DMA channel allocation.
+
+use super::{AnyChannel, Channel};
+
+/// A set of DMA channels handed out at runtime.
+///
+/// Board support code can hand its spare channels to a pool at init, and hand them out
+/// later from configuration, instead of hardcoding a channel type in every driver.
+/// Only drivers taking a type-erased channel can use them, such as [`copy`](super::copy) or
+/// raw [`Transfer`](super::Transfer)s; peripheral requests are wired to fixed channels.
+pub struct ChannelPool<const N: usize> {
+    channels: [Option<AnyChannel>; N],
+}
+
+impl<const N: usize> ChannelPool<N> {
+    /// Create a pool owning `channels`.
+    pub fn new(channels: [AnyChannel; N]) -> Self {
+        Self {
+            channels: channels.map(Some),
+        }
+    }
+
+    /// Take any free channel.
+    pub fn take(&mut self) -> Option<AnyChannel> {
+        self.channels.iter_mut().find_map(Option::take)
+    }
+
+    /// Take the channel with the given [`index`](AnyChannel::index), if it is free.
+    pub fn take_index(&mut self, index: u8) -> Option<AnyChannel> {
+        self.channels
+            .iter_mut()
+            .find(|ch| ch.as_ref().is_some_and(|ch| ch.index() == index))
+            .and_then(Option::take)
+    }
+
+    /// Give a channel back, making it available again.
+    ///
+    /// Panics if the pool is full, which means the channel was not taken from it.
+    pub fn give_back(&mut self, channel: impl Channel) {
+        let slot = self
+            .channels
+            .iter_mut()
+            .find(|ch| ch.is_none())
+            .expect("DMA channel pool is full");
+        *slot = Some(channel.degrade());
+    }
+
+    /// Number of free channels.
+    pub fn available(&self) -> usize {
+        self.channels.iter().filter(|ch| ch.is_some()).count()
+    }
+}
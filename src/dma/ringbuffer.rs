@@ -1,3 +1,10 @@
+//! Circular DMA buffer bookkeeping.
+//!
+//! These types only do the pointer math and overrun detection of a DMA channel running in
+//! circular mode over a buffer, the channel itself is reached through [`DmaCtrl`]. Use
+//! [`ReadableRingBuffer`](super::ReadableRingBuffer) and [`WritableRingBuffer`](super::WritableRingBuffer)
+//! for the DMA channels of this HAL, and these to stream through other DMA engines.
+
 #![cfg_attr(gpdma, allow(unused))]
 
 use core::future::poll_fn;
@@ -36,10 +43,18 @@ pub struct ReadableDmaRingBuffer<'a, W: Word> {
     start: usize,
 }
 
+/// The DMA overwrote data that was not read yet, or read data that was not written yet.
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct OverrunError;
 
+impl core::fmt::Display for OverrunError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("DMA ring buffer overrun")
+    }
+}
+
+/// Access to the DMA channel behind a ring buffer.
 pub trait DmaCtrl {
     /// Get the NDTR register value, i.e. the space left in the underlying
     /// buffer until the dma writer wraps.
@@ -78,6 +93,20 @@ impl<'a, W: Word> ReadableDmaRingBuffer<'a, W> {
         self.cap() - dma.get_remaining_transfers()
     }
 
+    /// The number of elements available for reading.
+    pub fn len(&mut self, dma: &mut impl DmaCtrl) -> Result<usize, OverrunError> {
+        let (end, complete_count) = critical_section::with(|_| (self.pos(dma), dma.get_complete_count()));
+
+        if complete_count > 1 || (complete_count == 1 && end > self.start) {
+            Err(OverrunError)
+        } else if complete_count == 0 && end >= self.start {
+            Ok(end - self.start)
+        } else {
+            // The writer wrapped, possibly before its interrupt was counted
+            Ok(self.cap() - self.start + end)
+        }
+    }
+
     /// Read an exact number of elements from the ringbuffer.
     ///
     /// Returns the remaining number of elements available for immediate reading.
@@ -2,7 +2,7 @@
 ///
 /// This module provides the time driver for the Embassy framework.
 
-#[cfg(all(qingke_v4, not(time_driver_timer)))]
+#[cfg(all(qingke_v4, not(time_driver_timer), not(time_driver_rtc)))]
 #[path = "time_driver_systick.rs"]
 pub mod time_driver_impl;
 
@@ -10,6 +10,10 @@ pub mod time_driver_impl;
 #[path = "time_driver_tim.rs"]
 pub mod time_driver_impl;
 
+#[cfg(time_driver_rtc)]
+#[path = "time_driver_rtc.rs"]
+pub mod time_driver_impl;
+
 /// Initialize the Embassy time driver.
 ///
 /// System global clocks must be initialized before calling this function.
@@ -27,9 +31,9 @@ pub unsafe fn init() {
     #[cfg(feature = "rt-wfi")]
     crate::pac::PFIC.sctlr().modify(|w| w.set_sevonpend(true));
 
-    #[cfg(all(qingke_v4, not(time_driver_timer)))]
+    #[cfg(all(qingke_v4, not(time_driver_timer), not(time_driver_rtc)))]
     time_driver_impl::init();
 
-    #[cfg(time_driver_timer)]
+    #[cfg(any(time_driver_timer, time_driver_rtc))]
     critical_section::with(|cs| time_driver_impl::init(cs));
 }
@@ -0,0 +1,290 @@
+//! Time driver implementation for the RTC counter.
+//!
+//! The RTC keeps counting in Stop mode when clocked from LSE or LSI, and its alarm wakes the
+//! core through EXTI line 17, so timers keep running across low power modes. The tick rate
+//! is the RTC clock divided by at least 2, e.g. `tick-hz-16_384` with a 32.768 kHz LSE.
+//!
+//! The 32-bit counter is extended to 64 bits with an overflow count. The overflow interrupt
+//! can't wake the core from Stop mode, so alarms further away than the current overflow period
+//! wake it every quarter of a period, and right after the overflow, to be armed again.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use core::{mem, ptr};
+
+use critical_section::CriticalSection;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time_driver::{AlarmHandle, Driver, TICK_HZ};
+
+use crate::pac;
+use crate::pac::EXTI;
+
+const ALARM_COUNT: usize = 1;
+
+/// EXTI line connected to the RTC alarm.
+const ALARM_EXTI_LINE: usize = 17;
+
+#[cfg(feature = "rt")]
+#[qingke_rt::interrupt]
+fn RTC() {
+    DRIVER.on_overflow()
+}
+
+#[cfg(feature = "rt")]
+#[qingke_rt::interrupt]
+fn RTCALARM() {
+    DRIVER.on_alarm()
+}
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+
+    // This is really a Option<(fn(*mut ()), *mut ())>
+    // but fn pointers aren't allowed in const yet
+    callback: Cell<*const ()>,
+    ctx: Cell<*mut ()>,
+}
+
+unsafe impl Send for AlarmState {}
+
+impl AlarmState {
+    const fn new() -> Self {
+        Self {
+            timestamp: Cell::new(u64::MAX),
+            callback: Cell::new(ptr::null()),
+            ctx: Cell::new(ptr::null_mut()),
+        }
+    }
+}
+
+pub(crate) struct RtcDriver {
+    /// Number of counter overflows, of 2^32 ticks each, since boot.
+    period: AtomicU32,
+    alarm_count: AtomicU8,
+    /// Timestamp at which to fire alarm. u64::MAX if no alarm is scheduled.
+    alarms: Mutex<CriticalSectionRawMutex, [AlarmState; ALARM_COUNT]>,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ALARM_STATE_NEW: AlarmState = AlarmState::new();
+
+embassy_time_driver::time_driver_impl!(static DRIVER: RtcDriver = RtcDriver {
+    period: AtomicU32::new(0),
+    alarm_count: AtomicU8::new(0),
+    alarms: Mutex::const_new(CriticalSectionRawMutex::new(), [ALARM_STATE_NEW; ALARM_COUNT]),
+});
+
+impl RtcDriver {
+    fn init(&'static self, _cs: CriticalSection) {
+        let rtc_freq = match crate::rcc::clocks().rtc {
+            Some(freq) => freq,
+            None => panic!("time-driver-rtc needs the RTC clock to be selected by the `ls` RCC config"),
+        };
+
+        // A reload value of 0 is not allowed, the RTC clock is divided by at least 2.
+        let prescaler = rtc_freq.0 / TICK_HZ as u32;
+        if prescaler < 2 || prescaler * TICK_HZ as u32 != rtc_freq.0 {
+            panic!(
+                "RTC clock of {} Hz can't be divided to the {} Hz tick rate",
+                rtc_freq.0, TICK_HZ
+            );
+        }
+
+        crate::rcc::enable_backup_domain();
+
+        pac::RTC.ctlrl().modify(|w| w.set_rsf(false));
+        while !pac::RTC.ctlrl().read().rsf() {}
+
+        write(|| {
+            let prescaler = prescaler - 1;
+            pac::RTC.psch().write(|w| w.set_prlh((prescaler >> 16) as u8));
+            pac::RTC.pscl().write(|w| w.set_prll(prescaler as u16));
+            pac::RTC.cnth().write(|w| w.set_cnth(0));
+            pac::RTC.cntl().write(|w| w.set_cntl(0));
+            pac::RTC.alrmh().write(|w| w.set_alrmh(u16::MAX));
+            pac::RTC.alrml().write(|w| w.set_alrml(u16::MAX));
+        });
+        pac::RTC.ctlrl().modify(|w| {
+            w.set_owf(false);
+            w.set_alrf(false);
+        });
+
+        // Overflows through the RTC global interrupt, alarms through EXTI to wake from Stop mode.
+        pac::RTC.ctlrh().write(|w| w.set_owie(true));
+        EXTI.rtenr().modify(|w| w.set_tr(ALARM_EXTI_LINE, true));
+        EXTI.intfr().write(|w| w.0 = 1 << ALARM_EXTI_LINE);
+        EXTI.intenr().modify(|w| w.set_mr(ALARM_EXTI_LINE, true));
+
+        unsafe {
+            qingke::pfic::enable_interrupt(pac::Interrupt::RTC as u8);
+            qingke::pfic::enable_interrupt(pac::Interrupt::RTCALARM as u8);
+        }
+    }
+
+    fn on_overflow(&self) {
+        critical_section::with(|cs| {
+            if pac::RTC.ctlrl().read().owf() {
+                pac::RTC.ctlrl().modify(|w| w.set_owf(false));
+                // We only modify the period from this interrupt, so we know this can't race.
+                let period = self.period.load(Ordering::Relaxed) + 1;
+                self.period.store(period, Ordering::Relaxed);
+
+                self.arm(cs);
+            }
+        })
+    }
+
+    fn on_alarm(&self) {
+        EXTI.intfr().write(|w| w.0 = 1 << ALARM_EXTI_LINE);
+        pac::RTC.ctlrl().modify(|w| w.set_alrf(false));
+
+        critical_section::with(|cs| {
+            let now = self.now();
+            for n in 0..ALARM_COUNT {
+                if self.alarms.borrow(cs)[n].timestamp.get() <= now {
+                    self.trigger_alarm(n, cs);
+                }
+            }
+
+            // Woken up to look again, or early.
+            self.arm(cs);
+        })
+    }
+
+    /// Program the compare register for the next alarm.
+    fn arm(&self, cs: CriticalSection) {
+        let now = self.now();
+        let at = self.alarms.borrow(cs)[0].timestamp.get();
+
+        let compare = if at >> 32 == now >> 32 {
+            at as u32
+        } else if at >> 32 == (now >> 32) + 1 && (at as u32) < now as u32 {
+            // The counter has to wrap before reaching it.
+            at as u32
+        } else {
+            // Wake up at the next quarter of the period to look again, or right after the overflow.
+            (now as u32 | 0x3FFF_FFFF).wrapping_add(1)
+        };
+
+        write(|| {
+            pac::RTC.alrmh().write(|w| w.set_alrmh((compare >> 16) as u16));
+            pac::RTC.alrml().write(|w| w.set_alrml(compare as u16));
+        });
+    }
+
+    fn get_alarm<'a>(&'a self, cs: CriticalSection<'a>, alarm: AlarmHandle) -> &'a AlarmState {
+        // safety: we're allowed to assume the AlarmState is created by us, and
+        // we never create one that's out of bounds.
+        unsafe { self.alarms.borrow(cs).get_unchecked(alarm.id() as usize) }
+    }
+
+    fn trigger_alarm(&self, n: usize, cs: CriticalSection) {
+        let alarm = &self.alarms.borrow(cs)[n];
+        alarm.timestamp.set(u64::MAX);
+
+        // Call after clearing alarm, so the callback can set another alarm.
+
+        // safety:
+        // - we can ignore the possibility of `f` being unset (null) because of the safety contract of `allocate_alarm`.
+        // - other than that we only store valid function pointers into alarm.callback
+        let f: fn(*mut ()) = unsafe { mem::transmute(alarm.callback.get()) };
+        f(alarm.ctx.get());
+    }
+}
+
+impl Driver for RtcDriver {
+    fn now(&self) -> u64 {
+        // The overflow interrupt can't run in between, so either it already counted the
+        // period, or the overflow flag is still pending.
+        critical_section::with(|_| {
+            let period = self.period.load(Ordering::Relaxed);
+            let counter = counter();
+            let pending = pac::RTC.ctlrl().read().owf();
+
+            // A counter read before the overflow is close to the end of the period.
+            let period = if pending && counter < 0x8000_0000 {
+                period + 1
+            } else {
+                period
+            };
+            ((period as u64) << 32) | counter as u64
+        })
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        critical_section::with(|_| {
+            let id = self.alarm_count.load(Ordering::Relaxed);
+            if id < ALARM_COUNT as u8 {
+                self.alarm_count.store(id + 1, Ordering::Relaxed);
+                Some(AlarmHandle::new(id))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+
+            alarm.callback.set(callback as *const ());
+            alarm.ctx.set(ctx);
+        })
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+            alarm.timestamp.set(timestamp);
+
+            if timestamp <= self.now() {
+                // If alarm timestamp has passed the alarm will not fire.
+                // Disarm the alarm and return `false` to indicate that.
+                alarm.timestamp.set(u64::MAX);
+                self.arm(cs);
+
+                return false;
+            }
+
+            self.arm(cs);
+
+            // Writing the compare register takes a few RTC clock cycles,
+            // reevaluate if the alarm timestamp is still in the future.
+            if timestamp <= self.now() {
+                // The alarm may or may not have fired.
+                // It is the caller's responsibility to handle this ambiguity.
+                alarm.timestamp.set(u64::MAX);
+
+                return false;
+            }
+
+            // We're confident the alarm will ring in the future.
+            true
+        })
+    }
+}
+
+fn counter() -> u32 {
+    loop {
+        // The two halves are read separately, retry if the low half wrapped in between.
+        let high = pac::RTC.cnth().read().cnth();
+        let low = pac::RTC.cntl().read().cntl();
+        if pac::RTC.cnth().read().cnth() == high {
+            return ((high as u32) << 16) | low as u32;
+        }
+    }
+}
+
+/// Write to the registers guarded by configuration mode.
+fn write(f: impl FnOnce()) {
+    while !pac::RTC.ctlrl().read().rtoff() {}
+    pac::RTC.ctlrl().modify(|w| w.set_cnf(true));
+    f();
+    pac::RTC.ctlrl().modify(|w| w.set_cnf(false));
+    while !pac::RTC.ctlrl().read().rtoff() {}
+}
+
+pub(crate) fn init(cs: CriticalSection) {
+    DRIVER.init(cs)
+}
@@ -0,0 +1,422 @@
+//! Ethernet MAC with the built-in 10BASE-T PHY of the CH32V307
+//!
+//! Frames go through DMA descriptor rings, with their buffers in a [`PacketQueue`], and
+//! [`Ethernet`] implements [`embassy_net_driver::Driver`] for `embassy-net`:
+//!
+//! ```rust,ignore
+//! bind_interrupts!(struct Irqs {
+//!     ETH => eth::InterruptHandler<peripherals::ETH>;
+//! });
+//!
+//! static PACKETS: StaticCell<PacketQueue<4, 4>> = StaticCell::new();
+//! let device = Ethernet::new(PACKETS.init(PacketQueue::new()), p.ETH, Irqs, mac_addr);
+//! let (stack, runner) = embassy_net::new(device, config, resources, seed);
+//! ```
+//!
+//! The PHY is on the dedicated TXP/TXN/RXP/RXN pins, wired to the RJ45 jack through its
+//! magnetics. The link state is read from the PHY on its link change interrupt, and every
+//! second in case one is missed, and the MAC follows the duplex mode negotiated.
+
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::Context;
+
+use embassy_net_driver::{Capabilities, HardwareAddress, LinkState};
+use embassy_sync::waitqueue::AtomicWaker;
+use embassy_time::{Duration, Timer};
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::eth::Eth;
+use crate::pac::EXTEND;
+use crate::{interrupt, into_ref, Peripheral, PeripheralRef, RccPeripheral};
+
+mod rx_desc;
+mod tx_desc;
+
+use rx_desc::{RDes, RDesRing};
+use tx_desc::{TDes, TDesRing};
+
+/// Largest frame, without its CRC.
+pub const MTU: usize = 1514;
+/// Size of the receive buffers, a whole frame with its CRC.
+pub const RX_BUFFER_SIZE: usize = 1536;
+/// Size of the transmit buffers.
+pub const TX_BUFFER_SIZE: usize = 1536;
+
+/// MII address of the built-in PHY.
+const PHY_ADDRESS: u8 = 1;
+
+// PHY registers
+const PHY_BCR: u8 = 0;
+const PHY_BSR: u8 = 1;
+const PHY_ANLPAR: u8 = 5;
+
+const PHY_BCR_RESET: u16 = 1 << 15;
+const PHY_BCR_AUTONEG: u16 = 1 << 12;
+const PHY_BSR_AUTONEG_COMPLETE: u16 = 1 << 5;
+const PHY_BSR_LINK: u16 = 1 << 2;
+const PHY_ANLPAR_10_FULL: u16 = 1 << 6;
+
+// DMASR bits, write 1 to clear
+const DMASR_TS: u32 = 1 << 0;
+const DMASR_RBUS: u32 = 1 << 7;
+const DMASR_RS: u32 = 1 << 6;
+const DMASR_AIS: u32 = 1 << 15;
+const DMASR_NIS: u32 = 1 << 16;
+/// Link change of the built-in PHY
+const DMASR_PLS: u32 = 1 << 31;
+
+/// How often the link state is read besides the link change interrupt.
+const LINK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        WAKER.wake();
+
+        let regs = T::regs();
+        let status = regs.dmasr().read().0;
+        regs.dmasr()
+            .write(|w| w.0 = status & (DMASR_TS | DMASR_RS | DMASR_RBUS | DMASR_AIS | DMASR_NIS | DMASR_PLS));
+    }
+}
+
+/// A frame buffer.
+#[repr(C, align(4))]
+pub struct Packet<const N: usize>([u8; N]);
+
+/// The descriptors and buffers of `TX` transmitted and `RX` received frames.
+///
+/// It has to be in RAM the DMA can reach, which is all of it on the CH32V307.
+pub struct PacketQueue<const TX: usize, const RX: usize> {
+    tx_desc: [TDes; TX],
+    rx_desc: [RDes; RX],
+    tx_buf: [Packet<TX_BUFFER_SIZE>; TX],
+    rx_buf: [Packet<RX_BUFFER_SIZE>; RX],
+}
+
+impl<const TX: usize, const RX: usize> PacketQueue<TX, RX> {
+    pub const fn new() -> Self {
+        const NEW_TDES: TDes = TDes::new();
+        const NEW_RDES: RDes = RDes::new();
+        const NEW_TX_BUF: Packet<TX_BUFFER_SIZE> = Packet([0; TX_BUFFER_SIZE]);
+        const NEW_RX_BUF: Packet<RX_BUFFER_SIZE> = Packet([0; RX_BUFFER_SIZE]);
+        Self {
+            tx_desc: [NEW_TDES; TX],
+            rx_desc: [NEW_RDES; RX],
+            tx_buf: [NEW_TX_BUF; TX],
+            rx_buf: [NEW_RX_BUF; RX],
+        }
+    }
+}
+
+/// Ethernet driver.
+pub struct Ethernet<'d, T: Instance> {
+    _peri: PeripheralRef<'d, T>,
+    tx: TDesRing<'d>,
+    rx: RDesRing<'d>,
+    mac_addr: [u8; 6],
+    link_up: bool,
+    link_poll: Timer,
+}
+
+impl<'d, T: Instance> Ethernet<'d, T> {
+    /// Start the MAC and the PHY, receiving frames for `mac_addr`, broadcasts and multicasts.
+    pub fn new<const TX: usize, const RX: usize>(
+        queue: &'d mut PacketQueue<TX, RX>,
+        peri: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        mac_addr: [u8; 6],
+    ) -> Self {
+        into_ref!(peri);
+
+        T::enable_and_reset();
+        crate::pac::RCC.ahbpcenr().modify(|w| {
+            w.set_ethmactxen(true);
+            w.set_ethmacrxen(true);
+        });
+        EXTEND.ctr().modify(|w| w.set_eth_10m_en(true));
+
+        let regs = T::regs();
+
+        // Reset the MAC and the DMA, which takes a few PHY clocks.
+        regs.dmabmr().modify(|w| w.set_sr(true));
+        while regs.dmabmr().read().sr() {}
+
+        regs.macmiiar().write(|w| w.set_cr(mdc_divider()));
+        mii_write(regs, PHY_BCR, PHY_BCR_RESET);
+        while mii_read(regs, PHY_BCR) & PHY_BCR_RESET != 0 {}
+        mii_write(regs, PHY_BCR, PHY_BCR_AUTONEG);
+
+        // 10 Mbps, the duplex mode follows the link.
+        regs.maccr().write(|w| {
+            w.set_fes(false);
+            w.set_ipco(false);
+        });
+        regs.macffr().write(|w| w.set_pam(true));
+
+        regs.maca0hr()
+            .write(|w| w.0 = (1 << 31) | (mac_addr[5] as u32) << 8 | mac_addr[4] as u32);
+        regs.maca0lr()
+            .write_value(u32::from_le_bytes([mac_addr[0], mac_addr[1], mac_addr[2], mac_addr[3]]));
+
+        regs.dmabmr().write(|w| {
+            w.set_aab(true);
+            w.set_pbl(32);
+        });
+
+        let tx = TDesRing::new(regs, &mut queue.tx_desc, &mut queue.tx_buf);
+        let rx = RDesRing::new(regs, &mut queue.rx_desc, &mut queue.rx_buf);
+
+        // Whole frames in and out of the FIFOs, so they're checked before being forwarded.
+        regs.dmaomr().write(|w| {
+            w.set_tsf(true);
+            w.set_rsf(true);
+        });
+        regs.dmaier()
+            .write(|w| w.0 = DMASR_TS | DMASR_RS | DMASR_RBUS | DMASR_AIS | DMASR_NIS | DMASR_PLS);
+
+        regs.maccr().modify(|w| {
+            w.set_te(true);
+            w.set_re(true);
+        });
+        regs.dmaomr().modify(|w| {
+            w.set_st(true);
+            w.set_sr(true);
+        });
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        Self {
+            _peri: peri,
+            tx,
+            rx,
+            mac_addr,
+            link_up: false,
+            link_poll: Timer::after(Duration::from_ticks(0)),
+        }
+    }
+
+    /// The MAC address.
+    pub fn mac_addr(&self) -> [u8; 6] {
+        self.mac_addr
+    }
+
+    /// Read register `reg` of the PHY.
+    pub fn phy_read(&mut self, reg: u8) -> u16 {
+        mii_read(T::regs(), reg)
+    }
+
+    /// Write register `reg` of the PHY.
+    pub fn phy_write(&mut self, reg: u8, value: u16) {
+        mii_write(T::regs(), reg, value)
+    }
+
+    fn poll_link(&mut self) -> bool {
+        let regs = T::regs();
+        let bsr = mii_read(regs, PHY_BSR);
+        // The link bit latches low, the second read is the current state.
+        let bsr = if bsr & PHY_BSR_LINK == 0 {
+            mii_read(regs, PHY_BSR)
+        } else {
+            bsr
+        };
+        let link_up = bsr & PHY_BSR_LINK != 0;
+
+        if link_up && !self.link_up {
+            let full_duplex =
+                bsr & PHY_BSR_AUTONEG_COMPLETE != 0 && mii_read(regs, PHY_ANLPAR) & PHY_ANLPAR_10_FULL != 0;
+            trace!("eth: link up, full duplex {}", full_duplex);
+            regs.maccr().modify(|w| w.set_dm(full_duplex));
+        } else if !link_up && self.link_up {
+            trace!("eth: link down");
+        }
+        self.link_up = link_up;
+        link_up
+    }
+}
+
+impl<'d, T: Instance> Drop for Ethernet<'d, T> {
+    fn drop(&mut self) {
+        let regs = T::regs();
+
+        T::Interrupt::disable();
+        regs.dmaomr().modify(|w| w.set_st(false));
+        regs.maccr().modify(|w| {
+            w.set_te(false);
+            w.set_re(false);
+        });
+        regs.dmaomr().modify(|w| w.set_sr(false));
+        EXTEND.ctr().modify(|w| w.set_eth_10m_en(false));
+    }
+}
+
+impl<'d, T: Instance> embassy_net_driver::Driver for Ethernet<'d, T> {
+    type RxToken<'a>
+        = RxToken<'a, 'd, T>
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = TxToken<'a, 'd, T>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, cx: &mut Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        WAKER.register(cx.waker());
+        let regs = T::regs();
+        if self.rx.available(regs).is_some() && self.tx.available().is_some() {
+            Some((
+                RxToken {
+                    rx: &mut self.rx,
+                    _phantom: PhantomData,
+                },
+                TxToken {
+                    tx: &mut self.tx,
+                    _phantom: PhantomData,
+                },
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn transmit(&mut self, cx: &mut Context) -> Option<Self::TxToken<'_>> {
+        WAKER.register(cx.waker());
+        if self.tx.available().is_some() {
+            Some(TxToken {
+                tx: &mut self.tx,
+                _phantom: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let mut caps = Capabilities::default();
+        caps.max_transmission_unit = MTU;
+        caps.max_burst_size = Some(self.tx.capacity().min(self.rx.capacity()));
+        caps
+    }
+
+    fn link_state(&mut self, cx: &mut Context) -> LinkState {
+        WAKER.register(cx.waker());
+        if Pin::new(&mut self.link_poll).poll(cx).is_ready() {
+            self.link_poll = Timer::after(LINK_POLL_INTERVAL);
+            let _ = Pin::new(&mut self.link_poll).poll(cx);
+        }
+
+        if self.poll_link() {
+            LinkState::Up
+        } else {
+            LinkState::Down
+        }
+    }
+
+    fn hardware_address(&self) -> HardwareAddress {
+        HardwareAddress::Ethernet(self.mac_addr)
+    }
+}
+
+/// A received frame.
+pub struct RxToken<'a, 'd, T: Instance> {
+    rx: &'a mut RDesRing<'d>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, 'd, T: Instance> embassy_net_driver::RxToken for RxToken<'a, 'd, T> {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let regs = T::regs();
+        let len = unwrap!(self.rx.available(regs));
+        let r = f(&mut self.rx.buffer()[..len]);
+        self.rx.pop(regs);
+        r
+    }
+}
+
+/// A frame to transmit.
+pub struct TxToken<'a, 'd, T: Instance> {
+    tx: &'a mut TDesRing<'d>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, 'd, T: Instance> embassy_net_driver::TxToken for TxToken<'a, 'd, T> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let buffer = unwrap!(self.tx.available());
+        let r = f(&mut buffer[..len]);
+        self.tx.transmit(T::regs(), len);
+        r
+    }
+}
+
+/// MDC clock divider for HCLK, keeping MDC at 2.5 MHz at most.
+fn mdc_divider() -> u8 {
+    match crate::rcc::clocks().hclk.0 {
+        0..=35_000_000 => 0b010,           // HCLK/16
+        35_000_001..=60_000_000 => 0b011,  // HCLK/26
+        60_000_001..=100_000_000 => 0b000, // HCLK/42
+        _ => 0b001,                        // HCLK/62
+    }
+}
+
+fn mii_read(regs: Eth, reg: u8) -> u16 {
+    while regs.macmiiar().read().mb() {}
+    regs.macmiiar().modify(|w| {
+        w.set_pa(PHY_ADDRESS);
+        w.set_mr(reg);
+        w.set_mw(false);
+        w.set_mb(true);
+    });
+    while regs.macmiiar().read().mb() {}
+    regs.macmiidr().read().md()
+}
+
+fn mii_write(regs: Eth, reg: u8, value: u16) {
+    while regs.macmiiar().read().mb() {}
+    regs.macmiidr().write(|w| w.set_md(value));
+    regs.macmiiar().modify(|w| {
+        w.set_pa(PHY_ADDRESS);
+        w.set_mr(reg);
+        w.set_mw(true);
+        w.set_mb(true);
+    });
+    while regs.macmiiar().read().mb() {}
+}
+
+trait SealedInstance {
+    fn regs() -> Eth;
+}
+
+/// Ethernet peripheral instance.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + RccPeripheral + 'static {
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+foreach_interrupt!(
+    ($inst:ident, eth, ETH, GLOBAL, $irq:ident) => {
+        impl SealedInstance for crate::peripherals::$inst {
+            fn regs() -> Eth {
+                crate::pac::$inst
+            }
+        }
+
+        impl Instance for crate::peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+    };
+);
@@ -0,0 +1,126 @@
+use core::sync::atomic::{fence, Ordering};
+
+use super::{Packet, RX_BUFFER_SIZE};
+use crate::pac::eth::Eth;
+
+/// Owned by the DMA
+const RDES0_OWN: u32 = 1 << 31;
+/// Error summary
+const RDES0_ES: u32 = 1 << 15;
+/// First and last descriptor of the frame
+const RDES0_FS: u32 = 1 << 9;
+const RDES0_LS: u32 = 1 << 8;
+const RDES0_FL_SHIFT: u32 = 16;
+const RDES0_FL_MASK: u32 = 0x3FFF;
+
+/// Receive end of ring
+const RDES1_RER: u32 = 1 << 15;
+const RDES1_RBS1_MASK: u32 = 0x1FFF;
+
+/// RX DMA descriptor, in ring mode.
+#[repr(C)]
+pub(crate) struct RDes {
+    rdes0: u32,
+    rdes1: u32,
+    rdes2: u32,
+    rdes3: u32,
+}
+
+impl RDes {
+    pub const fn new() -> Self {
+        Self {
+            rdes0: 0,
+            rdes1: 0,
+            rdes2: 0,
+            rdes3: 0,
+        }
+    }
+
+    fn status(&self) -> u32 {
+        unsafe { core::ptr::read_volatile(&self.rdes0) }
+    }
+
+    /// Hand the descriptor, and its buffer, to the DMA.
+    fn give_to_dma(&mut self) {
+        // The buffer must be read before the DMA can overwrite it.
+        fence(Ordering::Release);
+        unsafe { core::ptr::write_volatile(&mut self.rdes0, RDES0_OWN) };
+        fence(Ordering::SeqCst);
+    }
+
+    fn setup(&mut self, buffer: &mut Packet<RX_BUFFER_SIZE>, last: bool) {
+        let rdes1 = (RX_BUFFER_SIZE as u32 & RDES1_RBS1_MASK) | if last { RDES1_RER } else { 0 };
+        unsafe {
+            core::ptr::write_volatile(&mut self.rdes1, rdes1);
+            core::ptr::write_volatile(&mut self.rdes2, buffer.0.as_mut_ptr() as u32);
+            core::ptr::write_volatile(&mut self.rdes3, 0);
+        }
+        self.give_to_dma();
+    }
+}
+
+/// Ring of RX descriptors, each with its buffer.
+pub(crate) struct RDesRing<'a> {
+    descriptors: &'a mut [RDes],
+    buffers: &'a mut [Packet<RX_BUFFER_SIZE>],
+    index: usize,
+}
+
+impl<'a> RDesRing<'a> {
+    pub(crate) fn new(regs: Eth, descriptors: &'a mut [RDes], buffers: &'a mut [Packet<RX_BUFFER_SIZE>]) -> Self {
+        assert!(descriptors.len() > 1);
+        assert_eq!(descriptors.len(), buffers.len());
+
+        let last = descriptors.len() - 1;
+        for (i, (desc, buffer)) in descriptors.iter_mut().zip(buffers.iter_mut()).enumerate() {
+            desc.setup(buffer, i == last);
+        }
+        regs.dmardlar().write_value(descriptors.as_ptr() as u32);
+
+        Self {
+            descriptors,
+            buffers,
+            index: 0,
+        }
+    }
+
+    /// Number of frames the ring holds.
+    pub(crate) fn capacity(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    /// The length of the next received frame, without its CRC, dropping bad frames.
+    pub(crate) fn available(&mut self, regs: Eth) -> Option<usize> {
+        loop {
+            let status = self.descriptors[self.index].status();
+            if status & RDES0_OWN != 0 {
+                // Let the DMA resume if it ran out of descriptors.
+                regs.dmarpdr().write_value(1);
+                return None;
+            }
+            // The frame must be read after its status.
+            fence(Ordering::Acquire);
+
+            let whole = status & (RDES0_FS | RDES0_LS) == RDES0_FS | RDES0_LS;
+            let len = ((status >> RDES0_FL_SHIFT) & RDES0_FL_MASK) as usize;
+            if whole && status & RDES0_ES == 0 && len > 4 && len <= RX_BUFFER_SIZE {
+                return Some(len - 4);
+            }
+
+            trace!("eth: dropping a bad frame, status {:08x}", status);
+            self.pop(regs);
+        }
+    }
+
+    /// The buffer of the next received frame, once [`available`](Self::available).
+    pub(crate) fn buffer(&mut self) -> &mut [u8] {
+        &mut self.buffers[self.index].0
+    }
+
+    /// Give the buffer of the frame back to the DMA.
+    pub(crate) fn pop(&mut self, regs: Eth) {
+        self.descriptors[self.index].give_to_dma();
+        self.index = (self.index + 1) % self.descriptors.len();
+        regs.dmarpdr().write_value(1);
+    }
+}
@@ -0,0 +1,107 @@
+use core::sync::atomic::{fence, Ordering};
+
+use super::{Packet, TX_BUFFER_SIZE};
+use crate::pac::eth::Eth;
+
+/// Owned by the DMA
+const TDES0_OWN: u32 = 1 << 31;
+/// Interrupt on completion
+const TDES0_IC: u32 = 1 << 30;
+/// Last and first segment of the frame
+const TDES0_LS: u32 = 1 << 29;
+const TDES0_FS: u32 = 1 << 28;
+/// Transmit end of ring
+const TDES0_TER: u32 = 1 << 21;
+
+const TDES1_TBS1_MASK: u32 = 0x1FFF;
+
+/// TX DMA descriptor, in ring mode.
+#[repr(C)]
+pub(crate) struct TDes {
+    tdes0: u32,
+    tdes1: u32,
+    tdes2: u32,
+    tdes3: u32,
+}
+
+impl TDes {
+    pub const fn new() -> Self {
+        Self {
+            tdes0: 0,
+            tdes1: 0,
+            tdes2: 0,
+            tdes3: 0,
+        }
+    }
+
+    fn owned_by_dma(&self) -> bool {
+        unsafe { core::ptr::read_volatile(&self.tdes0) & TDES0_OWN != 0 }
+    }
+
+    fn setup(&mut self, buffer: &mut Packet<TX_BUFFER_SIZE>, last: bool) {
+        unsafe {
+            core::ptr::write_volatile(&mut self.tdes0, if last { TDES0_TER } else { 0 });
+            core::ptr::write_volatile(&mut self.tdes1, 0);
+            core::ptr::write_volatile(&mut self.tdes2, buffer.0.as_mut_ptr() as u32);
+            core::ptr::write_volatile(&mut self.tdes3, 0);
+        }
+    }
+}
+
+/// Ring of TX descriptors, each with its buffer.
+pub(crate) struct TDesRing<'a> {
+    descriptors: &'a mut [TDes],
+    buffers: &'a mut [Packet<TX_BUFFER_SIZE>],
+    index: usize,
+}
+
+impl<'a> TDesRing<'a> {
+    pub(crate) fn new(regs: Eth, descriptors: &'a mut [TDes], buffers: &'a mut [Packet<TX_BUFFER_SIZE>]) -> Self {
+        assert!(descriptors.len() > 1);
+        assert_eq!(descriptors.len(), buffers.len());
+
+        let last = descriptors.len() - 1;
+        for (i, (desc, buffer)) in descriptors.iter_mut().zip(buffers.iter_mut()).enumerate() {
+            desc.setup(buffer, i == last);
+        }
+        regs.dmatdlar().write_value(descriptors.as_ptr() as u32);
+
+        Self {
+            descriptors,
+            buffers,
+            index: 0,
+        }
+    }
+
+    /// Number of frames the ring holds.
+    pub(crate) fn capacity(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    /// The buffer for the next frame, if the DMA is done with it.
+    pub(crate) fn available(&mut self) -> Option<&mut [u8]> {
+        if self.descriptors[self.index].owned_by_dma() {
+            return None;
+        }
+        Some(&mut self.buffers[self.index].0)
+    }
+
+    /// Send the first `len` bytes of the buffer, once [`available`](Self::available).
+    pub(crate) fn transmit(&mut self, regs: Eth, len: usize) {
+        assert!(len <= TX_BUFFER_SIZE);
+
+        let last = self.index == self.descriptors.len() - 1;
+        let desc = &mut self.descriptors[self.index];
+        unsafe { core::ptr::write_volatile(&mut desc.tdes1, len as u32 & TDES1_TBS1_MASK) };
+
+        // The frame must be written before the DMA owns it.
+        fence(Ordering::Release);
+        let tdes0 = TDES0_OWN | TDES0_IC | TDES0_FS | TDES0_LS | if last { TDES0_TER } else { 0 };
+        unsafe { core::ptr::write_volatile(&mut desc.tdes0, tdes0) };
+        fence(Ordering::SeqCst);
+
+        self.index = (self.index + 1) % self.descriptors.len();
+        // Resume the DMA if it's suspended, out of frames.
+        regs.dmatpdr().write_value(1);
+    }
+}
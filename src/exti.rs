@@ -16,10 +16,12 @@ static EXTI_WAKERS: [AtomicWaker; EXTI_COUNT] = [NEW_AW; EXTI_COUNT];
 pub unsafe fn on_irq() {
     let exti = &crate::pac::EXTI;
 
-    let bits = exti.intfr().read();
-
-    // We don't handle or change any EXTI lines above 24.
-    let bits = bits.0 & 0x00FFFFFF;
+    // Only handle lines armed by a future. Lines above 24 are never touched, and
+    // pending flags of lines without an enabled interrupt are left to their owner.
+    let bits = exti.intfr().read().0 & exti.intenr().read().0 & 0x00FFFFFF;
+    if bits == 0 {
+        return;
+    }
 
     // Clear pending - Clears the EXTI's line pending bits.
     exti.intfr().write(|w| w.0 = bits);
@@ -49,6 +51,10 @@ impl Iterator for BitIter {
 }
 
 /// EXTI input driver
+///
+/// Waits for pin levels and edges asynchronously, using the EXTI line with the same number as the pin.
+/// The EXTI channel is checked at compile time for concrete pins, lines 5 to 9 and 10 to 15 share
+/// interrupt handlers on some chips, which is handled internally.
 pub struct ExtiInput<'d> {
     pin: Input<'d>,
 }
@@ -56,6 +62,9 @@ pub struct ExtiInput<'d> {
 impl<'d> Unpin for ExtiInput<'d> {}
 
 impl<'d> ExtiInput<'d> {
+    /// Create an EXTI input on `pin`, with its EXTI channel `ch`, e.g. `PA5` with `EXTI5`.
+    ///
+    /// With type erased pins and channels, panics if the pin and channel numbers differ.
     pub fn new<T: GpioPin>(
         pin: impl Peripheral<P = T> + 'd,
         ch: impl Peripheral<P = T::ExtiChannel> + 'd,
@@ -70,18 +79,42 @@ impl<'d> ExtiInput<'d> {
         }
     }
 
+    /// Get whether the pin input level is high.
     pub fn is_high(&self) -> bool {
         self.pin.is_high()
     }
 
+    /// Get whether the pin input level is low.
     pub fn is_low(&self) -> bool {
         self.pin.is_low()
     }
 
+    /// Get the current pin input level.
     pub fn get_level(&self) -> Level {
         self.pin.get_level()
     }
 
+    /// Generate a wakeup event on the selected edges of the pin, see [`enable_event`].
+    ///
+    /// The pin keeps generating events until [`disable_wakeup_event`](Self::disable_wakeup_event),
+    /// also while no wait future is pending. The edges of a pending wait apply to both, the
+    /// event edges are restored once it completes or is dropped.
+    pub fn enable_wakeup_event(&mut self, rising: bool, falling: bool) {
+        let pin = self.pin.pin.pin.pin();
+        let port = self.pin.pin.pin.port();
+        critical_section::with(|_| {
+            route_pin(pin, port);
+            set_edges(pin, rising, falling);
+        });
+        enable_event(pin);
+    }
+
+    /// Stop generating wakeup events.
+    pub fn disable_wakeup_event(&mut self) {
+        disable_event(self.pin.pin.pin.pin());
+    }
+
+    /// Wait until the pin is high, returning immediately if it already is.
     pub async fn wait_for_high<'a>(&'a mut self) {
         let fut = ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), true, false);
         if self.is_high() {
@@ -90,6 +123,7 @@ impl<'d> ExtiInput<'d> {
         fut.await
     }
 
+    /// Wait until the pin is low, returning immediately if it already is.
     pub async fn wait_for_low<'a>(&'a mut self) {
         let fut = ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), false, true);
         if self.is_low() {
@@ -98,71 +132,289 @@ impl<'d> ExtiInput<'d> {
         fut.await
     }
 
+    /// Wait for a rising edge.
     pub async fn wait_for_rising_edge<'a>(&'a mut self) {
         ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), true, false).await
     }
 
+    /// Wait for a falling edge.
     pub async fn wait_for_falling_edge<'a>(&'a mut self) {
         ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), false, true).await
     }
 
+    /// Wait for a rising or falling edge.
     pub async fn wait_for_any_edge<'a>(&'a mut self) {
         ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), true, true).await
     }
 }
 
+#[cfg(feature = "embassy")]
+impl<'d> ExtiInput<'d> {
+    /// Wait for the pin to change level and then stay stable for `stable`, filtering out
+    /// contact bounce. Returns the new level.
+    ///
+    /// The level at the time of the call is the reference, see [`Debouncer`] to track
+    /// the debounced level across calls.
+    pub async fn wait_for_debounced_edge(&mut self, stable: embassy_time::Duration) -> Level {
+        let level = self.get_level();
+        self.wait_for_debounced_change(level, stable).await
+    }
+
+    async fn wait_for_debounced_change(&mut self, from: Level, stable: embassy_time::Duration) -> Level {
+        use embassy_futures::select::{select, Either};
+
+        loop {
+            // Armed before the level is checked, so an edge in between isn't lost.
+            let edge = ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), true, true);
+            if self.get_level() == from {
+                edge.await;
+            } else {
+                drop(edge);
+            }
+
+            // Restart the stability timer on every edge.
+            loop {
+                if let Either::First(_) = select(embassy_time::Timer::after(stable), self.wait_for_any_edge()).await {
+                    break;
+                }
+            }
+
+            let level = self.get_level();
+            if level != from {
+                return level;
+            }
+        }
+    }
+}
+
+/// Debounced button or switch input.
+///
+/// ```rust,ignore
+/// let button = ExtiInput::new(p.PA0, p.EXTI0, Pull::Up);
+/// let mut button = Debouncer::new(button, Duration::from_millis(20));
+/// loop {
+///     button.wait_for_low().await;
+///     // pressed
+/// }
+/// ```
+#[cfg(feature = "embassy")]
+pub struct Debouncer<'d> {
+    input: ExtiInput<'d>,
+    stable: embassy_time::Duration,
+    level: Level,
+}
+
+#[cfg(feature = "embassy")]
+impl<'d> Debouncer<'d> {
+    /// Debounce `input`, a level change is reported once the pin is stable for `stable`.
+    pub fn new(input: ExtiInput<'d>, stable: embassy_time::Duration) -> Self {
+        let level = input.get_level();
+        Self { input, stable, level }
+    }
+
+    /// The current debounced level.
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    /// Wait for the debounced level to change, and return the new level.
+    pub async fn wait_for_change(&mut self) -> Level {
+        self.level = self.input.wait_for_debounced_change(self.level, self.stable).await;
+        self.level
+    }
+
+    /// Wait until the debounced level is high, returning immediately if it already is.
+    pub async fn wait_for_high(&mut self) {
+        while self.level != Level::High {
+            self.wait_for_change().await;
+        }
+    }
+
+    /// Wait until the debounced level is low, returning immediately if it already is.
+    pub async fn wait_for_low(&mut self) {
+        while self.level != Level::Low {
+            self.wait_for_change().await;
+        }
+    }
+
+    /// Release the input.
+    pub fn release(self) -> ExtiInput<'d> {
+        self.input
+    }
+}
+
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-struct ExtiInputFuture<'a> {
+pub(crate) struct ExtiInputFuture<'a> {
     pin: u8,
+    /// Edges of the wakeup event on the line, to restore after the wait
+    event_edges: Option<(bool, bool)>,
     phantom: PhantomData<&'a mut AnyPin>,
 }
 
 // EXTI0-EXTI23 Px0-Px23（x=A/B/C）
+/// Route EXTI line `pin` to the pin of that number on `port`.
+fn route_pin(pin: u8, port: u8) {
+    let afio = &crate::pac::AFIO;
+
+    let pin = pin as usize;
+
+    #[cfg(afio_v0)]
+    {
+        // AFIO_EXTICR
+        // stride: 2, len: 15, 8 lines
+        afio.exticr().modify(|w| w.set_exti(pin, port));
+    }
+    // V1, V2, V3, L1
+    #[cfg(any(afio_v3, afio_l1))]
+    {
+        // AFIO_EXTICRx
+        // stride: 4, len: 4, 16 lines
+        afio.exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
+    }
+    #[cfg(afio_x0)]
+    {
+        // stride: 2, len: 15, 24 lines
+        afio.exticr(pin / 16).modify(|w| w.set_exti(pin % 16, port));
+    }
+    #[cfg(afio_ch641)]
+    {
+        // single register
+        afio.exticr().modify(|w| w.set_exti(pin, port != 0));
+    }
+}
+
+pub(crate) fn set_edges(line: u8, rising: bool, falling: bool) {
+    let exti = &crate::pac::EXTI;
+    exti.rtenr().modify(|w| w.set_tr(line as usize, rising));
+    exti.ftenr().modify(|w| w.set_tr(line as usize, falling));
+}
+
 impl<'a> ExtiInputFuture<'a> {
     fn new(pin: u8, port: u8, rising: bool, falling: bool) -> Self {
-        critical_section::with(|_| {
+        let event_edges = critical_section::with(|_| {
             let exti = &crate::pac::EXTI;
-            let afio = &crate::pac::AFIO;
+            let event_edges = if exti.evenr().read().0 & (1 << pin) != 0 {
+                Some((
+                    exti.rtenr().read().tr(pin as usize),
+                    exti.ftenr().read().tr(pin as usize),
+                ))
+            } else {
+                None
+            };
+
+            route_pin(pin, port);
+            set_edges(pin, rising, falling);
+            event_edges
+        });
 
-            let port = port as u8;
-            let pin = pin as usize;
+        let mut fut = Self::new_line(pin);
+        fut.event_edges = event_edges;
+        fut
+    }
 
-            #[cfg(afio_v0)]
-            {
-                // AFIO_EXTICR
-                // stride: 2, len: 15, 8 lines
-                afio.exticr().modify(|w| w.set_exti(pin, port));
-            }
-            // V1, V2, V3, L1
-            #[cfg(any(afio_v3, afio_l1))]
-            {
-                // AFIO_EXTICRx
-                // stride: 4, len: 4, 16 lines
-                afio.exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
-            }
-            #[cfg(afio_x0)]
-            {
-                // stride: 2, len: 15, 24 lines
-                afio.exticr(pin / 16).modify(|w| w.set_exti(pin % 16, port));
-            }
-            #[cfg(afio_ch641)]
-            {
-                // single register
-                afio.exticr().modify(|w| w.set_exti(pin, port != 0));
-            }
+    /// Arm a line without touching its pin routing or edge configuration.
+    pub(crate) fn new_line(line: u8) -> Self {
+        critical_section::with(|_| {
+            let exti = &crate::pac::EXTI;
 
-            // See-also: 7.4.3
-            exti.intenr().modify(|w| w.set_mr(pin, true)); // enable interrupt
+            // Drop any edge seen before this future was created.
+            exti.intfr().write(|w| w.0 = 1 << line);
 
-            exti.rtenr().modify(|w| w.set_tr(pin, rising));
-            exti.ftenr().modify(|w| w.set_tr(pin, falling));
+            // See-also: 7.4.3
+            exti.intenr().modify(|w| w.set_mr(line as usize, true)); // enable interrupt
         });
 
         Self {
-            pin,
+            pin: line,
+            event_edges: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Set the pending flag of EXTI `line` by software, as if its event had occurred.
+///
+/// If the line interrupt is enabled, its handler runs, which makes EXTI lines usable to
+/// signal between tasks running at different interrupt priorities.
+pub fn software_trigger(line: u8) {
+    assert!((line as usize) < EXTI_COUNT);
+    crate::pac::EXTI.swievr().write(|w| w.0 = 1 << line);
+}
+
+/// Make EXTI `line` generate a wakeup event, instead of or in addition to its interrupt.
+///
+/// Events wake the core from `wfe`, and from sleep and stop modes entered with `wfe`,
+/// without running any interrupt handler, which gives the lowest wakeup latency. For GPIO
+/// lines, use [`ExtiInput::enable_wakeup_event`] to also route the pin and select its edges.
+pub fn enable_event(line: u8) {
+    assert!((line as usize) < EXTI_COUNT);
+    critical_section::with(|_| {
+        crate::pac::EXTI.evenr().modify(|w| w.0 |= 1 << line);
+    });
+}
+
+/// Stop EXTI `line` from generating wakeup events.
+pub fn disable_event(line: u8) {
+    assert!((line as usize) < EXTI_COUNT);
+    critical_section::with(|_| {
+        crate::pac::EXTI.evenr().modify(|w| w.0 &= !(1 << line));
+    });
+}
+
+/// An EXTI line used as a software event, without its GPIO pin, or one of the lines of
+/// internal event sources.
+///
+/// The channel singleton ensures no [`ExtiInput`] uses the line at the same time.
+pub struct ExtiLine<'d> {
+    line: u8,
+    phantom: PhantomData<&'d mut AnyChannel>,
+}
+
+impl<'d> ExtiLine<'d> {
+    /// Take EXTI channel `ch` for software events.
+    pub fn new(ch: impl Peripheral<P = impl Channel> + 'd) -> Self {
+        into_ref!(ch);
+        let line = ch.number();
+
+        // No pin edge reaches the line.
+        critical_section::with(|_| set_edges(line, false, false));
+
+        Self {
+            line,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Take EXTI `line` of an internal event source, such as the PVD, completing
+    /// [`wait`](Self::wait) on its rising edge.
+    ///
+    /// These lines have their own interrupt vector, whose handler must call [`on_irq`].
+    ///
+    /// # Safety
+    ///
+    /// `line` must not be a GPIO line, and no driver, such as the RTC for its alarm line,
+    /// or other `ExtiLine` may use it at the same time.
+    pub unsafe fn new_unchecked(line: u8) -> Self {
+        assert!((line as usize) < EXTI_COUNT);
+
+        critical_section::with(|_| set_edges(line, true, false));
+
+        Self {
+            line,
             phantom: PhantomData,
         }
     }
+
+    /// Trigger the line, completing a pending [`wait`](Self::wait).
+    pub fn trigger(&self) {
+        software_trigger(self.line);
+    }
+
+    /// Wait for the line to be triggered, by [`trigger`](Self::trigger), [`software_trigger`]
+    /// or its event source.
+    pub async fn wait(&mut self) {
+        ExtiInputFuture::new_line(self.line).await
+    }
 }
 
 impl<'a> Drop for ExtiInputFuture<'a> {
@@ -171,6 +423,9 @@ impl<'a> Drop for ExtiInputFuture<'a> {
             let exti = &crate::pac::EXTI;
             let pin = self.pin;
             exti.intenr().modify(|w| w.0 = w.0 & !(1 << pin));
+            if let Some((rising, falling)) = self.event_edges {
+                set_edges(pin, rising, falling);
+            }
         });
     }
 }
@@ -239,7 +494,7 @@ mod _exti_8lines {
     impl_exti!(EXTI7, 7);
 }
 
-#[cfg(not(any(ch32v0, ch643)))]
+#[cfg(not(ch32v0))]
 mod _exti_16lines {
     use super::*;
 
@@ -18,6 +18,9 @@ use pac::gpio::vals;
 
 use crate::{exti, impl_peripheral, into_ref, pac, peripherals, Peripheral, PeripheralRef};
 
+#[cfg(feature = "embassy")]
+pub mod matrix;
+
 /// Speed, for output mode
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -118,6 +121,9 @@ impl From<Level> for bool {
 /// This pin can either be a disconnected, input, or output pin, or both. The level register bit will remain
 /// set while not in output mode, so the pin's level will be 'remembered' when it is not in output
 /// mode.
+///
+/// The mode can be switched at any time, which is what bit-banged bidirectional protocols
+/// such as 1-Wire or I2C bus recovery need.
 pub struct Flex<'d> {
     pub(crate) pin: PeripheralRef<'d, AnyPin>,
 }
@@ -155,6 +161,16 @@ impl<'d> Flex<'d> {
         });
     }
 
+    /// Put the pin into output mode with the given speed and output type.
+    #[inline]
+    pub fn set_as_output_with_config(&mut self, config: OutputConfig) {
+        match config.output_type {
+            OutputType::PushPull => self.set_as_output(config.speed),
+            #[cfg(not(gpio_x0))]
+            OutputType::OpenDrain => self.set_as_output_open_drain(config.speed),
+        }
+    }
+
     /// Put the pin into output mode with open drain.
     ///
     /// Pull resistor is disabled in this mode.
@@ -174,6 +190,44 @@ impl<'d> Flex<'d> {
         });
     }
 
+    /// Put the pin into analog mode.
+    ///
+    /// The digital input buffer is disconnected, as required by the ADC, OPA and comparator inputs.
+    #[inline]
+    pub fn set_as_analog(&mut self) {
+        critical_section::with(|_| {
+            self.pin.set_as_analog();
+        });
+    }
+
+    /// Put the pin into its lowest power state, neither driving nor reading it.
+    #[inline]
+    pub fn set_as_disconnected(&mut self) {
+        critical_section::with(|_| {
+            self.pin.set_as_disconnected();
+        });
+    }
+
+    /// Lock the pin configuration, mode and pull, until the next reset.
+    ///
+    /// Safety relevant outputs such as gate driver enables or relays can then not be
+    /// reconfigured by runaway software. The output level can still be changed.
+    /// Only the first lock of each port takes effect, use [`Port::lock`] to lock several
+    /// pins of a port. Returns whether the lock is active.
+    pub fn lock(&mut self) -> bool {
+        self.pin.lock_port_pins(1 << self.pin.pin())
+    }
+
+    /// Whether the pin configuration is locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.pin.is_locked()
+    }
+
+    /// Get whether the pin input level is high.
+    ///
+    /// The level is read from the input data register, so this also works in output modes,
+    /// e.g. to read back an open drain line driven by another device.
     #[inline]
     pub fn is_high(&self) -> bool {
         self.pin.block().indr().read().idr(self.pin.pin() as usize)
@@ -267,6 +321,30 @@ impl<'d> Input<'d> {
     pub fn get_level(&self) -> Level {
         self.pin.get_level()
     }
+
+    /// Wait until the pin is at `level`, sampling it every `interval`.
+    ///
+    /// For slow signals on pins whose EXTI line is taken by another pin. Pulses shorter
+    /// than `interval` may be missed.
+    #[cfg(feature = "embassy")]
+    pub async fn wait_for_level_polling(&mut self, level: Level, interval: embassy_time::Duration) {
+        let mut ticker = embassy_time::Ticker::every(interval);
+        while self.get_level() != level {
+            ticker.next().await;
+        }
+    }
+
+    /// Wait until the pin is high, sampling it every `interval`.
+    #[cfg(feature = "embassy")]
+    pub async fn wait_for_high_polling(&mut self, interval: embassy_time::Duration) {
+        self.wait_for_level_polling(Level::High, interval).await
+    }
+
+    /// Wait until the pin is low, sampling it every `interval`.
+    #[cfg(feature = "embassy")]
+    pub async fn wait_for_low_polling(&mut self, interval: embassy_time::Duration) {
+        self.wait_for_level_polling(Level::Low, interval).await
+    }
 }
 
 /// GPIO output driver.
@@ -291,6 +369,22 @@ impl<'d> Output<'d> {
         Self { pin }
     }
 
+    /// Create GPIO output driver for a [Pin] with the provided [Level] and [OutputConfig].
+    ///
+    /// With [`OutputType::OpenDrain`] the pin is only driven low, use [`OutputOpenDrain`]
+    /// to also read back the line level.
+    #[inline]
+    pub fn new_with_config(
+        pin: impl Peripheral<P = impl Pin> + 'd,
+        initial_output: Level,
+        config: OutputConfig,
+    ) -> Self {
+        let mut pin = Flex::new(pin);
+        pin.set_level(initial_output);
+        pin.set_as_output_with_config(config);
+        Self { pin }
+    }
+
     /// Set the output as high.
     #[inline]
     pub fn set_high(&mut self) {
@@ -332,33 +426,61 @@ impl<'d> Output<'d> {
     pub fn toggle(&mut self) {
         self.pin.toggle();
     }
+
+    /// Lock the pin configuration until the next reset, see [`Flex::lock`].
+    pub fn lock(&mut self) -> bool {
+        self.pin.lock()
+    }
 }
 
 /// GPIO output open-drain driver.
 ///
+/// The pin is only driven low, a high level is left to a pull-up resistor or to other devices
+/// sharing the line. [`is_high`](Self::is_high) reads the actual line level, so the same pin
+/// can be used for 1-Wire, shared interrupt lines or multi-master signaling.
+///
+/// CH32X0 GPIOs have no open drain mode, it is emulated by switching the pin between
+/// a low push-pull output and a floating input.
+///
 /// Note that pins will **return to their floating state** when `OutputOpenDrain` is dropped.
 /// If pins should retain their state indefinitely, either keep ownership of the
 /// `OutputOpenDrain`, or pass it to [`core::mem::forget`].
 pub struct OutputOpenDrain<'d> {
     pub(crate) pin: Flex<'d>,
+    #[cfg(gpio_x0)]
+    speed: Speed,
 }
 
 impl<'d> OutputOpenDrain<'d> {
     /// Create a new GPIO open drain output driver for a [Pin] with the provided [Level] and [Speed], [Pull] configuration.
     #[inline]
     pub fn new(pin: impl Peripheral<P = impl Pin> + 'd, initial_output: Level, speed: Speed) -> Self {
-        let mut pin = Flex::new(pin);
+        #[cfg(not(gpio_x0))]
+        {
+            let mut pin = Flex::new(pin);
 
-        match initial_output {
-            Level::High => pin.set_high(),
-            Level::Low => pin.set_low(),
-        }
+            match initial_output {
+                Level::High => pin.set_high(),
+                Level::Low => pin.set_low(),
+            }
 
-        pin.set_as_output_open_drain(speed);
-        Self { pin }
+            pin.set_as_output_open_drain(speed);
+            Self { pin }
+        }
+        #[cfg(gpio_x0)]
+        {
+            let mut this = Self {
+                pin: Flex::new(pin),
+                speed,
+            };
+            this.set_level(initial_output);
+            this
+        }
     }
 
     /// Get whether the pin input level is high.
+    ///
+    /// This is the actual line level, which can be low while the output is set high.
     #[inline]
     pub fn is_high(&self) -> bool {
         !self.pin.is_low()
@@ -376,22 +498,29 @@ impl<'d> OutputOpenDrain<'d> {
         self.pin.get_level()
     }
 
-    /// Set the output as high.
+    /// Set the output as high, releasing the line.
     #[inline]
     pub fn set_high(&mut self) {
         self.pin.set_high();
+        #[cfg(gpio_x0)]
+        self.pin.set_as_input(Pull::None);
     }
 
     /// Set the output as low.
     #[inline]
     pub fn set_low(&mut self) {
         self.pin.set_low();
+        #[cfg(gpio_x0)]
+        self.pin.set_as_output(self.speed);
     }
 
     /// Set the output level.
     #[inline]
     pub fn set_level(&mut self, level: Level) {
-        self.pin.set_level(level);
+        match level {
+            Level::Low => self.set_low(),
+            Level::High => self.set_high(),
+        }
     }
 
     /// Get whether the output level is set to high.
@@ -415,19 +544,159 @@ impl<'d> OutputOpenDrain<'d> {
     /// Toggle pin output
     #[inline]
     pub fn toggle(&mut self) {
-        self.pin.toggle()
+        if self.is_set_low() {
+            self.set_high()
+        } else {
+            self.set_low()
+        }
+    }
+
+    /// Lock the pin configuration until the next reset, see [`Flex::lock`].
+    ///
+    /// Not available on CH32X0, where open drain is emulated by switching the pin mode.
+    #[cfg(not(gpio_x0))]
+    pub fn lock(&mut self) -> bool {
+        self.pin.lock()
+    }
+}
+
+/// Several consecutive pins of one GPIO port, accessed together.
+///
+/// Writes go through a single BSHR access, so all pins change at the same time, and reads
+/// sample all pins at once. Bit `i` of the values maps to the `i`th pin, which is useful
+/// for parallel LCD buses or fast parallel sampling.
+pub struct Port<'d, const N: usize> {
+    pins: [Flex<'d>; N],
+    block: pac::gpio::Gpio,
+    shift: u8,
+}
+
+impl<'d, const N: usize> Port<'d, N> {
+    /// Group consecutive pins of the same port, lowest pin first, e.g. PB8 to PB15.
+    ///
+    /// The group must not cross pin 16, the limit of a BSHR access.
+    pub fn new(pins: [Flex<'d>; N]) -> Self {
+        assert!(N > 0 && N <= 16);
+        let port = pins[0].pin.port();
+        let shift = pins[0].pin.pin();
+        for (i, pin) in pins.iter().enumerate() {
+            assert!(pin.pin.port() == port && pin.pin.pin() == shift + i as u8);
+        }
+        assert!(shift / 16 == (shift + N as u8 - 1) / 16);
+
+        Self {
+            block: pins[0].pin.block(),
+            pins,
+            shift,
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        (1 << N) - 1
+    }
+
+    /// Put all pins into input mode.
+    pub fn set_as_input(&mut self, pull: Pull) {
+        self.pins.iter_mut().for_each(|pin| pin.set_as_input(pull));
+    }
+
+    /// Put all pins into push-pull output mode.
+    pub fn set_as_output(&mut self, speed: Speed) {
+        self.pins.iter_mut().for_each(|pin| pin.set_as_output(speed));
+    }
+
+    /// Put all pins into open drain output mode.
+    pub fn set_as_output_open_drain(&mut self, speed: Speed) {
+        self.pins.iter_mut().for_each(|pin| pin.set_as_output_open_drain(speed));
+    }
+
+    /// Set the output level of all pins at once.
+    #[inline]
+    pub fn write(&mut self, value: u16) {
+        let value = value as u32 & self.mask();
+        self.write_bits(value, !value & self.mask());
+    }
+
+    /// Set the pins whose bit is set in `mask` high, leaving the others unchanged.
+    #[inline]
+    pub fn set_high(&mut self, mask: u16) {
+        self.write_bits(mask as u32 & self.mask(), 0);
+    }
+
+    /// Set the pins whose bit is set in `mask` low, leaving the others unchanged.
+    #[inline]
+    pub fn set_low(&mut self, mask: u16) {
+        self.write_bits(0, mask as u32 & self.mask());
+    }
+
+    #[inline]
+    fn write_bits(&mut self, set: u32, reset: u32) {
+        let shift = self.shift % 16;
+        let bits = (set << shift) | (reset << (shift + 16));
+        #[cfg(gpio_x0)]
+        if self.shift >= 16 {
+            self.block.bsxr().write(|w| w.0 = bits);
+            return;
+        }
+        self.block.bshr().write(|w| w.0 = bits);
+    }
+
+    /// Read the input level of all pins at once.
+    #[inline]
+    pub fn read(&self) -> u16 {
+        ((self.block.indr().read().0 >> self.shift) & self.mask()) as u16
+    }
+
+    /// Get the output level set on all pins.
+    #[inline]
+    pub fn output(&self) -> u16 {
+        ((self.block.outdr().read().0 >> self.shift) & self.mask()) as u16
+    }
+
+    /// Lock the configuration of all pins of the group until the next reset, see [`Flex::lock`].
+    pub fn lock(&mut self) -> bool {
+        self.pins[0].pin.lock_port_pins(self.mask() << self.shift)
+    }
+
+    /// Release the pins.
+    pub fn release(self) -> [Flex<'d>; N] {
+        self.pins
     }
 }
 
 /// GPIO output type
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum OutputType {
     /// Drive the pin both high or low.
+    #[default]
     PushPull,
     /// Drive the pin low, or don't drive it at all if the output level is high.
     #[cfg(not(gpio_x0))]
     OpenDrain,
 }
 
+/// GPIO output configuration.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutputConfig {
+    /// Output drive speed. Lower speeds have slower edges, which reduces EMI.
+    ///
+    /// CH32X0 GPIOs have a single speed, this setting is ignored there.
+    pub speed: Speed,
+    /// Push-pull or open drain.
+    pub output_type: OutputType,
+}
+
+impl From<Speed> for OutputConfig {
+    fn from(speed: Speed) -> Self {
+        Self {
+            speed,
+            ..Default::default()
+        }
+    }
+}
+
 impl From<OutputType> for AFType {
     fn from(value: OutputType) -> Self {
         match value {
@@ -568,6 +837,31 @@ pub(crate) trait SealedPin {
         self.set_mode_cnf(speed.into(), af_type.into());
     }
 
+    /// Run the LCKR sequence, freezing the configuration of the pins in `mask` of this pin's port until reset.
+    fn lock_port_pins(&self, mask: u32) -> bool {
+        let block = self.block();
+        let write = |lckk: bool| {
+            block.lckr().write(|w| {
+                w.0 = mask;
+                w.set_lckk(lckk);
+            })
+        };
+
+        critical_section::with(|_| {
+            write(true);
+            write(false);
+            write(true);
+            let _ = block.lckr().read();
+            block.lckr().read().lckk()
+        })
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        let lckr = self.block().lckr().read();
+        lckr.lckk() && lckr.lck(self._pin() as usize)
+    }
+
     /// Analog mode, both input and output
     #[inline]
     fn set_as_analog(&self) {
@@ -601,6 +895,12 @@ pub trait Pin: Peripheral<P = Self> + Into<AnyPin> + SealedPin + Sized + 'static
         self._port()
     }
 
+    /// Port of the pin as a letter, `'A'` for port 0
+    #[inline]
+    fn port_letter(&self) -> char {
+        (b'A' + self._port()) as char
+    }
+
     /// Convert from concrete pin type PX_XX to type erased `AnyPin`.
     #[inline]
     fn degrade(self) -> AnyPin {
@@ -608,6 +908,32 @@ pub trait Pin: Peripheral<P = Self> + Into<AnyPin> + SealedPin + Sized + 'static
             pin_port: self.pin_port(),
         }
     }
+
+    /// Put the pin into analog mode, without creating a driver for it.
+    ///
+    /// Both the digital input buffer and the output driver are disconnected. This is the
+    /// lowest power state of a pin, and keeps a floating input from oscillating.
+    #[inline]
+    fn set_as_analog(&mut self) {
+        critical_section::with(|_| <Self as SealedPin>::set_as_analog(self));
+    }
+}
+
+/// Put all given pins into analog mode and keep them there.
+///
+/// Call this at init with every pin the board leaves unconnected. Unused pins otherwise
+/// stay floating inputs, whose input buffers draw current in stop and standby modes.
+/// The pins are consumed, so they can't be accidentally reconfigured later.
+///
+/// ```ignore
+/// gpio::park([p.PA0.degrade(), p.PA1.degrade(), p.PC7.degrade()]);
+/// ```
+pub fn park(pins: impl IntoIterator<Item = AnyPin>) {
+    critical_section::with(|_| {
+        for pin in pins {
+            <AnyPin as SealedPin>::set_as_analog(&pin);
+        }
+    });
 }
 
 // Type-erased GPIO pin
@@ -621,12 +947,50 @@ impl AnyPin {
         Self { pin_port }
     }
 
+    /// Get a pin from its port letter and pin number, as stored in a board configuration.
+    ///
+    /// Returns `None` if the chip has no such pin, for example `('A', 16)` or a port
+    /// missing on this package.
+    ///
+    /// # Safety
+    ///
+    /// As with [`steal`](Self::steal), the pin must not be in use by another driver,
+    /// the usual ownership through [`Peripherals`](crate::Peripherals) is bypassed.
+    pub unsafe fn try_from_port_pin(port: char, pin: u8) -> Option<Self> {
+        if !port.is_ascii_alphabetic() {
+            return None;
+        }
+        let port = port.to_ascii_uppercase() as u8 - b'A';
+
+        foreach_pin!(
+            ($pin_name:ident, $port_name:ident, $port_num:expr, $pin_num:expr, $exti_ch:ident) => {
+                if port == $port_num && pin == $pin_num {
+                    return Some(Self::steal(port * 32 + pin));
+                }
+            };
+        );
+        None
+    }
+
     #[inline]
     fn _port(&self) -> u8 {
         self.pin_port / 32
     }
 }
 
+impl core::fmt::Debug for AnyPin {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "P{}{}", self.port_letter(), self._pin())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for AnyPin {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "P{}{}", self.port_letter(), self._pin())
+    }
+}
+
 impl_peripheral!(AnyPin);
 impl Pin for AnyPin {
     #[cfg(exti)]
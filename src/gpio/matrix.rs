@@ -0,0 +1,226 @@
+//! Keypad matrix scanning.
+//!
+//! Rows are pulled low one at a time through open drain outputs while the columns are read
+//! through pull-up inputs, so a pressed key reads low on its column. Open drain rows keep two
+//! pressed keys in the same column from shorting two driven outputs together.
+//!
+//! Without a diode per key, three keys pressed on the corners of a rectangle make the fourth
+//! corner appear pressed too. Such scans are detected as [`Ghosting`] and ignored by
+//! [`Keypad::wait_for_event`], the reported state then stays at the last unambiguous scan.
+
+use embassy_time::{block_for, Duration, Timer};
+
+use super::{Input, OutputOpenDrain};
+
+/// Keypad configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Time between two scans of the whole matrix.
+    pub scan_interval: Duration,
+    /// Time to wait after selecting a row before reading the columns.
+    pub settle_time: Duration,
+    /// Number of identical consecutive scans before a change is reported.
+    pub debounce_scans: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scan_interval: Duration::from_millis(5),
+            settle_time: Duration::from_micros(10),
+            debounce_scans: 3,
+        }
+    }
+}
+
+/// Position of a key in the matrix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Key {
+    pub row: usize,
+    pub col: usize,
+}
+
+/// A debounced key change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyEvent {
+    Pressed(Key),
+    Released(Key),
+}
+
+/// The scan could not tell which keys are pressed, because of ghosting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ghosting;
+
+/// Keypad matrix of `R` rows and `C` columns, at most 32 columns.
+pub struct Keypad<'d, const R: usize, const C: usize> {
+    rows: [OutputOpenDrain<'d>; R],
+    cols: [Input<'d>; C],
+    config: Config,
+    /// Debounced state, one column bitmask per row.
+    state: [u32; R],
+    /// Last raw scan and how many scans in a row it was seen.
+    candidate: [u32; R],
+    stable_scans: u8,
+    ghosting: bool,
+}
+
+impl<'d, const R: usize, const C: usize> Keypad<'d, R, C> {
+    /// Create a new keypad.
+    ///
+    /// The column inputs must be pulled up, either internally with [`Pull::Up`](super::Pull::Up)
+    /// or by external resistors.
+    pub fn new(rows: [OutputOpenDrain<'d>; R], cols: [Input<'d>; C], config: Config) -> Self {
+        assert!(C <= 32);
+
+        let mut this = Self {
+            rows,
+            cols,
+            config,
+            state: [0; R],
+            candidate: [0; R],
+            stable_scans: 0,
+            ghosting: false,
+        };
+        for row in this.rows.iter_mut() {
+            row.set_high();
+        }
+        this
+    }
+
+    /// Change the configuration.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Scan the matrix once, without debouncing.
+    ///
+    /// Returns one bitmask of pressed columns per row.
+    pub fn scan(&mut self) -> Result<[u32; R], Ghosting> {
+        let mut keys = [0; R];
+
+        for (row, output) in self.rows.iter_mut().enumerate() {
+            output.set_low();
+            block_for(self.config.settle_time);
+            for (col, input) in self.cols.iter().enumerate() {
+                if input.is_low() {
+                    keys[row] |= 1 << col;
+                }
+            }
+            output.set_high();
+        }
+
+        if has_ghosting(&keys) {
+            Err(Ghosting)
+        } else {
+            Ok(keys)
+        }
+    }
+
+    /// Whether a key is pressed, after debouncing.
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.state[key.row] & (1 << key.col) != 0
+    }
+
+    /// The debounced state, one bitmask of pressed columns per row.
+    pub fn state(&self) -> [u32; R] {
+        self.state
+    }
+
+    /// Whether the last scan was ambiguous because of ghosting.
+    pub fn is_ghosting(&self) -> bool {
+        self.ghosting
+    }
+
+    /// Scan the matrix periodically until a key is pressed or released.
+    ///
+    /// When several keys change at once, they are reported by consecutive calls.
+    pub async fn wait_for_event(&mut self) -> KeyEvent {
+        loop {
+            if let Some(event) = self.pending_event() {
+                return event;
+            }
+
+            Timer::after(self.config.scan_interval).await;
+            self.update();
+        }
+    }
+
+    /// Wait until a key is pressed, ignoring releases.
+    pub async fn wait_for_press(&mut self) -> Key {
+        loop {
+            if let KeyEvent::Pressed(key) = self.wait_for_event().await {
+                return key;
+            }
+        }
+    }
+
+    /// Release the row and column pins.
+    pub fn release(self) -> ([OutputOpenDrain<'d>; R], [Input<'d>; C]) {
+        (self.rows, self.cols)
+    }
+
+    fn update(&mut self) {
+        match self.scan() {
+            Ok(keys) => {
+                self.ghosting = false;
+                if keys == self.candidate {
+                    self.stable_scans = self.stable_scans.saturating_add(1);
+                } else {
+                    self.candidate = keys;
+                    self.stable_scans = 1;
+                }
+            }
+            Err(Ghosting) => {
+                self.ghosting = true;
+                self.stable_scans = 0;
+            }
+        }
+    }
+
+    fn pending_event(&mut self) -> Option<KeyEvent> {
+        if self.stable_scans < self.config.debounce_scans.max(1) {
+            return None;
+        }
+
+        for (row, (state, candidate)) in self.state.iter_mut().zip(&self.candidate).enumerate() {
+            let changed = *state ^ candidate;
+            if changed != 0 {
+                let col = changed.trailing_zeros() as usize;
+                *state ^= 1 << col;
+
+                let key = Key { row, col };
+                return Some(if *state & (1 << col) != 0 {
+                    KeyEvent::Pressed(key)
+                } else {
+                    KeyEvent::Released(key)
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Two rows sharing two pressed columns form a rectangle, whose corners can't be told apart.
+fn has_ghosting(keys: &[u32]) -> bool {
+    keys.iter()
+        .enumerate()
+        .any(|(i, a)| keys[i + 1..].iter().any(|b| (a & b).count_ones() >= 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghosting() {
+        assert!(!has_ghosting(&[0b011, 0b001]));
+        assert!(!has_ghosting(&[0b101, 0, 0b100]));
+        assert!(!has_ghosting(&[0b111]));
+        assert!(has_ghosting(&[0b011, 0b011]));
+        assert!(has_ghosting(&[0b110, 0, 0b110]));
+        assert!(has_ghosting(&[0b1001, 0b0001, 0b1011]));
+    }
+}
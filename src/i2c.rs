@@ -70,6 +70,8 @@ pub enum Error {
     Overrun,
     /// Zero-length transfers are not allowed.
     ZeroLengthTransfer,
+    /// DMA transfer error.
+    Dma,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -587,6 +589,7 @@ impl<'d, T: Instance> I2c<'d, T, Async> {
 
         // Wait for either the DMA transfer to successfully finish, or an I2C error to occur.
         match select(dma_transfer, poll_error).await {
+            Either::First(Err(_)) => Err(Error::Dma),
             Either::Second(Err(e)) => Err(e),
             _ => Ok(()),
         }?;
@@ -783,6 +786,7 @@ impl<'d, T: Instance> I2c<'d, T, Async> {
         });
 
         match select(dma_transfer, poll_error).await {
+            Either::First(Err(_)) => Err(Error::Dma),
             Either::Second(Err(e)) => Err(e),
             _ => Ok(()),
         }?;
@@ -903,6 +907,7 @@ impl embedded_hal::i2c::Error for Error {
             Self::Crc => embedded_hal::i2c::ErrorKind::Other,
             Self::Overrun => embedded_hal::i2c::ErrorKind::Overrun,
             Self::ZeroLengthTransfer => embedded_hal::i2c::ErrorKind::Other,
+            Self::Dma => embedded_hal::i2c::ErrorKind::Other,
         }
     }
 }
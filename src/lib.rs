@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(static_mut_refs, unexpected_cfgs)]
 
 use core::future::Future;
@@ -98,14 +98,22 @@ pub mod dma;
 
 #[cfg(adc)]
 pub mod adc;
+pub mod afio;
+#[cfg(bkp)]
+pub mod backup;
 #[cfg(dac)]
 pub mod dac;
+#[cfg(all(eth, feature = "eth"))]
+pub mod eth;
 pub mod exti;
 pub mod gpio;
 #[cfg(i2c)]
 pub mod i2c;
 #[cfg(rng)]
 pub mod rng;
+// The RTC time driver owns the counter and its interrupts.
+#[cfg(all(rtc, not(time_driver_rtc)))]
+pub mod rtc;
 #[cfg(sdio_v3)]
 pub mod sdio;
 pub mod signature;
@@ -113,6 +121,8 @@ pub mod signature;
 pub mod spi;
 #[cfg(any(timer_x0, timer_v3))]
 pub mod timer;
+#[cfg(any(adc_v0, adc_x0))]
+pub mod touch;
 pub mod usart;
 
 /// Common structures for USB drivers
@@ -2,13 +2,18 @@ use core::marker::PhantomData;
 use core::task::Poll;
 
 use ch32_metapac::otg::vals::{EpRxResponse, EpTxResponse, UsbToken};
-use embassy_usb_driver::{Direction, EndpointError, EndpointInfo};
+use embassy_usb_driver::{Direction, EndpointError, EndpointInfo, EndpointType};
 use futures::future::poll_fn;
 
 use super::{Instance, EP_MAX_PACKET_SIZE, EP_WAKERS};
 use crate::interrupt::typelevel::Interrupt;
 use crate::usb::{Dir, EndpointData, In, Out};
 
+/// Send without waiting for a handshake, for isochronous endpoints.
+const TX_RES_NONE: EpTxResponse = EpTxResponse::from_bits(0b01);
+/// Receive without handshaking, for isochronous endpoints.
+const RX_RES_NONE: EpRxResponse = EpRxResponse::from_bits(0b01);
+
 /// USB endpoint.
 pub struct Endpoint<'d, T, D> {
     _phantom: PhantomData<&'d (T, D)>,
@@ -91,10 +96,18 @@ impl<'d, T: Instance> embassy_usb_driver::EndpointIn for Endpoint<'d, T, In> {
         // Write buffer, txLen, and ACK
         self.data.buffer.write_volatile(buf);
         regs.uep_t_len(ep).write_value(buf.len() as u8);
-        regs.uep_tx_ctrl(ep).modify(|v| {
-            v.set_t_tog(!v.t_tog());
-            v.set_mask_t_res(EpTxResponse::ACK);
-        });
+        if self.info.ep_type == EndpointType::Isochronous {
+            // Full speed isochronous packets are all DATA0.
+            regs.uep_tx_ctrl(ep).modify(|v| {
+                v.set_t_tog(false);
+                v.set_mask_t_res(TX_RES_NONE);
+            });
+        } else {
+            regs.uep_tx_ctrl(ep).modify(|v| {
+                v.set_t_tog(!v.t_tog());
+                v.set_mask_t_res(EpTxResponse::ACK);
+            });
+        }
 
         // Wait for TX complete
         let tx_result = poll_fn(|ctx| {
@@ -146,10 +159,17 @@ impl<'d, T: Instance> embassy_usb_driver::EndpointOut for Endpoint<'d, T, Out> {
 
         // Tx Ctrl should be NAK
 
-        regs.uep_rx_ctrl(ep).modify(|v| {
-            v.set_r_tog(!v.r_tog());
-            v.set_mask_r_res(EpRxResponse::ACK);
-        });
+        if self.info.ep_type == EndpointType::Isochronous {
+            regs.uep_rx_ctrl(ep).modify(|v| {
+                v.set_r_tog(false);
+                v.set_mask_r_res(RX_RES_NONE);
+            });
+        } else {
+            regs.uep_rx_ctrl(ep).modify(|v| {
+                v.set_r_tog(!v.r_tog());
+                v.set_mask_r_res(EpRxResponse::ACK);
+            });
+        }
 
         // poll for packet
         let bytes_read = poll_fn(|ctx| {
@@ -163,8 +183,12 @@ impl<'d, T: Instance> embassy_usb_driver::EndpointOut for Endpoint<'d, T, Out> {
                             // upper bits are reserved (0)
                             let len = regs.rx_len().read().0 as usize;
 
-                            self.data.buffer.read_volatile(&mut buf[..len]);
-                            Poll::Ready(Ok(len))
+                            if len <= buf.len() {
+                                self.data.buffer.read_volatile(&mut buf[..len]);
+                                Poll::Ready(Ok(len))
+                            } else {
+                                Poll::Ready(Err(EndpointError::BufferOverflow))
+                            }
                         }
                         token => {
                             error!("Unexpected USB Token {}", token.to_bits());
@@ -0,0 +1,729 @@
+//! USB host mode of the USBFS/OTG_FS controller
+//!
+//! One full or low speed device on the root port, without hubs, with control, bulk and
+//! interrupt transfers. [`Host::enumerate`] gives the device an address and reads its
+//! descriptors, class drivers are built on top of the transfers:
+//!
+//! ```rust,ignore
+//! let mut buffers: [EndpointDataBuffer; 2] = core::array::from_fn(|_| EndpointDataBuffer::default());
+//! let mut host = Host::new(p.OTG_FS, Irqs, p.PA12, p.PA11, &mut buffers);
+//!
+//! host.wait_for_device().await;
+//! let device = host.enumerate(1).await?;
+//! let len = host.configuration_descriptor(&device, &mut config).await?;
+//! for ep in host::endpoints(&config[..len]) { ... }
+//! host.set_configuration(&device, 1).await?;
+//! ```
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+use embassy_time::Timer;
+
+use super::Instance;
+use crate::gpio::{AFType, Speed};
+use crate::interrupt::typelevel::Interrupt;
+use crate::usb::{EndpointBuffer, EndpointBufferAllocator, EndpointDataBuffer};
+use crate::{interrupt, Peripheral};
+
+pub(super) static HOST_WAKER: AtomicWaker = AtomicWaker::new();
+
+// Token PIDs
+const PID_OUT: u8 = 0x1;
+const PID_IN: u8 = 0x9;
+const PID_SETUP: u8 = 0xD;
+
+// Response PIDs, as reported in INT_ST
+const PID_ACK: u8 = 0x2;
+const PID_NAK: u8 = 0xA;
+const PID_STALL: u8 = 0xE;
+const PID_DATA0: u8 = 0x3;
+const PID_DATA1: u8 = 0xB;
+
+/// Attempts on a transaction the device doesn't answer.
+const MAX_RETRIES: usize = 3;
+
+const DESCRIPTOR_DEVICE: u8 = 1;
+const DESCRIPTOR_CONFIGURATION: u8 = 2;
+const DESCRIPTOR_ENDPOINT: u8 = 5;
+
+/// Host errors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The device was unplugged.
+    Disconnected,
+    /// The device answered with STALL.
+    Stall,
+    /// The device didn't answer.
+    Timeout,
+    /// The device sent more data than asked for.
+    BufferOverflow,
+    /// A descriptor doesn't parse.
+    InvalidDescriptor,
+}
+
+/// Speed of the attached device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DeviceSpeed {
+    Full,
+    Low,
+}
+
+/// A control request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SetupPacket {
+    /// `bmRequestType`, the top bit set for device to host requests.
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    /// Length of the data stage.
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// Standard GET_DESCRIPTOR request.
+    pub const fn get_descriptor(descriptor_type: u8, index: u8, length: u16) -> Self {
+        Self {
+            request_type: 0x80,
+            request: 0x06,
+            value: ((descriptor_type as u16) << 8) | index as u16,
+            index: 0,
+            length,
+        }
+    }
+
+    /// Standard SET_ADDRESS request.
+    pub const fn set_address(address: u8) -> Self {
+        Self {
+            request_type: 0x00,
+            request: 0x05,
+            value: address as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// Standard SET_CONFIGURATION request.
+    pub const fn set_configuration(value: u8) -> Self {
+        Self {
+            request_type: 0x00,
+            request: 0x09,
+            value: value as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    fn to_bytes(&self) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0] = self.request_type;
+        bytes[1] = self.request;
+        bytes[2..4].copy_from_slice(&self.value.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.index.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.length.to_le_bytes());
+        bytes
+    }
+
+    fn is_in(&self) -> bool {
+        self.request_type & 0x80 != 0
+    }
+}
+
+/// Standard device descriptor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceDescriptor {
+    pub usb_version: u16,
+    pub class: u8,
+    pub sub_class: u8,
+    pub protocol: u8,
+    pub max_packet_size0: u8,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub device_version: u16,
+    /// String descriptor indexes, 0 if absent.
+    pub manufacturer: u8,
+    pub product: u8,
+    pub serial_number: u8,
+    pub num_configurations: u8,
+}
+
+impl DeviceDescriptor {
+    fn parse(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() < 18 || buf[0] < 18 || buf[1] != DESCRIPTOR_DEVICE {
+            return Err(Error::InvalidDescriptor);
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+
+        Ok(Self {
+            usb_version: u16_at(2),
+            class: buf[4],
+            sub_class: buf[5],
+            protocol: buf[6],
+            max_packet_size0: buf[7],
+            vendor_id: u16_at(8),
+            product_id: u16_at(10),
+            device_version: u16_at(12),
+            manufacturer: buf[14],
+            product: buf[15],
+            serial_number: buf[16],
+            num_configurations: buf[17],
+        })
+    }
+}
+
+/// Standard endpoint descriptor, see [`endpoints`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EndpointDescriptor {
+    /// Endpoint number, with the top bit set for IN endpoints.
+    pub address: u8,
+    /// Transfer type in the low 2 bits: 0 control, 1 isochronous, 2 bulk, 3 interrupt.
+    pub attributes: u8,
+    pub max_packet_size: u16,
+    /// Polling interval, in frames for full and low speed devices.
+    pub interval: u8,
+}
+
+/// The endpoint descriptors of a configuration descriptor, in order.
+pub fn endpoints(config: &[u8]) -> impl Iterator<Item = EndpointDescriptor> + '_ {
+    let mut rest = config;
+    core::iter::from_fn(move || loop {
+        let len = *rest.first()? as usize;
+        if len < 2 || len > rest.len() {
+            return None;
+        }
+        let (desc, tail) = rest.split_at(len);
+        rest = tail;
+
+        if desc[1] == DESCRIPTOR_ENDPOINT && len >= 7 {
+            return Some(EndpointDescriptor {
+                address: desc[2],
+                attributes: desc[3],
+                max_packet_size: u16::from_le_bytes([desc[4], desc[5]]),
+                interval: desc[6],
+            });
+        }
+    })
+}
+
+/// An enumerated device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Device {
+    pub address: u8,
+    pub speed: DeviceSpeed,
+    pub descriptor: DeviceDescriptor,
+}
+
+/// A bulk or interrupt endpoint of a device, keeping its data toggle.
+pub struct Pipe {
+    address: u8,
+    endpoint: u8,
+    max_packet_size: u16,
+    interval_ms: u8,
+    toggle: bool,
+}
+
+impl Pipe {
+    /// A pipe to an endpoint of `device`, as found by [`endpoints`].
+    pub fn new(device: &Device, endpoint: &EndpointDescriptor) -> Self {
+        Self {
+            address: device.address,
+            endpoint: endpoint.address & 0x0F,
+            max_packet_size: endpoint.max_packet_size,
+            interval_ms: endpoint.interval.max(1),
+            toggle: false,
+        }
+    }
+
+    /// Start over from DATA0, after a configuration change or clearing a halt.
+    pub fn reset_toggle(&mut self) {
+        self.toggle = false;
+    }
+}
+
+enum Response {
+    Ack,
+    Nak,
+    Data(usize),
+}
+
+/// USB host driver.
+pub struct Host<'d, T: Instance> {
+    _phantom: PhantomData<&'d mut T>,
+    rx: EndpointBuffer<'d>,
+    tx: EndpointBuffer<'d>,
+}
+
+impl<'d, T: Instance> Drop for Host<'d, T> {
+    fn drop(&mut self) {
+        // Turn the port off, e.g. for the controller to become a device, see
+        // [`role`](super::role).
+        T::Interrupt::disable();
+        let h = T::hregs();
+        h.host_ctrl().write(|_| {});
+        h.ctrl().write(|_| {});
+    }
+}
+
+impl<'d, T: Instance> Host<'d, T> {
+    /// Start the controller in host mode, with a receive and a transmit buffer.
+    pub fn new(
+        _usb: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, super::InterruptHandler<T>> + 'd,
+        dp: impl Peripheral<P = impl crate::gpio::Pin> + 'd,
+        dm: impl Peripheral<P = impl crate::gpio::Pin> + 'd,
+        buffers: &'d mut [EndpointDataBuffer; 2],
+    ) -> Self {
+        let dp = dp.into_ref();
+        let dm = dm.into_ref();
+
+        dp.set_as_af_output(AFType::OutputPushPull, Speed::High);
+        dm.set_as_af_output(AFType::OutputPushPull, Speed::High);
+
+        T::enable_and_reset();
+
+        let mut allocator = EndpointBufferAllocator::new(buffers);
+        let rx = unwrap!(allocator.alloc_endpoint(64)).buffer;
+        let tx = unwrap!(allocator.alloc_endpoint(64)).buffer;
+
+        let h = T::hregs();
+
+        h.ctrl().write(|w| {
+            w.set_clr_all(true);
+            w.set_reset_sie(true);
+        });
+        embassy_time::block_for(embassy_time::Duration::from_micros(10));
+        h.ctrl().write(|w| w.set_host_mode(true));
+
+        h.host_ctrl().write(|_| {});
+        h.dev_ad().write(|_| {});
+        h.host_ep_mod().write(|w| {
+            w.set_tx_en(true);
+            w.set_rx_en(true);
+        });
+        h.host_rx_dma().write_value(rx.addr() as u32);
+        h.host_tx_dma().write_value(tx.addr() as u32);
+        h.host_rx_ctrl().write(|_| {});
+        h.host_tx_ctrl().write(|_| {});
+
+        h.ctrl().write(|w| {
+            w.set_host_mode(true);
+            w.set_int_busy(true);
+            w.set_dma_en(true);
+        });
+        h.host_setup().write(|w| w.set_sof_en(true));
+
+        h.int_fg().write(|w| {
+            w.set_fifo_ov(true);
+            w.set_hst_sof(true);
+            w.set_suspend(true);
+            w.set_transfer(true);
+            w.set_detect(true);
+        });
+        h.int_en().write(|w| {
+            w.set_transfer(true);
+            w.set_detect(true);
+        });
+
+        critical_section::with(|_| {
+            T::Interrupt::unpend();
+            unsafe { T::Interrupt::enable() };
+        });
+
+        Self {
+            _phantom: PhantomData,
+            rx,
+            tx,
+        }
+    }
+
+    /// Whether a device is plugged in.
+    pub fn is_attached(&self) -> bool {
+        T::hregs().mis_st().read().dev_attach()
+    }
+
+    /// Wait for a device, then reset the port for it.
+    pub async fn wait_for_device(&mut self) -> DeviceSpeed {
+        self.wait_for_attach(true).await;
+
+        // Let the power and the contacts settle.
+        Timer::after_millis(100).await;
+
+        // Low speed devices pull D- up.
+        let speed = if T::hregs().mis_st().read().dm_level() {
+            DeviceSpeed::Low
+        } else {
+            DeviceSpeed::Full
+        };
+        self.reset(speed).await;
+        speed
+    }
+
+    /// Wait for the device to be unplugged.
+    pub async fn wait_for_disconnect(&mut self) {
+        self.wait_for_attach(false).await;
+
+        let h = T::hregs();
+        h.host_ctrl().modify(|w| w.set_port_en(false));
+        h.dev_ad().write(|_| {});
+    }
+
+    async fn wait_for_attach(&mut self, attached: bool) {
+        let h = T::hregs();
+        poll_fn(|cx| {
+            HOST_WAKER.register(cx.waker());
+
+            h.int_fg().write(|w| w.set_detect(true));
+            if h.mis_st().read().dev_attach() == attached {
+                Poll::Ready(())
+            } else {
+                unsafe { T::Interrupt::enable() };
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Reset the device on the port, it answers on address 0 afterwards.
+    pub async fn reset(&mut self, speed: DeviceSpeed) {
+        let h = T::hregs();
+        let low_speed = speed == DeviceSpeed::Low;
+
+        h.dev_ad().write(|_| {});
+        h.host_ctrl().modify(|w| {
+            w.set_port_en(false);
+            w.set_bus_reset(true);
+        });
+        Timer::after_millis(15).await;
+        h.host_ctrl().modify(|w| w.set_bus_reset(false));
+
+        h.ctrl().modify(|w| w.set_low_speed(low_speed));
+        h.host_ctrl().modify(|w| {
+            w.set_low_speed(low_speed);
+            w.set_port_en(true);
+        });
+
+        // Reset recovery
+        Timer::after_millis(20).await;
+    }
+
+    /// Give the device on address 0 `address`, 1 to 127, and read its device descriptor.
+    pub async fn enumerate(&mut self, address: u8) -> Result<Device, Error> {
+        assert!((1..=127).contains(&address));
+
+        let speed = if T::hregs().host_ctrl().read().low_speed() {
+            DeviceSpeed::Low
+        } else {
+            DeviceSpeed::Full
+        };
+
+        // The first 8 bytes hold the max packet size of endpoint 0, at least 8.
+        let mut buf = [0; 18];
+        self.control_raw(
+            0,
+            8,
+            &SetupPacket::get_descriptor(DESCRIPTOR_DEVICE, 0, 8),
+            &mut buf[..8],
+        )
+        .await?;
+        let max_packet_size0 = buf[7];
+        if !matches!(max_packet_size0, 8 | 16 | 32 | 64) {
+            return Err(Error::InvalidDescriptor);
+        }
+
+        self.control_raw(0, max_packet_size0, &SetupPacket::set_address(address), &mut [])
+            .await?;
+        // SET_ADDRESS recovery
+        Timer::after_millis(2).await;
+
+        let len = self
+            .control_raw(
+                address,
+                max_packet_size0,
+                &SetupPacket::get_descriptor(DESCRIPTOR_DEVICE, 0, 18),
+                &mut buf,
+            )
+            .await?;
+
+        Ok(Device {
+            address,
+            speed,
+            descriptor: DeviceDescriptor::parse(&buf[..len])?,
+        })
+    }
+
+    /// Read the whole first configuration descriptor, with its interfaces and endpoints,
+    /// and return its length.
+    pub async fn configuration_descriptor(&mut self, device: &Device, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut header = [0; 9];
+        self.control_in(
+            device,
+            &SetupPacket::get_descriptor(DESCRIPTOR_CONFIGURATION, 0, 9),
+            &mut header,
+        )
+        .await?;
+        if header[1] != DESCRIPTOR_CONFIGURATION {
+            return Err(Error::InvalidDescriptor);
+        }
+
+        let total = u16::from_le_bytes([header[2], header[3]]);
+        if total as usize > buf.len() {
+            return Err(Error::BufferOverflow);
+        }
+        self.control_in(
+            device,
+            &SetupPacket::get_descriptor(DESCRIPTOR_CONFIGURATION, 0, total),
+            &mut buf[..total as usize],
+        )
+        .await
+    }
+
+    /// Select a configuration, by its `bConfigurationValue`.
+    pub async fn set_configuration(&mut self, device: &Device, value: u8) -> Result<(), Error> {
+        self.control_out(device, &SetupPacket::set_configuration(value), &[])
+            .await
+    }
+
+    /// Control transfer reading up to `setup.length` bytes into `buf`.
+    pub async fn control_in(&mut self, device: &Device, setup: &SetupPacket, buf: &mut [u8]) -> Result<usize, Error> {
+        assert!(setup.is_in());
+        let len = buf.len().min(setup.length as usize);
+        self.control_raw(
+            device.address,
+            device.descriptor.max_packet_size0,
+            setup,
+            &mut buf[..len],
+        )
+        .await
+    }
+
+    /// Control transfer writing `data`, as long as `setup.length`.
+    pub async fn control_out(&mut self, device: &Device, setup: &SetupPacket, data: &[u8]) -> Result<(), Error> {
+        assert!(!setup.is_in());
+        assert_eq!(data.len(), setup.length as usize);
+        let mps = device.descriptor.max_packet_size0;
+
+        self.select(device.address);
+        self.setup_stage(setup).await?;
+
+        let mut toggle = true;
+        for chunk in data.chunks(mps as usize) {
+            self.out_packet(0, &mut toggle, chunk).await?;
+        }
+
+        // Status stage
+        let mut toggle = true;
+        self.in_packet(0, &mut toggle, &mut [], true).await?;
+        Ok(())
+    }
+
+    /// Control transfer with an IN data stage, or without a data stage.
+    async fn control_raw(&mut self, address: u8, mps: u8, setup: &SetupPacket, buf: &mut [u8]) -> Result<usize, Error> {
+        self.select(address);
+        self.setup_stage(setup).await?;
+
+        if !setup.is_in() {
+            assert!(buf.is_empty());
+            let mut toggle = true;
+            self.in_packet(0, &mut toggle, &mut [], true).await?;
+            return Ok(0);
+        }
+
+        let mut toggle = true;
+        let mut len = 0;
+        while len < buf.len() {
+            let end = buf.len().min(len + mps as usize);
+            let n = unwrap!(self.in_packet(0, &mut toggle, &mut buf[len..end], true).await?);
+            len += n;
+            if n < mps as usize {
+                break;
+            }
+        }
+
+        // Status stage, in the other direction, always DATA1.
+        let mut toggle = true;
+        self.out_packet(0, &mut toggle, &[]).await?;
+        Ok(len)
+    }
+
+    async fn setup_stage(&mut self, setup: &SetupPacket) -> Result<(), Error> {
+        self.tx.write_volatile(&setup.to_bytes());
+        T::hregs().host_tx_len().write_value(8);
+
+        for _ in 0..MAX_RETRIES {
+            match self.transaction(PID_SETUP, 0, false).await? {
+                Some(Response::Ack) => return Ok(()),
+                // Devices can't NAK a SETUP, retry on garbled ones.
+                _ => continue,
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Read a bulk transfer into `buf`, until it's full or a short packet ends the transfer.
+    pub async fn bulk_in(&mut self, pipe: &mut Pipe, buf: &mut [u8]) -> Result<usize, Error> {
+        self.select(pipe.address);
+
+        let mps = pipe.max_packet_size as usize;
+        let mut len = 0;
+        while len < buf.len() {
+            let end = buf.len().min(len + mps);
+            let n = unwrap!(
+                self.in_packet(pipe.endpoint, &mut pipe.toggle, &mut buf[len..end], true)
+                    .await?
+            );
+            len += n;
+            if n < mps {
+                break;
+            }
+        }
+        Ok(len)
+    }
+
+    /// Write `data` as a bulk transfer.
+    ///
+    /// A transfer that is a multiple of the packet size isn't ended with a zero-length packet,
+    /// send `&[]` after it when the class needs one.
+    pub async fn bulk_out(&mut self, pipe: &mut Pipe, data: &[u8]) -> Result<(), Error> {
+        self.select(pipe.address);
+
+        if data.is_empty() {
+            return self.out_packet(pipe.endpoint, &mut pipe.toggle, &[]).await;
+        }
+        for chunk in data.chunks(pipe.max_packet_size as usize) {
+            self.out_packet(pipe.endpoint, &mut pipe.toggle, chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Poll an interrupt IN endpoint at its interval until it has a report.
+    pub async fn interrupt_in(&mut self, pipe: &mut Pipe, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = buf.len().min(pipe.max_packet_size as usize);
+        loop {
+            self.select(pipe.address);
+            if let Some(n) = self
+                .in_packet(pipe.endpoint, &mut pipe.toggle, &mut buf[..len], false)
+                .await?
+            {
+                return Ok(n);
+            }
+            Timer::after_millis(pipe.interval_ms as u64).await;
+        }
+    }
+
+    /// Write a report to an interrupt OUT endpoint.
+    pub async fn interrupt_out(&mut self, pipe: &mut Pipe, data: &[u8]) -> Result<(), Error> {
+        assert!(data.len() <= pipe.max_packet_size as usize);
+        self.select(pipe.address);
+        self.out_packet(pipe.endpoint, &mut pipe.toggle, data).await
+    }
+
+    fn select(&mut self, address: u8) {
+        T::hregs().dev_ad().write(|w| w.set_mask_usb_addr(address));
+    }
+
+    /// One IN packet, retried while NAKed if `wait`, `None` if NAKed otherwise.
+    async fn in_packet(
+        &mut self,
+        endp: u8,
+        toggle: &mut bool,
+        buf: &mut [u8],
+        wait: bool,
+    ) -> Result<Option<usize>, Error> {
+        let mut retries = 0;
+        loop {
+            match self.transaction(PID_IN, endp, *toggle).await? {
+                Some(Response::Data(len)) => {
+                    if len > buf.len() {
+                        return Err(Error::BufferOverflow);
+                    }
+                    self.rx.read_volatile(&mut buf[..len]);
+                    *toggle = !*toggle;
+                    return Ok(Some(len));
+                }
+                Some(Response::Nak) if !wait => return Ok(None),
+                Some(_) => embassy_futures::yield_now().await,
+                None => {
+                    retries += 1;
+                    if retries == MAX_RETRIES {
+                        return Err(Error::Timeout);
+                    }
+                }
+            }
+        }
+    }
+
+    /// One OUT packet, retried while NAKed.
+    async fn out_packet(&mut self, endp: u8, toggle: &mut bool, data: &[u8]) -> Result<(), Error> {
+        self.tx.write_volatile(data);
+        T::hregs().host_tx_len().write_value(data.len() as u8);
+
+        let mut retries = 0;
+        loop {
+            match self.transaction(PID_OUT, endp, *toggle).await? {
+                Some(Response::Ack) => {
+                    *toggle = !*toggle;
+                    return Ok(());
+                }
+                Some(_) => embassy_futures::yield_now().await,
+                None => {
+                    retries += 1;
+                    if retries == MAX_RETRIES {
+                        return Err(Error::Timeout);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run one transaction, `None` when the device didn't answer.
+    async fn transaction(&mut self, pid: u8, endp: u8, toggle: bool) -> Result<Option<Response>, Error> {
+        let h = T::hregs();
+
+        if pid == PID_IN {
+            h.host_rx_ctrl().write(|w| w.set_r_tog(toggle));
+        } else {
+            h.host_tx_ctrl().write(|w| w.set_t_tog(toggle));
+        }
+        h.int_fg().write(|w| w.set_transfer(true));
+        h.host_ep_pid().write(|w| {
+            w.set_token(pid);
+            w.set_endp(endp);
+        });
+
+        let status = poll_fn(|cx| {
+            HOST_WAKER.register(cx.waker());
+
+            if !h.mis_st().read().dev_attach() {
+                return Poll::Ready(Err(Error::Disconnected));
+            }
+            if h.int_fg().read().transfer() {
+                let status = h.int_st().read();
+                h.int_fg().write(|w| w.set_transfer(true));
+                Poll::Ready(Ok(status))
+            } else {
+                unsafe { T::Interrupt::enable() };
+                Poll::Pending
+            }
+        })
+        .await?;
+
+        Ok(match status.h_res() {
+            PID_ACK => Some(Response::Ack),
+            PID_NAK => Some(Response::Nak),
+            PID_STALL => return Err(Error::Stall),
+            PID_DATA0 | PID_DATA1 if status.tog_ok() => Some(Response::Data(h.rx_len().read().0 as usize)),
+            // A retransmission of data already received, the device missed the ACK.
+            PID_DATA0 | PID_DATA1 => Some(Response::Nak),
+            _ => None,
+        })
+    }
+}
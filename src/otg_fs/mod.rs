@@ -1,5 +1,15 @@
 //! USB_FS and OTG_FS device mode peripheral driver
-//! Note that this currently only implements device mode
+//!
+//! The full-speed USBFS/OTG_FS controller of the CH32V305/V307 and CH32X035, driven through
+//! [`embassy_usb_driver::Driver`]. Each endpoint transfers by DMA from one of the
+//! [`EndpointDataBuffer`]s handed to [`Driver::new`], the control pipe takes the one
+//! left after the class endpoints.
+//!
+//! Host mode is in [`host`], with USB sticks in `msc` with the `usb-host-msc` feature. OTG
+//! ports switch between the two with [`role`].
+//!
+//! Self-powered devices sense VBUS with [`Driver::set_vbus_detect`], so the D+ pull-up is only
+//! on while the host powers the bus.
 //!
 //! <div class="warning">
 //! There's a lot of TODOs and panics where things are not implemented
@@ -18,11 +28,14 @@
 //! - [x] Interrupt In
 //! - [ ] Bulk Out (Expected to work but not tested)
 //! - [ ] Bulk In (Expected to work but not tested)
+//! - [ ] Isochronous Out, one packet per frame (Expected to work but not tested)
+//! - [ ] Isochronous In, one packet per frame (Expected to work but not tested)
 //!
 //! Other Features:
-//! - [ ] Set endpoint stall
-//! - [ ] Get endpoint stall status
-//! - [ ] Remote wakeup
+//! - [x] Set endpoint stall
+//! - [x] Get endpoint stall status
+//! - [ ] Remote wakeup (Expected to work but not tested)
+//! - [ ] VBUS detection (Expected to work but not tested)
 //!
 
 use core::future::poll_fn;
@@ -36,10 +49,15 @@ use endpoint::{ControlPipe, Endpoint};
 
 use crate::gpio::{AFType, Speed};
 use crate::interrupt::typelevel::Interrupt;
+use crate::usb::vbus::VbusDetect;
 use crate::usb::{Dir, EndpointBufferAllocator, EndpointDataBuffer, In, Out};
 use crate::{interrupt, peripherals, Peripheral, RccPeripheral};
 
 pub mod endpoint;
+pub mod host;
+#[cfg(feature = "usb-host-msc")]
+pub mod msc;
+pub mod role;
 
 // TODO: We technically support 16, but we only allow 8 for now (0, 1-7).
 const MAX_NR_EP: usize = 8;
@@ -55,6 +73,13 @@ pub struct InterruptHandler<T: Instance> {
 
 impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
     unsafe fn on_interrupt() {
+        if T::hregs().ctrl().read().host_mode() {
+            // Transfers and attach/detach are handled by the waiting host task.
+            T::Interrupt::disable();
+            host::HOST_WAKER.wake();
+            return;
+        }
+
         let regs = T::regs();
         let int_fg = regs.int_fg().read();
 
@@ -100,16 +125,19 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
     }
 }
 
+/// USB driver.
 pub struct Driver<'d, T: Instance, const NR_EP: usize> {
     phantom: PhantomData<&'d mut T>,
     allocator: EndpointBufferAllocator<'d, NR_EP>,
     next_ep_addr: u8,
+    vbus: Option<VbusDetect<'d>>,
 }
 
 impl<'d, T, const NR_EP: usize> Driver<'d, T, NR_EP>
 where
     T: Instance,
 {
+    /// Create a new USB driver, with one buffer for each endpoint and one for the control pipe.
     pub fn new(
         _usb: impl Peripheral<P = T> + 'd,
         // _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
@@ -133,9 +161,17 @@ where
             allocator,
             // 0 is reserved for the control endpoint
             next_ep_addr: 1,
+            vbus: None,
         }
     }
 
+    /// Sense VBUS on `vbus`, connecting to the host only while it powers the bus.
+    ///
+    /// Without it, the bus is taken as powered from the start.
+    pub fn set_vbus_detect(&mut self, vbus: VbusDetect<'d>) {
+        self.vbus = Some(vbus);
+    }
+
     fn alloc_ep_address(&mut self) -> u8 {
         if self.next_ep_addr as usize >= MAX_NR_EP {
             panic!("ep addr overflow")
@@ -223,10 +259,10 @@ impl<'d, T: Instance, const NR_EP: usize> embassy_usb_driver::Driver<'d> for Dri
             w.set_bus_rst(true);
         });
 
+        // The pull-up is enabled with the bus.
         regs.ctrl().write(|w| {
             w.set_int_busy(true);
             w.set_dma_en(true);
-            w.set_dev_pu_en(true);
         });
 
         let ep0_buf = self.allocator.alloc_endpoint(control_max_packet_size).unwrap();
@@ -245,6 +281,7 @@ impl<'d, T: Instance, const NR_EP: usize> embassy_usb_driver::Driver<'d> for Dri
         (
             Bus {
                 _phantom: PhantomData,
+                vbus: self.vbus,
                 inited: false,
             },
             ControlPipe::new(ep0_buf),
@@ -252,13 +289,15 @@ impl<'d, T: Instance, const NR_EP: usize> embassy_usb_driver::Driver<'d> for Dri
     }
 }
 
+/// USB bus.
 pub struct Bus<'d, T> {
     _phantom: PhantomData<&'d T>,
+    vbus: Option<VbusDetect<'d>>,
     inited: bool,
 }
 
 impl<'d, T: Instance> Bus<'d, T> {
-    fn bus_reset(&mut self) {
+    fn bus_reset() {
         let regs = T::regs();
 
         // Reset device address
@@ -273,18 +312,26 @@ impl<'d, T: Instance> Bus<'d, T> {
 
         // Mark all other EPs as NAK
         for i in 1..=7 {
-            use embassy_usb_driver::Bus;
             regs.uep_rx_ctrl(i).write(|v| v.set_mask_r_res(EpRxResponse::NAK));
             regs.uep_tx_ctrl(i).write(|v| v.set_mask_t_res(EpTxResponse::NAK));
 
             // It looks like the HW has a bug, after reset, all EP Tx/Rx
             // are set to enabled. So we disable them here after bus reset.
-            self.endpoint_set_enabled(EndpointAddress::from_parts(i, Direction::In), false);
-            self.endpoint_set_enabled(EndpointAddress::from_parts(i, Direction::Out), false);
+            set_endpoint_enabled::<T>(EndpointAddress::from_parts(i, Direction::In), false);
+            set_endpoint_enabled::<T>(EndpointAddress::from_parts(i, Direction::Out), false);
         }
     }
 }
 
+impl<'d, T: Instance> Drop for Bus<'d, T> {
+    fn drop(&mut self) {
+        // Disconnect, e.g. for the port to become a host, see [`role`].
+        T::Interrupt::disable();
+        T::regs().ctrl().modify(|w| w.set_dev_pu_en(false));
+        T::regs().udev_ctrl().modify(|w| w.set_port_en(false));
+    }
+}
+
 impl<'d, T> embassy_usb_driver::Bus for Bus<'d, T>
 where
     T: Instance,
@@ -298,23 +345,27 @@ where
             w.set_pd_dis(true);
             w.set_port_en(true);
         });
+        regs.ctrl().modify(|w| w.set_dev_pu_en(true));
 
         // Do a bus reset on "enable"
-        self.bus_reset();
+        Self::bus_reset();
     }
 
     async fn disable(&mut self) {
-        trace!("disable")
+        trace!("disable");
+        let regs = T::regs();
+
+        regs.ctrl().modify(|w| w.set_dev_pu_en(false));
+        regs.udev_ctrl().modify(|w| w.set_port_en(false));
     }
 
     async fn poll(&mut self) -> embassy_usb_driver::Event {
-        // TODO: VBUS detection
-        if !self.inited {
+        if self.vbus.is_none() && !self.inited {
             self.inited = true;
             return Event::PowerDetected;
         }
 
-        poll_fn(|ctx| {
+        let bus = poll_fn(|ctx| {
             BUS_WAKER.register(ctx.waker());
 
             let poll_res = {
@@ -333,7 +384,7 @@ where
                     }
                 } else if interrupt_flags.bus_rst() {
                     trace!("bus: reset");
-                    self.bus_reset();
+                    Self::bus_reset();
 
                     regs.int_fg().write(|v| {
                         v.set_bus_rst(true);
@@ -346,88 +397,138 @@ where
             };
             unsafe { T::Interrupt::enable() };
             poll_res
-        })
-        .await
+        });
+
+        match &mut self.vbus {
+            Some(vbus) => vbus.poll(bus).await,
+            None => bus.await,
+        }
     }
 
     fn endpoint_set_enabled(&mut self, ep_addr: EndpointAddress, enabled: bool) {
-        #[cfg(feature = "defmt")]
-        trace!(
-            "[USBFS] Endpoint: {}, {}: Set enable={}",
-            ep_addr.index(),
-            ep_addr.direction(),
-            enabled
-        );
-        let regs = T::regs();
-
-        match (ep_addr.index(), ep_addr.direction()) {
-            (4, Direction::In) => regs.uep4_1_mod().modify(|v| {
-                v.set_uep4_tx_en(enabled);
-            }),
-            (4, Direction::Out) => regs.uep4_1_mod().modify(|v| {
-                v.set_uep4_rx_en(enabled);
-            }),
-            (1, Direction::In) => regs.uep4_1_mod().modify(|v| {
-                v.set_uep1_tx_en(enabled);
-            }),
-            (1, Direction::Out) => regs.uep4_1_mod().modify(|v| {
-                v.set_uep1_rx_en(enabled);
-            }),
+        set_endpoint_enabled::<T>(ep_addr, enabled);
+    }
 
-            (2, Direction::In) => regs.uep2_3_mod().modify(|v| {
-                v.set_uep2_tx_en(enabled);
-            }),
-            (2, Direction::Out) => regs.uep2_3_mod().modify(|v| {
-                v.set_uep2_rx_en(enabled);
-            }),
-            (3, Direction::In) => regs.uep2_3_mod().modify(|v| {
-                v.set_uep3_tx_en(enabled);
-            }),
-            (3, Direction::Out) => regs.uep2_3_mod().modify(|v| {
-                v.set_uep3_rx_en(enabled);
-            }),
+    fn endpoint_set_stalled(&mut self, ep_addr: EndpointAddress, stalled: bool) {
+        let regs = T::regs();
+        let ep = ep_addr.index();
 
-            (5, Direction::In) => regs.uep5_6_mod().modify(|v| {
-                v.set_uep5_tx_en(enabled);
-            }),
-            (5, Direction::Out) => regs.uep5_6_mod().modify(|v| {
-                v.set_uep5_rx_en(enabled);
-            }),
-            (6, Direction::In) => regs.uep5_6_mod().modify(|v| {
-                v.set_uep6_tx_en(enabled);
+        // Clearing a halt also resets the data toggle, as after a bus reset.
+        match ep_addr.direction() {
+            Direction::In => regs.uep_tx_ctrl(ep).modify(|v| {
+                if stalled {
+                    v.set_mask_t_res(EpTxResponse::STALL);
+                } else {
+                    v.set_mask_t_res(EpTxResponse::NAK);
+                    v.set_t_tog(false);
+                }
             }),
-            (6, Direction::Out) => regs.uep5_6_mod().modify(|v| {
-                v.set_uep6_rx_en(enabled);
+            Direction::Out => regs.uep_rx_ctrl(ep).modify(|v| {
+                if stalled {
+                    v.set_mask_r_res(EpRxResponse::STALL);
+                } else {
+                    v.set_mask_r_res(EpRxResponse::NAK);
+                    v.set_r_tog(false);
+                }
             }),
+        }
+        EP_WAKERS[ep].wake();
+    }
 
-            (7, Direction::In) => regs.uep7_mod().modify(|v| {
-                v.set_uep7_tx_en(enabled);
-            }),
-            (7, Direction::Out) => regs.uep7_mod().modify(|v| {
-                v.set_uep7_rx_en(enabled);
-            }),
+    fn endpoint_is_stalled(&mut self, ep_addr: EndpointAddress) -> bool {
+        let regs = T::regs();
+        let ep = ep_addr.index();
 
-            _ => {
-                #[cfg(feature = "defmt")]
-                defmt::panic!("setting non-existent endpoint {} to {}", ep_addr, enabled);
-                #[cfg(not(feature = "defmt"))]
-                panic!()
-            }
+        match ep_addr.direction() {
+            Direction::In => regs.uep_tx_ctrl(ep).read().mask_t_res() == EpTxResponse::STALL,
+            Direction::Out => regs.uep_rx_ctrl(ep).read().mask_r_res() == EpRxResponse::STALL,
         }
-        EP_WAKERS[ep_addr.index() as usize].wake();
     }
 
-    fn endpoint_set_stalled(&mut self, _ep_addr: EndpointAddress, _stalled: bool) {
-        todo!()
-    }
+    async fn remote_wakeup(&mut self) -> Result<(), embassy_usb_driver::Unsupported> {
+        let regs = T::regs();
 
-    fn endpoint_is_stalled(&mut self, _ep_addr: EndpointAddress) -> bool {
-        todo!()
+        // Swapping the pull-up to D- drives the K state, which is resume signaling, as in
+        // the WCH examples.
+        critical_section::with(|_| {
+            regs.ctrl().modify(|w| w.set_low_speed(!w.low_speed()));
+            regs.udev_ctrl().modify(|w| w.set_low_speed(!w.low_speed()));
+        });
+        embassy_time::Timer::after_millis(8).await;
+        critical_section::with(|_| {
+            regs.ctrl().modify(|w| w.set_low_speed(!w.low_speed()));
+            regs.udev_ctrl().modify(|w| w.set_low_speed(!w.low_speed()));
+        });
+        embassy_time::Timer::after_millis(1).await;
+        Ok(())
     }
+}
 
-    async fn remote_wakeup(&mut self) -> Result<(), embassy_usb_driver::Unsupported> {
-        todo!()
+fn set_endpoint_enabled<T: Instance>(ep_addr: EndpointAddress, enabled: bool) {
+    #[cfg(feature = "defmt")]
+    trace!(
+        "[USBFS] Endpoint: {}, {}: Set enable={}",
+        ep_addr.index(),
+        ep_addr.direction(),
+        enabled
+    );
+    let regs = T::regs();
+
+    match (ep_addr.index(), ep_addr.direction()) {
+        (4, Direction::In) => regs.uep4_1_mod().modify(|v| {
+            v.set_uep4_tx_en(enabled);
+        }),
+        (4, Direction::Out) => regs.uep4_1_mod().modify(|v| {
+            v.set_uep4_rx_en(enabled);
+        }),
+        (1, Direction::In) => regs.uep4_1_mod().modify(|v| {
+            v.set_uep1_tx_en(enabled);
+        }),
+        (1, Direction::Out) => regs.uep4_1_mod().modify(|v| {
+            v.set_uep1_rx_en(enabled);
+        }),
+
+        (2, Direction::In) => regs.uep2_3_mod().modify(|v| {
+            v.set_uep2_tx_en(enabled);
+        }),
+        (2, Direction::Out) => regs.uep2_3_mod().modify(|v| {
+            v.set_uep2_rx_en(enabled);
+        }),
+        (3, Direction::In) => regs.uep2_3_mod().modify(|v| {
+            v.set_uep3_tx_en(enabled);
+        }),
+        (3, Direction::Out) => regs.uep2_3_mod().modify(|v| {
+            v.set_uep3_rx_en(enabled);
+        }),
+
+        (5, Direction::In) => regs.uep5_6_mod().modify(|v| {
+            v.set_uep5_tx_en(enabled);
+        }),
+        (5, Direction::Out) => regs.uep5_6_mod().modify(|v| {
+            v.set_uep5_rx_en(enabled);
+        }),
+        (6, Direction::In) => regs.uep5_6_mod().modify(|v| {
+            v.set_uep6_tx_en(enabled);
+        }),
+        (6, Direction::Out) => regs.uep5_6_mod().modify(|v| {
+            v.set_uep6_rx_en(enabled);
+        }),
+
+        (7, Direction::In) => regs.uep7_mod().modify(|v| {
+            v.set_uep7_tx_en(enabled);
+        }),
+        (7, Direction::Out) => regs.uep7_mod().modify(|v| {
+            v.set_uep7_rx_en(enabled);
+        }),
+
+        _ => {
+            #[cfg(feature = "defmt")]
+            defmt::panic!("setting non-existent endpoint {} to {}", ep_addr, enabled);
+            #[cfg(not(feature = "defmt"))]
+            panic!()
+        }
     }
+    EP_WAKERS[ep_addr.index() as usize].wake();
 }
 
 pin_trait!(DpPin, Instance);
@@ -435,6 +536,7 @@ pin_trait!(DmPin, Instance);
 
 trait SealedInstance: RccPeripheral {
     fn regs() -> crate::pac::otg::Usbd;
+    fn hregs() -> crate::pac::otg::Usbh;
 }
 
 /// OTG_FS peripheral instance
@@ -447,8 +549,12 @@ foreach_peripheral!(
     (otg, $inst:ident) => {
         impl SealedInstance for peripherals::$inst {
             fn regs() -> crate::pac::otg::Usbd {
-                // datasheet
-                unsafe { crate::pac::otg::Usbd::from_ptr(crate::pac::OTG_FS.as_ptr()) }
+                // Device mode registers of the instance, OTG_FS on the V30x and USBFS on the X035.
+                unsafe { crate::pac::otg::Usbd::from_ptr(crate::pac::$inst.as_ptr()) }
+            }
+
+            fn hregs() -> crate::pac::otg::Usbh {
+                unsafe { crate::pac::otg::Usbh::from_ptr(crate::pac::$inst.as_ptr()) }
             }
         }
 
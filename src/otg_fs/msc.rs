@@ -0,0 +1,463 @@
+//! USB mass storage devices on the [`host`](super::host), e.g. USB sticks and card readers
+//!
+//! Bulk-only transport (BOT) with the SCSI transparent command set, which is what nearly all
+//! of them speak. [`MassStorage`] reads and writes whole blocks, and implements the
+//! `embedded-storage-async` traits on byte offsets for filesystem crates:
+//!
+//! ```rust,ignore
+//! let device = host.enumerate(1).await?;
+//! let len = host.configuration_descriptor(&device, &mut config).await?;
+//! let mut disk = MassStorage::new(&mut host, device, &config[..len]).await?;
+//!
+//! let mut mbr = [0; 512];
+//! disk.read_blocks(0, &mut mbr).await?;
+//! ```
+//!
+//! Only the first logical unit is used, and media up to 2 TiB, with blocks of up to 512 bytes.
+
+use embassy_time::Timer;
+
+use super::host::{self, Device, EndpointDescriptor, Host, Pipe, SetupPacket};
+use super::Instance;
+
+const DESCRIPTOR_INTERFACE: u8 = 4;
+const DESCRIPTOR_ENDPOINT: u8 = 5;
+
+const CLASS_MASS_STORAGE: u8 = 0x08;
+const SUBCLASS_SCSI: u8 = 0x06;
+const PROTOCOL_BULK_ONLY: u8 = 0x50;
+
+const REQ_BULK_ONLY_RESET: u8 = 0xFF;
+const REQ_GET_MAX_LUN: u8 = 0xFE;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CBW_LEN: usize = 31;
+const CSW_LEN: usize = 13;
+
+const CSW_PASSED: u8 = 0;
+const CSW_FAILED: u8 = 1;
+
+const SCSI_TEST_UNIT_READY: u8 = 0x00;
+const SCSI_REQUEST_SENSE: u8 = 0x03;
+const SCSI_INQUIRY: u8 = 0x12;
+const SCSI_READ_CAPACITY_10: u8 = 0x25;
+const SCSI_READ_10: u8 = 0x28;
+const SCSI_WRITE_10: u8 = 0x2A;
+
+/// Largest block size supported.
+pub const MAX_BLOCK_SIZE: usize = 512;
+
+/// How many times to ask a device that is spinning up or reporting a media change whether
+/// it's ready.
+const READY_ATTEMPTS: usize = 20;
+
+/// Mass storage errors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A transfer failed.
+    Host(host::Error),
+    /// The configuration has no bulk-only SCSI interface.
+    NotMassStorage,
+    /// The device failed the command, with its sense data.
+    Sense { key: u8, asc: u8, ascq: u8 },
+    /// The device answered out of protocol, it was reset.
+    PhaseError,
+    /// The access is beyond the end of the medium, or not a whole number of blocks.
+    OutOfBounds,
+    /// The blocks are larger than [`MAX_BLOCK_SIZE`].
+    UnsupportedBlockSize,
+}
+
+impl From<host::Error> for Error {
+    fn from(e: host::Error) -> Self {
+        Self::Host(e)
+    }
+}
+
+enum Data<'b> {
+    None,
+    In(&'b mut [u8]),
+    Out(&'b [u8]),
+}
+
+/// A mass storage device.
+pub struct MassStorage<'a, 'd, T: Instance> {
+    host: &'a mut Host<'d, T>,
+    device: Device,
+    interface: u8,
+    bulk_in: Pipe,
+    bulk_in_address: u8,
+    bulk_out: Pipe,
+    bulk_out_address: u8,
+    tag: u32,
+    block_size: u32,
+    block_count: u32,
+}
+
+impl<'a, 'd, T: Instance> MassStorage<'a, 'd, T> {
+    /// Configure the enumerated `device`, whose configuration descriptor is `config`, and
+    /// wait for its medium to be ready.
+    pub async fn new(host: &'a mut Host<'d, T>, device: Device, config: &[u8]) -> Result<Self, Error> {
+        if config.len() < 9 {
+            return Err(Error::NotMassStorage);
+        }
+        let (interface, ep_in, ep_out) = find_interface(config).ok_or(Error::NotMassStorage)?;
+        host.set_configuration(&device, config[5]).await?;
+
+        let mut this = Self {
+            host,
+            device,
+            interface,
+            bulk_in: Pipe::new(&device, &ep_in),
+            bulk_in_address: ep_in.address,
+            bulk_out: Pipe::new(&device, &ep_out),
+            bulk_out_address: ep_out.address,
+            tag: 0,
+            block_size: 0,
+            block_count: 0,
+        };
+
+        // Devices with a single LUN may stall this.
+        let mut max_lun = [0];
+        match this.class_request(REQ_GET_MAX_LUN, &mut max_lun).await {
+            Ok(_) => trace!("mass storage: {} LUNs", max_lun[0] + 1),
+            Err(Error::Host(host::Error::Stall)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut inquiry = [0; 36];
+        this.command(&[SCSI_INQUIRY, 0, 0, 0, 36, 0], Data::In(&mut inquiry))
+            .await?;
+
+        let mut attempts = 0;
+        loop {
+            match this.command(&[SCSI_TEST_UNIT_READY, 0, 0, 0, 0, 0], Data::None).await {
+                Ok(_) => break,
+                Err(Error::Sense { .. }) if attempts < READY_ATTEMPTS => {
+                    attempts += 1;
+                    Timer::after_millis(100).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut capacity = [0; 8];
+        this.command(
+            &[SCSI_READ_CAPACITY_10, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            Data::In(&mut capacity),
+        )
+        .await?;
+        let last_block = u32::from_be_bytes([capacity[0], capacity[1], capacity[2], capacity[3]]);
+        this.block_size = u32::from_be_bytes([capacity[4], capacity[5], capacity[6], capacity[7]]);
+        // Larger media report 0xFFFFFFFF and need READ CAPACITY (16), keep to the first 2 TiB.
+        this.block_count = last_block.saturating_add(1);
+
+        if this.block_size == 0 || this.block_size as usize > MAX_BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize);
+        }
+        Ok(this)
+    }
+
+    /// The device.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Size of the blocks, in bytes.
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Number of blocks of the medium.
+    pub fn block_count(&self) -> u32 {
+        self.block_count
+    }
+
+    /// Read the blocks starting at `lba` into `buf`, a whole number of blocks.
+    pub async fn read_blocks(&mut self, lba: u32, buf: &mut [u8]) -> Result<(), Error> {
+        let cb = self.read_write_command(SCSI_READ_10, lba, buf.len())?;
+        self.command(&cb, Data::In(buf)).await?;
+        Ok(())
+    }
+
+    /// Write `data`, a whole number of blocks, to the blocks starting at `lba`.
+    pub async fn write_blocks(&mut self, lba: u32, data: &[u8]) -> Result<(), Error> {
+        let cb = self.read_write_command(SCSI_WRITE_10, lba, data.len())?;
+        self.command(&cb, Data::Out(data)).await?;
+        Ok(())
+    }
+
+    fn read_write_command(&self, opcode: u8, lba: u32, len: usize) -> Result<[u8; 10], Error> {
+        let block_size = self.block_size as usize;
+        let count = len / block_size;
+        if len % block_size != 0 || count > u16::MAX as usize || lba as u64 + count as u64 > self.block_count as u64 {
+            return Err(Error::OutOfBounds);
+        }
+
+        let lba = lba.to_be_bytes();
+        let count = (count as u16).to_be_bytes();
+        Ok([opcode, 0, lba[0], lba[1], lba[2], lba[3], 0, count[0], count[1], 0])
+    }
+
+    /// Run a SCSI command, returning the length of the data transferred.
+    async fn command(&mut self, cb: &[u8], data: Data<'_>) -> Result<usize, Error> {
+        let (len, status) = self.transport(cb, data).await?;
+        if status == CSW_FAILED {
+            return Err(self.request_sense().await?);
+        }
+        Ok(len)
+    }
+
+    async fn request_sense(&mut self) -> Result<Error, Error> {
+        let mut sense = [0; 18];
+        let (_, status) = self
+            .transport(&[SCSI_REQUEST_SENSE, 0, 0, 0, 18, 0], Data::In(&mut sense))
+            .await?;
+        if status != CSW_PASSED {
+            return Err(Error::PhaseError);
+        }
+        Ok(Error::Sense {
+            key: sense[2] & 0x0F,
+            asc: sense[12],
+            ascq: sense[13],
+        })
+    }
+
+    /// One command, data and status transport, returning the length of the data and the
+    /// command status.
+    async fn transport(&mut self, cb: &[u8], data: Data<'_>) -> Result<(usize, u8), Error> {
+        assert!(cb.len() <= 16);
+        self.tag = self.tag.wrapping_add(1);
+
+        let (length, flags) = match &data {
+            Data::None => (0, 0),
+            Data::In(buf) => (buf.len(), 0x80),
+            Data::Out(data) => (data.len(), 0x00),
+        };
+        let mut cbw = [0; CBW_LEN];
+        cbw[0..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        cbw[4..8].copy_from_slice(&self.tag.to_le_bytes());
+        cbw[8..12].copy_from_slice(&(length as u32).to_le_bytes());
+        cbw[12] = flags;
+        // LUN 0
+        cbw[13] = 0;
+        cbw[14] = cb.len() as u8;
+        cbw[15..15 + cb.len()].copy_from_slice(cb);
+
+        if let Err(e) = self.host.bulk_out(&mut self.bulk_out, &cbw).await {
+            if e == host::Error::Stall {
+                self.reset_recovery().await?;
+            }
+            return Err(e.into());
+        }
+
+        // A stalled data stage still ends with a status.
+        let transferred = match data {
+            Data::None => Ok(0),
+            Data::In(buf) => self.host.bulk_in(&mut self.bulk_in, buf).await,
+            Data::Out(data) => self.host.bulk_out(&mut self.bulk_out, data).await.map(|_| data.len()),
+        };
+        let transferred = match transferred {
+            Ok(n) => n,
+            Err(host::Error::Stall) => {
+                let address = match flags {
+                    0x80 => self.bulk_in_address,
+                    _ => self.bulk_out_address,
+                };
+                self.clear_halt(address).await?;
+                0
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut csw = [0; CSW_LEN];
+        let len = match self.host.bulk_in(&mut self.bulk_in, &mut csw).await {
+            Err(host::Error::Stall) => {
+                self.clear_halt(self.bulk_in_address).await?;
+                self.host.bulk_in(&mut self.bulk_in, &mut csw).await?
+            }
+            len => len?,
+        };
+
+        let signature = u32::from_le_bytes([csw[0], csw[1], csw[2], csw[3]]);
+        let tag = u32::from_le_bytes([csw[4], csw[5], csw[6], csw[7]]);
+        let residue = u32::from_le_bytes([csw[8], csw[9], csw[10], csw[11]]);
+        let status = csw[12];
+        if len != CSW_LEN || signature != CSW_SIGNATURE || tag != self.tag || status > CSW_FAILED {
+            warn!("mass storage: bad status, resetting");
+            self.reset_recovery().await?;
+            return Err(Error::PhaseError);
+        }
+
+        Ok((transferred.min(length - (residue as usize).min(length)), status))
+    }
+
+    /// Bulk-only mass storage reset, then clear both halts.
+    async fn reset_recovery(&mut self) -> Result<(), Error> {
+        self.class_request(REQ_BULK_ONLY_RESET, &mut []).await?;
+        self.clear_halt(self.bulk_in_address).await?;
+        self.clear_halt(self.bulk_out_address).await
+    }
+
+    async fn clear_halt(&mut self, address: u8) -> Result<(), Error> {
+        let setup = SetupPacket {
+            request_type: 0x02,
+            // CLEAR_FEATURE(ENDPOINT_HALT)
+            request: 0x01,
+            value: 0,
+            index: address as u16,
+            length: 0,
+        };
+        self.host.control_out(&self.device, &setup, &[]).await?;
+
+        if address & 0x80 != 0 {
+            self.bulk_in.reset_toggle();
+        } else {
+            self.bulk_out.reset_toggle();
+        }
+        Ok(())
+    }
+
+    async fn class_request(&mut self, request: u8, buf: &mut [u8]) -> Result<usize, Error> {
+        let setup = SetupPacket {
+            request_type: if buf.is_empty() { 0x21 } else { 0xA1 },
+            request,
+            value: 0,
+            index: self.interface as u16,
+            length: buf.len() as u16,
+        };
+        if buf.is_empty() {
+            self.host.control_out(&self.device, &setup, &[]).await?;
+            Ok(0)
+        } else {
+            Ok(self.host.control_in(&self.device, &setup, buf).await?)
+        }
+    }
+}
+
+impl<'a, 'd, T: Instance> embedded_storage_async::ReadStorage for MassStorage<'a, 'd, T> {
+    type Error = Error;
+
+    async fn read(&mut self, offset: u32, mut bytes: &mut [u8]) -> Result<(), Error> {
+        if offset as u64 + bytes.len() as u64 > self.capacity() as u64 {
+            return Err(Error::OutOfBounds);
+        }
+
+        let block_size = self.block_size as usize;
+        let mut offset = offset as usize;
+        while !bytes.is_empty() {
+            let lba = (offset / block_size) as u32;
+            let skip = offset % block_size;
+
+            let n = if skip == 0 && bytes.len() >= block_size {
+                let n = (bytes.len() / block_size).min(u16::MAX as usize) * block_size;
+                self.read_blocks(lba, &mut bytes[..n]).await?;
+                n
+            } else {
+                let mut block = [0; MAX_BLOCK_SIZE];
+                self.read_blocks(lba, &mut block[..block_size]).await?;
+                let n = (block_size - skip).min(bytes.len());
+                bytes[..n].copy_from_slice(&block[skip..skip + n]);
+                n
+            };
+            offset += n;
+            bytes = &mut bytes[n..];
+        }
+        Ok(())
+    }
+
+    /// The size of the medium, limited to the 4 GiB the byte offsets reach.
+    fn capacity(&self) -> usize {
+        let capacity = self.block_count as u64 * self.block_size as u64;
+        capacity.min(u32::MAX as u64) as usize
+    }
+}
+
+impl<'a, 'd, T: Instance> embedded_storage_async::Storage for MassStorage<'a, 'd, T> {
+    async fn write(&mut self, offset: u32, mut bytes: &[u8]) -> Result<(), Error> {
+        use embedded_storage_async::ReadStorage;
+
+        if offset as u64 + bytes.len() as u64 > self.capacity() as u64 {
+            return Err(Error::OutOfBounds);
+        }
+
+        let block_size = self.block_size as usize;
+        let mut offset = offset as usize;
+        while !bytes.is_empty() {
+            let lba = (offset / block_size) as u32;
+            let skip = offset % block_size;
+
+            let n = if skip == 0 && bytes.len() >= block_size {
+                let n = (bytes.len() / block_size).min(u16::MAX as usize) * block_size;
+                self.write_blocks(lba, &bytes[..n]).await?;
+                n
+            } else {
+                // Partial block, read-modify-write.
+                let mut block = [0; MAX_BLOCK_SIZE];
+                self.read_blocks(lba, &mut block[..block_size]).await?;
+                let n = (block_size - skip).min(bytes.len());
+                block[skip..skip + n].copy_from_slice(&bytes[..n]);
+                self.write_blocks(lba, &block[..block_size]).await?;
+                n
+            };
+            offset += n;
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+}
+
+/// The first bulk-only SCSI interface of a configuration, with its bulk IN and OUT endpoints.
+fn find_interface(config: &[u8]) -> Option<(u8, EndpointDescriptor, EndpointDescriptor)> {
+    let mut rest = config;
+    let mut interface = None;
+    let mut ep_in = None;
+    let mut ep_out = None;
+
+    loop {
+        let len = *rest.first()? as usize;
+        if len < 2 || len > rest.len() {
+            return None;
+        }
+        let (desc, tail) = rest.split_at(len);
+        rest = tail;
+
+        match desc[1] {
+            DESCRIPTOR_INTERFACE if len >= 9 => {
+                if interface.is_some() && ep_in.is_some() && ep_out.is_some() {
+                    break;
+                }
+                let matches = desc[3] == 0
+                    && desc[5] == CLASS_MASS_STORAGE
+                    && desc[6] == SUBCLASS_SCSI
+                    && desc[7] == PROTOCOL_BULK_ONLY;
+                interface = matches.then_some(desc[2]);
+                ep_in = None;
+                ep_out = None;
+            }
+            // Bulk endpoints
+            DESCRIPTOR_ENDPOINT if len >= 7 && interface.is_some() && desc[3] & 0x03 == 0x02 => {
+                let ep = EndpointDescriptor {
+                    address: desc[2],
+                    attributes: desc[3],
+                    max_packet_size: u16::from_le_bytes([desc[4], desc[5]]),
+                    interval: desc[6],
+                };
+                if ep.address & 0x80 != 0 {
+                    ep_in.get_or_insert(ep);
+                } else {
+                    ep_out.get_or_insert(ep);
+                }
+            }
+            _ => {}
+        }
+
+        if rest.is_empty() {
+            break;
+        }
+    }
+
+    Some((interface?, ep_in?, ep_out?))
+}
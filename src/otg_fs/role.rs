@@ -0,0 +1,102 @@
+//! Host or device role from the ID pin, for OTG ports
+//!
+//! The A end of an OTG cable grounds the ID pin of a micro-AB receptacle, making the port a
+//! host, which powers VBUS. Otherwise it's a device. The controller runs one role at a time,
+//! the application builds the [`Driver`](super::Driver) or the [`Host`](super::host::Host)
+//! for the current role on reborrowed peripherals, and tears it down when the role changes:
+//!
+//! ```rust,ignore
+//! let id = ExtiInput::new(p.PA10, p.EXTI10, Pull::Up);
+//! let vbus = Output::new(p.PB12, Level::Low, Speed::Low);
+//! let mut otg = RoleDetect::new(id, Some(vbus));
+//!
+//! loop {
+//!     match otg.role() {
+//!         Role::Device => {
+//!             let driver = otg_fs::Driver::new(&mut p.OTG_FS, &mut p.PA12, &mut p.PA11, &mut ep_buffers);
+//!             let mut device = build_device(driver);
+//!             select(device.run(), otg.wait_for_change()).await;
+//!         }
+//!         Role::Host => {
+//!             let mut host = Host::new(&mut p.OTG_FS, Irqs, &mut p.PA12, &mut p.PA11, &mut host_buffers);
+//!             select(run_host(&mut host), otg.wait_for_change()).await;
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Dropping the device stack disconnects from the host, and dropping the [`Host`](super::host::Host)
+//! turns the port off, before the other role starts.
+
+use embassy_time::Duration;
+
+use crate::exti::{Debouncer, ExtiInput};
+use crate::gpio::{Level, Output};
+
+/// How long the ID pin has to be stable, while the plug goes in.
+const ID_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Role of the port.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Role {
+    /// ID pin grounded, by an A plug.
+    Host,
+    /// ID pin floating, with a B plug or nothing.
+    Device,
+}
+
+impl Role {
+    fn from_id(level: Level) -> Self {
+        match level {
+            Level::Low => Self::Host,
+            Level::High => Self::Device,
+        }
+    }
+}
+
+/// ID pin sensing, switching VBUS for the host role.
+pub struct RoleDetect<'d> {
+    id: Debouncer<'d>,
+    vbus: Option<Output<'d>>,
+}
+
+impl<'d> RoleDetect<'d> {
+    /// Sense the role on `id`, which needs a pull-up, and drive the switch powering VBUS with
+    /// `vbus`, high while host.
+    pub fn new(id: ExtiInput<'d>, vbus: Option<Output<'d>>) -> Self {
+        let mut this = Self {
+            id: Debouncer::new(id, ID_DEBOUNCE),
+            vbus,
+        };
+        this.set_vbus();
+        this
+    }
+
+    /// The current role.
+    pub fn role(&self) -> Role {
+        Role::from_id(self.id.get_level())
+    }
+
+    /// Wait for the role to change, and return the new one.
+    ///
+    /// VBUS is switched off as soon as the port stops being host, and on when it becomes one.
+    pub async fn wait_for_change(&mut self) -> Role {
+        let role = Role::from_id(self.id.wait_for_change().await);
+        trace!("otg: role {:?}", role);
+        self.set_vbus();
+        role
+    }
+
+    fn set_vbus(&mut self) {
+        let host = self.role() == Role::Host;
+        if let Some(vbus) = &mut self.vbus {
+            vbus.set_level(Level::from(host));
+        }
+    }
+
+    /// Release the pins.
+    pub fn release(self) -> (ExtiInput<'d>, Option<Output<'d>>) {
+        (self.id.release(), self.vbus)
+    }
+}
@@ -9,6 +9,9 @@
 mod i2c2 {
     impl crate::peripheral::SealedRemapPeripheral for crate::peripherals::I2C2 {
         fn set_remap(_remap: u8) {}
+        fn get_remap() -> u8 {
+            0
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::I2C2 {}
 }
@@ -22,6 +25,10 @@ mod usart1 {
             afio.pcfr1().modify(|w| w.set_usart1_rm(remap & 0b1 != 0));
             afio.pcfr2().modify(|w| w.set_usart1_rm2(remap & 0b10 != 0));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            (afio.pcfr1().read().usart1_rm() as u8) | ((afio.pcfr2().read().usart1_rm2() as u8) << 1)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::USART1 {}
 }
@@ -34,6 +41,10 @@ mod usart1 {
             afio.pcfr1().modify(|w| w.set_usart1_rm(remap & 0b1 != 0));
             afio.pcfr1().modify(|w| w.set_usart1_rm1(remap & 0b10 != 0));
         }
+        fn get_remap() -> u8 {
+            let pcfr1 = crate::pac::AFIO.pcfr1().read();
+            (pcfr1.usart1_rm() as u8) | ((pcfr1.usart1_rm1() as u8) << 1)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::USART1 {}
 }
@@ -46,6 +57,10 @@ mod i2c1 {
             afio.pcfr1().modify(|w| w.set_i2c1_rm(remap & 0b1 != 0));
             afio.pcfr1().modify(|w| w.set_i2c1_rm1(remap & 0b10 != 0));
         }
+        fn get_remap() -> u8 {
+            let pcfr1 = crate::pac::AFIO.pcfr1().read();
+            (pcfr1.i2c1_rm() as u8) | ((pcfr1.i2c1_rm1() as u8) << 1)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::I2C1 {}
 }
@@ -54,6 +69,9 @@ mod i2c1 {
 mod spi2 {
     impl crate::peripheral::SealedRemapPeripheral for crate::peripherals::SPI2 {
         fn set_remap(_remap: u8) {}
+        fn get_remap() -> u8 {
+            0
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::SPI2 {}
 }
@@ -62,6 +80,9 @@ mod spi2 {
 mod tim5 {
     impl crate::peripheral::SealedRemapPeripheral for crate::peripherals::TIM5 {
         fn set_remap(_remap: u8) {}
+        fn get_remap() -> u8 {
+            0
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::TIM5 {}
 }
@@ -70,6 +91,9 @@ mod tim5 {
 mod tim6 {
     impl crate::peripheral::SealedRemapPeripheral for crate::peripherals::TIM6 {
         fn set_remap(_remap: u8) {}
+        fn get_remap() -> u8 {
+            0
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::TIM6 {}
 }
@@ -78,6 +102,9 @@ mod tim6 {
 mod tim7 {
     impl crate::peripheral::SealedRemapPeripheral for crate::peripherals::TIM7 {
         fn set_remap(_remap: u8) {}
+        fn get_remap() -> u8 {
+            0
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::TIM7 {}
 }
@@ -86,6 +113,9 @@ mod tim7 {
 mod sdio {
     impl crate::peripheral::SealedRemapPeripheral for crate::peripherals::SDIO {
         fn set_remap(_remap: u8) {}
+        fn get_remap() -> u8 {
+            0
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::SDIO {}
 }
@@ -98,6 +128,10 @@ mod ch32l1 {
             afio.pcfr2().modify(|w| w.set_usart1_rm_h((remap & 0b110) >> 1));
             afio.pcfr1().modify(|w| w.set_usart1_rm(remap & 0b1 != 0));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            (afio.pcfr2().read().usart1_rm_h() << 1) | (afio.pcfr1().read().usart1_rm() as u8)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::USART1 {}
 
@@ -107,6 +141,10 @@ mod ch32l1 {
             afio.pcfr2().modify(|w| w.set_usart2_rm_h(remap & 0b10 != 0));
             afio.pcfr1().modify(|w| w.set_usart2_rm(remap & 0b1 != 0));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            ((afio.pcfr2().read().usart2_rm_h() as u8) << 1) | (afio.pcfr1().read().usart2_rm() as u8)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::USART2 {}
 
@@ -116,6 +154,10 @@ mod ch32l1 {
             afio.pcfr2().modify(|w| w.set_spi1_rm_h(remap & 0b10 != 0));
             afio.pcfr1().modify(|w| w.set_spi1_rm(remap & 0b1 != 0));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            ((afio.pcfr2().read().spi1_rm_h() as u8) << 1) | (afio.pcfr1().read().spi1_rm() as u8)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::SPI1 {}
 
@@ -125,6 +167,10 @@ mod ch32l1 {
             afio.pcfr2().modify(|w| w.set_tim1_rm_h(remap & 0b100 != 0));
             afio.pcfr1().modify(|w| w.set_tim1_rm(remap & 0b11));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            ((afio.pcfr2().read().tim1_rm_h() as u8) << 2) | afio.pcfr1().read().tim1_rm()
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::TIM1 {}
 
@@ -134,6 +180,10 @@ mod ch32l1 {
             afio.pcfr2().modify(|w| w.set_tim2_rm_h(remap & 0b100 != 0));
             afio.pcfr1().modify(|w| w.set_tim2_rm(remap & 0b11));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            ((afio.pcfr2().read().tim2_rm_h() as u8) << 2) | afio.pcfr1().read().tim2_rm()
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::TIM2 {}
 
@@ -143,6 +193,10 @@ mod ch32l1 {
             afio.pcfr2().modify(|w| w.set_i2c1_rm_h(remap & 0b10 != 0));
             afio.pcfr1().modify(|w| w.set_i2c1_rm(remap & 0b1 != 0));
         }
+        fn get_remap() -> u8 {
+            let afio = &crate::pac::AFIO;
+            ((afio.pcfr2().read().i2c1_rm_h() as u8) << 1) | (afio.pcfr1().read().i2c1_rm() as u8)
+        }
     }
     impl crate::peripheral::RemapPeripheral for crate::peripherals::I2C1 {}
 }
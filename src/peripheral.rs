@@ -15,6 +15,7 @@ pub(crate) trait SealedRccPeripheral {
 
 pub(crate) trait SealedRemapPeripheral {
     fn set_remap(remap: u8);
+    fn get_remap() -> u8;
 }
 
 #[allow(private_bounds)]
@@ -11,6 +11,8 @@ static mut CLOCKS: Clocks = Clocks {
 
     pclk1_tim: DEFAULT_FREQUENCY,
     pclk2_tim: DEFAULT_FREQUENCY,
+
+    rtc: None,
 };
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -25,6 +27,9 @@ pub struct Clocks {
 
     pub(crate) pclk1_tim: Hertz,
     pub(crate) pclk2_tim: Hertz,
+
+    /// RTC clock, when selected by the `ls` part of the RCC config
+    pub rtc: Option<Hertz>,
 }
 
 #[inline]
@@ -59,18 +64,44 @@ pub const LSI_FREQ: Hertz = Hertz(40_000);
 #[cfg(ch32v208)]
 pub const LSI_FREQ: Hertz = Hertz(32_768);
 
+/// LSE mode.
+///
+/// The LSE oscillator of CH32 chips has no drive strength setting, unlike some STM32 parts.
 #[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum LseMode {
-    Oscillator, // (LseDrive),
+    /// 32.768 kHz crystal between OSC32_IN and OSC32_OUT
+    Oscillator,
+    /// External clock on OSC32_IN (LSEBYP=1)
     Bypass,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct LseConfig {
     pub frequency: Hertz,
     pub mode: LseMode,
+    /// Give up waiting for the crystal to start after about this many milliseconds.
+    ///
+    /// Crystals typically take 1 to 3 seconds to start, `None` waits forever.
+    pub timeout_ms: Option<u32>,
 }
 
+impl Default for LseConfig {
+    fn default() -> Self {
+        Self {
+            frequency: Hertz(32_768),
+            mode: LseMode::Oscillator,
+            timeout_ms: Some(3_000),
+        }
+    }
+}
+
+/// The LSE did not become ready within its timeout.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LseTimeout;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RtcClockSource {
     LSE,
     LSI,
@@ -91,7 +122,8 @@ impl LsConfig {
             rtc: RtcClockSource::LSE,
             lse: Some(LseConfig {
                 frequency: Hertz(32_768),
-                mode: LseMode::Oscillator, // (LseDrive::MediumHigh),
+                mode: LseMode::Oscillator,
+                timeout_ms: Some(3_000),
             }),
             lsi: false,
         }
@@ -120,12 +152,152 @@ impl Default for LsConfig {
     }
 }
 
-// TODO: implement LS clock configuration
-#[allow(unused)]
+#[cfg(rtc)]
 impl LsConfig {
+    /// Start the low speed oscillators and select the RTC clock, returning its frequency.
+    ///
+    /// When the LSE does not start in time, the RTC falls back to LSI. An RTC already running
+    /// from another clock is left alone, changing it needs a backup domain reset, which also
+    /// clears the backup registers, see `rtc::Rtc::new`.
+    ///
+    /// [`off`](Self::off) leaves the backup domain write protected, for `rtc::Rtc::new` to
+    /// configure, and only reports the clock of an RTC still running from before the reset.
     pub(crate) fn init(&self) -> Option<Hertz> {
-        todo!()
+        use crate::pac::rcc::vals::Rtcsel;
+        use crate::pac::RCC;
+
+        let off = self.rtc == RtcClockSource::DISABLE && !self.lsi && self.lse.is_none();
+        if off {
+            return match RCC.bdctlr().read().rtcsel().to_bits() {
+                0b01 if RCC.bdctlr().read().lserdy() => Some(Hertz(32_768)),
+                0b10 if RCC.rstsckr().read().lsirdy() => Some(LSI_FREQ),
+                _ => None,
+            };
+        }
+
+        enable_backup_domain();
+
+        let lse_ok = match &self.lse {
+            Some(lse) => match enable_lse(lse) {
+                Ok(()) => true,
+                Err(LseTimeout) => {
+                    warn!("LSE did not start, falling back to LSI");
+                    false
+                }
+            },
+            None => false,
+        };
+
+        let source = match self.rtc {
+            RtcClockSource::LSE if !lse_ok => RtcClockSource::LSI,
+            source => source,
+        };
+
+        if self.lsi || source == RtcClockSource::LSI {
+            enable_lsi();
+        }
+
+        let sel = match source {
+            RtcClockSource::DISABLE => Rtcsel::from_bits(0b00),
+            RtcClockSource::LSE => Rtcsel::from_bits(0b01),
+            RtcClockSource::LSI => Rtcsel::from_bits(0b10),
+            RtcClockSource::HSE => Rtcsel::from_bits(0b11),
+        };
+        let current = RCC.bdctlr().read().rtcsel();
+        if current == Rtcsel::from_bits(0) && source != RtcClockSource::DISABLE {
+            RCC.bdctlr().modify(|w| {
+                w.set_rtcsel(sel);
+                w.set_rtcen(true);
+            });
+        } else if current != sel {
+            warn!("RTC keeps running from its previous clock");
+        }
+
+        match RCC.bdctlr().read().rtcsel().to_bits() {
+            0b01 => Some(self.lse.as_ref().map_or(Hertz(32_768), |lse| lse.frequency)),
+            0b10 => Some(LSI_FREQ),
+            // The HSE frequency is not known here, see `rtc::Config::hse_div128`.
+            _ => None,
+        }
+    }
+}
+
+/// Start the LSE and wait for it to become ready.
+///
+/// On timeout the oscillator is turned off again.
+#[cfg(rtc)]
+pub fn enable_lse(config: &LseConfig) -> Result<(), LseTimeout> {
+    use crate::pac::RCC;
+
+    enable_backup_domain();
+
+    if RCC.bdctlr().read().lserdy() {
+        return Ok(());
     }
+
+    RCC.bdctlr().modify(|w| w.set_lseon(false));
+    RCC.bdctlr().modify(|w| w.set_lsebyp(config.mode == LseMode::Bypass));
+    RCC.bdctlr().modify(|w| w.set_lseon(true));
+
+    // Counting polls against HCLK, each poll takes a few cycles at least.
+    let polls_per_ms = clocks().hclk.0 / 1_000 / 4;
+    let mut polls = config.timeout_ms.map(|ms| ms.saturating_mul(polls_per_ms));
+    while !RCC.bdctlr().read().lserdy() {
+        match &mut polls {
+            Some(0) => {
+                RCC.bdctlr().modify(|w| w.set_lseon(false));
+                return Err(LseTimeout);
+            }
+            Some(n) => *n -= 1,
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Stop the LSE.
+///
+/// This stops an RTC clocked from it.
+#[cfg(rtc)]
+pub fn disable_lse() {
+    enable_backup_domain();
+    crate::pac::RCC.bdctlr().modify(|w| w.set_lseon(false));
+}
+
+/// Whether the LSE is running.
+#[cfg(rtc)]
+pub fn is_lse_ready() -> bool {
+    crate::pac::RCC.bdctlr().read().lserdy()
+}
+
+/// Start the LSI and wait for it to become ready.
+pub fn enable_lsi() {
+    use crate::pac::RCC;
+
+    RCC.rstsckr().modify(|w| w.set_lsion(true));
+    while !RCC.rstsckr().read().lsirdy() {}
+}
+
+/// Stop the LSI.
+///
+/// The LSI keeps running while the independent watchdog is started.
+pub fn disable_lsi() {
+    crate::pac::RCC.rstsckr().modify(|w| w.set_lsion(false));
+}
+
+/// Enable the PWR and BKP clocks and allow writes to the backup domain.
+#[cfg(any(rtc, bkp))]
+pub(crate) fn enable_backup_domain() {
+    use crate::pac::{PWR, RCC};
+
+    critical_section::with(|_| {
+        RCC.apb1pcenr().modify(|w| {
+            w.set_pwren(true);
+            w.set_bkpen(true);
+        });
+        PWR.ctlr().modify(|w| w.set_dbp(true));
+    });
 }
 
 pub unsafe fn init(config: Config) {
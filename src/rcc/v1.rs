@@ -60,6 +60,8 @@ pub struct Config {
     pub ahb_pre: AHBPrescaler,
     pub apb1_pre: APBPrescaler,
     pub apb2_pre: APBPrescaler,
+
+    pub ls: super::LsConfig,
 }
 
 impl Config {
@@ -77,6 +79,7 @@ impl Config {
         ahb_pre: AHBPrescaler::DIV1,
         apb1_pre: APBPrescaler::DIV1,
         apb2_pre: APBPrescaler::DIV1,
+        ls: super::LsConfig::off(),
     };
     pub const SYSCLK_FREQ_72MHZ_HSE: Config = Self {
         hse: Some(Hse {
@@ -92,6 +95,7 @@ impl Config {
         ahb_pre: AHBPrescaler::DIV1,
         apb1_pre: APBPrescaler::DIV2,
         apb2_pre: APBPrescaler::DIV2,
+        ls: super::LsConfig::off(),
     };
     pub const SYSCLK_FREQ_96MHZ_HSE: Config = Self {
         hse: Some(Hse {
@@ -107,6 +111,7 @@ impl Config {
         ahb_pre: AHBPrescaler::DIV1,
         apb1_pre: APBPrescaler::DIV2,
         apb2_pre: APBPrescaler::DIV2,
+        ls: super::LsConfig::off(),
     };
 }
 
@@ -121,6 +126,7 @@ impl Default for Config {
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV1,
             apb2_pre: APBPrescaler::DIV1,
+            ls: super::LsConfig::off(),
         }
     }
 }
@@ -234,6 +240,12 @@ pub(crate) unsafe fn init(config: Config) {
 
     super::CLOCKS.pclk1_tim = pclk1_tim;
     super::CLOCKS.pclk2_tim = pclk2_tim;
+
+    // After HCLK is known, it paces the LSE timeout.
+    #[cfg(rtc)]
+    {
+        super::CLOCKS.rtc = config.ls.init();
+    }
 }
 
 fn calc_pclk<D>(hclk: Hertz, ppre: D) -> (Hertz, Hertz)
@@ -136,7 +136,7 @@ impl Config {
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV1,
             apb2_pre: APBPrescaler::DIV1,
-            ls: super::LsConfig::default_lsi(),
+            ls: super::LsConfig::off(),
             hspll_src: HsPllSource::HSE,
             hspll: Some(HsPll {
                 pre: HsPllPrescaler::DIV2,
@@ -159,7 +159,7 @@ impl Config {
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV1,
             apb2_pre: APBPrescaler::DIV1,
-            ls: super::LsConfig::default_lsi(),
+            ls: super::LsConfig::off(),
             hspll_src: HsPllSource::HSE,
             hspll: Some(HsPll {
                 pre: HsPllPrescaler::DIV2,
@@ -179,7 +179,7 @@ impl Config {
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV1,
             apb2_pre: APBPrescaler::DIV1,
-            ls: super::LsConfig::default_lsi(),
+            ls: super::LsConfig::off(),
             hspll_src: HsPllSource::HSI,
             hspll: Some(HsPll {
                 pre: HsPllPrescaler::DIV2,
@@ -199,7 +199,7 @@ impl Config {
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV4, // 24MHz
             apb2_pre: APBPrescaler::DIV4,
-            ls: super::LsConfig::default_lsi(),
+            ls: super::LsConfig::off(),
             hspll_src: HsPllSource::HSI,
             hspll: Some(HsPll {
                 pre: HsPllPrescaler::DIV2,
@@ -220,7 +220,7 @@ impl Default for Config {
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV1,
             apb2_pre: APBPrescaler::DIV1,
-            ls: super::LsConfig::default(),
+            ls: super::LsConfig::off(),
             hspll_src: HsPllSource::HSE,
             hspll: None,
         }
@@ -415,6 +415,12 @@ pub(crate) unsafe fn init(config: Config) {
 
     super::CLOCKS.pclk1_tim = pclk1_tim;
     super::CLOCKS.pclk2_tim = pclk2_tim;
+
+    // After HCLK is known, it paces the LSE timeout.
+    #[cfg(rtc)]
+    {
+        super::CLOCKS.rtc = config.ls.init();
+    }
 }
 
 fn calc_pclk<D>(hclk: Hertz, ppre: D) -> (Hertz, Hertz)
@@ -0,0 +1,252 @@
+//! Calendar date and time, converted to and from unix timestamps.
+//!
+//! The RTC only counts seconds, so the calendar is computed in software for the
+//! proleptic Gregorian calendar, UTC, from 1970 up to the end of the 32-bit counter in 2106.
+
+/// Day of the week.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum DayOfWeek {
+    Monday = 0,
+    Tuesday = 1,
+    Wednesday = 2,
+    Thursday = 3,
+    Friday = 4,
+    Saturday = 5,
+    Sunday = 6,
+}
+
+impl DayOfWeek {
+    fn from_days(days: u32) -> Self {
+        // 1970-01-01 was a Thursday
+        match (days + 3) % 7 {
+            0 => Self::Monday,
+            1 => Self::Tuesday,
+            2 => Self::Wednesday,
+            3 => Self::Thursday,
+            4 => Self::Friday,
+            5 => Self::Saturday,
+            _ => Self::Sunday,
+        }
+    }
+}
+
+/// Errors when building a [`DateTime`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The date is out of the range the 32-bit RTC counter can hold, 1970 to early 2106.
+    InvalidYear,
+    /// The month is not between 1 and 12.
+    InvalidMonth,
+    /// The day is 0 or past the end of the month.
+    InvalidDay,
+    /// The hour is not between 0 and 23.
+    InvalidHour,
+    /// The minute is not between 0 and 59.
+    InvalidMinute,
+    /// The second is not between 0 and 59.
+    InvalidSecond,
+}
+
+/// A date and time in UTC, without leap seconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    day_of_week: DayOfWeek,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl DateTime {
+    /// Build a date and time, checking each field.
+    ///
+    /// `month` and `day` start at 1.
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Result<Self, Error> {
+        if !(1970..=2106).contains(&year) {
+            return Err(Error::InvalidYear);
+        }
+        if !(1..=12).contains(&month) {
+            return Err(Error::InvalidMonth);
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(Error::InvalidDay);
+        }
+        if hour > 23 {
+            return Err(Error::InvalidHour);
+        }
+        if minute > 59 {
+            return Err(Error::InvalidMinute);
+        }
+        if second > 59 {
+            return Err(Error::InvalidSecond);
+        }
+
+        let days = days_from_civil(year, month, day);
+        let timestamp = days as u64 * 86400 + hour as u64 * 3600 + minute as u64 * 60 + second as u64;
+        if timestamp > u32::MAX as u64 {
+            return Err(Error::InvalidYear);
+        }
+
+        Ok(Self {
+            year,
+            month,
+            day,
+            day_of_week: DayOfWeek::from_days(days),
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    /// The date and time `timestamp` seconds after 1970-01-01 00:00:00 UTC.
+    pub fn from_unix(timestamp: u32) -> Self {
+        let days = timestamp / 86400;
+        let secs = timestamp % 86400;
+        let (year, month, day) = civil_from_days(days);
+
+        Self {
+            year,
+            month,
+            day,
+            day_of_week: DayOfWeek::from_days(days),
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    /// Seconds since 1970-01-01 00:00:00 UTC.
+    pub fn to_unix(&self) -> u32 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+            + self.hour as u32 * 3600
+            + self.minute as u32 * 60
+            + self.second as u32
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Month, from 1 to 12.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Day of the month, starting at 1.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn day_of_week(&self) -> DayOfWeek {
+        self.day_of_week
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+}
+
+// Within 1970..=2106, 2000 is a leap year and 2100 is the only skipped one.
+fn is_leap_year(year: u16) -> bool {
+    year & 3 == 0 && year != 2100
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01, after Howard Hinnant's `days_from_civil`, with eras starting in March.
+fn days_from_civil(year: u16, month: u8, day: u8) -> u32 {
+    let year = if month <= 2 { year as u32 - 1 } else { year as u32 };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = (month as u32 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as u32 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: u32) -> (u16, u8, u8) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u32;
+    (year as u16, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch() {
+        let dt = DateTime::from_unix(0);
+        assert_eq!((dt.year(), dt.month(), dt.day()), (1970, 1, 1));
+        assert_eq!((dt.hour(), dt.minute(), dt.second()), (0, 0, 0));
+        assert_eq!(dt.day_of_week(), DayOfWeek::Thursday);
+    }
+
+    #[test]
+    fn leap_day() {
+        let dt = DateTime::new(2000, 2, 29, 12, 34, 56).unwrap();
+        assert_eq!(dt.to_unix(), 951_827_696);
+        assert_eq!(dt.day_of_week(), DayOfWeek::Tuesday);
+        assert_eq!(DateTime::from_unix(951_827_696), dt);
+
+        assert_eq!(DateTime::new(2100, 2, 29, 0, 0, 0), Err(Error::InvalidDay));
+        assert_eq!(DateTime::new(2023, 2, 29, 0, 0, 0), Err(Error::InvalidDay));
+        assert!(DateTime::new(2024, 2, 29, 0, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn end_of_counter() {
+        let dt = DateTime::from_unix(u32::MAX);
+        assert_eq!((dt.year(), dt.month(), dt.day()), (2106, 2, 7));
+        assert_eq!((dt.hour(), dt.minute(), dt.second()), (6, 28, 15));
+        assert_eq!(dt.day_of_week(), DayOfWeek::Sunday);
+
+        assert_eq!(DateTime::new(2106, 2, 7, 6, 28, 16), Err(Error::InvalidYear));
+        assert_eq!(DateTime::new(1969, 12, 31, 23, 59, 59), Err(Error::InvalidYear));
+    }
+
+    #[test]
+    fn round_trip() {
+        for timestamp in (0..u32::MAX - 86_399).step_by(86_399 * 7 + 1234) {
+            assert_eq!(DateTime::from_unix(timestamp).to_unix(), timestamp);
+        }
+    }
+
+    #[test]
+    fn invalid_fields() {
+        assert_eq!(DateTime::new(2024, 0, 1, 0, 0, 0), Err(Error::InvalidMonth));
+        assert_eq!(DateTime::new(2024, 13, 1, 0, 0, 0), Err(Error::InvalidMonth));
+        assert_eq!(DateTime::new(2024, 4, 31, 0, 0, 0), Err(Error::InvalidDay));
+        assert_eq!(DateTime::new(2024, 1, 1, 24, 0, 0), Err(Error::InvalidHour));
+        assert_eq!(DateTime::new(2024, 1, 1, 0, 60, 0), Err(Error::InvalidMinute));
+        assert_eq!(DateTime::new(2024, 1, 1, 0, 0, 60), Err(Error::InvalidSecond));
+    }
+}
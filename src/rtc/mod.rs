@@ -0,0 +1,362 @@
+//! Real Time Clock (RTC)
+//!
+//! The RTC of CH32V1, CH32V2, CH32V3 and CH32L1 is a 32-bit counter in the backup domain,
+//! clocked from LSE, LSI or HSE divided by 128. This driver prescales it to 1 Hz and uses it
+//! as a unix timestamp, so it keeps counting across resets, and across power loss on boards
+//! with a battery on VBAT.
+
+mod datetime;
+#[cfg(bkp)]
+mod sync;
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{Context, Poll};
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+pub use datetime::{DateTime, DayOfWeek, Error as DateTimeError};
+#[cfg(bkp)]
+pub use sync::{Adjustment, SyncRecord, MIN_DRIFT_INTERVAL};
+
+use crate::exti::ExtiInputFuture;
+use crate::pac::rcc::vals::Rtcsel;
+use crate::rcc::LseConfig;
+pub use crate::rcc::RtcClockSource;
+use crate::time::Hertz;
+use crate::{into_ref, pac, peripherals, Peripheral, PeripheralRef};
+
+/// EXTI line connected to the RTC alarm.
+const ALARM_EXTI_LINE: u8 = 17;
+
+static SECOND_WAKER: AtomicWaker = AtomicWaker::new();
+/// Second interrupts handled since reset, wrapping
+static SECONDS: AtomicU32 = AtomicU32::new(0);
+
+/// RTC global interrupt handler, for the second interrupt.
+///
+/// Called by the `RTC` interrupt handler of the HAL with the `rt` feature, call it from your
+/// own handler otherwise.
+pub unsafe fn on_irq() {
+    let r = pac::RTC;
+    if r.ctlrl().read().secf() {
+        r.ctlrl().modify(|w| w.set_secf(false));
+        critical_section::with(|_| {
+            let seconds = SECONDS.load(Ordering::Acquire);
+            SECONDS.store(seconds.wrapping_add(1), Ordering::Release);
+        });
+        SECOND_WAKER.wake();
+    }
+}
+
+/// RTC configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Clock counted by the RTC.
+    pub source: RtcClockSource,
+    /// Frequency of the RTC clock, after the fixed divider for HSE.
+    pub frequency: Hertz,
+}
+
+impl Config {
+    /// 32.768 kHz crystal on LSE.
+    pub const fn lse() -> Self {
+        Self {
+            source: RtcClockSource::LSE,
+            frequency: Hertz(32_768),
+        }
+    }
+
+    /// The internal low speed oscillator, only accurate to a few percent.
+    pub const fn lsi() -> Self {
+        Self {
+            source: RtcClockSource::LSI,
+            frequency: crate::rcc::LSI_FREQ,
+        }
+    }
+
+    /// HSE divided by 128. The RTC stops when the core enters Stop or Standby mode.
+    pub const fn hse_div128(hse: Hertz) -> Self {
+        Self {
+            source: RtcClockSource::HSE,
+            frequency: Hertz(hse.0 / 128),
+        }
+    }
+
+    /// The clock selected by the `ls` part of the RCC config, if any.
+    ///
+    /// An RTC clocked from HSE divided by 128 must be configured with [`hse_div128`](Self::hse_div128).
+    pub fn from_rcc() -> Option<Self> {
+        let frequency = crate::rcc::clocks().rtc?;
+        let source = match pac::RCC.bdctlr().read().rtcsel().to_bits() {
+            0b01 => RtcClockSource::LSE,
+            0b10 => RtcClockSource::LSI,
+            _ => return None,
+        };
+        Some(Self { source, frequency })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::lse()
+    }
+}
+
+/// RTC driver.
+pub struct Rtc<'d> {
+    _rtc: PeripheralRef<'d, peripherals::RTC>,
+}
+
+impl<'d> Rtc<'d> {
+    /// Create a new RTC driver.
+    ///
+    /// If the RTC is already running from `config.source`, such as after a reset, it is left
+    /// untouched and keeps its time. Selecting another source resets the backup domain, which
+    /// also clears the backup registers.
+    pub fn new(rtc: impl Peripheral<P = peripherals::RTC> + 'd, config: Config) -> Self {
+        into_ref!(rtc);
+        assert!(config.frequency.0 > 0 && config.frequency.0 <= 1 << 20);
+
+        crate::rcc::enable_backup_domain();
+
+        let rcc = pac::RCC;
+        let sel = rtcsel(config.source);
+        let bdctlr = rcc.bdctlr().read();
+        if !bdctlr.rtcen() || bdctlr.rtcsel() != sel {
+            if bdctlr.rtcsel() != sel && bdctlr.rtcsel() != Rtcsel::from_bits(0) {
+                // RTCSEL can only be changed by a backup domain reset
+                rcc.bdctlr().modify(|w| w.set_bdrst(true));
+                rcc.bdctlr().modify(|w| w.set_bdrst(false));
+            }
+
+            match config.source {
+                RtcClockSource::LSE => {
+                    let lse = LseConfig {
+                        timeout_ms: None,
+                        ..Default::default()
+                    };
+                    unwrap!(crate::rcc::enable_lse(&lse));
+                }
+                RtcClockSource::LSI => crate::rcc::enable_lsi(),
+                RtcClockSource::HSE | RtcClockSource::DISABLE => {}
+            }
+
+            rcc.bdctlr().modify(|w| {
+                w.set_rtcsel(sel);
+                w.set_rtcen(config.source != RtcClockSource::DISABLE);
+            });
+        }
+
+        Self::wait_for_sync();
+        // The reload value is write-only. Writing it again doesn't disturb the counter.
+        Self::write(|r| {
+            let prescaler = config.frequency.0 - 1;
+            r.psch().write(|w| w.set_prlh((prescaler >> 16) as u8));
+            r.pscl().write(|w| w.set_prll(prescaler as u16));
+        });
+
+        // The alarm reaches the core through its EXTI line, see `wait_for_alarm`.
+        critical_section::with(|_| crate::exti::set_edges(ALARM_EXTI_LINE, true, false));
+        unsafe { qingke::pfic::enable_interrupt(pac::Interrupt::RTCALARM as u8) };
+
+        Self { _rtc: rtc }
+    }
+
+    /// Seconds since 1970-01-01 00:00:00 UTC.
+    pub fn now(&self) -> u32 {
+        counter()
+    }
+
+    /// Set the time, in seconds since 1970-01-01 00:00:00 UTC.
+    pub fn set_time(&mut self, timestamp: u32) {
+        Self::write(|r| {
+            r.cnth().write(|w| w.set_cnth((timestamp >> 16) as u16));
+            r.cntl().write(|w| w.set_cntl(timestamp as u16));
+        });
+    }
+
+    /// The current date and time.
+    pub fn now_datetime(&self) -> DateTime {
+        DateTime::from_unix(self.now())
+    }
+
+    /// Set the date and time.
+    pub fn set_datetime(&mut self, datetime: &DateTime) {
+        self.set_time(datetime.to_unix());
+    }
+
+    /// Set the alarm to `at`, in seconds since 1970-01-01 00:00:00 UTC, or clear it with `None`.
+    ///
+    /// The alarm flag is set when the counter reaches `at`. It also wakes the chip from
+    /// Standby mode, which restarts it from reset.
+    pub fn set_alarm(&mut self, at: Option<u32>) {
+        let at = at.unwrap_or(u32::MAX);
+        Self::write(|r| {
+            r.alrmh().write(|w| w.set_alrmh((at >> 16) as u16));
+            r.alrml().write(|w| w.set_alrml(at as u16));
+        });
+        pac::RTC.ctlrl().modify(|w| w.set_alrf(false));
+    }
+
+    /// Whether the alarm time was reached since the alarm was set.
+    pub fn is_alarm_pending(&self) -> bool {
+        pac::RTC.ctlrl().read().alrf()
+    }
+
+    /// Wait until the time reaches `at`, in seconds since 1970-01-01 00:00:00 UTC.
+    ///
+    /// This uses the RTC alarm interrupt, through EXTI line 17, so the wait continues in
+    /// Sleep and Stop modes with the RTC clocked from LSE or LSI. Returns immediately if
+    /// `at` is not in the future.
+    ///
+    /// Without the `rt` feature, the `RTCALARM` interrupt handler must call [`crate::exti::on_irq`].
+    pub async fn wait_for_alarm(&mut self, at: u32) {
+        // Arm the EXTI line before setting the alarm, so its edge can't be missed.
+        let fut = ExtiInputFuture::new_line(ALARM_EXTI_LINE);
+
+        self.set_alarm(Some(at));
+        if at > self.now() {
+            fut.await;
+        }
+
+        self.set_alarm(None);
+    }
+
+    /// Slow the clock down by `value` steps of 1 in 2^20, about 0.954 ppm each.
+    ///
+    /// Up to 127 steps, 121 ppm, can be removed. Measure the drift with
+    /// [`set_calibration_output`](Self::set_calibration_output), or against an external time
+    /// source, and configure the RTC clock slightly fast when it needs to be sped up.
+    pub fn set_calibration(&mut self, value: u8) {
+        assert!(value <= 0x7F);
+        pac::BKP.octlr().modify(|w| w.set_cal(value));
+    }
+
+    /// The calibration value, in steps of 1 in 2^20.
+    pub fn calibration(&self) -> u8 {
+        pac::BKP.octlr().read().cal()
+    }
+
+    /// Output the RTC clock divided by 64 on the TAMPER pin, to measure its drift.
+    ///
+    /// The output is not affected by the calibration value. The tamper detection must be off.
+    pub fn set_calibration_output(&mut self, enable: bool) {
+        pac::BKP.octlr().modify(|w| w.set_cco(enable));
+    }
+
+    /// Get a stream of ticks from the RTC second interrupt, one per second.
+    ///
+    /// The second interrupt wakes the core from Sleep mode, so housekeeping work driven by it
+    /// keeps running while the core otherwise sleeps. Use [`wait_for_alarm`](Self::wait_for_alarm)
+    /// to wake up from Stop mode.
+    pub fn ticks(&mut self) -> Ticks<'_> {
+        let seen = SECONDS.load(Ordering::Acquire);
+
+        pac::RTC.ctlrh().modify(|w| w.set_secie(true));
+        unsafe { qingke::pfic::enable_interrupt(pac::Interrupt::RTC as u8) };
+
+        Ticks {
+            seen,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Wait for the APB1 interface to see the registers of the RTC clock domain.
+    ///
+    /// Needed after a reset or wakeup, before reading the counter.
+    fn wait_for_sync() {
+        let r = pac::RTC;
+        r.ctlrl().modify(|w| w.set_rsf(false));
+        while !r.ctlrl().read().rsf() {}
+    }
+
+    /// Write to the registers guarded by configuration mode.
+    fn write<R>(f: impl FnOnce(pac::rtc::Rtc) -> R) -> R {
+        let r = pac::RTC;
+        while !r.ctlrl().read().rtoff() {}
+        r.ctlrl().modify(|w| w.set_cnf(true));
+        let result = f(r);
+        r.ctlrl().modify(|w| w.set_cnf(false));
+        while !r.ctlrl().read().rtoff() {}
+        result
+    }
+}
+
+fn counter() -> u32 {
+    let r = pac::RTC;
+    loop {
+        // The two halves are read separately, retry if the low half wrapped in between.
+        let high = r.cnth().read().cnth();
+        let low = r.cntl().read().cntl();
+        if r.cnth().read().cnth() == high {
+            return ((high as u32) << 16) | low as u32;
+        }
+    }
+}
+
+fn rtcsel(source: RtcClockSource) -> Rtcsel {
+    match source {
+        RtcClockSource::DISABLE => Rtcsel::from_bits(0b00),
+        RtcClockSource::LSE => Rtcsel::from_bits(0b01),
+        RtcClockSource::LSI => Rtcsel::from_bits(0b10),
+        RtcClockSource::HSE => Rtcsel::from_bits(0b11),
+    }
+}
+
+/// Ticks of the RTC second interrupt, see [`Rtc::ticks`].
+///
+/// Also a [`futures::Stream`] of timestamps that never ends.
+pub struct Ticks<'a> {
+    seen: u32,
+    phantom: PhantomData<&'a mut ()>,
+}
+
+impl<'a> Ticks<'a> {
+    /// Wait for the next second, returning the current time in seconds since 1970-01-01 00:00:00 UTC.
+    ///
+    /// If more than one second passed since the last call, this returns immediately, once.
+    pub async fn next(&mut self) -> u32 {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<u32> {
+        SECOND_WAKER.register(cx.waker());
+
+        let seconds = SECONDS.load(Ordering::Acquire);
+        if seconds != self.seen {
+            self.seen = seconds;
+            Poll::Ready(counter())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a> futures::Stream for Ticks<'a> {
+    type Item = u32;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+impl<'a> Drop for Ticks<'a> {
+    fn drop(&mut self) {
+        pac::RTC.ctlrh().modify(|w| w.set_secie(false));
+    }
+}
+
+#[cfg(feature = "rt")]
+#[qingke_rt::interrupt]
+unsafe fn RTC() {
+    on_irq();
+}
+
+#[cfg(feature = "rt")]
+#[qingke_rt::interrupt]
+unsafe fn RTCALARM() {
+    crate::exti::on_irq();
+}
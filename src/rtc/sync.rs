@@ -0,0 +1,118 @@
+//! Synchronization with an external time source, such as GPS or NTP over a modem.
+
+use super::Rtc;
+use crate::backup::{Backup, Register, Value};
+
+/// Shortest time between two drift measurements, in seconds.
+///
+/// Times are compared in whole seconds, so one day gives a resolution of about 12 ppm.
+pub const MIN_DRIFT_INTERVAL: u32 = 86400;
+
+/// Drift bookkeeping kept in the backup registers by [`Rtc::adjust`].
+///
+/// Declare where it lives once, e.g. `const SYNC: Register<SyncRecord> = Register::new(0);`,
+/// it takes 6 registers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncRecord {
+    /// Start of the current drift measurement, 0 when none.
+    base: u32,
+    /// Seconds stepped since `base`.
+    stepped: i32,
+    /// Last measured drift, in parts per billion, positive when the RTC runs fast.
+    drift_ppb: i32,
+}
+
+impl SyncRecord {
+    /// Last measured drift, in parts per billion, positive when the RTC runs fast.
+    ///
+    /// This is the drift left with the calibration value in use during the measurement.
+    pub fn drift_ppb(&self) -> i32 {
+        self.drift_ppb
+    }
+
+    /// Time the current drift measurement started at, if any.
+    pub fn measuring_since(&self) -> Option<u32> {
+        (self.base != 0).then_some(self.base)
+    }
+}
+
+impl Value for SyncRecord {
+    const WORDS: usize = 6;
+
+    fn from_words(words: &[u16]) -> Self {
+        Self {
+            base: u32::from_words(&words[0..2]),
+            stepped: i32::from_words(&words[2..4]),
+            drift_ppb: i32::from_words(&words[4..6]),
+        }
+    }
+
+    fn to_words(&self, words: &mut [u16]) {
+        self.base.to_words(&mut words[0..2]);
+        self.stepped.to_words(&mut words[2..4]);
+        self.drift_ppb.to_words(&mut words[4..6]);
+    }
+}
+
+/// What [`Rtc::adjust`] did.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Adjustment {
+    /// Seconds the counter was stepped by, positive when the RTC was behind.
+    pub step: i64,
+    /// Drift measured since the previous measurement, in parts per billion, if one was due.
+    pub drift_ppb: Option<i32>,
+    /// Calibration value in use from now on, see [`Rtc::set_calibration`].
+    pub calibration: u8,
+}
+
+impl<'d> Rtc<'d> {
+    /// Synchronize to `external`, the correct time in seconds since 1970-01-01 00:00:00 UTC.
+    ///
+    /// The counter is stepped to the external time. Once at least [`MIN_DRIFT_INTERVAL`]
+    /// passed since the measurement started, the drift rate is computed from all the steps
+    /// in between, stored in `record`, and the clock is slewed towards the external time by
+    /// trimming the calibration value. The calibration can only slow the clock down, so an
+    /// RTC running slow is only corrected down to a calibration value of 0.
+    ///
+    /// Everything is kept in the backup registers, so measurements span resets.
+    pub fn adjust(&mut self, external: u32, backup: &mut Backup<'_>, record: Register<SyncRecord>) -> Adjustment {
+        let step = external as i64 - self.now() as i64;
+        let mut sync = backup.get(record);
+        let mut drift_ppb = None;
+
+        if sync.base == 0 || external < sync.base {
+            sync.base = external;
+            sync.stepped = 0;
+        } else if external - sync.base >= MIN_DRIFT_INTERVAL {
+            // Seconds the RTC fell behind over the whole measurement.
+            let behind = sync.stepped as i64 + step;
+            let ppb = -behind * 1_000_000_000 / (external - sync.base) as i64;
+            let ppb = ppb.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+
+            // Each calibration step slows the clock by 1 in 2^20.
+            let steps = ppb as i64 * (1 << 20) / 1_000_000_000;
+            let calibration = (self.calibration() as i64 + steps).clamp(0, 0x7F) as u8;
+            self.set_calibration(calibration);
+
+            sync.base = external;
+            sync.stepped = 0;
+            sync.drift_ppb = ppb;
+            drift_ppb = Some(ppb);
+        } else {
+            sync.stepped += step as i32;
+        }
+
+        if step != 0 {
+            self.set_time(external);
+        }
+        backup.set(record, sync);
+
+        Adjustment {
+            step,
+            drift_ppb,
+            calibration: self.calibration(),
+        }
+    }
+}
@@ -14,6 +14,18 @@ pub fn unique_id() -> [u8; 12] {
     unsafe { core::ptr::read_volatile(ESIG_UID) }
 }
 
+/// Formats the unique ID into `buf` as 24 uppercase hex digits, e.g. for a serial number
+pub fn unique_id_hex(buf: &mut [u8; 24]) -> &str {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    for (i, b) in unique_id().iter().enumerate() {
+        buf[2 * i] = HEX[(b >> 4) as usize];
+        buf[2 * i + 1] = HEX[(b & 0xF) as usize];
+    }
+    // Only ASCII hex digits were written.
+    unsafe { core::str::from_utf8_unchecked(buf) }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct ChipID(u32);
 
@@ -39,6 +39,8 @@ pub enum Error {
     ModeFault,
     /// Overrun.
     Overrun,
+    /// DMA transfer error.
+    Dma,
 }
 
 #[derive(Copy, Clone)]
@@ -515,11 +517,11 @@ impl<'d, T: Instance> Spi<'d, T, Async> {
             w.set_spe(true);
         });
 
-        tx_f.await;
+        let result = tx_f.await;
 
         finish_dma(T::REGS);
 
-        Ok(())
+        result.map_err(|_| Error::Dma)
     }
 
     /// SPI read, using DMA.
@@ -557,11 +559,11 @@ impl<'d, T: Instance> Spi<'d, T, Async> {
             w.set_spe(true);
         });
 
-        join(tx_f, rx_f).await;
+        let (tx_result, rx_result) = join(tx_f, rx_f).await;
 
         finish_dma(T::REGS);
 
-        Ok(())
+        tx_result.and(rx_result).map_err(|_| Error::Dma)
     }
 
     async fn transfer_inner<W: Word>(&mut self, read: *mut [W], write: *const [W]) -> Result<(), Error> {
@@ -598,11 +600,11 @@ impl<'d, T: Instance> Spi<'d, T, Async> {
             w.set_spe(true);
         });
 
-        join(tx_f, rx_f).await;
+        let (tx_result, rx_result) = join(tx_f, rx_f).await;
 
         finish_dma(T::REGS);
 
-        Ok(())
+        tx_result.and(rx_result).map_err(|_| Error::Dma)
     }
 
     /// Bidirectional transfer, using DMA.
@@ -762,6 +764,7 @@ impl embedded_hal::spi::Error for Error {
             Self::Crc => embedded_hal::spi::ErrorKind::Other,
             Self::ModeFault => embedded_hal::spi::ErrorKind::ModeFault,
             Self::Overrun => embedded_hal::spi::ErrorKind::Overrun,
+            Self::Dma => embedded_hal::spi::ErrorKind::Other,
         }
     }
 }
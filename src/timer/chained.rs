@@ -0,0 +1,78 @@
+//! 32-bit counter built from two chained 16-bit timers.
+//!
+//! The master timer counts the low half. Its update event, on each overflow, is routed
+//! through TRGO to the slave timer, which counts it as an external clock for the high half.
+
+use super::low_level::{MasterMode, SlaveMode, Timer};
+use super::sync::TriggerFrom;
+use super::GeneralInstance16bit;
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// Two 16-bit timers chained into one 32-bit counter.
+pub struct ChainedTimer<'d, M: GeneralInstance16bit, S: TriggerFrom<M>> {
+    master: Timer<'d, M>,
+    slave: Timer<'d, S>,
+}
+
+impl<'d, M: GeneralInstance16bit, S: TriggerFrom<M>> ChainedTimer<'d, M, S> {
+    /// Chain `master` (low half) into `slave` (high half), on the internal trigger of
+    /// `slave` connected to `master`, see [`TriggerFrom`].
+    ///
+    /// `freq` is the tick frequency of the 32-bit counter.
+    pub fn new(master: impl Peripheral<P = M> + 'd, slave: impl Peripheral<P = S> + 'd, freq: Hertz) -> Self {
+        let master = Timer::new(master);
+        let slave = Timer::new(slave);
+
+        master.set_tick_frequency(freq);
+        master.set_master_mode(MasterMode::Update);
+
+        // Count the master update events: external clock mode 1 on the internal trigger.
+        slave.regs_basic().psc().write_value(0);
+        slave.regs_basic().atrlr().write_value(u16::MAX);
+        slave.set_trigger_source(S::SOURCE);
+        slave.set_slave_mode(SlaveMode::ExternalClock);
+
+        let this = Self { master, slave };
+        this.reset();
+        this
+    }
+
+    /// Start counting.
+    pub fn start(&self) {
+        self.slave.start();
+        self.master.start();
+    }
+
+    /// Stop counting.
+    pub fn stop(&self) {
+        self.master.stop();
+        self.slave.stop();
+    }
+
+    /// Reset the counter to 0.
+    pub fn reset(&self) {
+        self.master.reset();
+        self.slave.reset();
+    }
+
+    /// Read the 32-bit counter value.
+    pub fn count(&self) -> u32 {
+        let master = self.master.regs_basic();
+        let slave = self.slave.regs_basic();
+
+        // Re-read if the low half overflowed between the two reads.
+        loop {
+            let high = slave.cnt().read();
+            let low = master.cnt().read();
+            if slave.cnt().read() == high {
+                return ((high as u32) << 16) | low as u32;
+            }
+        }
+    }
+
+    /// Ticks elapsed since `since`, a previous [`count`](Self::count) value, handling wrap-around.
+    pub fn elapsed_since(&self, since: u32) -> u32 {
+        self.count().wrapping_sub(since)
+    }
+}
@@ -2,10 +2,13 @@
 
 use core::marker::PhantomData;
 
-use super::low_level::{CountingMode, OutputPolarity, Timer};
+use super::low_level::{CountingMode, IdleState, LockLevel, OutputPolarity, Timer, TriggerSource};
 use super::simple_pwm::{Ch1, Ch2, Ch3, Ch4, PwmPin};
-use super::{AdvancedInstance, Channel, Channel1ComplementaryPin, Channel2ComplementaryPin, Channel3ComplementaryPin};
-use crate::gpio::{AFType, AnyPin};
+use super::{
+    AdvancedInstance, BreakInputPin, Channel, Channel1ComplementaryPin, Channel2ComplementaryPin,
+    Channel3ComplementaryPin,
+};
+use crate::gpio::{AFType, AnyPin, Pull};
 use crate::pac::timer::vals::Ckd;
 use crate::time::Hertz;
 use crate::timer::low_level::OutputCompareMode;
@@ -40,6 +43,39 @@ macro_rules! complementary_channel_impl {
     };
 }
 
+/// Break input pin wrapper.
+pub struct BreakPin<'d, T> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<T>,
+}
+
+impl<'d, T: AdvancedInstance> BreakPin<'d, T> {
+    /// Create a new break input pin instance.
+    pub fn new<const REMAP: u8>(pin: impl Peripheral<P = impl BreakInputPin<T, REMAP>> + 'd, pull: Pull) -> Self {
+        into_ref!(pin);
+
+        T::set_remap(REMAP);
+        critical_section::with(|_| {
+            pin.set_as_input(pull);
+        });
+        BreakPin {
+            _pin: pin.map_into(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Break input configuration.
+#[derive(Debug, Clone, Copy)]
+pub struct BreakConfig {
+    /// Break is triggered by a high level on the break input.
+    pub active_high: bool,
+    /// Re-enable the outputs automatically on the next update event once the break input is inactive.
+    ///
+    /// Otherwise outputs stay in their idle state until [`ComplementaryPwm::resume_outputs`].
+    pub automatic_output_enable: bool,
+}
+
 complementary_channel_impl!(new_ch1, Ch1, Channel1ComplementaryPin);
 complementary_channel_impl!(new_ch2, Ch2, Channel2ComplementaryPin);
 complementary_channel_impl!(new_ch3, Ch3, Channel3ComplementaryPin);
@@ -139,6 +175,116 @@ impl<'d, T: AdvancedInstance> ComplementaryPwm<'d, T> {
         self.inner.set_complementary_output_polarity(channel, polarity);
     }
 
+    /// Set the output polarity of the main output only.
+    pub fn set_main_polarity(&mut self, channel: Channel, polarity: OutputPolarity) {
+        self.inner.set_output_polarity(channel, polarity);
+    }
+
+    /// Set the output polarity of the complementary output only.
+    pub fn set_complementary_polarity(&mut self, channel: Channel, polarity: OutputPolarity) {
+        self.inner.set_complementary_output_polarity(channel, polarity);
+    }
+
+    /// Set the level of the main and complementary outputs while the outputs are
+    /// disabled (MOE cleared), e.g. after a break event.
+    ///
+    /// With dead-time inserted, both outputs must not be set to the same active level.
+    pub fn set_idle_state(&mut self, channel: Channel, main: IdleState, complementary: IdleState) {
+        self.inner.set_output_idle_state(channel, main);
+        if channel != Channel::Ch4 {
+            self.inner.set_complementary_output_idle_state(channel, complementary);
+        }
+    }
+
+    /// Enable the break input.
+    ///
+    /// When the break input becomes active, hardware clears MOE and all outputs go to
+    /// their idle state (see [`set_idle_state`](Self::set_idle_state)) without software
+    /// involvement, e.g. driven by an overcurrent comparator.
+    pub fn enable_break(&mut self, _pin: BreakPin<'d, T>, config: BreakConfig) {
+        // Keep outputs driving their idle levels instead of floating.
+        self.inner.set_off_state_selection(true, true);
+        self.inner.set_break_polarity(config.active_high);
+        self.inner.set_automatic_output_enable(config.automatic_output_enable);
+        self.inner.clear_break_interrupt();
+        self.inner.enable_break(true);
+    }
+
+    /// Disable the break input.
+    pub fn disable_break(&mut self) {
+        self.inner.enable_break(false);
+    }
+
+    /// Whether a break occurred since the last call, clearing the flag.
+    pub fn take_break_event(&mut self) -> bool {
+        self.inner.clear_break_interrupt()
+    }
+
+    /// Whether the outputs are enabled, i.e. not stopped by a break.
+    pub fn outputs_enabled(&self) -> bool {
+        self.inner.get_moe()
+    }
+
+    /// Re-enable the outputs after a break.
+    ///
+    /// Has no effect while the break input is still active.
+    pub fn resume_outputs(&mut self) {
+        self.inner.set_moe(true);
+    }
+
+    /// Apply output enables and compare modes only on commutation events, for six-step BLDC control.
+    ///
+    /// Commutation events are generated by [`commutate`](Self::commutate), and on each rising edge
+    /// of `trigger`, e.g. the internal trigger connected to the TRGO of a
+    /// [`HallSensor`](super::hall::HallSensor) timer. The next step is prepared with
+    /// [`set_outputs`](Self::set_outputs) while the current one is still running.
+    pub fn enable_commutation(&mut self, trigger: TriggerSource) {
+        self.inner.set_trigger_source(trigger);
+        self.inner.set_commutation_on_trigger(true);
+        self.inner.set_commutation_preload(true);
+    }
+
+    /// Apply output enables immediately again.
+    pub fn disable_commutation(&mut self) {
+        self.inner.set_commutation_preload(false);
+        self.inner.set_commutation_on_trigger(false);
+    }
+
+    /// Enable the main and complementary outputs of a channel independently.
+    ///
+    /// With commutation enabled, this takes effect on the next commutation event.
+    pub fn set_outputs(&mut self, channel: Channel, main: bool, complementary: bool) {
+        self.inner.enable_channel(channel, main);
+        if channel != Channel::Ch4 {
+            self.inner.enable_complementary_channel(channel, complementary);
+        }
+    }
+
+    /// Generate a commutation event by software.
+    pub fn commutate(&mut self) {
+        self.inner.generate_commutation_event();
+    }
+
+    /// Whether a commutation event occurred since the last call, clearing the flag.
+    pub fn take_commutation_event(&mut self) -> bool {
+        self.inner.clear_commutation_interrupt()
+    }
+
+    /// Generate the update event, and with it the update interrupt, DMA request and update
+    /// trigger output, only every `periods` PWM periods, to reduce the interrupt load at high PWM frequencies.
+    ///
+    /// `periods` ranges from 1 to 256, or 1 to 128 in center-aligned modes.
+    pub fn set_update_rate(&mut self, periods: u16) {
+        self.inner.set_update_rate(periods);
+    }
+
+    /// Write protect the configuration against runaway software.
+    ///
+    /// The lock level can only be written once after reset.
+    pub fn lock(&mut self, level: LockLevel) {
+        self.inner.set_lock_level(level);
+    }
+
     /// Set the dead time as a proportion of max_duty
     pub fn set_dead_time(&mut self, value: u16) {
         let (ckd, value) = compute_dead_time_value(value);
@@ -146,6 +292,14 @@ impl<'d, T: AdvancedInstance> ComplementaryPwm<'d, T> {
         self.inner.set_dead_time_clock_division(ckd);
         self.inner.set_dead_time_value(value);
     }
+
+    /// Set the dead time in nanoseconds, rounded to the nearest achievable value.
+    ///
+    /// The longest dead time is 1008 timer clock cycles times 4.
+    pub fn set_dead_time_ns(&mut self, ns: u32) {
+        let ticks = (T::frequency().0 as u64 * ns as u64 + 500_000_000) / 1_000_000_000;
+        self.set_dead_time(ticks.min(u16::MAX as u64) as u16);
+    }
 }
 
 fn compute_dead_time_value(value: u16) -> (Ckd, u8) {
@@ -200,12 +354,12 @@ fn compute_dead_time_value(value: u16) -> (Ckd, u8) {
         let target = value / outdiv;
         let (these_bits, result) = if target < 128 {
             (target as u8, target)
-        } else if target < 255 {
-            (64 + (target / 2) as u8, (target - target % 2))
-        } else if target < 508 {
-            (32 + (target / 8) as u8, (target - target % 8))
-        } else if target < 1008 {
-            (32 + (target / 16) as u8, (target - target % 16))
+        } else if target < 256 {
+            (0x80 | ((target / 2) - 64) as u8, (target - target % 2))
+        } else if target < 512 {
+            (0xC0 | ((target / 8) - 32) as u8, (target - target % 8))
+        } else if target < 1024 {
+            (0xE0 | ((target / 16) - 32) as u8, (target - target % 16))
         } else {
             (u8::MAX, 1008)
         };
@@ -224,3 +378,21 @@ fn compute_dead_time_value(value: u16) -> (Ckd, u8) {
 
     (ckd, bits)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_time() {
+        // Exact in the first, linear, range.
+        assert_eq!(compute_dead_time_value(0), (Ckd::DIV_1, 0));
+        assert_eq!(compute_dead_time_value(100), (Ckd::DIV_1, 100));
+        // (64 + 36) * 2
+        assert_eq!(compute_dead_time_value(200), (Ckd::DIV_1, 0xA4));
+        // (64 + 11) * 2 * 2, exact with the clock divided, where DIV_1 would give 296.
+        assert_eq!(compute_dead_time_value(300), (Ckd::DIV_2, 0x8B));
+        // Saturates at the longest dead-time.
+        assert_eq!(compute_dead_time_value(5000), (Ckd::DIV_4, 0xFF));
+    }
+}
@@ -0,0 +1,66 @@
+//! Hardware timer based delay.
+//!
+//! Unlike the SysTick [`Delay`](crate::delay::Delay), this does not depend on SysTick,
+//! so it can be used alongside the embassy time driver, and counts at the full timer
+//! clock for sub-microsecond resolution.
+
+use super::low_level::Timer;
+use super::BasicInstance;
+use crate::Peripheral;
+
+/// A delay provider using a timer counter.
+///
+/// The counter free-runs at the timer clock over its full 16-bit range. Delays poll the
+/// counter and accumulate elapsed ticks, so they are not limited by the counter range.
+pub struct Delay<'d, T: BasicInstance> {
+    inner: Timer<'d, T>,
+    ticks_per_us: u32,
+}
+
+impl<'d, T: BasicInstance> Delay<'d, T> {
+    /// Create a new delay provider, and start the timer.
+    pub fn new(tim: impl Peripheral<P = T> + 'd) -> Self {
+        let inner = Timer::new(tim);
+
+        let regs = inner.regs_basic();
+        regs.psc().write_value(0);
+        regs.atrlr().write_value(u16::MAX);
+        regs.swevgr().write(|w| w.set_ug(true));
+        inner.start();
+
+        Self {
+            ticks_per_us: T::frequency().0 / 1_000_000,
+            inner,
+        }
+    }
+
+    fn delay_ticks(&mut self, ticks: u64) {
+        let cnt = self.inner.regs_basic().cnt();
+
+        let mut last = cnt.read();
+        let mut elapsed = 0u64;
+        while elapsed < ticks {
+            let now = cnt.read();
+            elapsed += now.wrapping_sub(last) as u64;
+            last = now;
+        }
+    }
+}
+
+impl<'d, T: BasicInstance> embedded_hal::delay::DelayNs for Delay<'d, T> {
+    #[inline]
+    fn delay_ns(&mut self, ns: u32) {
+        let ticks = (ns as u64 * T::frequency().0 as u64).div_ceil(1_000_000_000);
+        self.delay_ticks(ticks)
+    }
+
+    #[inline]
+    fn delay_us(&mut self, us: u32) {
+        self.delay_ticks(us as u64 * self.ticks_per_us as u64)
+    }
+
+    #[inline]
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_ticks(ms as u64 * 1000 * self.ticks_per_us as u64)
+    }
+}
@@ -0,0 +1,97 @@
+//! Hall sensor interface for BLDC commutation.
+//!
+//! The three hall sensor inputs are XORed onto TI1 of a general-purpose timer. Each sensor
+//! edge resets the counter and captures the time since the previous edge into CH1, which
+//! gives the rotor speed. CH2 then generates a pulse after a programmable delay, routed through
+//! TRGO to an advanced timer, whose preloaded output configuration is applied on that
+//! commutation event (see [`ComplementaryPwm::enable_commutation`]).
+//!
+//! [`ComplementaryPwm::enable_commutation`]: super::complementary_pwm::ComplementaryPwm::enable_commutation
+
+use super::input_capture::CapturePin;
+use super::low_level::{InputTISelection, MasterMode, OutputCompareMode, Timer, TriggerSource};
+use super::simple_pwm::{Ch1, Ch2, Ch3};
+use super::{Channel, GeneralInstance16bit};
+use crate::pac::timer::vals;
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// Hall sensor interface driver.
+pub struct HallSensor<'d, T: GeneralInstance16bit> {
+    inner: Timer<'d, T>,
+    h1: CapturePin<'d, T, Ch1>,
+    h2: CapturePin<'d, T, Ch2>,
+    h3: CapturePin<'d, T, Ch3>,
+}
+
+impl<'d, T: GeneralInstance16bit> HallSensor<'d, T> {
+    /// Create a new hall sensor interface.
+    ///
+    /// `freq` is the counter tick frequency. It must be low enough for the counter not to
+    /// overflow between two hall edges at the lowest speed of interest.
+    /// `commutation_delay` is the number of ticks from a hall edge to the TRGO commutation pulse.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        h1: CapturePin<'d, T, Ch1>,
+        h2: CapturePin<'d, T, Ch2>,
+        h3: CapturePin<'d, T, Ch3>,
+        freq: Hertz,
+        commutation_delay: u16,
+    ) -> Self {
+        let inner = Timer::new(tim);
+
+        inner.set_tick_frequency(freq);
+        inner.set_ti1_xor(true);
+
+        // CH1 captures the counter on every hall edge, which also resets the counter.
+        inner.set_input_ti_selection(Channel::Ch1, InputTISelection::TRC);
+        inner.set_trigger_source(TriggerSource::Ti1Edge);
+        inner
+            .regs_gp16()
+            .smcfgr()
+            .modify(|w| w.set_sms(vals::Sms::from_bits(0b100)));
+
+        // CH2 produces the delayed commutation pulse on TRGO.
+        inner.set_output_compare_mode(Channel::Ch2, OutputCompareMode::PwmMode2);
+        inner.set_compare_value(Channel::Ch2, commutation_delay.max(1) as u32);
+        inner.set_master_mode(MasterMode::CompareOc2);
+
+        inner.enable_channel(Channel::Ch1, true);
+        inner.start();
+
+        Self { inner, h1, h2, h3 }
+    }
+
+    /// Current state of the hall sensors: H1 in bit 0, H2 in bit 1 and H3 in bit 2.
+    pub fn state(&self) -> u8 {
+        self.h1.is_high() as u8 | (self.h2.is_high() as u8) << 1 | (self.h3.is_high() as u8) << 2
+    }
+
+    /// Set the delay, in ticks, from a hall edge to the commutation pulse.
+    pub fn set_commutation_delay(&mut self, ticks: u16) {
+        self.inner.set_compare_value(Channel::Ch2, ticks.max(1) as u32);
+    }
+
+    /// Ticks between the last two hall edges.
+    ///
+    /// A full electrical revolution is six hall edges.
+    pub fn edge_interval(&self) -> u16 {
+        self.inner.get_capture_value(Channel::Ch1) as u16
+    }
+
+    /// Get and clear the hall edge flag, set on every hall sensor transition.
+    pub fn take_edge_event(&mut self) -> bool {
+        let regs = self.inner.regs_gp16();
+        let edge = regs.intfr().read().ccif(0);
+        if edge {
+            regs.intfr().modify(|w| w.set_ccif(0, false));
+        }
+        edge
+    }
+
+    /// Whether the counter overflowed since the last hall edge, i.e. the rotor is stalled
+    /// or turning slower than the tick frequency allows to measure. Clears the flag.
+    pub fn take_overflow_event(&mut self) -> bool {
+        self.inner.clear_update_interrupt()
+    }
+}
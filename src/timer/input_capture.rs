@@ -0,0 +1,212 @@
+//! Input capture driver.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use super::low_level::{InputCaptureMode, InputTISelection, Timer};
+use super::simple_pwm::{Ch1, Ch2, Ch3, Ch4};
+use super::{
+    CaptureCompareInterruptHandler, Channel, Channel1Pin, Channel2Pin, Channel3Pin, Channel4Pin, GeneralInstance16bit,
+};
+use crate::gpio::{AnyPin, Pull, SealedPin};
+use crate::interrupt::typelevel::{Binding, Interrupt};
+use crate::pac::timer::vals::FilterValue;
+use crate::time::Hertz;
+use crate::{into_ref, Peripheral, PeripheralRef};
+
+/// Capture pin wrapper.
+///
+/// This wraps a pin to make it usable as a timer capture input.
+pub struct CapturePin<'d, T, C> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<(T, C)>,
+}
+
+macro_rules! channel_impl {
+    ($new_chx:ident, $channel:ident, $pin_trait:ident) => {
+        impl<'d, T: GeneralInstance16bit> CapturePin<'d, T, $channel> {
+            #[doc = concat!("Create a new ", stringify!($channel), " capture pin instance.")]
+            pub fn $new_chx<const REMAP: u8>(
+                pin: impl Peripheral<P = impl $pin_trait<T, REMAP>> + 'd,
+                pull: Pull,
+            ) -> Self {
+                into_ref!(pin);
+                critical_section::with(|_| {
+                    pin.set_as_input(pull);
+                    T::set_remap(REMAP);
+                });
+                CapturePin {
+                    _pin: pin.map_into(),
+                    phantom: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+impl<'d, T, C> CapturePin<'d, T, C> {
+    /// Current input level of the pin.
+    pub(crate) fn is_high(&self) -> bool {
+        self._pin.block().indr().read().idr(self._pin._pin() as usize)
+    }
+}
+
+channel_impl!(new_ch1, Ch1, Channel1Pin);
+channel_impl!(new_ch2, Ch2, Channel2Pin);
+channel_impl!(new_ch3, Ch3, Channel3Pin);
+channel_impl!(new_ch4, Ch4, Channel4Pin);
+
+/// Input capture driver.
+///
+/// The counter free-runs at the tick frequency given on creation, and each captured
+/// edge latches the counter value into the channel register. Timestamps wrap around
+/// with the counter, so compute intervals with wrapping arithmetic.
+pub struct InputCapture<'d, T: GeneralInstance16bit> {
+    inner: Timer<'d, T>,
+}
+
+impl<'d, T: GeneralInstance16bit> InputCapture<'d, T> {
+    /// Create a new input capture driver.
+    ///
+    /// `freq` is the counter tick frequency, which sets the timestamp resolution.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        _ch1: Option<CapturePin<'d, T, Ch1>>,
+        _ch2: Option<CapturePin<'d, T, Ch2>>,
+        _ch3: Option<CapturePin<'d, T, Ch3>>,
+        _ch4: Option<CapturePin<'d, T, Ch4>>,
+        _irq: impl Binding<T::CaptureCompareInterrupt, CaptureCompareInterruptHandler<T>> + 'd,
+        freq: Hertz,
+    ) -> Self {
+        let this = Self { inner: Timer::new(tim) };
+
+        this.inner.set_tick_frequency(freq);
+        this.inner.start();
+
+        T::CaptureCompareInterrupt::unpend();
+        unsafe { T::CaptureCompareInterrupt::enable() };
+
+        this
+    }
+
+    /// Enable capture on the given channel.
+    pub fn enable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, true);
+    }
+
+    /// Disable capture on the given channel.
+    pub fn disable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, false);
+    }
+
+    /// Check whether given channel is enabled.
+    pub fn is_enabled(&self, channel: Channel) -> bool {
+        self.inner.get_channel_enable_state(channel)
+    }
+
+    /// Set the edge that triggers a capture.
+    ///
+    /// Capturing both edges is not available on 2-channel GPTM timers.
+    pub fn set_input_capture_mode(&mut self, channel: Channel, mode: InputCaptureMode) {
+        let idx = channel.index();
+        self.inner.regs_gp16().ccer().modify(|w| {
+            let (ccp, _ccnp) = match mode {
+                InputCaptureMode::Rising => (false, false),
+                InputCaptureMode::Falling => (true, false),
+                InputCaptureMode::BothEdges => (true, true),
+            };
+            w.set_ccp(idx, ccp);
+            #[cfg(not(timer_x0))]
+            w.set_ccnp(idx, _ccnp);
+            #[cfg(timer_x0)]
+            assert!(!_ccnp);
+        });
+    }
+
+    /// Capture once every `factor` edges, `factor` being 1, 2, 4 or 8.
+    pub fn set_prescaler(&mut self, channel: Channel, factor: u8) {
+        let psc = match factor {
+            1 => 0,
+            2 => 1,
+            4 => 2,
+            8 => 3,
+            _ => panic!("invalid capture prescaler"),
+        };
+        self.inner.set_input_capture_prescaler(channel, psc);
+    }
+
+    /// Set the digital input filter of a channel.
+    pub fn set_filter(&mut self, channel: Channel, filter: FilterValue) {
+        self.inner.set_input_capture_filter(channel, filter);
+    }
+
+    /// Get the last captured counter value of a channel.
+    pub fn get_capture_value(&self, channel: Channel) -> u32 {
+        self.inner.get_capture_value(channel)
+    }
+
+    fn configure(&mut self, channel: Channel, mode: InputCaptureMode, tisel: InputTISelection) {
+        // CCxS must be written while the channel is disabled.
+        self.inner.enable_channel(channel, false);
+        self.inner.set_input_ti_selection(channel, tisel);
+        self.set_input_capture_mode(channel, mode);
+        self.inner.clear_input_interrupt(channel);
+        self.inner.enable_channel(channel, true);
+    }
+
+    fn is_captured(&self, channel: Channel) -> bool {
+        self.inner.regs_gp16().intfr().read().ccif(channel.index())
+    }
+
+    /// Wait for an edge and return its timestamp, busy-looping.
+    pub fn blocking_wait_for_capture(&mut self, channel: Channel, mode: InputCaptureMode) -> u32 {
+        self.configure(channel, mode, InputTISelection::Normal);
+
+        while !self.is_captured(channel) {}
+
+        // Reading the capture register clears CCxIF.
+        self.get_capture_value(channel)
+    }
+
+    /// Wait for an edge and return its timestamp, using the capture interrupt.
+    pub async fn wait_for_capture(&mut self, channel: Channel, mode: InputCaptureMode) -> u32 {
+        self.configure(channel, mode, InputTISelection::Normal);
+        self.wait_captured(channel).await
+    }
+
+    /// Wait for an edge on the pin of the paired channel (CH1 with CH2, CH3 with CH4).
+    ///
+    /// This allows capturing both edges of one pin on two channels.
+    pub async fn wait_for_capture_alternate(&mut self, channel: Channel, mode: InputCaptureMode) -> u32 {
+        self.configure(channel, mode, InputTISelection::Alternate);
+        self.wait_captured(channel).await
+    }
+
+    async fn wait_captured(&mut self, channel: Channel) -> u32 {
+        poll_fn(|cx| {
+            T::state().cc_waker[channel.index()].register(cx.waker());
+
+            if self.is_captured(channel) {
+                Poll::Ready(())
+            } else {
+                self.inner.enable_input_interrupt(channel, true);
+                Poll::Pending
+            }
+        })
+        .await;
+
+        self.inner.enable_input_interrupt(channel, false);
+        self.get_capture_value(channel)
+    }
+
+    /// Wait for a rising edge and return its timestamp.
+    pub async fn wait_for_rising_edge(&mut self, channel: Channel) -> u32 {
+        self.wait_for_capture(channel, InputCaptureMode::Rising).await
+    }
+
+    /// Wait for a falling edge and return its timestamp.
+    pub async fn wait_for_falling_edge(&mut self, channel: Channel) -> u32 {
+        self.wait_for_capture(channel, InputCaptureMode::Falling).await
+    }
+}
@@ -1,5 +1,9 @@
 //! Low-level timer driver.
 
+use core::future::poll_fn;
+use core::mem::ManuallyDrop;
+use core::task::Poll;
+
 use super::*;
 use crate::pac::timer::vals;
 use crate::time::Hertz;
@@ -149,6 +153,36 @@ impl From<OutputCompareMode> for vals::Ocm {
     }
 }
 
+/// Trigger output (TRGO) source, aka. master mode selection.
+///
+/// TRGO can start ADC conversions or clock/trigger other timers.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterMode {
+    /// The UG bit is used as TRGO.
+    Reset = 0b000,
+    /// The counter enable signal is used as TRGO.
+    Enable = 0b001,
+    /// The update event is used as TRGO.
+    Update = 0b010,
+    /// A pulse is sent when CC1IF is set, on capture or compare match.
+    ComparePulse = 0b011,
+    /// OC1REF is used as TRGO.
+    CompareOc1 = 0b100,
+    /// OC2REF is used as TRGO.
+    CompareOc2 = 0b101,
+    /// OC3REF is used as TRGO.
+    CompareOc3 = 0b110,
+    /// OC4REF is used as TRGO.
+    CompareOc4 = 0b111,
+}
+
+impl From<MasterMode> for vals::Mms {
+    fn from(mode: MasterMode) -> Self {
+        vals::Mms::from_bits(mode as u8)
+    }
+}
+
 /// Timer output pin polarity.
 #[derive(Clone, Copy)]
 pub enum OutputPolarity {
@@ -167,6 +201,90 @@ impl From<OutputPolarity> for bool {
     }
 }
 
+/// Output level while the outputs of an advanced timer are disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleState {
+    /// Output is low when idle.
+    Low,
+    /// Output is high when idle.
+    High,
+}
+
+impl From<IdleState> for bool {
+    fn from(state: IdleState) -> Self {
+        match state {
+            IdleState::Low => false,
+            IdleState::High => true,
+        }
+    }
+}
+
+/// Write protection level of the advanced timer configuration (BDTR.LOCK).
+///
+/// Once written, the lock level can only be changed by a reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockLevel {
+    /// No write protection.
+    Off = 0b00,
+    /// Lock dead time, break and AOE settings, and output idle states.
+    Level1 = 0b01,
+    /// Level 1, plus output polarities and off-state selections.
+    Level2 = 0b10,
+    /// Level 2, plus output compare modes and preloads.
+    Level3 = 0b11,
+}
+
+/// Trigger input (TRGI) source of the slave mode controller (SMCFGR.TS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerSource {
+    /// Internal trigger 0, TRGO of another timer.
+    Itr0 = 0b000,
+    /// Internal trigger 1, TRGO of another timer.
+    Itr1 = 0b001,
+    /// Internal trigger 2, TRGO of another timer.
+    Itr2 = 0b010,
+    /// Internal trigger 3, TRGO of another timer.
+    Itr3 = 0b011,
+    /// Both edges of the TI1 input.
+    Ti1Edge = 0b100,
+    /// Filtered TI1 input, with the channel 1 polarity.
+    Ti1 = 0b101,
+    /// Filtered TI2 input, with the channel 2 polarity.
+    Ti2 = 0b110,
+    /// Filtered external trigger input (ETR).
+    Etr = 0b111,
+}
+
+/// Slave mode controller mode (SMCFGR.SMS), reacting to the trigger input (TRGI).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlaveMode {
+    /// Slave mode disabled, the counter is clocked by the internal clock.
+    Disabled = 0b000,
+    /// A rising edge of TRGI resets the counter and generates an update event.
+    Reset = 0b100,
+    /// The counter is clocked only while TRGI is high.
+    Gated = 0b101,
+    /// A rising edge of TRGI starts the counter.
+    Trigger = 0b110,
+    /// Rising edges of TRGI clock the counter (external clock mode 1).
+    ExternalClock = 0b111,
+}
+
+/// Prescaler of the external trigger input (SMCFGR.ETPS).
+///
+/// The ETR frequency after the prescaler must stay below a quarter of the timer clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalTriggerPrescaler {
+    /// No division.
+    Div1 = 0b00,
+    /// Divide by 2.
+    Div2 = 0b01,
+    /// Divide by 4.
+    Div4 = 0b10,
+    /// Divide by 8.
+    Div8 = 0b11,
+}
+
 /// Low-level timer driver.
 pub struct Timer<'d, T: CoreInstance> {
     tim: PeripheralRef<'d, T>,
@@ -188,6 +306,16 @@ impl<'d, T: CoreInstance> Timer<'d, T> {
         Self { tim }
     }
 
+    /// Create another handle to the same timer.
+    ///
+    /// The handle must not be dropped, as that would disable the timer under the original one.
+    pub(crate) unsafe fn clone_unchecked(&self) -> ManuallyDrop<Self> {
+        let tim = unsafe { core::ptr::read(&*self.tim as *const T) };
+        ManuallyDrop::new(Self {
+            tim: PeripheralRef::new(tim),
+        })
+    }
+
     #[cfg(any(ch32l1, ch32v208))]
     fn regs_gp32_unchecked(&self) -> crate::pac::timer::Gptm32 {
         unsafe { crate::pac::timer::Gptm32::from_ptr(T::regs()) }
@@ -265,6 +393,39 @@ impl<'d, T: BasicInstance> Timer<'d, T> {
         }
     }
 
+    /// Set the counter tick frequency, with the counter running over its full range.
+    ///
+    /// Used when the counter serves as a time base for captures rather than a PWM period.
+    pub fn set_tick_frequency(&self, frequency: Hertz) {
+        let f = frequency.0;
+        assert!(f > 0);
+        let timer_f = T::frequency().0;
+
+        let psc: u16 = ((timer_f / f).max(1) - 1).try_into().unwrap();
+
+        match T::BITS {
+            TimerBits::Bits16 => {
+                let regs = self.regs_basic();
+                regs.psc().write_value(psc);
+                regs.atrlr().write_value(u16::MAX);
+
+                regs.ctlr1().modify(|r| r.set_urs(vals::Urs::COUNTERONLY));
+                regs.swevgr().write(|r| r.set_ug(true));
+                regs.ctlr1().modify(|r| r.set_urs(vals::Urs::ANYEVENT));
+            }
+            #[cfg(any(ch32l1, ch32v208))]
+            TimerBits::Bits32 => {
+                let regs = self.regs_gp32_unchecked();
+                regs.psc().write_value(psc);
+                regs.atrlr().write_value(u32::MAX);
+
+                regs.ctlr1().modify(|r| r.set_urs(vals::Urs::COUNTERONLY));
+                regs.swevgr().write(|r| r.set_ug(true));
+                regs.ctlr1().modify(|r| r.set_urs(vals::Urs::ANYEVENT));
+            }
+        }
+    }
+
     /// Clear update interrupt.
     ///
     /// Returns whether the update interrupt flag was set.
@@ -286,6 +447,26 @@ impl<'d, T: BasicInstance> Timer<'d, T> {
         self.regs_basic().dmaintenr().modify(|r| r.set_uie(enable));
     }
 
+    /// Enable or disable the generation of update events (UDIS).
+    ///
+    /// While disabled, counter overflows and triggers do not reload the preloaded registers.
+    pub fn enable_update_event(&self, enable: bool) {
+        self.regs_basic().ctlr1().modify(|r| r.set_udis(!enable));
+    }
+
+    /// Select which events raise the update interrupt and DMA request (URS).
+    ///
+    /// With `counter_only`, software updates (UG) and slave mode resets update the
+    /// registers silently, only counter overflows and underflows raise the interrupt.
+    pub fn set_update_request_source(&self, counter_only: bool) {
+        let urs = if counter_only {
+            vals::Urs::COUNTERONLY
+        } else {
+            vals::Urs::ANYEVENT
+        };
+        self.regs_basic().ctlr1().modify(|r| r.set_urs(urs));
+    }
+
     /// Enable/disable autoreload preload.
     pub fn set_autoreload_preload(&self, enable: bool) {
         self.regs_basic().ctlr1().modify(|r| r.set_arpe(enable));
@@ -314,6 +495,38 @@ impl<'d, T: BasicInstance> Timer<'d, T> {
         }
     }
 
+    /// Enable the update interrupt line, required by [`wait_for_update`](Self::wait_for_update).
+    pub fn bind_update_interrupt(
+        &self,
+        _irq: impl interrupt::typelevel::Binding<T::UpdateInterrupt, UpdateInterruptHandler<T>> + 'd,
+    ) {
+        use interrupt::typelevel::Interrupt;
+
+        T::UpdateInterrupt::unpend();
+        unsafe { T::UpdateInterrupt::enable() };
+    }
+
+    /// Wait for the next update event (overflow, underflow or UG).
+    ///
+    /// The update interrupt must be bound, see [`bind_update_interrupt`](Self::bind_update_interrupt).
+    pub async fn wait_for_update(&self) {
+        let regs = self.regs_basic();
+        regs.intfr().modify(|r| r.set_uif(false));
+
+        poll_fn(|cx| {
+            T::state().up_waker.register(cx.waker());
+
+            if regs.intfr().read().uif() {
+                regs.intfr().modify(|r| r.set_uif(false));
+                Poll::Ready(())
+            } else {
+                regs.dmaintenr().modify(|r| r.set_uie(true));
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+
     /// Enable/disable the update dma.
     #[cfg(not(timer_x0))] // GPTM_2CH
     pub fn enable_update_dma(&self, enable: bool) {
@@ -406,6 +619,54 @@ impl<'d, T: GeneralInstance16bit> Timer<'d, T> {
             .modify(|r| r.set_icpsc(raw_channel % 2, factor));
     }
 
+    /// Enable the capture/compare interrupt line, required by
+    /// [`wait_for_compare`](Self::wait_for_compare) and [`wait_for_capture`](Self::wait_for_capture).
+    pub fn bind_capture_compare_interrupt(
+        &self,
+        _irq: impl interrupt::typelevel::Binding<T::CaptureCompareInterrupt, CaptureCompareInterruptHandler<T>> + 'd,
+    ) {
+        use interrupt::typelevel::Interrupt;
+
+        T::CaptureCompareInterrupt::unpend();
+        unsafe { T::CaptureCompareInterrupt::enable() };
+    }
+
+    async fn wait_for_cc_flag(&self, channel: Channel) {
+        let regs = self.regs_gp16();
+        regs.intfr().modify(|r| r.set_ccif(channel.index(), false));
+
+        poll_fn(|cx| {
+            T::state().cc_waker[channel.index()].register(cx.waker());
+
+            if regs.intfr().read().ccif(channel.index()) {
+                Poll::Ready(())
+            } else {
+                self.enable_input_interrupt(channel, true);
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+
+    /// Wait for the next compare match of an output channel.
+    ///
+    /// The capture/compare interrupt must be bound, see
+    /// [`bind_capture_compare_interrupt`](Self::bind_capture_compare_interrupt).
+    pub async fn wait_for_compare(&self, channel: Channel) {
+        self.wait_for_cc_flag(channel).await;
+        self.clear_input_interrupt(channel);
+    }
+
+    /// Wait for the next capture of an input channel, returning the captured counter value.
+    ///
+    /// The capture/compare interrupt must be bound, see
+    /// [`bind_capture_compare_interrupt`](Self::bind_capture_compare_interrupt).
+    pub async fn wait_for_capture(&self, channel: Channel) -> u32 {
+        self.wait_for_cc_flag(channel).await;
+        // Reading the capture register clears the flag.
+        self.get_capture_value(channel)
+    }
+
     /// Set input TI selection.
     pub fn set_input_ti_selection(&self, channel: Channel, tisel: InputTISelection) {
         let raw_channel = channel.index();
@@ -449,6 +710,15 @@ impl<'d, T: GeneralInstance16bit> Timer<'d, T> {
             .modify(|w| w.set_ccp(channel.index(), polarity.into()));
     }
 
+    /// Get the output polarity of a channel.
+    pub fn get_output_polarity(&self, channel: Channel) -> OutputPolarity {
+        if self.regs_gp16().ccer().read().ccp(channel.index()) {
+            OutputPolarity::ActiveLow
+        } else {
+            OutputPolarity::ActiveHigh
+        }
+    }
+
     /// Enable/disable a channel.
     pub fn enable_channel(&self, channel: Channel, enable: bool) {
         self.regs_gp16().ccer().modify(|w| w.set_cce(channel.index(), enable));
@@ -527,6 +797,72 @@ impl<'d, T: GeneralInstance16bit> Timer<'d, T> {
             .dmaintenr()
             .modify(|w| w.set_ccde(channel.index(), ccde))
     }
+
+    /// Set the trigger output (TRGO) source.
+    #[cfg(not(timer_x0))] // no CTLR2
+    pub fn set_master_mode(&self, mode: MasterMode) {
+        self.regs_gp16().ctlr2().modify(|w| w.set_mms(mode.into()));
+    }
+
+    /// Configure the external trigger input (ETR): prescaler, digital filter and polarity.
+    ///
+    /// With `inverted` the falling edges of ETR are active instead of the rising ones.
+    pub fn set_external_trigger(&self, prescaler: ExternalTriggerPrescaler, filter: vals::FilterValue, inverted: bool) {
+        self.regs_gp16().smcfgr().modify(|w| {
+            w.set_etps(vals::Etps::from_bits(prescaler as u8));
+            w.set_etf(filter);
+            w.set_etp(inverted);
+        });
+    }
+
+    /// Clock the counter from the active edges of the external trigger input (external clock mode 2).
+    ///
+    /// This mode can be combined with a slave mode, e.g. to reset or gate the counter on TRGI.
+    pub fn enable_external_clock_mode2(&self, enable: bool) {
+        self.regs_gp16().smcfgr().modify(|w| w.set_ece(enable));
+    }
+
+    /// Clock the counter from the rising edges of the trigger input (external clock mode 1).
+    pub fn set_external_clock_mode1(&self, source: TriggerSource) {
+        self.regs_gp16().smcfgr().modify(|w| {
+            w.set_ts(vals::Ts::from_bits(source as u8));
+            w.set_sms(vals::Sms::from_bits(0b111));
+        });
+    }
+
+    /// Select the trigger input (TRGI) of the slave mode controller.
+    pub fn set_trigger_source(&self, source: TriggerSource) {
+        self.regs_gp16()
+            .smcfgr()
+            .modify(|w| w.set_ts(vals::Ts::from_bits(source as u8)));
+    }
+
+    /// Set the slave mode, reacting to the trigger input selected by [`set_trigger_source`](Self::set_trigger_source).
+    pub fn set_slave_mode(&self, mode: SlaveMode) {
+        self.regs_gp16()
+            .smcfgr()
+            .modify(|w| w.set_sms(vals::Sms::from_bits(mode as u8)));
+    }
+
+    /// Delay the effect of the trigger input on this timer, so it stays in step with the
+    /// timers it drives through TRGO (MSM).
+    pub fn set_master_slave_mode(&self, enable: bool) {
+        self.regs_gp16().smcfgr().modify(|w| w.set_msm(enable));
+    }
+
+    /// Connect the XOR of the CH1, CH2 and CH3 pins to TI1, for hall sensor interfacing (TI1S).
+    #[cfg(not(timer_x0))] // no CTLR2
+    pub fn set_ti1_xor(&self, enable: bool) {
+        self.regs_gp16().ctlr2().modify(|w| w.set_ti1s(enable));
+    }
+
+    /// Clock the counter from the internal prescaled clock again, disabling both external clock modes.
+    pub fn set_internal_clock(&self) {
+        self.regs_gp16().smcfgr().modify(|w| {
+            w.set_ece(false);
+            w.set_sms(vals::Sms::from_bits(0b000));
+        });
+    }
 }
 
 impl<'d, T: AdvancedInstance> Timer<'d, T> {
@@ -563,6 +899,117 @@ impl<'d, T: AdvancedInstance> Timer<'d, T> {
             .ccer()
             .modify(|w| w.set_ccne(channel.index(), enable));
     }
+
+    /// Enable/disable the break input.
+    pub fn enable_break(&self, enable: bool) {
+        self.regs_advanced().bdtr().modify(|w| w.set_bke(enable));
+    }
+
+    /// Set the active level of the break input.
+    pub fn set_break_polarity(&self, active_high: bool) {
+        self.regs_advanced().bdtr().modify(|w| w.set_bkp(active_high));
+    }
+
+    /// Set whether MOE is set again automatically on the next update event after a break (AOE).
+    pub fn set_automatic_output_enable(&self, enable: bool) {
+        self.regs_advanced().bdtr().modify(|w| w.set_aoe(enable));
+    }
+
+    /// Set the off-state selections for run (OSSR) and idle (OSSI) modes.
+    ///
+    /// When set, disabled outputs keep driving their inactive or idle level instead of floating.
+    pub fn set_off_state_selection(&self, run: bool, idle: bool) {
+        self.regs_advanced().bdtr().modify(|w| {
+            w.set_ossr(run);
+            w.set_ossi(idle);
+        });
+    }
+
+    /// Set the write protection level.
+    pub fn set_lock_level(&self, level: LockLevel) {
+        self.regs_advanced().bdtr().modify(|w| w.set_lock(level as u8));
+    }
+
+    /// Get and clear the break interrupt flag.
+    pub fn clear_break_interrupt(&self) -> bool {
+        let regs = self.regs_advanced();
+        let bif = regs.intfr().read().bif();
+        if bif {
+            regs.intfr().modify(|w| w.set_bif(false));
+        }
+        bif
+    }
+
+    /// Set the repetition counter (RPTCR): an update event is generated every `value + 1`
+    /// counter overflows, or half periods in center-aligned mode.
+    ///
+    /// The new value is applied on the next update event.
+    pub fn set_repetition_counter(&self, value: u8) {
+        self.regs_advanced().rptcr().modify(|w| w.set_rptcr(value));
+    }
+
+    /// Get the repetition counter.
+    pub fn get_repetition_counter(&self) -> u8 {
+        self.regs_advanced().rptcr().read().rptcr()
+    }
+
+    /// Generate an update event only every `periods` counter periods, 1 to 256, or 1 to 128
+    /// in center-aligned modes, where both the top and the bottom of the counter count as a
+    /// repetition.
+    pub fn set_update_rate(&self, periods: u16) {
+        let repetitions = if self.get_counting_mode().is_center_aligned() {
+            assert!((1..=128).contains(&periods));
+            periods * 2
+        } else {
+            assert!((1..=256).contains(&periods));
+            periods
+        };
+        self.set_repetition_counter((repetitions - 1) as u8);
+    }
+
+    /// Get state of MOE-bit in BDTR register.
+    pub fn get_moe(&self) -> bool {
+        self.regs_advanced().bdtr().read().moe()
+    }
+
+    /// Preload CCxE, CCxNE and OCxM, so they are only applied on a commutation event (CCPC).
+    pub fn set_commutation_preload(&self, enable: bool) {
+        self.regs_advanced().ctlr2().modify(|w| w.set_ccpc(enable));
+    }
+
+    /// Generate commutation events on rising edges of TRGI too, not only by software (CCUS).
+    pub fn set_commutation_on_trigger(&self, enable: bool) {
+        self.regs_advanced().ctlr2().modify(|w| w.set_ccus(enable));
+    }
+
+    /// Generate a commutation event by software.
+    pub fn generate_commutation_event(&self) {
+        self.regs_advanced().swevgr().write(|w| w.set_comg(true));
+    }
+
+    /// Get and clear the commutation interrupt flag.
+    pub fn clear_commutation_interrupt(&self) -> bool {
+        let regs = self.regs_advanced();
+        let comif = regs.intfr().read().comif();
+        if comif {
+            regs.intfr().modify(|w| w.set_comif(false));
+        }
+        comif
+    }
+
+    /// Set the output idle state (OISx), applied when MOE is cleared.
+    pub fn set_output_idle_state(&self, channel: Channel, state: IdleState) {
+        self.regs_advanced()
+            .ctlr2()
+            .modify(|w| w.set_ois(channel.index(), state.into()));
+    }
+
+    /// Set the complementary output idle state (OISxN), applied when MOE is cleared.
+    pub fn set_complementary_output_idle_state(&self, channel: Channel, state: IdleState) {
+        self.regs_advanced()
+            .ctlr2()
+            .modify(|w| w.set_oisn(channel.index(), state.into()));
+    }
 }
 
 // GPTM 2CH does not have these features
@@ -589,6 +1036,11 @@ impl<'d, T: AdvancedInstance> Timer<'d, T> {
             .modify(|w| w.set_ccde(channel.index(), ccde))
     }
 
+    /// Set the trigger output (TRGO) source.
+    pub fn set_master_mode(&self, mode: MasterMode) {
+        self.regs_advanced().ctlr2().modify(|w| w.set_mms(mode.into()));
+    }
+
     pub fn set_input_capture_mode(&self, channel: Channel, mode: InputCaptureMode) {
         self.regs_advanced().ccer().modify(|r| match mode {
             InputCaptureMode::Rising => {
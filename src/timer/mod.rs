@@ -11,12 +11,36 @@
 //! - No too many levels of abstraction
 //! - 2CH GPTM instances are also have helper functions defined
 
+use core::marker::PhantomData;
+
+use embassy_sync::waitqueue::AtomicWaker;
+
 use crate::peripheral::RccPeripheral;
 use crate::{interrupt, RemapPeripheral};
 
+#[cfg(not(timer_x0))]
+pub mod chained;
 pub mod complementary_pwm;
+pub mod delay;
+#[cfg(not(timer_x0))]
+pub mod hall;
+pub mod input_capture;
 pub mod low_level;
+pub mod one_pulse;
+pub mod output_compare;
+pub mod periodic;
+pub mod pulse_counter;
+pub mod pwm_input;
+pub mod qei;
+pub mod servo;
 pub mod simple_pwm;
+#[cfg(not(timer_x0))]
+pub mod sync;
+pub mod tone;
+#[cfg(not(timer_x0))]
+pub mod ws2812;
+
+pub use periodic::Periodic;
 
 /// Timer channel.
 #[derive(Clone, Copy, PartialEq)]
@@ -54,6 +78,26 @@ pub enum TimerBits {
     Bits32,
 }
 
+/// Per-timer wakers, woken from the timer interrupt handlers.
+pub struct State {
+    pub(crate) up_waker: AtomicWaker,
+    pub(crate) cc_waker: [AtomicWaker; 4],
+}
+
+impl State {
+    pub const fn new() -> Self {
+        Self {
+            up_waker: AtomicWaker::new(),
+            cc_waker: [
+                AtomicWaker::new(),
+                AtomicWaker::new(),
+                AtomicWaker::new(),
+                AtomicWaker::new(),
+            ],
+        }
+    }
+}
+
 /// Core timer instance.
 pub trait CoreInstance: RccPeripheral + RemapPeripheral + 'static {
     /// Update Interrupt for this timer.
@@ -66,6 +110,10 @@ pub trait CoreInstance: RccPeripheral + RemapPeripheral + 'static {
     ///
     /// This is a raw pointer to the register block. The actual register block layout varies depending on the timer type.
     fn regs() -> *mut ();
+
+    /// Wakers of this timer.
+    #[doc(hidden)]
+    fn state() -> &'static State;
 }
 
 /// Basic timer instance, BCTM
@@ -123,6 +171,47 @@ dma_trait!(Ch2Dma, GeneralInstance16bit);
 dma_trait!(Ch3Dma, GeneralInstance16bit);
 dma_trait!(Ch4Dma, GeneralInstance16bit);
 
+/// Update interrupt handler.
+pub struct UpdateInterruptHandler<T: CoreInstance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: CoreInstance> interrupt::typelevel::Handler<T::UpdateInterrupt> for UpdateInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let regs = unsafe { crate::pac::timer::Bctm::from_ptr(T::regs()) };
+
+        if regs.intfr().read().uif() && regs.dmaintenr().read().uie() {
+            // Disable the interrupt, the flag is left for the waiting task to check.
+            regs.dmaintenr().modify(|w| w.set_uie(false));
+            T::state().up_waker.wake();
+        }
+    }
+}
+
+/// Capture/compare interrupt handler.
+pub struct CaptureCompareInterruptHandler<T: GeneralInstance16bit> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: GeneralInstance16bit> interrupt::typelevel::Handler<T::CaptureCompareInterrupt>
+    for CaptureCompareInterruptHandler<T>
+{
+    unsafe fn on_interrupt() {
+        let regs = unsafe { crate::pac::timer::Gptm::from_ptr(T::regs()) };
+
+        let intfr = regs.intfr().read();
+        let dmaintenr = regs.dmaintenr().read();
+
+        for ch in 0..4 {
+            if intfr.ccif(ch) && dmaintenr.ccie(ch) {
+                // Disable the interrupt, the flag is left for the waiting task to check.
+                regs.dmaintenr().modify(|w| w.set_ccie(ch, false));
+                T::state().cc_waker[ch].wake();
+            }
+        }
+    }
+}
+
 #[allow(unused)]
 macro_rules! impl_core_timer {
     ($inst:ident, $bits:expr) => {
@@ -134,6 +223,11 @@ macro_rules! impl_core_timer {
             fn regs() -> *mut () {
                 crate::pac::$inst.as_ptr()
             }
+
+            fn state() -> &'static State {
+                static STATE: State = State::new();
+                &STATE
+            }
         }
     };
 }
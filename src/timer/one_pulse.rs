@@ -0,0 +1,116 @@
+//! One-pulse mode driver.
+//!
+//! The counter runs once per trigger: outputs go active `delay` ticks after the trigger
+//! and inactive again `width` ticks later, then the counter stops until the next trigger.
+
+use super::input_capture::CapturePin;
+use super::low_level::{InputTISelection, OutputCompareMode, Timer};
+use super::simple_pwm::{Ch1, Ch2, Ch3, Ch4, PwmPin};
+use super::{Channel, GeneralInstance16bit};
+use crate::pac::timer::vals;
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// One-pulse driver.
+pub struct OnePulse<'d, T: GeneralInstance16bit> {
+    inner: Timer<'d, T>,
+}
+
+impl<'d, T: GeneralInstance16bit> OnePulse<'d, T> {
+    /// Create a new one-pulse driver, triggered by software.
+    ///
+    /// `freq` is the counter tick frequency, which sets the resolution of delay and width.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        _ch1: Option<PwmPin<'d, T, Ch1>>,
+        _ch2: Option<PwmPin<'d, T, Ch2>>,
+        _ch3: Option<PwmPin<'d, T, Ch3>>,
+        _ch4: Option<PwmPin<'d, T, Ch4>>,
+        freq: Hertz,
+    ) -> Self {
+        let this = Self { inner: Timer::new(tim) };
+
+        this.inner.set_tick_frequency(freq);
+        this.inner.regs_basic().ctlr1().modify(|w| w.set_opm(true));
+
+        [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4]
+            .iter()
+            .for_each(|&channel| {
+                // Inactive while CNT < CCR, active from CCR to ARR.
+                this.inner.set_output_compare_mode(channel, OutputCompareMode::PwmMode2);
+            });
+
+        this.inner.enable_outputs();
+
+        this
+    }
+
+    /// Trigger on an edge of the CH1 input instead of software.
+    ///
+    /// CH1 can then not be used as an output.
+    pub fn set_trigger_ch1(&mut self, _pin: CapturePin<'d, T, Ch1>, falling: bool) {
+        self.set_trigger_input(Channel::Ch1, falling, 0b101);
+    }
+
+    /// Trigger on an edge of the CH2 input instead of software.
+    ///
+    /// CH2 can then not be used as an output.
+    pub fn set_trigger_ch2(&mut self, _pin: CapturePin<'d, T, Ch2>, falling: bool) {
+        self.set_trigger_input(Channel::Ch2, falling, 0b110);
+    }
+
+    fn set_trigger_input(&mut self, channel: Channel, falling: bool, ts: u8) {
+        self.inner.enable_channel(channel, false);
+        self.inner.set_input_ti_selection(channel, InputTISelection::Normal);
+        let regs = self.inner.regs_gp16();
+        regs.ccer().modify(|w| w.set_ccp(channel.index(), falling));
+
+        // Trigger mode: the counter is started by the trigger input.
+        regs.smcfgr().modify(|w| {
+            w.set_ts(vals::Ts::from_bits(ts));
+            w.set_sms(vals::Sms::from_bits(0b110));
+        });
+    }
+
+    /// Set the pulse delay and width, in ticks, for all channels.
+    ///
+    /// `delay` and `width` must be at least 1, and the last tick of the pulse,
+    /// `delay + width - 1`, must fit in 16 bits.
+    pub fn set_pulse(&mut self, delay: u16, width: u16) {
+        assert!(delay > 0 && width > 0);
+        // The pulse lasts until the counter reaches ATRLR, inclusive.
+        let end = delay.checked_add(width - 1).unwrap();
+
+        self.inner.regs_basic().atrlr().write_value(end);
+        for channel in [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4] {
+            self.inner.set_compare_value(channel, delay as u32);
+        }
+    }
+
+    /// Set the pulse delay of one channel, in ticks, keeping the common pulse end.
+    pub fn set_channel_delay(&mut self, channel: Channel, delay: u16) {
+        self.inner.set_compare_value(channel, delay as u32);
+    }
+
+    /// Enable the given channel output.
+    pub fn enable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, true);
+    }
+
+    /// Disable the given channel output.
+    pub fn disable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, false);
+    }
+
+    /// Start a pulse now.
+    ///
+    /// Ignored if a pulse is already in progress.
+    pub fn trigger(&mut self) {
+        self.inner.start();
+    }
+
+    /// Whether a pulse is in progress.
+    pub fn is_busy(&self) -> bool {
+        self.inner.regs_basic().ctlr1().read().cen()
+    }
+}
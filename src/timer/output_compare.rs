@@ -0,0 +1,128 @@
+//! Output compare driver: toggle and forced output modes.
+//!
+//! Unlike [`SimplePwm`](super::simple_pwm::SimplePwm), compare matches here act on the
+//! output level directly. In toggle mode each channel outputs a square wave at half the
+//! counter period frequency, 50% duty, with its phase set by the compare value. Compare
+//! values are not preloaded, so they can be moved on the fly to phase-lock an output in software.
+
+use super::low_level::{OutputCompareMode, OutputPolarity, Timer};
+use super::simple_pwm::{Ch1, Ch2, Ch3, Ch4, PwmPin};
+use super::{Channel, GeneralInstance16bit};
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// Output compare driver.
+pub struct OutputCompare<'d, T: GeneralInstance16bit> {
+    inner: Timer<'d, T>,
+}
+
+impl<'d, T: GeneralInstance16bit> OutputCompare<'d, T> {
+    /// Create a new output compare driver.
+    ///
+    /// `freq` is the counter period frequency, so toggling channels output `freq / 2`.
+    /// All channels start frozen, their output level is not changed by compare matches.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        _ch1: Option<PwmPin<'d, T, Ch1>>,
+        _ch2: Option<PwmPin<'d, T, Ch2>>,
+        _ch3: Option<PwmPin<'d, T, Ch3>>,
+        _ch4: Option<PwmPin<'d, T, Ch4>>,
+        freq: Hertz,
+    ) -> Self {
+        let inner = Timer::new(tim);
+
+        inner.set_frequency(freq);
+        inner.enable_outputs();
+
+        [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4]
+            .iter()
+            .for_each(|&channel| {
+                inner.set_output_compare_mode(channel, OutputCompareMode::Frozen);
+                inner.set_output_compare_preload(channel, false);
+            });
+
+        inner.start();
+
+        Self { inner }
+    }
+
+    /// Enable the given channel.
+    pub fn enable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, true);
+    }
+
+    /// Disable the given channel.
+    pub fn disable(&mut self, channel: Channel) {
+        self.inner.enable_channel(channel, false);
+    }
+
+    /// Set the output polarity for a given channel.
+    pub fn set_polarity(&mut self, channel: Channel, polarity: OutputPolarity) {
+        self.inner.set_output_polarity(channel, polarity);
+    }
+
+    /// Set the output compare mode for a given channel.
+    pub fn set_mode(&mut self, channel: Channel, mode: OutputCompareMode) {
+        self.inner.set_output_compare_mode(channel, mode);
+    }
+
+    /// Number of counter ticks in one period. Compare values range from 0 to this value, excluded.
+    pub fn get_period_ticks(&self) -> u32 {
+        self.inner.get_max_compare_value() + 1
+    }
+
+    /// Current counter value.
+    pub fn get_counter(&self) -> u32 {
+        self.inner.regs_basic().cnt().read() as u32
+    }
+
+    /// Toggle the output of a channel every time the counter reaches `compare`.
+    pub fn toggle(&mut self, channel: Channel, compare: u32) {
+        self.set_compare(channel, compare);
+        self.inner.set_output_compare_mode(channel, OutputCompareMode::Toggle);
+    }
+
+    /// Set the compare value of a channel, i.e. the phase of a toggling output.
+    pub fn set_compare(&mut self, channel: Channel, compare: u32) {
+        assert!(compare < self.get_period_ticks());
+        self.inner.set_compare_value(channel, compare);
+    }
+
+    /// Get the compare value of a channel.
+    pub fn get_compare(&self, channel: Channel) -> u32 {
+        self.inner.get_compare_value(channel)
+    }
+
+    /// Move the compare value of a channel by `ticks`, wrapping around the period.
+    ///
+    /// Delays (positive) or advances (negative) the next edge of a toggling output.
+    pub fn shift_compare(&mut self, channel: Channel, ticks: i32) {
+        let period = self.get_period_ticks() as i32;
+        let compare = (self.get_compare(channel) as i32 + ticks).rem_euclid(period);
+        self.inner.set_compare_value(channel, compare as u32);
+    }
+
+    /// Force the output of a channel to its active or inactive level, regardless of the counter.
+    pub fn force(&mut self, channel: Channel, active: bool) {
+        let mode = if active {
+            OutputCompareMode::ForceActive
+        } else {
+            OutputCompareMode::ForceInactive
+        };
+        self.inner.set_output_compare_mode(channel, mode);
+    }
+
+    /// Drive the output active when the counter reaches `compare`, and leave it there.
+    pub fn set_active_on_match(&mut self, channel: Channel, compare: u32) {
+        self.set_compare(channel, compare);
+        self.inner
+            .set_output_compare_mode(channel, OutputCompareMode::ActiveOnMatch);
+    }
+
+    /// Drive the output inactive when the counter reaches `compare`, and leave it there.
+    pub fn set_inactive_on_match(&mut self, channel: Channel, compare: u32) {
+        self.set_compare(channel, compare);
+        self.inner
+            .set_output_compare_mode(channel, OutputCompareMode::InactiveOnMatch);
+    }
+}
@@ -0,0 +1,96 @@
+//! Periodic ticker driven by a hardware timer.
+//!
+//! Ticks come from the timer update event, so they stay on a fixed grid however long the
+//! task takes to handle each one, unlike rescheduling a software timer after each iteration.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use super::low_level::Timer;
+use super::{BasicInstance, UpdateInterruptHandler};
+use crate::interrupt::typelevel::Binding;
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// Periodic ticker.
+pub struct Periodic<'d, T: BasicInstance> {
+    inner: Timer<'d, T>,
+}
+
+impl<'d, T: BasicInstance> Periodic<'d, T> {
+    /// Create a new ticker firing at `freq`, and start it.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        irq: impl Binding<T::UpdateInterrupt, UpdateInterruptHandler<T>> + 'd,
+        freq: Hertz,
+    ) -> Self {
+        let inner = Timer::new(tim);
+
+        inner.set_frequency(freq);
+        inner.clear_update_interrupt();
+        inner.bind_update_interrupt(irq);
+        inner.start();
+
+        Self { inner }
+    }
+
+    /// Change the tick rate. The current period is restarted.
+    pub fn set_frequency(&mut self, freq: Hertz) {
+        self.inner.stop();
+        self.inner.set_frequency(freq);
+        self.inner.reset();
+        self.inner.clear_update_interrupt();
+        self.inner.start();
+    }
+
+    /// Current tick rate.
+    pub fn get_frequency(&self) -> Hertz {
+        self.inner.get_frequency()
+    }
+
+    /// Pause the ticker.
+    pub fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    /// Resume the ticker, starting a new period.
+    pub fn start(&mut self) {
+        self.inner.reset();
+        self.inner.clear_update_interrupt();
+        self.inner.start();
+    }
+
+    /// Whether a tick elapsed since the last call to [`next`](Self::next), i.e. the task is late.
+    pub fn is_late(&self) -> bool {
+        self.inner.regs_basic().intfr().read().uif()
+    }
+
+    /// Wait for the next tick.
+    ///
+    /// If a tick already elapsed since the previous call, this returns immediately, so a
+    /// slow iteration is caught up without shifting the following ticks. Several elapsed
+    /// ticks are only reported once.
+    pub async fn next(&mut self) {
+        let regs = self.inner.regs_basic();
+
+        poll_fn(|cx| {
+            T::state().up_waker.register(cx.waker());
+
+            if regs.intfr().read().uif() {
+                regs.intfr().modify(|r| r.set_uif(false));
+                Poll::Ready(())
+            } else {
+                regs.dmaintenr().modify(|r| r.set_uie(true));
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+impl<'d, T: BasicInstance> Drop for Periodic<'d, T> {
+    fn drop(&mut self) {
+        self.inner.stop();
+        self.inner.enable_update_interrupt(false);
+    }
+}
@@ -0,0 +1,182 @@
+//! Hardware pulse counter.
+//!
+//! The timer counter is clocked by an external signal instead of the internal clock,
+//! so pulses from flow meters, anemometers and the like are counted without CPU load.
+
+use core::marker::PhantomData;
+
+use super::input_capture::CapturePin;
+use super::low_level::{ExternalTriggerPrescaler, InputTISelection, Timer, TriggerSource};
+use super::simple_pwm::{Ch1, Ch2};
+use super::{Channel, ExternalTriggerPin, GeneralInstance16bit};
+use crate::gpio::{AnyPin, Pull};
+use crate::pac::timer::vals::FilterValue;
+use crate::{into_ref, Peripheral, PeripheralRef};
+
+/// External trigger (ETR) pin wrapper.
+pub struct EtrPin<'d, T> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<T>,
+}
+
+impl<'d, T: GeneralInstance16bit> EtrPin<'d, T> {
+    /// Create a new external trigger pin instance.
+    pub fn new<const REMAP: u8>(pin: impl Peripheral<P = impl ExternalTriggerPin<T, REMAP>> + 'd, pull: Pull) -> Self {
+        into_ref!(pin);
+
+        T::set_remap(REMAP);
+        critical_section::with(|_| {
+            pin.set_as_input(pull);
+        });
+        EtrPin {
+            _pin: pin.map_into(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Counted edge of the input signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// Count rising edges.
+    Rising,
+    /// Count falling edges.
+    Falling,
+    /// Count both edges. Only available on the channel 1 input.
+    Both,
+}
+
+/// Pulse counter configuration.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Counted edge.
+    pub edge: Edge,
+    /// Digital filter applied to the input, to reject glitches and contact bounce.
+    pub filter: FilterValue,
+    /// Input prescaler. Only available on the ETR input.
+    pub prescaler: ExternalTriggerPrescaler,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            edge: Edge::Rising,
+            filter: FilterValue::from_bits(0),
+            prescaler: ExternalTriggerPrescaler::Div1,
+        }
+    }
+}
+
+/// Pulse counter driver.
+///
+/// The hardware counter is 16 bits, [`total`](PulseCounter::total) extends it in software
+/// to 32 bits. It must be called at least once per 65536 pulses to not miss a wrap-around.
+pub struct PulseCounter<'d, T: GeneralInstance16bit> {
+    inner: Timer<'d, T>,
+    last_count: u16,
+    total: u32,
+}
+
+impl<'d, T: GeneralInstance16bit> PulseCounter<'d, T> {
+    /// Count pulses on the ETR pin, using external clock mode 2.
+    ///
+    /// [`Edge::Both`] is not supported on this input.
+    pub fn new_etr(tim: impl Peripheral<P = T> + 'd, _pin: EtrPin<'d, T>, config: Config) -> Self {
+        assert!(config.edge != Edge::Both);
+
+        let inner = Timer::new(tim);
+        inner.set_external_trigger(config.prescaler, config.filter, config.edge == Edge::Falling);
+        inner.enable_external_clock_mode2(true);
+
+        Self::start_counting(inner)
+    }
+
+    /// Count pulses on the channel 1 pin, using external clock mode 1.
+    ///
+    /// The prescaler of `config` is ignored on this input.
+    pub fn new_ch1(tim: impl Peripheral<P = T> + 'd, _pin: CapturePin<'d, T, Ch1>, config: Config) -> Self {
+        let inner = Timer::new(tim);
+        let source = match config.edge {
+            Edge::Both => TriggerSource::Ti1Edge,
+            _ => TriggerSource::Ti1,
+        };
+        Self::configure_channel(&inner, Channel::Ch1, &config);
+        inner.set_external_clock_mode1(source);
+
+        Self::start_counting(inner)
+    }
+
+    /// Count pulses on the channel 2 pin, using external clock mode 1.
+    ///
+    /// [`Edge::Both`] is not supported on this input, the prescaler of `config` is ignored.
+    pub fn new_ch2(tim: impl Peripheral<P = T> + 'd, _pin: CapturePin<'d, T, Ch2>, config: Config) -> Self {
+        assert!(config.edge != Edge::Both);
+
+        let inner = Timer::new(tim);
+        Self::configure_channel(&inner, Channel::Ch2, &config);
+        inner.set_external_clock_mode1(TriggerSource::Ti2);
+
+        Self::start_counting(inner)
+    }
+
+    fn configure_channel(inner: &Timer<'d, T>, channel: Channel, config: &Config) {
+        inner.set_input_ti_selection(channel, InputTISelection::Normal);
+        inner.set_input_capture_filter(channel, config.filter);
+        inner
+            .regs_gp16()
+            .ccer()
+            .modify(|w| w.set_ccp(channel.index(), config.edge == Edge::Falling));
+    }
+
+    fn start_counting(inner: Timer<'d, T>) -> Self {
+        let regs = inner.regs_basic();
+        regs.psc().write_value(0);
+        regs.atrlr().write_value(u16::MAX);
+        inner.reset();
+        inner.start();
+
+        Self {
+            inner,
+            last_count: 0,
+            total: 0,
+        }
+    }
+
+    /// Resume counting.
+    pub fn start(&mut self) {
+        self.inner.start();
+    }
+
+    /// Pause counting. Pulses are ignored until [`start`](Self::start).
+    pub fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    /// Raw 16-bit hardware counter value.
+    pub fn count(&self) -> u16 {
+        self.inner.regs_basic().cnt().read()
+    }
+
+    /// Total number of pulses counted since creation or the last [`reset`](Self::reset).
+    pub fn total(&mut self) -> u32 {
+        let count = self.count();
+        self.total = self.total.wrapping_add(count.wrapping_sub(self.last_count) as u32);
+        self.last_count = count;
+        self.total
+    }
+
+    /// Return the number of pulses counted since the previous call, e.g. once per second
+    /// for a frequency or flow rate.
+    pub fn take(&mut self) -> u32 {
+        let before = self.total;
+        self.total().wrapping_sub(before)
+    }
+
+    /// Reset the counter to 0.
+    pub fn reset(&mut self) {
+        self.inner.reset();
+        self.last_count = 0;
+        self.total = 0;
+    }
+}
@@ -0,0 +1,151 @@
+//! PWM input driver.
+//!
+//! Measures period and duty cycle of a PWM signal on one pin, using two capture
+//! channels: the timer resets on each rising edge, CH1 and CH2 latch the period and
+//! the high time.
+
+use super::low_level::{InputTISelection, Timer};
+use super::{Channel, Channel1Pin, Channel2Pin, GeneralInstance16bit};
+use crate::gpio::{AnyPin, Pull};
+use crate::pac::timer::vals;
+use crate::time::Hertz;
+use crate::{into_ref, Peripheral, PeripheralRef};
+
+/// PWM input driver.
+pub struct PwmInput<'d, T: GeneralInstance16bit> {
+    channel: Channel,
+    inner: Timer<'d, T>,
+    _pin: PeripheralRef<'d, AnyPin>,
+}
+
+impl<'d, T: GeneralInstance16bit> PwmInput<'d, T> {
+    /// Create a new PWM input driver measuring the signal on the CH1 pin.
+    ///
+    /// `freq` is the counter tick frequency, the measured period must be shorter than
+    /// 65536 ticks.
+    pub fn new<const REMAP: u8>(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl Channel1Pin<T, REMAP>> + 'd,
+        pull: Pull,
+        freq: Hertz,
+    ) -> Self {
+        into_ref!(pin);
+        critical_section::with(|_| {
+            pin.set_as_input(pull);
+            T::set_remap(REMAP);
+        });
+
+        Self::new_inner(tim, pin.map_into(), freq, Channel::Ch1, Channel::Ch2)
+    }
+
+    /// Create a new PWM input driver measuring the signal on the CH2 pin.
+    pub fn new_alt<const REMAP: u8>(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl Channel2Pin<T, REMAP>> + 'd,
+        pull: Pull,
+        freq: Hertz,
+    ) -> Self {
+        into_ref!(pin);
+        critical_section::with(|_| {
+            pin.set_as_input(pull);
+            T::set_remap(REMAP);
+        });
+
+        Self::new_inner(tim, pin.map_into(), freq, Channel::Ch2, Channel::Ch1)
+    }
+
+    fn new_inner(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: PeripheralRef<'d, AnyPin>,
+        freq: Hertz,
+        ch1: Channel,
+        ch2: Channel,
+    ) -> Self {
+        let inner = Timer::new(tim);
+
+        inner.set_tick_frequency(freq);
+
+        // The period channel captures rising edges of its own input,
+        // the width channel captures falling edges of the same input.
+        inner.set_input_ti_selection(ch1, InputTISelection::Normal);
+        inner.set_input_ti_selection(ch2, InputTISelection::Alternate);
+        let regs = inner.regs_gp16();
+        regs.ccer().modify(|w| {
+            w.set_ccp(ch1.index(), false);
+            w.set_ccp(ch2.index(), true);
+        });
+
+        // Reset the counter on each rising edge, TI1FP1 or TI2FP2.
+        let ts = match ch1 {
+            Channel::Ch1 => 0b101,
+            _ => 0b110,
+        };
+        regs.smcfgr().modify(|w| {
+            w.set_ts(vals::Ts::from_bits(ts));
+            w.set_sms(vals::Sms::from_bits(0b100));
+        });
+
+        inner.enable_channel(ch1, true);
+        inner.enable_channel(ch2, true);
+        inner.start();
+
+        Self {
+            channel: ch1,
+            inner,
+            _pin: pin,
+        }
+    }
+
+    fn width_channel(&self) -> Channel {
+        match self.channel {
+            Channel::Ch1 => Channel::Ch2,
+            _ => Channel::Ch1,
+        }
+    }
+
+    /// Enable measurements.
+    pub fn enable(&mut self) {
+        self.inner.enable_channel(self.channel, true);
+        self.inner.enable_channel(self.width_channel(), true);
+    }
+
+    /// Disable measurements.
+    pub fn disable(&mut self) {
+        self.inner.enable_channel(self.channel, false);
+        self.inner.enable_channel(self.width_channel(), false);
+    }
+
+    /// Check whether measurements are enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.get_channel_enable_state(self.channel)
+    }
+
+    /// Period of the last complete cycle, in ticks.
+    pub fn get_period_ticks(&self) -> u32 {
+        self.inner.get_capture_value(self.channel)
+    }
+
+    /// High time of the last complete cycle, in ticks.
+    pub fn get_width_ticks(&self) -> u32 {
+        self.inner.get_capture_value(self.width_channel())
+    }
+
+    /// Frequency of the signal, or `None` while no full cycle has been measured.
+    pub fn get_frequency(&self) -> Option<Hertz> {
+        let period = self.get_period_ticks();
+        if period == 0 {
+            return None;
+        }
+        let tick_f = T::frequency().0 / (self.inner.regs_basic().psc().read() as u32 + 1);
+        Some(Hertz(tick_f / period))
+    }
+
+    /// Duty cycle of the signal in percent, 0.0 to 100.0.
+    pub fn get_duty_cycle(&self) -> f32 {
+        let period = self.get_period_ticks();
+        if period == 0 {
+            return 0.0;
+        }
+        100.0 * self.get_width_ticks() as f32 / period as f32
+    }
+}
@@ -0,0 +1,147 @@
+//! Quadrature encoder interface.
+
+use core::marker::PhantomData;
+
+use super::low_level::{InputTISelection, Timer};
+use super::simple_pwm::{Ch1, Ch2};
+use super::{Channel, Channel1Pin, Channel2Pin, GeneralInstance16bit};
+use crate::gpio::{AnyPin, Pull};
+use crate::pac::timer::vals;
+use crate::{into_ref, Peripheral, PeripheralRef};
+
+/// Counting direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    /// Counting up.
+    Upcounting,
+    /// Counting down.
+    Downcounting,
+}
+
+/// Which encoder edges are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EncoderMode {
+    /// Count edges of TI1, 2 counts per encoder cycle.
+    Ti1 = 0b010,
+    /// Count edges of TI2, 2 counts per encoder cycle.
+    Ti2 = 0b001,
+    /// Count edges of both TI1 and TI2, 4 counts per encoder cycle.
+    Both = 0b011,
+}
+
+/// Quadrature encoder pin wrapper.
+pub struct QeiPin<'d, T, C> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<(T, C)>,
+}
+
+macro_rules! channel_impl {
+    ($new_chx:ident, $channel:ident, $pin_trait:ident) => {
+        impl<'d, T: GeneralInstance16bit> QeiPin<'d, T, $channel> {
+            #[doc = concat!("Create a new ", stringify!($channel), " QEI pin instance.")]
+            pub fn $new_chx<const REMAP: u8>(
+                pin: impl Peripheral<P = impl $pin_trait<T, REMAP>> + 'd,
+                pull: Pull,
+            ) -> Self {
+                into_ref!(pin);
+                critical_section::with(|_| {
+                    pin.set_as_input(pull);
+                    T::set_remap(REMAP);
+                });
+                QeiPin {
+                    _pin: pin.map_into(),
+                    phantom: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+channel_impl!(new_ch1, Ch1, Channel1Pin);
+channel_impl!(new_ch2, Ch2, Channel2Pin);
+
+/// Quadrature decoder driver.
+///
+/// The hardware counter is 16 bits, [`position`](Qei::position) extends it in software
+/// to 32 bits. It must be called at least once per half counter range of movement,
+/// 32768 counts, to not miss a wrap-around.
+pub struct Qei<'d, T: GeneralInstance16bit> {
+    inner: Timer<'d, T>,
+    last_count: u16,
+    position: i32,
+}
+
+impl<'d, T: GeneralInstance16bit> Qei<'d, T> {
+    /// Create a new quadrature decoder driver.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        _ch1: QeiPin<'d, T, Ch1>,
+        _ch2: QeiPin<'d, T, Ch2>,
+        mode: EncoderMode,
+    ) -> Self {
+        let inner = Timer::new(tim);
+        let regs = inner.regs_gp16();
+
+        inner.set_input_ti_selection(Channel::Ch1, InputTISelection::Normal);
+        inner.set_input_ti_selection(Channel::Ch2, InputTISelection::Normal);
+        regs.ccer().modify(|w| {
+            w.set_ccp(0, false);
+            w.set_ccp(1, false);
+        });
+
+        regs.smcfgr().modify(|w| w.set_sms(vals::Sms::from_bits(mode as u8)));
+
+        regs.atrlr().write_value(u16::MAX);
+        regs.cnt().write_value(0);
+
+        inner.start();
+
+        Self {
+            inner,
+            last_count: 0,
+            position: 0,
+        }
+    }
+
+    /// Set the digital input filter of both encoder inputs.
+    pub fn set_filter(&mut self, filter: vals::FilterValue) {
+        self.inner.set_input_capture_filter(Channel::Ch1, filter);
+        self.inner.set_input_capture_filter(Channel::Ch2, filter);
+    }
+
+    /// Invert the direction of counting.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inner.regs_gp16().ccer().modify(|w| w.set_ccp(0, inverted));
+    }
+
+    /// Get the direction the encoder last moved in.
+    #[cfg(not(timer_x0))]
+    pub fn read_direction(&self) -> Direction {
+        match self.inner.regs_gp16().ctlr1().read().dir() {
+            vals::Dir::DOWN => Direction::Downcounting,
+            vals::Dir::UP => Direction::Upcounting,
+        }
+    }
+
+    /// Get the raw 16 bit counter value.
+    pub fn count(&self) -> u16 {
+        self.inner.regs_gp16().cnt().read()
+    }
+
+    /// Get the position, extended to 32 bits.
+    pub fn position(&mut self) -> i32 {
+        let count = self.count();
+        let delta = count.wrapping_sub(self.last_count) as i16;
+        self.last_count = count;
+        self.position = self.position.wrapping_add(delta as i32);
+        self.position
+    }
+
+    /// Set the current position.
+    pub fn set_position(&mut self, position: i32) {
+        self.last_count = self.count();
+        self.position = position;
+    }
+}
@@ -0,0 +1,102 @@
+//! Hobby servo driver.
+//!
+//! Servos expect a pulse every 20 ms, whose width sets the position. Pulse widths for the end
+//! positions vary between servo models, so they are part of the [`Config`].
+//!
+//! ```rust,ignore
+//! let pwm = SimplePwm::new(p.TIM2, Some(PwmPin::new_ch1::<0>(p.PA0)), None, None, None, servo::FREQUENCY, Default::default());
+//! let mut servo = Servo::new(pwm.split().ch1, Default::default());
+//! servo.set_angle(90.0);
+//! ```
+
+use core::time::Duration;
+
+use super::simple_pwm::SimplePwmChannel;
+use super::GeneralInstance16bit;
+use crate::time::Hertz;
+
+/// The servo pulse rate, 50 Hz, the [`SimplePwm`](super::simple_pwm::SimplePwm) must be created with.
+pub const FREQUENCY: Hertz = Hertz(50);
+
+/// Servo calibration.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Pulse width at angle 0.
+    pub min_pulse: Duration,
+    /// Pulse width at [`max_angle`](Self::max_angle).
+    pub max_pulse: Duration,
+    /// Angle range of the servo, in degrees.
+    pub max_angle: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_pulse: Duration::from_micros(1000),
+            max_pulse: Duration::from_micros(2000),
+            max_angle: 180.0,
+        }
+    }
+}
+
+/// Hobby servo driver, over one PWM channel.
+pub struct Servo<'d, T: GeneralInstance16bit> {
+    channel: SimplePwmChannel<'d, T>,
+    config: Config,
+}
+
+impl<'d, T: GeneralInstance16bit> Servo<'d, T> {
+    /// Create a new servo driver, and enable its output.
+    ///
+    /// Panics unless the PWM runs at [`FREQUENCY`], within 1 Hz of timer rounding.
+    /// The output stays idle until a position is set.
+    pub fn new(mut channel: SimplePwmChannel<'d, T>, config: Config) -> Self {
+        assert!(config.min_pulse < config.max_pulse);
+        assert!(config.max_angle > 0.0);
+        let freq = channel.get_frequency().0;
+        assert!(
+            freq.abs_diff(FREQUENCY.0) <= 1,
+            "servo PWM runs at {} Hz instead of {} Hz",
+            freq,
+            FREQUENCY.0
+        );
+
+        channel.set_duty(0);
+        channel.enable();
+
+        Self { channel, config }
+    }
+
+    /// Set the pulse width, clamped to the calibration limits.
+    pub fn set_pulse_width(&mut self, width: Duration) {
+        let width = width.clamp(self.config.min_pulse, self.config.max_pulse);
+
+        let period_ns = 1_000_000_000 / self.channel.get_frequency().0 as u64;
+        let max_duty = self.channel.get_max_duty() as u64;
+        let duty = width.as_nanos() as u64 * max_duty / period_ns;
+        self.channel.set_duty(duty.min(max_duty) as u32);
+    }
+
+    /// Set the position in degrees, clamped to `0.0..=max_angle`.
+    pub fn set_angle(&mut self, degrees: f32) {
+        let degrees = degrees.clamp(0.0, self.config.max_angle);
+
+        let min = self.config.min_pulse.as_nanos() as f32;
+        let range = self.config.max_pulse.as_nanos() as f32 - min;
+        let width = min + range * degrees / self.config.max_angle;
+        self.set_pulse_width(Duration::from_nanos(width as u64));
+    }
+
+    /// Stop sending pulses, most servos then stop holding their position.
+    pub fn release(&mut self) {
+        self.channel.set_duty(0);
+    }
+
+    /// Change the calibration.
+    pub fn set_config(&mut self, config: Config) {
+        assert!(config.min_pulse < config.max_pulse);
+        assert!(config.max_angle > 0.0);
+
+        self.config = config;
+    }
+}
@@ -1,10 +1,13 @@
 //! Simple PWM driver.
 
 use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 
-use super::low_level::{CountingMode, OutputCompareMode, OutputPolarity, Timer};
-use super::{Channel, Channel1Pin, Channel2Pin, Channel3Pin, Channel4Pin, GeneralInstance16bit};
+use super::low_level::{CountingMode, IdleState, OutputCompareMode, OutputPolarity, Timer};
+use super::{AdvancedInstance, Channel, Channel1Pin, Channel2Pin, Channel3Pin, Channel4Pin, GeneralInstance16bit};
 use crate::gpio::{AFType, AnyPin};
+#[cfg(not(timer_x0))]
+use crate::pac::timer::vals;
 use crate::time::Hertz;
 use crate::{into_ref, Peripheral, PeripheralRef};
 
@@ -49,6 +52,90 @@ channel_impl!(new_ch2, Ch2, Channel2Pin);
 channel_impl!(new_ch3, Ch3, Channel3Pin);
 channel_impl!(new_ch4, Ch4, Channel4Pin);
 
+/// A single channel of a [`SimplePwm`], obtained from [`SimplePwm::split`].
+///
+/// Implements [`embedded_hal::pwm::SetDutyCycle`], so each channel can be handed to a different driver.
+pub struct SimplePwmChannel<'d, T: GeneralInstance16bit> {
+    timer: ManuallyDrop<Timer<'d, T>>,
+    channel: Channel,
+}
+
+impl<'d, T: GeneralInstance16bit> SimplePwmChannel<'d, T> {
+    /// Enable the channel.
+    pub fn enable(&mut self) {
+        self.timer.enable_channel(self.channel, true);
+    }
+
+    /// Disable the channel.
+    pub fn disable(&mut self) {
+        self.timer.enable_channel(self.channel, false);
+    }
+
+    /// Check whether the channel is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.timer.get_channel_enable_state(self.channel)
+    }
+
+    /// Get max duty value.
+    pub fn get_max_duty(&self) -> u32 {
+        self.timer.get_max_compare_value() + 1
+    }
+
+    /// Set the duty, from 0 for 0% duty to [`get_max_duty`](Self::get_max_duty) for 100% duty, both included.
+    pub fn set_duty(&mut self, duty: u32) {
+        assert!(duty <= self.get_max_duty());
+        self.timer.set_compare_value(self.channel, duty)
+    }
+
+    /// Get the duty.
+    pub fn get_duty(&self) -> u32 {
+        self.timer.get_compare_value(self.channel)
+    }
+
+    /// Set the output polarity.
+    pub fn set_polarity(&mut self, polarity: OutputPolarity) {
+        self.timer.set_output_polarity(self.channel, polarity);
+    }
+
+    /// Get the PWM frequency.
+    pub fn get_frequency(&self) -> Hertz {
+        let freq = self.timer.get_frequency();
+        if self.timer.get_counting_mode().is_center_aligned() {
+            freq / 2u32
+        } else {
+            freq
+        }
+    }
+}
+
+impl<'d, T: GeneralInstance16bit> embedded_hal::pwm::ErrorType for SimplePwmChannel<'d, T> {
+    type Error = core::convert::Infallible;
+}
+
+impl<'d, T: GeneralInstance16bit> embedded_hal::pwm::SetDutyCycle for SimplePwmChannel<'d, T> {
+    fn max_duty_cycle(&self) -> u16 {
+        // The duty cycle is a u16, 100% of a full range 16-bit timer is clamped to 0xFFFF.
+        self.get_max_duty().min(u16::MAX as u32) as u16
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.set_duty(duty as u32);
+        Ok(())
+    }
+}
+
+/// The channels of a [`SimplePwm`], obtained from [`SimplePwm::split`].
+pub struct SimplePwmChannels<'d, T: GeneralInstance16bit> {
+    /// Channel 1.
+    pub ch1: SimplePwmChannel<'d, T>,
+    /// Channel 2.
+    pub ch2: SimplePwmChannel<'d, T>,
+    /// Channel 3.
+    pub ch3: SimplePwmChannel<'d, T>,
+    /// Channel 4.
+    pub ch4: SimplePwmChannel<'d, T>,
+}
+
 /// Simple PWM driver.
 pub struct SimplePwm<'d, T: GeneralInstance16bit> {
     inner: Timer<'d, T>,
@@ -93,6 +180,25 @@ impl<'d, T: GeneralInstance16bit> SimplePwm<'d, T> {
         this
     }
 
+    /// Split the driver into individually owned channels.
+    ///
+    /// The period can no longer be changed afterwards, and the timer stays enabled
+    /// when the channels are dropped.
+    pub fn split(self) -> SimplePwmChannels<'d, T> {
+        let inner = ManuallyDrop::new(self.inner);
+        let channel = |channel| SimplePwmChannel {
+            timer: unsafe { inner.clone_unchecked() },
+            channel,
+        };
+
+        SimplePwmChannels {
+            ch1: channel(Channel::Ch1),
+            ch2: channel(Channel::Ch2),
+            ch3: channel(Channel::Ch3),
+            ch4: channel(Channel::Ch4),
+        }
+    }
+
     /// Enable the given channel.
     pub fn enable(&mut self, channel: Channel) {
         self.inner.enable_channel(channel, true);
@@ -110,15 +216,26 @@ impl<'d, T: GeneralInstance16bit> SimplePwm<'d, T> {
 
     /// Set PWM frequency.
     ///
-    /// Note: when you call this, the max duty value changes, so you will have to
-    /// call `set_duty` on all channels with the duty calculated based on the new max duty.
+    /// The max duty value changes with the frequency, the duty values of all channels are
+    /// rescaled so their duty ratio is preserved.
     pub fn set_frequency(&mut self, freq: Hertz) {
         let multiplier = if self.inner.get_counting_mode().is_center_aligned() {
             2u8
         } else {
             1u8
         };
+
+        let channels = [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4];
+        let old_max_duty = self.get_max_duty() as u64;
+        let duties = channels.map(|channel| self.get_duty(channel) as u64);
+
         self.inner.set_frequency(freq * multiplier);
+
+        let max_duty = self.get_max_duty() as u64;
+        for (channel, duty) in channels.into_iter().zip(duties) {
+            self.inner
+                .set_compare_value(channel, (duty * max_duty / old_max_duty) as u32);
+        }
     }
 
     /// Get max duty value.
@@ -136,6 +253,24 @@ impl<'d, T: GeneralInstance16bit> SimplePwm<'d, T> {
         self.inner.set_compare_value(channel, duty)
     }
 
+    /// Set the duty for a given channel as the fraction `num / denom`, independently of the max duty.
+    pub fn set_duty_fraction(&mut self, channel: Channel, num: u32, denom: u32) {
+        assert!(denom > 0 && num <= denom);
+        let duty = self.get_max_duty() as u64 * num as u64 / denom as u64;
+        self.inner.set_compare_value(channel, duty as u32);
+    }
+
+    /// Set the duty for a given channel in percent, from 0 to 100 both included.
+    pub fn set_duty_percent(&mut self, channel: Channel, percent: u8) {
+        self.set_duty_fraction(channel, percent as u32, 100);
+    }
+
+    /// Set the duty for a given channel as a 16-bit normalized value,
+    /// 0 for 0% duty and `u16::MAX` for 100% duty.
+    pub fn set_duty_normalized(&mut self, channel: Channel, duty: u16) {
+        self.set_duty_fraction(channel, duty as u32, u16::MAX as u32);
+    }
+
     /// Get the duty for a given channel.
     ///
     /// The value ranges from 0 for 0% duty, to [`get_max_duty`](Self::get_max_duty) for 100% duty, both included.
@@ -144,29 +279,215 @@ impl<'d, T: GeneralInstance16bit> SimplePwm<'d, T> {
     }
 
     /// Set the output polarity for a given channel.
+    ///
+    /// With [`OutputPolarity::ActiveLow`] the output is low during the duty part of the period,
+    /// so duty values keep meaning "active time" for active-low loads such as LEDs sinking current.
     pub fn set_polarity(&mut self, channel: Channel, polarity: OutputPolarity) {
         self.inner.set_output_polarity(channel, polarity);
     }
 
+    /// Get the output polarity for a given channel.
+    pub fn get_polarity(&self, channel: Channel) -> OutputPolarity {
+        self.inner.get_output_polarity(channel)
+    }
+
     /// Set the output compare mode for a given channel.
     pub fn set_output_compare_mode(&mut self, channel: Channel, mode: OutputCompareMode) {
         self.inner.set_output_compare_mode(channel, mode);
     }
 
-    /// Generate a sequence of PWM waveform
+    /// Set the trigger output (TRGO) source, e.g. to start ADC conversions once per PWM period.
+    #[cfg(not(timer_x0))]
+    pub fn set_trigger_output(&mut self, mode: super::low_level::MasterMode) {
+        self.inner.set_master_mode(mode);
+    }
+
+    /// Change the counting mode, keeping the PWM frequency.
+    ///
+    /// In center-aligned modes the counter counts up then down, so each output is
+    /// symmetric around the period center, which is the usual choice for motor control.
+    /// The timer is briefly stopped, duty ratios are preserved.
+    #[cfg(not(timer_x0))]
+    pub fn set_counting_mode(&mut self, mode: CountingMode) {
+        let freq = self.get_frequency();
+
+        self.inner.stop();
+        self.inner.set_counting_mode(mode);
+        self.set_frequency(freq);
+        self.inner.start();
+    }
+
+    /// Get the counting mode.
+    pub fn get_counting_mode(&self) -> CountingMode {
+        self.inner.get_counting_mode()
+    }
+
+    /// Get the PWM frequency.
+    pub fn get_frequency(&self) -> Hertz {
+        let freq = self.inner.get_frequency();
+        if self.inner.get_counting_mode().is_center_aligned() {
+            freq / 2u32
+        } else {
+            freq
+        }
+    }
+
+    /// Enable or disable preloading of the duty value of a channel.
+    ///
+    /// Preload is enabled by default, so a new duty takes effect on the next update event.
+    /// In center-aligned modes an update event happens both at the top and at the bottom of
+    /// the period, so writing the duty from the update interrupt with preload enabled gives
+    /// asymmetric PWM, with different compare values for the up and down counting halves.
+    pub fn set_duty_preload(&mut self, channel: Channel, preload: bool) {
+        self.inner.set_output_compare_preload(channel, preload);
+    }
+
+    /// Use channel 4 as a mid-cycle trigger output (TRGO), e.g. for ADC conversions.
+    ///
+    /// In center-aligned mode TRGO rises at the top of the counter, the center of every
+    /// channel's active time, where switching noise is lowest. Channel 4 can then not
+    /// be used as a PWM output.
+    #[cfg(not(timer_x0))]
+    pub fn set_center_trigger(&mut self) {
+        self.inner
+            .set_output_compare_mode(Channel::Ch4, OutputCompareMode::PwmMode2);
+        self.inner
+            .set_compare_value(Channel::Ch4, self.inner.get_max_compare_value().saturating_sub(1));
+        self.inner.set_master_mode(super::low_level::MasterMode::CompareOc4);
+    }
+
+    /// Run this PWM as a phase-shifted follower of `master`, `phase_deg` degrees (0..360) behind it.
+    ///
+    /// The period of `master` is copied, and this timer is stopped and armed to start on the
+    /// next start of `master`. Configure all followers first, then call
+    /// [`restart_synchronized`](Self::restart_synchronized) on the master once, e.g. with two
+    /// followers at 120 and 240 degrees for a three-phase interleaved converter.
+    /// Only edge-aligned counting is supported.
+    #[cfg(not(timer_x0))]
+    pub fn set_phase_follower<M: GeneralInstance16bit>(&mut self, master: &SimplePwm<'_, M>, phase_deg: u16)
+    where
+        T: super::sync::TriggerFrom<M>,
+    {
+        assert!(phase_deg < 360);
+        assert!(self.inner.get_counting_mode().is_edge_aligned());
+        assert!(master.inner.get_counting_mode().is_edge_aligned());
+
+        self.inner.stop();
+
+        let master_regs = master.inner.regs_basic();
+        let regs = self.inner.regs_basic();
+        let arr = master_regs.atrlr().read();
+        regs.psc().write_value(master_regs.psc().read());
+        regs.atrlr().write_value(arr);
+        regs.ctlr1().modify(|r| r.set_urs(vals::Urs::COUNTERONLY));
+        regs.swevgr().write(|r| r.set_ug(true));
+        regs.ctlr1().modify(|r| r.set_urs(vals::Urs::ANYEVENT));
+
+        // Starting `delay` ticks into the period puts this output `phase` behind the master.
+        let period = arr as u32 + 1;
+        let delay = period * phase_deg as u32 / 360;
+        regs.cnt().write_value(((period - delay) % period) as u16);
+
+        super::sync::connect(
+            &master.inner,
+            &self.inner,
+            super::low_level::MasterMode::Enable,
+            super::low_level::SlaveMode::Trigger,
+        );
+    }
+
+    /// Restart the counter from 0, starting all phase followers at the same time.
+    ///
+    /// See [`set_phase_follower`](Self::set_phase_follower).
+    #[cfg(not(timer_x0))]
+    pub fn restart_synchronized(&mut self) {
+        self.inner.stop();
+        self.inner.reset();
+        self.inner.start();
+    }
+
+    /// Stream duty values of several channels from a buffer, using a DMA burst on each update event.
+    ///
+    /// `duty` holds one frame per PWM period, each frame being the duty of `starting_channel`
+    /// up to `ending_channel`, in order. Its length must be a multiple of the frame length.
     ///
     /// Note:
     /// you will need to provide corresponding TIMx_UP DMA channel to use this method.
-    #[cfg(todo)]
+    #[cfg(not(timer_x0))]
+    pub async fn waveform_burst(
+        &mut self,
+        dma: impl Peripheral<P = impl super::UpDma<T>>,
+        starting_channel: Channel,
+        ending_channel: Channel,
+        duty: &[u16],
+    ) -> Result<(), crate::dma::Error> {
+        // CH1CVR is the 14th register of the timer, counting in 32 bit words.
+        const CH1CVR_INDEX: u8 = 13;
+
+        let start = starting_channel.index();
+        let end = ending_channel.index();
+        assert!(start <= end);
+        let frame_len = end - start + 1;
+        assert!(!duty.is_empty() && duty.len() % frame_len == 0);
+
+        into_ref!(dma);
+        let req = dma.request();
+
+        let regs = self.inner.regs_gp16();
+
+        let original_update_dma_state = self.inner.get_update_dma_state();
+        let original_ccer = regs.ccer().read();
+
+        regs.dmacfgr().modify(|w| {
+            w.set_dba(CH1CVR_INDEX + start as u8);
+            w.set_dbl((frame_len - 1) as u8);
+        });
+
+        for i in start..=end {
+            regs.ccer().modify(|w| w.set_cce(i, true));
+        }
+
+        if !original_update_dma_state {
+            self.inner.enable_update_dma(true);
+        }
+
+        let result = unsafe {
+            crate::dma::Transfer::new_write(
+                &mut dma,
+                req,
+                duty,
+                regs.dmaadr().as_ptr() as *mut u16,
+                Default::default(),
+            )
+            .await
+        };
+
+        if !original_update_dma_state {
+            self.inner.enable_update_dma(false);
+        }
+
+        regs.ccer().write_value(original_ccer);
+
+        result
+    }
+
+    /// Stream duty values of one channel from a buffer, one value per PWM period, using DMA on each update event.
+    ///
+    /// This is the usual way to drive WS2812 style addressable LEDs, see [`ws2812`](super::ws2812),
+    /// or to play arbitrary soft-PWM patterns. The duty and enable state of the channel are
+    /// restored afterwards.
+    ///
+    /// Note:
+    /// you will need to provide corresponding TIMx_UP DMA channel to use this method.
+    #[cfg(not(timer_x0))]
     pub async fn waveform_up(
         &mut self,
         dma: impl Peripheral<P = impl super::UpDma<T>>,
         channel: Channel,
         duty: &[u16],
-    ) {
+    ) -> Result<(), crate::dma::Error> {
         into_ref!(dma);
 
-        #[allow(clippy::let_unit_value)] // eg. stm32f334
         let req = dma.request();
 
         let original_duty_state = self.get_duty(channel);
@@ -181,29 +502,21 @@ impl<'d, T: GeneralInstance16bit> SimplePwm<'d, T> {
             self.enable(channel);
         }
 
-        unsafe {
-            #[cfg(not(any(bdma, gpdma)))]
-            use crate::dma::{Burst, FifoThreshold};
-            use crate::dma::{Transfer, TransferOptions};
-
-            let dma_transfer_option = TransferOptions {
-                #[cfg(not(any(bdma, gpdma)))]
-                fifo_threshold: Some(FifoThreshold::Full),
-                #[cfg(not(any(bdma, gpdma)))]
-                mburst: Burst::Incr8,
-                ..Default::default()
-            };
-
-            Transfer::new_write(
+        let result = unsafe {
+            crate::dma::Transfer::new_write(
                 &mut dma,
                 req,
                 duty,
-                self.inner.regs_1ch().ccr(channel.index()).as_ptr() as *mut _,
-                dma_transfer_option,
+                self.inner.regs_gp16().chcvr(channel.index()).as_ptr() as *mut u16,
+                Default::default(),
             )
             .await
         };
 
+        if !original_update_dma_state {
+            self.inner.enable_update_dma(false);
+        }
+
         // restore output compare state
         if !original_enable_state {
             self.disable(channel);
@@ -211,14 +524,34 @@ impl<'d, T: GeneralInstance16bit> SimplePwm<'d, T> {
 
         self.set_duty(channel, original_duty_state);
 
-        // Since DMA is closed before timer update event trigger DMA is turn off,
-        // this can almost always trigger a DMA FIFO error.
-        //
-        // optional TODO:
-        // clean FEIF after disable UDE
-        if !original_update_dma_state {
-            self.inner.enable_update_dma(false);
-        }
+        result
+    }
+}
+
+impl<'d, T: AdvancedInstance> SimplePwm<'d, T> {
+    /// Set the level a channel output is driven to while the outputs are disabled.
+    ///
+    /// Advanced timers drive their outputs to the idle level while MOE is cleared, by
+    /// [`set_outputs_enabled`](Self::set_outputs_enabled) or a break, which keeps gate
+    /// drivers in a safe state. The idle level is the pin level, independent of the polarity.
+    pub fn set_idle_state(&mut self, channel: Channel, state: IdleState) {
+        self.inner.set_output_idle_state(channel, state);
+        // Drive the idle level instead of releasing the pins while MOE is cleared.
+        self.inner.set_off_state_selection(true, true);
+    }
+
+    /// Enable or disable all outputs at once (MOE), disabled outputs go to their idle state.
+    pub fn set_outputs_enabled(&mut self, enable: bool) {
+        self.inner.set_moe(enable);
+    }
+
+    /// Generate the update event, and with it the update interrupt, DMA request and
+    /// [`MasterMode::Update`](super::low_level::MasterMode::Update) trigger output, only every `periods` PWM periods.
+    ///
+    /// `periods` ranges from 1 to 256, or 1 to 128 in center-aligned modes. Duty changes
+    /// are then also applied only every `periods` periods.
+    pub fn set_update_rate(&mut self, periods: u16) {
+        self.inner.set_update_rate(periods);
     }
 }
 
@@ -0,0 +1,108 @@
+//! Timer synchronization: routing the trigger output (TRGO) of a master timer to the
+//! trigger input (TRGI) of slave timers.
+//!
+//! The internal trigger each slave sees a master on is fixed by the silicon, [`TriggerFrom`]
+//! encodes that table so a wrong routing fails to compile.
+//!
+//! ```rust,ignore
+//! use ch32_hal::timer::low_level::{MasterMode, SlaveMode, Timer};
+//! use ch32_hal::timer::sync;
+//!
+//! let master = Timer::new(p.TIM1);
+//! let slave = Timer::new(p.TIM2);
+//! sync::connect(&master, &slave, MasterMode::Enable, SlaveMode::Trigger);
+//! // Starting TIM1 starts TIM2 on the same clock cycle.
+//! master.start();
+//! ```
+
+use super::low_level::{MasterMode, SlaveMode, Timer, TriggerSource};
+use super::GeneralInstance16bit;
+use crate::peripherals;
+
+/// A slave timer that can be triggered by master timer `M`, through internal trigger [`SOURCE`](Self::SOURCE).
+pub trait TriggerFrom<M: GeneralInstance16bit>: GeneralInstance16bit {
+    /// Internal trigger input connected to the TRGO of `M`.
+    const SOURCE: TriggerSource;
+}
+
+macro_rules! impl_trigger_from {
+    ($slave:ident, $master:ident, $source:ident) => {
+        impl TriggerFrom<peripherals::$master> for peripherals::$slave {
+            const SOURCE: TriggerSource = TriggerSource::$source;
+        }
+    };
+}
+
+// Internal trigger connections, ITR0 to ITR3 of each slave.
+#[cfg(all(peri_tim1, peri_tim5))]
+impl_trigger_from!(TIM1, TIM5, Itr0);
+#[cfg(all(peri_tim1, peri_tim2))]
+impl_trigger_from!(TIM1, TIM2, Itr1);
+#[cfg(all(peri_tim1, peri_tim3))]
+impl_trigger_from!(TIM1, TIM3, Itr2);
+#[cfg(all(peri_tim1, peri_tim4))]
+impl_trigger_from!(TIM1, TIM4, Itr3);
+#[cfg(all(peri_tim2, peri_tim1))]
+impl_trigger_from!(TIM2, TIM1, Itr0);
+#[cfg(all(peri_tim2, peri_tim8))]
+impl_trigger_from!(TIM2, TIM8, Itr1);
+#[cfg(all(peri_tim2, peri_tim3))]
+impl_trigger_from!(TIM2, TIM3, Itr2);
+#[cfg(all(peri_tim2, peri_tim4))]
+impl_trigger_from!(TIM2, TIM4, Itr3);
+#[cfg(all(peri_tim3, peri_tim1))]
+impl_trigger_from!(TIM3, TIM1, Itr0);
+#[cfg(all(peri_tim3, peri_tim2))]
+impl_trigger_from!(TIM3, TIM2, Itr1);
+#[cfg(all(peri_tim3, peri_tim5))]
+impl_trigger_from!(TIM3, TIM5, Itr2);
+#[cfg(all(peri_tim3, peri_tim4))]
+impl_trigger_from!(TIM3, TIM4, Itr3);
+#[cfg(all(peri_tim4, peri_tim1))]
+impl_trigger_from!(TIM4, TIM1, Itr0);
+#[cfg(all(peri_tim4, peri_tim2))]
+impl_trigger_from!(TIM4, TIM2, Itr1);
+#[cfg(all(peri_tim4, peri_tim3))]
+impl_trigger_from!(TIM4, TIM3, Itr2);
+#[cfg(all(peri_tim4, peri_tim8))]
+impl_trigger_from!(TIM4, TIM8, Itr3);
+#[cfg(all(peri_tim5, peri_tim2))]
+impl_trigger_from!(TIM5, TIM2, Itr0);
+#[cfg(all(peri_tim5, peri_tim3))]
+impl_trigger_from!(TIM5, TIM3, Itr1);
+#[cfg(all(peri_tim5, peri_tim4))]
+impl_trigger_from!(TIM5, TIM4, Itr2);
+#[cfg(all(peri_tim5, peri_tim8))]
+impl_trigger_from!(TIM5, TIM8, Itr3);
+#[cfg(all(peri_tim8, peri_tim1))]
+impl_trigger_from!(TIM8, TIM1, Itr0);
+#[cfg(all(peri_tim8, peri_tim2))]
+impl_trigger_from!(TIM8, TIM2, Itr1);
+#[cfg(all(peri_tim8, peri_tim4))]
+impl_trigger_from!(TIM8, TIM4, Itr2);
+#[cfg(all(peri_tim8, peri_tim5))]
+impl_trigger_from!(TIM8, TIM5, Itr3);
+
+/// Route the TRGO of `master` to the TRGI of `slave`.
+///
+/// `master_mode` selects the master event driving TRGO, `slave_mode` what the slave does on it.
+/// For lockstep operation use [`MasterMode::Enable`] with [`SlaveMode::Trigger`], then only start
+/// the master; for gating use [`MasterMode::CompareOc1`] (or another OCxREF) with [`SlaveMode::Gated`].
+pub fn connect<M: GeneralInstance16bit, S: TriggerFrom<M>>(
+    master: &Timer<'_, M>,
+    slave: &Timer<'_, S>,
+    master_mode: MasterMode,
+    slave_mode: SlaveMode,
+) {
+    master.set_master_mode(master_mode);
+    // Let the master wait for its own slaves, in case it is itself triggered.
+    master.set_master_slave_mode(true);
+
+    slave.set_trigger_source(S::SOURCE);
+    slave.set_slave_mode(slave_mode);
+}
+
+/// Disconnect `slave` from its master, it runs from its own internal clock again.
+pub fn disconnect<S: GeneralInstance16bit>(slave: &Timer<'_, S>) {
+    slave.set_slave_mode(SlaveMode::Disabled);
+}
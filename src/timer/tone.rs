@@ -0,0 +1,68 @@
+//! Buzzer tone generation.
+//!
+//! ```rust,ignore
+//! let pwm = SimplePwm::new(p.TIM3, None, Some(PwmPin::new_ch2::<0>(p.PA7)), None, None, hz(440), Default::default());
+//! let mut tone = Tone::new(pwm, Channel::Ch2);
+//! tone.play(hz(440), Duration::from_millis(200)).await;
+//! ```
+
+use super::simple_pwm::SimplePwm;
+use super::{Channel, GeneralInstance16bit};
+use crate::time::Hertz;
+
+/// Square wave tone generator for passive buzzers and speakers.
+pub struct Tone<'d, T: GeneralInstance16bit> {
+    pwm: SimplePwm<'d, T>,
+    channel: Channel,
+}
+
+impl<'d, T: GeneralInstance16bit> Tone<'d, T> {
+    /// Create a new tone generator on a channel of `pwm`. The output starts silent.
+    pub fn new(mut pwm: SimplePwm<'d, T>, channel: Channel) -> Self {
+        pwm.disable(channel);
+        Self { pwm, channel }
+    }
+
+    /// Start playing a tone until [`stop`](Self::stop). A frequency of 0 Hz is silence.
+    pub fn start(&mut self, freq: Hertz) {
+        if freq.0 == 0 {
+            self.stop();
+            return;
+        }
+
+        self.pwm.set_frequency(freq);
+        self.pwm.set_duty_fraction(self.channel, 1, 2);
+        self.pwm.enable(self.channel);
+    }
+
+    /// Stop playing.
+    pub fn stop(&mut self) {
+        self.pwm.disable(self.channel);
+    }
+
+    /// Whether a tone is playing.
+    pub fn is_playing(&self) -> bool {
+        self.pwm.is_enabled(self.channel)
+    }
+
+    /// Play a tone for `duration`, then stop. A frequency of 0 Hz is a rest.
+    #[cfg(feature = "embassy")]
+    pub async fn play(&mut self, freq: Hertz, duration: embassy_time::Duration) {
+        self.start(freq);
+        embassy_time::Timer::after(duration).await;
+        self.stop();
+    }
+
+    /// Play a sequence of `(frequency, duration)` notes.
+    #[cfg(feature = "embassy")]
+    pub async fn play_melody(&mut self, notes: &[(Hertz, embassy_time::Duration)]) {
+        for &(freq, duration) in notes {
+            self.play(freq, duration).await;
+        }
+    }
+
+    /// Release the PWM driver.
+    pub fn release(self) -> SimplePwm<'d, T> {
+        self.pwm
+    }
+}
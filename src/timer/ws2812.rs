@@ -0,0 +1,85 @@
+//! WS2812 addressable LED encoder, for [`SimplePwm::waveform_up`].
+//!
+//! Each LED bit is one 800 kHz PWM period, a short high pulse for a 0 and a long one for a 1.
+//! Colors are sent green first, most significant bit first.
+//!
+//! ```rust,ignore
+//! let mut pwm = SimplePwm::new(p.TIM1, Some(PwmPin::new_ch1::<0>(p.PA8)), None, None, None, ws2812::FREQUENCY, Default::default());
+//! let encoder = Ws2812Encoder::new(pwm.get_max_duty());
+//!
+//! let mut buf = [0u16; ws2812::buffer_len(8)];
+//! encoder.encode(&[[255, 0, 0]; 8], &mut buf);
+//! pwm.waveform_up(&mut p.DMA1_CH5, Channel::Ch1, &buf).await.unwrap();
+//! ```
+//!
+//! [`SimplePwm::waveform_up`]: super::simple_pwm::SimplePwm::waveform_up
+
+use crate::time::Hertz;
+
+/// The WS2812 bit rate, to create the PWM with.
+pub const FREQUENCY: Hertz = Hertz(800_000);
+
+/// Number of idle periods appended after the LED data, 50 us of low level latching the colors.
+pub const RESET_PERIODS: usize = 40;
+
+/// Buffer length needed to encode `leds` LEDs, including the trailing reset periods.
+pub const fn buffer_len(leds: usize) -> usize {
+    leds * 24 + RESET_PERIODS
+}
+
+/// Encodes LED colors into PWM duty values.
+#[derive(Debug, Clone, Copy)]
+pub struct Ws2812Encoder {
+    bit0: u16,
+    bit1: u16,
+}
+
+impl Ws2812Encoder {
+    /// Create an encoder for a PWM running at [`FREQUENCY`] with the given max duty.
+    pub fn new(max_duty: u32) -> Self {
+        // T0H is 0.4 us and T1H is 0.8 us of the 1.25 us period.
+        Self {
+            bit0: (max_duty * 8 / 25) as u16,
+            bit1: (max_duty * 16 / 25) as u16,
+        }
+    }
+
+    /// Encode `[red, green, blue]` colors into `buf`, followed by the reset periods.
+    ///
+    /// Returns the number of duty values written, `buf` must be at least [`buffer_len`] long.
+    pub fn encode(&self, colors: &[[u8; 3]], buf: &mut [u16]) -> usize {
+        let len = buffer_len(colors.len());
+        assert!(buf.len() >= len);
+
+        let mut slots = buf.iter_mut();
+        for &[r, g, b] in colors {
+            for byte in [g, r, b] {
+                for bit in (0..8).rev() {
+                    let duty = if byte & (1 << bit) != 0 { self.bit1 } else { self.bit0 };
+                    *slots.next().unwrap() = duty;
+                }
+            }
+        }
+        slots.take(RESET_PERIODS).for_each(|slot| *slot = 0);
+
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let encoder = Ws2812Encoder::new(100);
+        let mut buf = [0xFFFF; buffer_len(1)];
+
+        assert_eq!(encoder.encode(&[[0x80, 0x01, 0x00]], &mut buf), 24 + RESET_PERIODS);
+        // Green first, MSB first.
+        assert_eq!(buf[..8], [32, 32, 32, 32, 32, 32, 32, 64]);
+        assert_eq!(buf[8..16], [64, 32, 32, 32, 32, 32, 32, 32]);
+        assert_eq!(buf[16..24], [32; 8]);
+        assert!(buf[24..].iter().all(|&duty| duty == 0));
+    }
+}
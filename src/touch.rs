@@ -0,0 +1,199 @@
+//! Touch sensing on top of the TKEY unit.
+//!
+//! Raw TKEY counts drift with temperature, supply and humidity, so each key tracks
+//! its own untouched baseline. A key is considered touched when its count drops more
+//! than `threshold` below the baseline for `debounce` consecutive samples, and released
+//! once it comes back within `threshold - hysteresis`.
+
+use crate::adc::{AnyAdcChannel, Instance, TouchKey};
+use crate::mode::Mode;
+
+/// Touch filtering configuration.
+#[derive(Debug, Copy, Clone)]
+pub struct Config {
+    /// Drop below the baseline, in raw counts, that counts as a touch.
+    pub threshold: u16,
+    /// Margin below `threshold` required to release a touched key.
+    pub hysteresis: u16,
+    /// Consecutive samples needed to change a key state.
+    pub debounce: u8,
+    /// Baseline filter strength, the baseline moves by `1 / 2^baseline_shift` of the
+    /// difference on each untouched sample.
+    pub baseline_shift: u8,
+    /// Time between two scans in [`TouchSensor::wait_for_event`].
+    #[cfg(feature = "embassy")]
+    pub scan_interval: embassy_time::Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            threshold: 100,
+            hysteresis: 20,
+            debounce: 3,
+            baseline_shift: 4,
+            #[cfg(feature = "embassy")]
+            scan_interval: embassy_time::Duration::from_millis(10),
+        }
+    }
+}
+
+/// A key state change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// Key at the given index was touched.
+    Touched(usize),
+    /// Key at the given index was released.
+    Released(usize),
+}
+
+struct Key<T> {
+    channel: AnyAdcChannel<T>,
+    /// Baseline, in raw counts scaled by 2^baseline_shift.
+    baseline: u32,
+    value: u16,
+    touched: bool,
+    counter: u8,
+}
+
+/// A set of `N` touch keys sharing one TKEY unit.
+pub struct TouchSensor<'d, T: Instance, M: Mode, const N: usize> {
+    tkey: TouchKey<'d, T, M>,
+    keys: [Key<T>; N],
+    config: Config,
+}
+
+impl<'d, T: Instance, M: Mode, const N: usize> TouchSensor<'d, T, M, N> {
+    /// Create a touch sensor and calibrate the baseline of every key.
+    ///
+    /// The keys must not be touched while this runs.
+    pub fn new(tkey: TouchKey<'d, T, M>, channels: [AnyAdcChannel<T>; N], config: Config) -> Self {
+        assert!(config.hysteresis < config.threshold);
+        assert!(config.baseline_shift < 16);
+
+        let mut this = Self {
+            tkey,
+            keys: channels.map(|channel| Key {
+                channel,
+                baseline: 0,
+                value: 0,
+                touched: false,
+                counter: 0,
+            }),
+            config,
+        };
+        this.calibrate();
+        this
+    }
+
+    /// Re-measure the untouched baseline of every key and clear the key states.
+    pub fn calibrate(&mut self) {
+        const SAMPLES: u32 = 16;
+
+        for key in self.keys.iter_mut() {
+            let mut sum = 0u32;
+            for _ in 0..SAMPLES {
+                sum += self.tkey.measure(&mut key.channel) as u32;
+            }
+            key.value = (sum / SAMPLES) as u16;
+            key.baseline = (key.value as u32) << self.config.baseline_shift;
+            key.touched = false;
+            key.counter = 0;
+        }
+    }
+
+    /// Measure every key once and update its state.
+    ///
+    /// Returns the first state change found, if any. Keys are scanned in index order.
+    pub fn scan(&mut self) -> Option<Event> {
+        let mut event = None;
+
+        for (i, key) in self.keys.iter_mut().enumerate() {
+            let value = self.tkey.measure(&mut key.channel);
+            if let Some(e) = Self::update_key(&self.config, key, value, i) {
+                event.get_or_insert(e);
+            }
+        }
+
+        event
+    }
+
+    fn update_key(config: &Config, key: &mut Key<T>, value: u16, index: usize) -> Option<Event> {
+        key.value = value;
+
+        let baseline = (key.baseline >> config.baseline_shift) as u16;
+        let delta = baseline.saturating_sub(value);
+
+        let changing = if key.touched {
+            delta < config.threshold - config.hysteresis
+        } else {
+            delta > config.threshold
+        };
+
+        if !key.touched && !changing {
+            // Only track drift while untouched, a held finger must not become the baseline.
+            key.baseline = key.baseline - (key.baseline >> config.baseline_shift) + value as u32;
+        }
+
+        if !changing {
+            key.counter = 0;
+            return None;
+        }
+
+        key.counter += 1;
+        if key.counter < config.debounce {
+            return None;
+        }
+
+        key.counter = 0;
+        key.touched = !key.touched;
+        if key.touched {
+            Some(Event::Touched(index))
+        } else {
+            Some(Event::Released(index))
+        }
+    }
+
+    /// Whether the key at `index` is currently touched.
+    pub fn is_touched(&self, index: usize) -> bool {
+        self.keys[index].touched
+    }
+
+    /// Last raw count measured on the key at `index`.
+    pub fn value(&self, index: usize) -> u16 {
+        self.keys[index].value
+    }
+
+    /// Current untouched baseline of the key at `index`, in raw counts.
+    pub fn baseline(&self, index: usize) -> u16 {
+        (self.keys[index].baseline >> self.config.baseline_shift) as u16
+    }
+
+    /// Release the TKEY unit and the channels.
+    pub fn release(self) -> (TouchKey<'d, T, M>, [AnyAdcChannel<T>; N]) {
+        (self.tkey, self.keys.map(|key| key.channel))
+    }
+}
+
+#[cfg(feature = "embassy")]
+impl<'d, T: Instance, M: Mode, const N: usize> TouchSensor<'d, T, M, N> {
+    /// Scan the keys every `scan_interval` until a key changes state.
+    pub async fn wait_for_event(&mut self) -> Event {
+        loop {
+            if let Some(event) = self.scan() {
+                return event;
+            }
+            embassy_time::Timer::after(self.config.scan_interval).await;
+        }
+    }
+
+    /// Wait until a key is touched, returning its index.
+    pub async fn wait_for_touch(&mut self) -> usize {
+        loop {
+            if let Event::Touched(index) = self.wait_for_event().await {
+                return index;
+            }
+        }
+    }
+}
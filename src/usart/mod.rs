@@ -153,6 +153,8 @@ pub enum Error {
     Parity,
     /// Buffer too large for DMA
     BufferTooLong,
+    /// DMA transfer error
+    Dma,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -277,8 +279,7 @@ impl<'d, T: Instance> UartTx<'d, T, Async> {
         // If we don't assign future to a variable, the data register pointer
         // is held across an await and makes the future non-Send.
         let transfer = unsafe { ch.write(buffer, T::regs().datar().as_ptr() as _, Default::default()) };
-        transfer.await;
-        Ok(())
+        transfer.await.map_err(|_| Error::Dma)
     }
 }
 
@@ -638,7 +639,10 @@ impl<'d, T: Instance> UartRx<'d, T, Async> {
         // when transfer is dropped, it will stop the DMA request
         let r = match select(transfer, abort).await {
             // DMA transfer completed first
-            Either::Left(((), _)) => Ok(ReadCompletionEvent::DmaCompleted),
+            Either::Left((Ok(()), _)) => Ok(ReadCompletionEvent::DmaCompleted),
+
+            // DMA transfer failed
+            Either::Left((Err(_), _)) => Err(Error::Dma),
 
             // Idle line detected first
             Either::Right((Ok(()), transfer)) => Ok(ReadCompletionEvent::Idle(
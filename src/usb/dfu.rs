@@ -0,0 +1,192 @@
+//! USB DFU runtime interface, and reset into the WCH ISP bootloader
+//!
+//! With the `usb-dfu` feature, [`DfuRuntime`] adds the DFU runtime interface to a device, so
+//! `dfu-util -e` or WCH's tools can ask it to detach. The application then resets into the
+//! bootloader in system flash, which takes the update over USB, without pressing BOOT0:
+//!
+//! ```rust,ignore
+//! static STATE: StaticCell<dfu::State> = StaticCell::new();
+//! let dfu = dfu::DfuRuntime::new(&mut builder, STATE.init(dfu::State::new()), 1000);
+//! let mut device = builder.build();
+//!
+//! let detach = async {
+//!     dfu.wait_for_detach().await;
+//!     // Let the status stage of the request complete.
+//!     Timer::after_millis(10).await;
+//!     dfu::reset_into_isp()
+//! };
+//! join(device.run(), detach).await;
+//! ```
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_usb::control::{InResponse, OutResponse, Recipient, Request, RequestType};
+use embassy_usb::driver::Driver;
+use embassy_usb::types::InterfaceNumber;
+use embassy_usb::{Builder, Handler};
+
+use crate::pac::FLASH;
+
+const USB_CLASS_APPLICATION_SPECIFIC: u8 = 0xFE;
+const DFU_SUBCLASS: u8 = 0x01;
+const DFU_PROTOCOL_RUNTIME: u8 = 0x01;
+const DESC_DFU_FUNCTIONAL: u8 = 0x21;
+
+const REQ_DETACH: u8 = 0;
+const REQ_GETSTATUS: u8 = 3;
+const REQ_GETSTATE: u8 = 5;
+
+/// appIDLE
+const STATE_APP_IDLE: u8 = 0;
+/// appDETACH
+const STATE_APP_DETACH: u8 = 1;
+
+/// bitWillDetach, the device detaches by itself instead of waiting for a bus reset.
+const ATTR_WILL_DETACH: u8 = 1 << 3;
+/// bitCanDnload, downloads are done by the bootloader.
+const ATTR_CAN_DNLOAD: u8 = 1 << 0;
+
+/// Transfer size of the bootloader, reported in the functional descriptor.
+const TRANSFER_SIZE: u16 = 64;
+
+const FLASH_KEY1: u32 = 0x4567_0123;
+const FLASH_KEY2: u32 = 0xCDEF_89AB;
+
+static DETACH: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// State of the DFU runtime interface, for [`DfuRuntime::new`].
+pub struct State {
+    control: Control,
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self {
+            control: Control {
+                interface: InterfaceNumber(0),
+                state: STATE_APP_IDLE,
+            },
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Control {
+    interface: InterfaceNumber,
+    state: u8,
+}
+
+impl Control {
+    fn accepts(&self, req: &Request) -> bool {
+        req.request_type == RequestType::Class
+            && req.recipient == Recipient::Interface
+            && req.index == u8::from(self.interface) as u16
+    }
+}
+
+impl Handler for Control {
+    fn control_out(&mut self, req: Request, _data: &[u8]) -> Option<OutResponse> {
+        if !self.accepts(&req) {
+            return None;
+        }
+
+        match req.request {
+            REQ_DETACH => {
+                trace!("DFU detach");
+                self.state = STATE_APP_DETACH;
+                DETACH.signal(());
+                Some(OutResponse::Accepted)
+            }
+            _ => Some(OutResponse::Rejected),
+        }
+    }
+
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if !self.accepts(&req) {
+            return None;
+        }
+
+        match req.request {
+            REQ_GETSTATUS => {
+                // bStatus OK, bwPollTimeout 0, bState, iString
+                buf[..6].copy_from_slice(&[0, 0, 0, 0, self.state, 0]);
+                Some(InResponse::Accepted(&buf[..6]))
+            }
+            REQ_GETSTATE => {
+                buf[0] = self.state;
+                Some(InResponse::Accepted(&buf[..1]))
+            }
+            _ => Some(InResponse::Rejected),
+        }
+    }
+
+    fn reset(&mut self) {
+        // A host ignoring bitWillDetach resets the bus instead of waiting.
+        if self.state == STATE_APP_DETACH {
+            reset_into_isp();
+        }
+    }
+}
+
+/// DFU runtime interface.
+pub struct DfuRuntime {
+    _private: (),
+}
+
+impl DfuRuntime {
+    /// Add the interface to `builder`.
+    ///
+    /// `detach_timeout_ms` is how long the host waits for the device to re-enumerate as the
+    /// bootloader after a detach request.
+    pub fn new<'d, D: Driver<'d>>(builder: &mut Builder<'d, D>, state: &'d mut State, detach_timeout_ms: u16) -> Self {
+        let mut func = builder.function(USB_CLASS_APPLICATION_SPECIFIC, DFU_SUBCLASS, DFU_PROTOCOL_RUNTIME);
+        let mut iface = func.interface();
+        state.control.interface = iface.interface_number();
+        let mut alt = iface.alt_setting(USB_CLASS_APPLICATION_SPECIFIC, DFU_SUBCLASS, DFU_PROTOCOL_RUNTIME, None);
+
+        let timeout = detach_timeout_ms.to_le_bytes();
+        let transfer_size = TRANSFER_SIZE.to_le_bytes();
+        alt.descriptor(
+            DESC_DFU_FUNCTIONAL,
+            &[
+                ATTR_WILL_DETACH | ATTR_CAN_DNLOAD,
+                timeout[0],
+                timeout[1],
+                transfer_size[0],
+                transfer_size[1],
+                // bcdDFUVersion 1.1
+                0x10,
+                0x01,
+            ],
+        );
+        drop(func);
+
+        builder.handler(&mut state.control);
+        Self { _private: () }
+    }
+
+    /// Wait for the host to request a detach, after which the application should call
+    /// [`reset_into_isp`].
+    pub async fn wait_for_detach(&self) {
+        DETACH.wait().await
+    }
+}
+
+/// Reset into the WCH ISP bootloader in system flash, as if BOOT0 was held high.
+///
+/// The boot mode is selected by `FLASH_STATR.BOOT_MODE`, which survives the software reset
+/// issued here.
+pub fn reset_into_isp() -> ! {
+    critical_section::with(|_| {
+        FLASH.boot_modekeyr().write_value(FLASH_KEY1);
+        FLASH.boot_modekeyr().write_value(FLASH_KEY2);
+        FLASH.statr().modify(|w| w.set_boot_mode(true));
+        FLASH.ctlr().modify(|w| w.set_lock(true));
+    });
+    qingke::pfic::system_reset()
+}
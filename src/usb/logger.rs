@@ -0,0 +1,133 @@
+//! Logging over a USB CDC-ACM serial port
+//!
+//! With the `usb-log` feature, [`println!`](crate::println) and the `log` crate write to a
+//! ring buffer instead of the SDI debug interface, and [`run`] sends it to a virtual serial
+//! port, so devices without a WCH-Link attached still produce logs:
+//!
+//! ```rust,ignore
+//! #[embassy_executor::task]
+//! async fn usb_log(driver: usbd::Driver<'static, peripherals::USBD>) {
+//!     static STATE: StaticCell<logger::State> = StaticCell::new();
+//!     let config = embassy_usb::Config::new(0x6666, 0xcafe);
+//!     logger::run(driver, config, STATE.init(logger::State::new())).await
+//! }
+//!
+//! spawner.spawn(usb_log(driver)).unwrap();
+//! hal::usb::logger::init_log(log::LevelFilter::Info);
+//! ```
+//!
+//! Output is kept while no terminal is connected until the buffer is full, newer output is
+//! dropped then.
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pipe::Pipe;
+use embassy_usb::class::cdc_acm::{self, CdcAcmClass};
+use embassy_usb::{Builder, Config};
+use embassy_usb_driver::Driver;
+
+/// Size of the ring buffer holding output not sent yet.
+pub const BUFFER_SIZE: usize = 1024;
+
+const MAX_PACKET_SIZE: u16 = 64;
+
+static BUFFER: Pipe<CriticalSectionRawMutex, BUFFER_SIZE> = Pipe::new();
+
+/// Queue `bytes` for the serial port, dropping what doesn't fit.
+///
+/// Never blocks, so it can be called from interrupt handlers.
+pub fn write(bytes: &[u8]) {
+    let _ = BUFFER.try_write(bytes);
+}
+
+/// Buffers of the USB device run by [`run`].
+pub struct State<'d> {
+    cdc: cdc_acm::State<'d>,
+    config_descriptor: [u8; 128],
+    bos_descriptor: [u8; 16],
+    msos_descriptor: [u8; 0],
+    control_buf: [u8; 64],
+}
+
+impl<'d> State<'d> {
+    pub fn new() -> Self {
+        Self {
+            cdc: cdc_acm::State::new(),
+            config_descriptor: [0; 128],
+            bos_descriptor: [0; 16],
+            msos_descriptor: [0; 0],
+            control_buf: [0; 64],
+        }
+    }
+}
+
+impl<'d> Default for State<'d> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run a USB device with a single CDC-ACM interface carrying the log output.
+///
+/// Meant to be the body of a task of its own.
+pub async fn run<'d, D: Driver<'d>>(driver: D, config: Config<'d>, state: &'d mut State<'d>) -> ! {
+    let mut builder = Builder::new(
+        driver,
+        config,
+        &mut state.config_descriptor,
+        &mut state.bos_descriptor,
+        &mut state.msos_descriptor,
+        &mut state.control_buf,
+    );
+    let mut class = CdcAcmClass::new(&mut builder, &mut state.cdc, MAX_PACKET_SIZE);
+    let mut device = builder.build();
+
+    let log = async {
+        let mut buf = [0; MAX_PACKET_SIZE as usize];
+        loop {
+            class.wait_connection().await;
+
+            loop {
+                let n = BUFFER.read(&mut buf).await;
+                if class.write_packet(&buf[..n]).await.is_err() {
+                    break;
+                }
+                // A full packet doesn't end the transfer, flush it when nothing follows.
+                if n == buf.len() && BUFFER.is_empty() && class.write_packet(&[]).await.is_err() {
+                    break;
+                }
+            }
+        }
+    };
+
+    embassy_futures::join::join(device.run(), log).await;
+    unreachable!()
+}
+
+struct Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            crate::println!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
+
+/// Route the `log` crate to the serial port, up to `level`.
+///
+/// Call it once, before any other task logs.
+pub fn init_log(level: log::LevelFilter) {
+    // Not all cores have atomics, the logger is set before any concurrent use instead.
+    critical_section::with(|_| unsafe {
+        let _ = log::set_logger_racy(&LOGGER);
+        log::set_max_level_racy(level);
+    });
+}
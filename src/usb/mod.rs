@@ -1,40 +1,76 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use embassy_usb_driver::EndpointAllocError;
 
-pub(crate) struct EndpointBufferAllocator<'d, const NR_EP: usize> {
-    ep_buffer: &'d mut [EndpointDataBuffer; NR_EP],
-    ep_next: usize,
+// The boot mode can only be selected by software where FLASH has BOOT_MODEKEYR.
+#[cfg(all(feature = "usb-dfu", any(ch32v2, ch32v3, ch32x0, ch32l1)))]
+pub mod dfu;
+#[cfg(feature = "usb-log")]
+pub mod logger;
+pub mod vbus;
+
+/// The unique ID of the chip as 24 hex digits, for the serial number string descriptor.
+///
+/// Every board then enumerates with a distinct serial, and hosts keep e.g. their serial port
+/// names and settings per board.
+pub fn serial_number() -> &'static str {
+    static mut SERIAL_NUMBER: [u8; 24] = [0; 24];
+    static FORMATTED: AtomicBool = AtomicBool::new(false);
+
+    critical_section::with(|_| {
+        // Only borrowed mutably once, in the critical section, before any shared borrow.
+        if !FORMATTED.load(Ordering::Relaxed) {
+            crate::signature::unique_id_hex(unsafe { &mut *core::ptr::addr_of_mut!(SERIAL_NUMBER) });
+            FORMATTED.store(true, Ordering::Relaxed);
+        }
+        let buf = unsafe { &*core::ptr::addr_of!(SERIAL_NUMBER) };
+        // Only ASCII hex digits were written.
+        unsafe { core::str::from_utf8_unchecked(buf) }
+    })
+}
+
+/// An `embassy-usb` [`Config`](embassy_usb::Config) with the [`serial_number`] of the chip.
+#[cfg(feature = "usb-config")]
+pub fn config(vid: u16, pid: u16) -> embassy_usb::Config<'static> {
+    let mut config = embassy_usb::Config::new(vid, pid);
+    config.serial_number = Some(serial_number());
+    config
 }
 
-impl<'d, const NR_EP: usize> EndpointBufferAllocator<'d, NR_EP> {
-    pub fn new(ep_buffer: &'d mut [EndpointDataBuffer; NR_EP]) -> Self {
-        Self { ep_buffer, ep_next: 0 }
+pub(crate) struct EndpointBufferAllocator<'d, const NR_EP: usize, const N: usize = ENDPOINT_DATA_BUFFER_SIZE> {
+    ep_buffer: &'d mut [EndpointDataBuffer<N>],
+}
+
+impl<'d, const NR_EP: usize, const N: usize> EndpointBufferAllocator<'d, NR_EP, N> {
+    pub fn new(ep_buffer: &'d mut [EndpointDataBuffer<N>; NR_EP]) -> Self {
+        Self { ep_buffer }
     }
 
     pub fn alloc_endpoint(
         &mut self,
         max_packet_size: u16,
     ) -> Result<EndpointData<'d>, embassy_usb_driver::EndpointAllocError> {
-        if self.ep_next >= NR_EP {
-            error!("out of endpoint buffers");
+        if max_packet_size as usize > N {
+            error!("endpoint buffers are too small for {} bytes packets", max_packet_size);
             return Err(EndpointAllocError);
         }
 
-        let ep_buf_idx = self.ep_next;
-        self.ep_next += 1;
-
-        // TODO: Fix this, and allow variable buffer sizes
-        assert!(max_packet_size as usize <= ENDPOINT_DATA_BUFFER_SIZE);
+        let Some((ep_buf, rest)) = core::mem::take(&mut self.ep_buffer).split_first_mut() else {
+            error!("out of endpoint buffers");
+            return Err(EndpointAllocError);
+        };
+        self.ep_buffer = rest;
 
         Ok(EndpointData {
             max_packet_size,
-            buffer: unsafe { core::mem::transmute(&self.ep_buffer[ep_buf_idx] as *const EndpointDataBuffer) },
+            buffer: EndpointBuffer { data: &mut ep_buf.data },
         })
     }
 }
 
 pub struct EndpointData<'d> {
     pub max_packet_size: u16,
-    pub buffer: &'d mut EndpointDataBuffer,
+    pub buffer: EndpointBuffer<'d>,
 }
 
 impl<'d> EndpointData<'d> {
@@ -43,40 +79,42 @@ impl<'d> EndpointData<'d> {
     }
 }
 
-// todo generic
-const ENDPOINT_DATA_BUFFER_SIZE: usize = 64;
+/// Default size of an [`EndpointDataBuffer`], the largest full-speed packet.
+pub const ENDPOINT_DATA_BUFFER_SIZE: usize = 64;
 
+/// DMA buffer of one endpoint, holding packets of up to `N` bytes.
+///
+/// High-speed bulk endpoints need `EndpointDataBuffer<512>`.
 #[repr(C, align(4))]
-pub struct EndpointDataBuffer {
-    data: [u8; ENDPOINT_DATA_BUFFER_SIZE as usize],
+pub struct EndpointDataBuffer<const N: usize = ENDPOINT_DATA_BUFFER_SIZE> {
+    data: [u8; N],
 }
 
-impl Default for EndpointDataBuffer {
+impl<const N: usize> Default for EndpointDataBuffer<N> {
     fn default() -> Self {
-        unsafe {
-            EndpointDataBuffer {
-                data: core::mem::zeroed(),
-            }
-        }
+        EndpointDataBuffer { data: [0; N] }
     }
 }
 
-impl EndpointDataBuffer {
+/// An allocated [`EndpointDataBuffer`].
+pub struct EndpointBuffer<'d> {
+    data: &'d mut [u8],
+}
+
+impl<'d> EndpointBuffer<'d> {
     pub(crate) fn read_volatile(&self, buf: &mut [u8]) {
         assert!(buf.len() <= self.data.len());
-        let len = buf.len();
 
-        for i in 0..len {
-            buf[i] = unsafe { core::ptr::read_volatile(&self.data[i]) };
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = unsafe { core::ptr::read_volatile(&self.data[i]) };
         }
     }
 
     pub(crate) fn write_volatile(&mut self, buf: &[u8]) {
         assert!(buf.len() <= self.data.len());
-        let len = buf.len();
 
-        for i in 0..len {
-            unsafe { core::ptr::write_volatile(&mut self.data[i], buf[i]) };
+        for (i, b) in buf.iter().enumerate() {
+            unsafe { core::ptr::write_volatile(&mut self.data[i], *b) };
         }
     }
 
@@ -0,0 +1,82 @@
+//! VBUS sensing for self-powered devices
+//!
+//! A self-powered device must only pull D+ up while the host powers VBUS, or it back-powers the
+//! host through the pull-up. VBUS is sensed on a 5 V tolerant pin, or through a divider:
+//!
+//! ```rust,ignore
+//! let vbus = VbusDetect::new(ExtiInput::new(p.PB5, p.EXTI5, Pull::Down));
+//! let mut driver = otg_fs::Driver::new(p.OTG_FS, p.PA12, p.PA11, &mut buffers);
+//! driver.set_vbus_detect(vbus);
+//! ```
+//!
+//! The drivers then report [`Event::PowerDetected`] and [`Event::PowerRemoved`], and
+//! `embassy-usb` enables and disables the bus, with its pull-up, accordingly.
+
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Timer};
+use embassy_usb_driver::Event;
+
+use crate::exti::ExtiInput;
+
+/// How long VBUS has to stay up before the device connects, the plug bounces.
+const VBUS_DEBOUNCE: Duration = Duration::from_millis(10);
+
+/// VBUS sensing on an EXTI input.
+pub struct VbusDetect<'d> {
+    input: ExtiInput<'d>,
+    powered: bool,
+}
+
+impl<'d> VbusDetect<'d> {
+    /// Sense VBUS on `input`, high while the host powers the bus.
+    pub fn new(input: ExtiInput<'d>) -> Self {
+        Self { input, powered: false }
+    }
+
+    /// Whether VBUS is up.
+    pub fn is_powered(&self) -> bool {
+        self.input.is_high()
+    }
+
+    /// Wait until VBUS is up and stable, returning immediately if it already is.
+    pub async fn wait_for_vbus(&mut self) {
+        loop {
+            self.input.wait_for_high().await;
+            Timer::after(VBUS_DEBOUNCE).await;
+            if self.input.is_high() {
+                return;
+            }
+        }
+    }
+
+    /// Wait until VBUS is gone, returning immediately if it already is.
+    ///
+    /// There's no debouncing, the pull-up has to go as soon as the host stops powering the bus.
+    pub async fn wait_for_disconnect(&mut self) {
+        self.input.wait_for_low().await
+    }
+
+    /// Release the input.
+    pub fn release(self) -> ExtiInput<'d> {
+        self.input
+    }
+
+    /// The next event of a bus, whose other events `bus` waits for.
+    ///
+    /// Only power events are reported while VBUS is down.
+    pub(crate) async fn poll(&mut self, bus: impl core::future::Future<Output = Event>) -> Event {
+        if !self.powered {
+            self.wait_for_vbus().await;
+            self.powered = true;
+            return Event::PowerDetected;
+        }
+
+        match select(self.wait_for_disconnect(), bus).await {
+            Either::First(()) => {
+                self.powered = false;
+                Event::PowerRemoved
+            }
+            Either::Second(event) => event,
+        }
+    }
+}
@@ -1,6 +1,31 @@
 //! Universal Serial Bus Device(USBD)
 //!
-//! This peripheral is almost the same as usb_v1 in embassy-stm32.
+//! Full-speed device controller of the CH32V103 and CH32V20x, driven through
+//! [`embassy_usb_driver::Driver`], so the classes of `embassy-usb` (CDC-ACM, HID, ...) run on it.
+//!
+//! This peripheral is almost the same as usb_v1 in embassy-stm32: 8 bidirectional endpoints,
+//! each with its own buffers in the dedicated 512-byte packet memory (PMA), described by the
+//! buffer table at its start. Endpoint 0 is reserved for the control pipe.
+//!
+//! On suspend, the transceiver is put in low-power mode until the host resumes the bus, or
+//! [`Bus::remote_wakeup`](driver::Bus::remote_wakeup) wakes it up.
+//!
+//! Isochronous endpoints are double-buffered, so they take both directions of their endpoint
+//! and twice the packet memory. So do bulk endpoints after
+//! [`Driver::set_double_buffered_bulk`], which keeps the host from waiting on the application
+//! between back-to-back packets.
+//!
+//! Self-powered devices sense VBUS with [`Driver::set_vbus_detect`], so the D+ pull-up is only
+//! on while the host powers the bus.
+//!
+//! ```rust,ignore
+//! bind_interrupts!(struct Irqs {
+//!     USB_LP_CAN1_RX0 => usbd::InterruptHandler<peripherals::USBD>;
+//! });
+//!
+//! let driver = usbd::Driver::new(p.USBD, Irqs, p.PA12, p.PA11);
+//! let mut builder = embassy_usb::Builder::new(driver, config, ...);
+//! ```
 
 #![macro_use]
 
@@ -20,6 +45,7 @@ use crate::pac::usbd::regs;
 use crate::pac::usbd::vals::{EpType, Stat};
 use crate::pac::{EXTEND, USBRAM};
 use crate::peripheral::RccPeripheral;
+use crate::usb::vbus::VbusDetect;
 use crate::{interrupt, into_ref, Peripheral};
 
 /// Interrupt handler.
@@ -30,13 +56,9 @@ pub struct InterruptHandler<T: Instance> {
 impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
     unsafe fn on_interrupt() {
         let regs = T::regs();
-        let x = regs.istr().read().0;
-        crate::println!("USB IRQ: {:08x}", x);
-
         let istr = regs.istr().read();
 
         if istr.susp() {
-            //crate::println!("USB IRQ: susp");
             IRQ_SUSPEND.store(true, Ordering::Relaxed);
             regs.cntr().modify(|w| {
                 w.set_fsusp(true);
@@ -53,7 +75,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
         }
 
         if istr.wkup() {
-            crate::println!("USB IRQ: wkup");
+            trace!("USB IRQ: wkup");
             IRQ_RESUME.store(true, Ordering::Relaxed);
             regs.cntr().modify(|w| {
                 w.set_fsusp(false);
@@ -70,7 +92,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
         }
 
         if istr.reset() {
-            crate::println!("USB IRQ: reset");
+            trace!("USB IRQ: reset");
             IRQ_RESET.store(true, Ordering::Relaxed);
 
             // Write 0 to clear.
@@ -86,14 +108,16 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
             let index = istr.ep_id() as usize;
             let mut epr = regs.epr(index).read();
             if epr.ctr_rx() {
+                EP_OUT_DONE[index].store(true, Ordering::Relaxed);
                 if index == 0 && epr.setup() {
                     EP0_SETUP.store(true, Ordering::Relaxed);
                 }
-                crate::println!("EP {} RX, setup={}", index, epr.setup());
+                trace!("EP {} RX, setup={}", index, epr.setup());
                 EP_OUT_WAKERS[index].wake();
             }
             if epr.ctr_tx() {
-                //crate::println!("EP {} TX", index);
+                EP_IN_DONE[index].store(true, Ordering::Relaxed);
+                EP_IN_FULL[index].store(false, Ordering::Relaxed);
                 EP_IN_WAKERS[index].wake();
             }
             epr.set_dtog_rx(false);
@@ -118,6 +142,12 @@ static BUS_WAKER: AtomicWaker = NEW_AW;
 static EP0_SETUP: AtomicBool = AtomicBool::new(false);
 static EP_IN_WAKERS: [AtomicWaker; EP_COUNT] = [NEW_AW; EP_COUNT];
 static EP_OUT_WAKERS: [AtomicWaker; EP_COUNT] = [NEW_AW; EP_COUNT];
+// Isochronous endpoints stay VALID after a transfer, completions are flagged instead.
+const NEW_FLAG: AtomicBool = AtomicBool::new(false);
+static EP_IN_DONE: [AtomicBool; EP_COUNT] = [NEW_FLAG; EP_COUNT];
+static EP_OUT_DONE: [AtomicBool; EP_COUNT] = [NEW_FLAG; EP_COUNT];
+// Both buffers of a double-buffered bulk IN endpoint hold packets to send.
+static EP_IN_FULL: [AtomicBool; EP_COUNT] = [NEW_FLAG; EP_COUNT];
 static IRQ_RESET: AtomicBool = AtomicBool::new(false);
 static IRQ_SUSPEND: AtomicBool = AtomicBool::new(false);
 static IRQ_RESUME: AtomicBool = AtomicBool::new(false);
@@ -176,6 +206,26 @@ mod btable {
     pub(super) fn read_out_len<T: Instance>(index: usize) -> u16 {
         USBRAM.mem(index * 4 + 3).read()
     }
+
+    // Isochronous endpoints are double-buffered, using the slots of the other direction for
+    // their second buffer.
+
+    pub(super) fn write_in_alt<T: Instance>(index: usize, addr: u16) {
+        USBRAM.mem(index * 4 + 2).write_value(addr);
+    }
+
+    pub(super) fn write_in_len_alt<T: Instance>(index: usize, len: u16) {
+        USBRAM.mem(index * 4 + 3).write_value(len);
+    }
+
+    pub(super) fn write_out_alt<T: Instance>(index: usize, addr: u16, max_len_bits: u16) {
+        USBRAM.mem(index * 4 + 0).write_value(addr);
+        USBRAM.mem(index * 4 + 1).write_value(max_len_bits);
+    }
+
+    pub(super) fn read_out_len_alt<T: Instance>(index: usize) -> u16 {
+        USBRAM.mem(index * 4 + 1).read()
+    }
 }
 
 struct EndpointBuffer<T: Instance> {
@@ -213,6 +263,7 @@ struct EndpointData {
     ep_type: EndpointType, // only valid if used_in || used_out
     used_in: bool,
     used_out: bool,
+    double_buffered: bool,
 }
 
 /// USB driver.
@@ -220,6 +271,8 @@ pub struct Driver<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
     alloc: [EndpointData; EP_COUNT],
     ep_mem_free: u16, // first free address in EP mem, in bytes.
+    double_buffered_bulk: bool,
+    vbus: Option<VbusDetect<'d>>,
 }
 
 impl<'d, T: Instance> Driver<'d, T> {
@@ -246,10 +299,8 @@ impl<'d, T: Instance> Driver<'d, T> {
         // init
         {
             // FV2x_V3x
-            EXTEND.ctr().modify(|w| {
-                w.set_usbdpu(true);
-                w.set_usbdls(false); // full speed
-            });
+            // The pull-up is enabled with the bus.
+            EXTEND.ctr().modify(|w| w.set_usbdls(false)); // full speed
 
             T::enable_and_reset();
         }
@@ -274,11 +325,30 @@ impl<'d, T: Instance> Driver<'d, T> {
                 ep_type: EndpointType::Bulk,
                 used_in: false,
                 used_out: false,
+                double_buffered: false,
             }; EP_COUNT],
             ep_mem_free: EP_COUNT as u16 * 8, // for each EP, 4 regs, so 8 bytes
+            double_buffered_bulk: false,
+            vbus: None,
         }
     }
 
+    /// Sense VBUS on `vbus`, connecting to the host only while it powers the bus.
+    ///
+    /// Without it, the bus is taken as powered from the start.
+    pub fn set_vbus_detect(&mut self, vbus: VbusDetect<'d>) {
+        self.vbus = Some(vbus);
+    }
+
+    /// Double-buffer the bulk endpoints allocated from now on.
+    ///
+    /// The host can then send or fetch the next packet while the application handles the
+    /// last one. Each bulk endpoint takes a whole endpoint register and twice the packet
+    /// memory, 256 bytes for the two endpoints of a CDC-ACM port.
+    pub fn set_double_buffered_bulk(&mut self, enabled: bool) {
+        self.double_buffered_bulk = enabled;
+    }
+
     fn alloc_ep_mem(&mut self, len: u16) -> u16 {
         assert!(len as usize % USBRAM_ALIGN == 0);
         let addr = self.ep_mem_free;
@@ -295,7 +365,7 @@ impl<'d, T: Instance> Driver<'d, T> {
         max_packet_size: u16,
         interval_ms: u8,
     ) -> Result<Endpoint<'d, T, D>, driver::EndpointAllocError> {
-        crate::println!(
+        trace!(
             "allocating type={:?} mps={:?} interval_ms={}, dir={:?}",
             ep_type,
             max_packet_size,
@@ -303,6 +373,9 @@ impl<'d, T: Instance> Driver<'d, T> {
             D::dir()
         );
 
+        let double_buffered =
+            ep_type == EndpointType::Isochronous || (ep_type == EndpointType::Bulk && self.double_buffered_bulk);
+
         let index = self.alloc.iter_mut().enumerate().find(|(i, ep)| {
             if *i == 0 && ep_type != EndpointType::Control {
                 return false; // reserved for control pipe
@@ -312,7 +385,9 @@ impl<'d, T: Instance> Driver<'d, T> {
                 Direction::Out => ep.used_out,
                 Direction::In => ep.used_in,
             };
-            !used || (ep.ep_type == ep_type && !used_dir)
+            // Double-buffered endpoints take both directions for their second buffer.
+            let shared = !double_buffered && !ep.double_buffered && ep.ep_type == ep_type;
+            !used || (shared && !used_dir)
         });
 
         let (index, ep) = match index {
@@ -321,8 +396,9 @@ impl<'d, T: Instance> Driver<'d, T> {
         };
 
         ep.ep_type = ep_type;
+        ep.double_buffered = double_buffered;
 
-        let buf = match D::dir() {
+        let (buf, buf_alt) = match D::dir() {
             Direction::Out => {
                 assert!(!ep.used_out);
                 ep.used_out = true;
@@ -330,14 +406,25 @@ impl<'d, T: Instance> Driver<'d, T> {
                 let (len, len_bits) = calc_out_len(max_packet_size);
                 let addr = self.alloc_ep_mem(len);
 
-                crate::println!("  len_bits = {:04x}", len_bits);
+                trace!("  len_bits = {:04x}", len_bits);
                 btable::write_out::<T>(index, addr, len_bits);
 
-                EndpointBuffer {
+                let buf_alt = double_buffered.then(|| {
+                    let addr = self.alloc_ep_mem(len);
+                    btable::write_out_alt::<T>(index, addr, len_bits);
+                    EndpointBuffer {
+                        addr,
+                        len,
+                        _phantom: PhantomData,
+                    }
+                });
+
+                let buf = EndpointBuffer {
                     addr,
                     len,
                     _phantom: PhantomData,
-                }
+                };
+                (buf, buf_alt)
             }
             Direction::In => {
                 assert!(!ep.used_in);
@@ -349,15 +436,32 @@ impl<'d, T: Instance> Driver<'d, T> {
                 // ep_in_len is written when actually TXing packets.
                 btable::write_in::<T>(index, addr);
 
-                EndpointBuffer {
+                let buf_alt = double_buffered.then(|| {
+                    let addr = self.alloc_ep_mem(len);
+                    btable::write_in_alt::<T>(index, addr);
+                    btable::write_in_len_alt::<T>(index, 0);
+                    EndpointBuffer {
+                        addr,
+                        len,
+                        _phantom: PhantomData,
+                    }
+                });
+
+                let buf = EndpointBuffer {
                     addr,
                     len,
                     _phantom: PhantomData,
-                }
+                };
+                (buf, buf_alt)
             }
         };
 
-        crate::println!("  index={} addr={} len={}", index, buf.addr, buf.len);
+        if double_buffered {
+            ep.used_in = true;
+            ep.used_out = true;
+        }
+
+        trace!("  index={} addr={} len={}", index, buf.addr, buf.len);
 
         Ok(Endpoint {
             _phantom: PhantomData,
@@ -368,6 +472,7 @@ impl<'d, T: Instance> Driver<'d, T> {
                 interval_ms,
             },
             buf,
+            buf_alt,
         })
     }
 }
@@ -417,17 +522,21 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
             w.set_ctrm(true);
         });
 
-        crate::println!("enabled");
+        trace!("enabled");
 
         let mut ep_types = [EpType::BULK; EP_COUNT - 1];
+        let mut double_buffered = [false; EP_COUNT - 1];
         for i in 1..EP_COUNT {
             ep_types[i - 1] = convert_type(self.alloc[i].ep_type);
+            double_buffered[i - 1] = self.alloc[i].double_buffered;
         }
 
         (
             Bus {
                 phantom: PhantomData,
                 ep_types,
+                double_buffered,
+                vbus: self.vbus,
                 inited: false,
             },
             ControlPipe {
@@ -444,19 +553,26 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
 pub struct Bus<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
     ep_types: [EpType; EP_COUNT - 1],
+    double_buffered: [bool; EP_COUNT - 1],
+    vbus: Option<VbusDetect<'d>>,
     inited: bool,
 }
 
 impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
     async fn poll(&mut self) -> Event {
-        poll_fn(move |cx| {
-            BUS_WAKER.register(cx.waker());
+        if self.vbus.is_none() && !self.inited {
+            self.inited = true;
+            return Event::PowerDetected;
+        }
 
-            // TODO: implement VBUS detection.
-            if !self.inited {
-                self.inited = true;
-                return Poll::Ready(Event::PowerDetected);
-            }
+        let Self {
+            ep_types,
+            double_buffered,
+            vbus,
+            ..
+        } = self;
+        let bus = poll_fn(move |cx| {
+            BUS_WAKER.register(cx.waker());
 
             let regs = T::regs();
 
@@ -468,7 +584,7 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
             if IRQ_RESET.load(Ordering::Acquire) {
                 IRQ_RESET.store(false, Ordering::Relaxed);
 
-                crate::println!("RESET");
+                trace!("RESET");
                 regs.daddr().write(|w| {
                     w.set_ef(true);
                     w.set_add(0);
@@ -483,7 +599,9 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
                 for i in 1..EP_COUNT {
                     regs.epr(i).write(|w| {
                         w.set_ea(i as _);
-                        w.set_ep_type(self.ep_types[i - 1]);
+                        w.set_ep_type(ep_types[i - 1]);
+                        // DBL_BUF for bulk endpoints
+                        w.set_ep_kind(double_buffered[i - 1] && ep_types[i - 1] == EpType::BULK);
                     })
                 }
 
@@ -503,8 +621,12 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
             }
 
             Poll::Pending
-        })
-        .await
+        });
+
+        match vbus {
+            Some(vbus) => vbus.poll(bus).await,
+            None => bus.await,
+        }
     }
 
     fn endpoint_set_stalled(&mut self, ep_addr: EndpointAddress, stalled: bool) {
@@ -562,15 +684,24 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
     }
 
     fn endpoint_set_enabled(&mut self, ep_addr: EndpointAddress, enabled: bool) {
-        crate::println!("set_enabled {:x?} {}", ep_addr, enabled);
+        trace!(
+            "set_enabled ep{} {:?} {}",
+            ep_addr.index(),
+            ep_addr.direction(),
+            enabled
+        );
         // This can race, so do a retry loop.
         let reg = T::regs().epr(ep_addr.index() as _);
-        crate::println!("EPR before: {:04x}", reg.read().0);
         match ep_addr.direction() {
             Direction::In => {
+                // Isochronous endpoints don't handshake, they send whatever is in the buffer, and
+                // double-buffered ones NAK by themselves while there's nothing to send.
+                let always_valid = ep_addr.index() > 0 && self.double_buffered[ep_addr.index() - 1];
+                EP_IN_FULL[ep_addr.index()].store(false, Ordering::Relaxed);
                 loop {
                     let want_stat = match enabled {
                         false => Stat::DISABLED,
+                        true if always_valid => Stat::VALID,
                         true => Stat::NAK,
                     };
                     let r = reg.read();
@@ -600,14 +731,28 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
                 EP_OUT_WAKERS[ep_addr.index()].wake();
             }
         }
-        crate::println!("EPR after: {:04x}", reg.read().0);
     }
 
-    async fn enable(&mut self) {}
-    async fn disable(&mut self) {}
+    async fn enable(&mut self) {
+        EXTEND.ctr().modify(|w| w.set_usbdpu(true));
+    }
+
+    async fn disable(&mut self) {
+        EXTEND.ctr().modify(|w| w.set_usbdpu(false));
+    }
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        Err(Unsupported)
+        let regs = T::regs();
+        regs.cntr().modify(|w| {
+            w.set_lpmode(false);
+            w.set_fsusp(false);
+        });
+
+        // Drive resume signaling for 1 to 15 ms.
+        regs.cntr().modify(|w| w.set_resume(true));
+        embassy_time::Timer::after_millis(10).await;
+        regs.cntr().modify(|w| w.set_resume(false));
+        Ok(())
     }
 }
 
@@ -636,6 +781,8 @@ pub struct Endpoint<'d, T: Instance, D> {
     _phantom: PhantomData<(&'d mut T, D)>,
     info: EndpointInfo,
     buf: EndpointBuffer<T>,
+    /// Second buffer of double-buffered endpoints.
+    buf_alt: Option<EndpointBuffer<T>>,
 }
 
 impl<'d, T: Instance, D> Endpoint<'d, T, D> {
@@ -648,13 +795,42 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
     fn read_data(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
         let index = self.info.addr.index();
         let rx_len = btable::read_out_len::<T>(index) as usize & 0x3FF;
-        crate::println!("READ DONE, rx_len = {}", rx_len);
+        trace!("READ DONE, rx_len = {}", rx_len);
         if rx_len > buf.len() {
             return Err(EndpointError::BufferOverflow);
         }
         self.buf.read(&mut buf[..rx_len]);
         Ok(rx_len)
     }
+
+    // Buffer 0 of double-buffered endpoints is in the TX slot of the buffer table, buffer 1
+    // in the RX slot.
+
+    /// Fill buffer 0 or 1 of a double-buffered IN endpoint.
+    fn write_buffer(&mut self, second: bool, buf: &[u8]) {
+        if !second {
+            return self.write_data(buf);
+        }
+
+        let index = self.info.addr.index();
+        unwrap!(self.buf_alt.as_mut()).write(buf);
+        btable::write_in_len_alt::<T>(index, buf.len() as _);
+    }
+
+    /// Read buffer 0 or 1 of a double-buffered OUT endpoint.
+    fn read_buffer(&mut self, second: bool, buf: &mut [u8]) -> Result<usize, EndpointError> {
+        if second {
+            return self.read_data(buf);
+        }
+
+        let index = self.info.addr.index();
+        let rx_len = btable::read_out_len_alt::<T>(index) as usize & 0x3FF;
+        if rx_len > buf.len() {
+            return Err(EndpointError::BufferOverflow);
+        }
+        unwrap!(self.buf_alt.as_mut()).read(&mut buf[..rx_len]);
+        Ok(rx_len)
+    }
 }
 
 impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, In> {
@@ -663,7 +839,7 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, In> {
     }
 
     async fn wait_enabled(&mut self) {
-        crate::println!("wait_enabled IN WAITING");
+        trace!("wait_enabled IN WAITING");
         let index = self.info.addr.index();
         poll_fn(|cx| {
             EP_IN_WAKERS[index].register(cx.waker());
@@ -675,7 +851,7 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, In> {
             }
         })
         .await;
-        crate::println!("wait_enabled IN OK");
+        trace!("wait_enabled IN OK");
     }
 }
 
@@ -685,7 +861,7 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, Out> {
     }
 
     async fn wait_enabled(&mut self) {
-        crate::println!("wait_enabled OUT WAITING");
+        trace!("wait_enabled OUT WAITING");
         let index = self.info.addr.index();
         poll_fn(|cx| {
             EP_OUT_WAKERS[index].register(cx.waker());
@@ -697,14 +873,37 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, Out> {
             }
         })
         .await;
-        crate::println!("wait_enabled OUT OK");
+        trace!("wait_enabled OUT OK");
     }
 }
 
 impl<'d, T: Instance> driver::EndpointOut for Endpoint<'d, T, Out> {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
-        crate::println!("READ WAITING, buf.len() = {}", buf.len());
+        trace!("READ WAITING, buf.len() = {}", buf.len());
         let index = self.info.addr.index();
+
+        if self.info.ep_type == EndpointType::Isochronous {
+            poll_fn(|cx| {
+                EP_OUT_WAKERS[index].register(cx.waker());
+                if T::regs().epr(index).read().stat_rx() == Stat::DISABLED {
+                    Poll::Ready(Err(EndpointError::Disabled))
+                } else if EP_OUT_DONE[index].load(Ordering::Relaxed) {
+                    EP_OUT_DONE[index].store(false, Ordering::Relaxed);
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await?;
+            // The USB toggles DTOG_RX after receiving into the buffer it pointed at.
+            let dtog_rx = T::regs().epr(index).read().dtog_rx();
+            return self.read_buffer(!dtog_rx, buf);
+        }
+
+        if self.buf_alt.is_some() {
+            return self.read_double_buffered(buf).await;
+        }
+
         let stat = poll_fn(|cx| {
             EP_OUT_WAKERS[index].register(cx.waker());
             let regs = T::regs();
@@ -732,7 +931,7 @@ impl<'d, T: Instance> driver::EndpointOut for Endpoint<'d, T, Out> {
             w.set_ctr_rx(true); // don't clear
             w.set_ctr_tx(true); // don't clear
         });
-        crate::println!("READ OK, rx_len = {}", rx_len);
+        trace!("READ OK, rx_len = {}", rx_len);
 
         Ok(rx_len)
     }
@@ -746,7 +945,34 @@ impl<'d, T: Instance> driver::EndpointIn for Endpoint<'d, T, In> {
 
         let index = self.info.addr.index();
 
-        crate::println!("WRITE WAITING");
+        if self.info.ep_type == EndpointType::Isochronous {
+            if T::regs().epr(index).read().stat_tx() == Stat::DISABLED {
+                return Err(EndpointError::Disabled);
+            }
+            EP_IN_DONE[index].store(false, Ordering::Relaxed);
+            // The USB sends from the buffer DTOG_TX points at, fill the other one.
+            let dtog_tx = T::regs().epr(index).read().dtog_tx();
+            self.write_buffer(!dtog_tx, buf);
+
+            // One packet per frame, wait for the one before to go out.
+            return poll_fn(|cx| {
+                EP_IN_WAKERS[index].register(cx.waker());
+                if T::regs().epr(index).read().stat_tx() == Stat::DISABLED {
+                    Poll::Ready(Err(EndpointError::Disabled))
+                } else if EP_IN_DONE[index].load(Ordering::Relaxed) {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+        }
+
+        if self.buf_alt.is_some() {
+            return self.write_double_buffered(buf).await;
+        }
+
+        trace!("WRITE WAITING");
         let stat = poll_fn(|cx| {
             EP_IN_WAKERS[index].register(cx.waker());
             let regs = T::regs();
@@ -775,8 +1001,83 @@ impl<'d, T: Instance> driver::EndpointIn for Endpoint<'d, T, In> {
             w.set_ctr_tx(true); // don't clear
         });
 
-        crate::println!("WRITE OK");
+        trace!("WRITE OK");
+
+        Ok(())
+    }
+}
+
+impl<'d, T: Instance> Endpoint<'d, T, Out> {
+    /// Read from a double-buffered bulk endpoint, which stays VALID.
+    ///
+    /// The application reads the buffer SW_BUF points at, and toggles it to hand the buffer
+    /// back. The USB NAKs while DTOG_RX and SW_BUF are equal after receiving, both buffers
+    /// being full.
+    async fn read_double_buffered(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
+        let index = self.info.addr.index();
+        let sw_buf = poll_fn(|cx| {
+            EP_OUT_WAKERS[index].register(cx.waker());
+            let epr = T::regs().epr(index).read();
+            // SW_BUF is DTOG_TX for OUT endpoints.
+            let sw_buf = epr.dtog_tx();
+            if epr.stat_rx() == Stat::DISABLED {
+                Poll::Ready(Err(EndpointError::Disabled))
+            } else if epr.dtog_rx() != sw_buf || EP_OUT_DONE[index].load(Ordering::Relaxed) {
+                Poll::Ready(Ok(sw_buf))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
 
+        let rx_len = self.read_buffer(sw_buf, buf);
+
+        critical_section::with(|_| {
+            EP_OUT_DONE[index].store(false, Ordering::Relaxed);
+            let reg = T::regs().epr(index);
+            let mut w = invariant(reg.read());
+            w.set_dtog_tx(true);
+            reg.write_value(w);
+        });
+        rx_len
+    }
+}
+
+impl<'d, T: Instance> Endpoint<'d, T, In> {
+    /// Queue a packet on a double-buffered bulk endpoint, which stays VALID.
+    ///
+    /// The application fills the buffer SW_BUF points at, and toggles it to hand the buffer
+    /// over. The USB NAKs while DTOG_TX and SW_BUF are equal, nothing being queued, unless
+    /// both buffers are.
+    async fn write_double_buffered(&mut self, buf: &[u8]) -> Result<(), EndpointError> {
+        let index = self.info.addr.index();
+        let sw_buf = poll_fn(|cx| {
+            EP_IN_WAKERS[index].register(cx.waker());
+            let epr = T::regs().epr(index).read();
+            // SW_BUF is DTOG_RX for IN endpoints.
+            let sw_buf = epr.dtog_rx();
+            if epr.stat_tx() == Stat::DISABLED {
+                Poll::Ready(Err(EndpointError::Disabled))
+            } else if epr.dtog_tx() == sw_buf && EP_IN_FULL[index].load(Ordering::Relaxed) {
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(sw_buf))
+            }
+        })
+        .await?;
+
+        self.write_buffer(sw_buf, buf);
+
+        critical_section::with(|_| {
+            let reg = T::regs().epr(index);
+            let epr = reg.read();
+            let mut w = invariant(epr);
+            w.set_dtog_rx(true);
+            reg.write_value(w);
+            if epr.dtog_tx() == !sw_buf {
+                EP_IN_FULL[index].store(true, Ordering::Relaxed);
+            }
+        });
         Ok(())
     }
 }
@@ -796,7 +1097,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
 
     async fn setup(&mut self) -> [u8; 8] {
         loop {
-            crate::println!("SETUP read waiting");
+            trace!("SETUP read waiting");
             poll_fn(|cx| {
                 EP_OUT_WAKERS[0].register(cx.waker());
                 if EP0_SETUP.load(Ordering::Relaxed) {
@@ -810,13 +1111,13 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
             let mut buf = [0; 8];
             let rx_len = self.ep_out.read_data(&mut buf);
             if rx_len != Ok(8) {
-                crate::println!("SETUP read failed: {:?}", rx_len);
+                trace!("SETUP read failed: {:?}", rx_len);
                 continue;
             }
 
             EP0_SETUP.store(false, Ordering::Relaxed);
 
-            crate::println!("SETUP read ok");
+            trace!("SETUP read ok");
             return buf;
         }
     }
@@ -852,7 +1153,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
             });
         }
 
-        crate::println!("data_out WAITING, buf.len() = {}", buf.len());
+        trace!("data_out WAITING, buf.len() = {}", buf.len());
         poll_fn(|cx| {
             EP_OUT_WAKERS[0].register(cx.waker());
             let regs = T::regs();
@@ -865,7 +1166,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
         .await;
 
         if EP0_SETUP.load(Ordering::Relaxed) {
-            crate::println!("received another SETUP, aborting data_out.");
+            trace!("received another SETUP, aborting data_out.");
             return Err(EndpointError::Disabled);
         }
 
@@ -887,7 +1188,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
     }
 
     async fn data_in(&mut self, data: &[u8], first: bool, last: bool) -> Result<(), EndpointError> {
-        crate::println!("control: data_in");
+        trace!("control: data_in");
 
         if data.len() > self.ep_in.info.max_packet_size as usize {
             return Err(EndpointError::BufferOverflow);
@@ -919,7 +1220,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
             });
         }
 
-        crate::println!("WRITE WAITING");
+        trace!("WRITE WAITING");
         poll_fn(|cx| {
             EP_IN_WAKERS[0].register(cx.waker());
             EP_OUT_WAKERS[0].register(cx.waker());
@@ -933,7 +1234,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
         .await;
 
         if EP0_SETUP.load(Ordering::Relaxed) {
-            crate::println!("received another SETUP, aborting data_in.");
+            trace!("received another SETUP, aborting data_in.");
             return Err(EndpointError::Disabled);
         }
 
@@ -948,14 +1249,14 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
             w.set_ctr_tx(true); // don't clear
         });
 
-        crate::println!("WRITE OK");
+        trace!("WRITE OK");
 
         Ok(())
     }
 
     async fn accept(&mut self) {
         let regs = T::regs();
-        crate::println!("control: accept");
+        trace!("control: accept");
 
         self.ep_in.write_data(&[]);
 
@@ -968,7 +1269,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
             w.set_ctr_rx(true); // don't clear
             w.set_ctr_tx(true); // don't clear
         });
-        crate::println!("control: accept WAITING");
+        trace!("control: accept WAITING");
 
         // Wait is needed, so that we don't set the address too soon, breaking the status stage.
         // (embassy-usb sets the address after accept() returns)
@@ -983,12 +1284,12 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
         })
         .await;
 
-        crate::println!("control: accept OK");
+        trace!("control: accept OK");
     }
 
     async fn reject(&mut self) {
         let regs = T::regs();
-        crate::println!("control: reject");
+        trace!("control: reject");
 
         // Set IN+OUT to stall
         let epr = regs.epr(0).read();
@@ -1005,7 +1306,7 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
         self.accept().await;
 
         let regs = T::regs();
-        crate::println!("setting addr: {}", addr);
+        trace!("setting addr: {}", addr);
         regs.daddr().write(|w| {
             w.set_ef(true);
             w.set_add(addr);
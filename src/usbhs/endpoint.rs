@@ -19,12 +19,15 @@ impl<'d, T: Instance, D: Dir> Endpoint<'d, T, D> {
         let index = info.addr.index();
         T::dregs().ep_max_len(index).write(|v| v.set_len(info.max_packet_size));
         if info.ep_type != EndpointType::Control {
+            let isochronous = info.ep_type == EndpointType::Isochronous;
             match info.addr.direction() {
                 Direction::Out => {
                     T::dregs().ep_rx_dma(index - 1).write_value(data.buffer.addr() as u32);
+                    T::dregs().ep_type().modify(|v| v.set_r_type(index - 1, isochronous));
                 }
                 Direction::In => {
                     T::dregs().ep_tx_dma(index - 1).write_value(data.buffer.addr() as u32);
+                    T::dregs().ep_type().modify(|v| v.set_t_type(index - 1, isochronous));
                 }
             }
         }
@@ -68,7 +71,7 @@ impl<'d, T: Instance, D: Dir> Endpoint<'d, T, D> {
                 let res = match status.token() {
                     UsbToken::OUT => {
                         let len = r.rx_len().read() as usize;
-                        if len == buf.len() {
+                        if len <= buf.len() {
                             self.data.buffer.read_volatile(&mut buf[..len]);
                             Poll::Ready(Ok(len))
                         } else {
@@ -81,13 +84,17 @@ impl<'d, T: Instance, D: Dir> Endpoint<'d, T, D> {
                     }
                 };
 
+                // Isochronous endpoints stay on DATA0, one packet per (micro)frame.
+                let toggle = self.info.ep_type != EndpointType::Isochronous;
                 d.ep_rx_ctrl(index).modify(|v| {
                     v.set_mask_uep_r_res(EpRxResponse::NAK);
-                    v.set_mask_uep_r_tog(if let EpTog::DATA0 = v.mask_uep_r_tog() {
-                        EpTog::DATA1
-                    } else {
-                        EpTog::DATA0
-                    });
+                    if toggle {
+                        v.set_mask_uep_r_tog(if let EpTog::DATA0 = v.mask_uep_r_tog() {
+                            EpTog::DATA1
+                        } else {
+                            EpTog::DATA0
+                        });
+                    }
                 });
 
                 d.int_fg().write(|w| w.set_transfer(true));
@@ -132,13 +139,16 @@ impl<'d, T: Instance, D: Dir> Endpoint<'d, T, D> {
                         Poll::Ready(Err(EndpointError::Disabled))
                     }
                 };
+                let toggle = self.info.ep_type != EndpointType::Isochronous;
                 d.ep_tx_ctrl(index).modify(|v| {
                     v.set_mask_uep_t_res(EpTxResponse::NAK);
-                    v.set_mask_uep_t_tog(if let EpTog::DATA0 = v.mask_uep_t_tog() {
-                        EpTog::DATA1
-                    } else {
-                        EpTog::DATA0
-                    });
+                    if toggle {
+                        v.set_mask_uep_t_tog(if let EpTog::DATA0 = v.mask_uep_t_tog() {
+                            EpTog::DATA1
+                        } else {
+                            EpTog::DATA0
+                        });
+                    }
                 });
 
                 d.int_fg().write(|w| w.set_transfer(true));
@@ -1,4 +1,9 @@
 //! USBHS device mode peripheral driver
+//!
+//! The high-speed controller of the CH32V305/V307, with its built-in 480 Mbps PHY, driven
+//! through [`embassy_usb_driver::Driver`]. Bulk endpoints take 512-byte packets when
+//! given buffers that large, see [`Driver`].
+//!
 //! Note that this currently only implements device mode
 //!
 //! <div class="warning">
@@ -19,22 +24,24 @@
 //! Other Endpoints:
 //! - [x] Interrupt Out
 //! - [x] Interrupt In
-//! - [ ] Bulk Out (Expected to work but not tested)
-//! - [ ] Bulk In (Expected to work but not tested)
-//! - [ ] Isochronous Out (Does not work)
-//! - [ ] Isochronous In (Does not work)
+//! - [ ] Bulk Out, 512 bytes (Expected to work but not tested)
+//! - [ ] Bulk In, 512 bytes (Expected to work but not tested)
+//! - [ ] Isochronous Out, one packet per (micro)frame (Expected to work but not tested)
+//! - [ ] Isochronous In, one packet per (micro)frame (Expected to work but not tested)
 //!
 //! Other Features:
 //! - [ ] Set endpoint stall
 //! - [ ] Get endpoint stall status
-//! - [ ] Remote wakeup
+//! - [ ] Remote wakeup (Expected to work but not tested)
+//! - [x] PHY suspended while the bus is, resumed by the wakeup interrupt
+//! - [ ] VBUS detection with [`Driver::set_vbus_detect`] (Expected to work but not tested)
 //!
 
 use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::task::Poll;
 
-use ch32_metapac::usbhs::regs::{EpBufMod, EpConfig, EpType};
+use ch32_metapac::usbhs::regs::{EpBufMod, EpConfig};
 use ch32_metapac::usbhs::vals::{EpRxResponse, EpTog, EpTxResponse, SpeedType, UsbToken};
 use control::ControlPipe;
 use embassy_sync::waitqueue::AtomicWaker;
@@ -45,7 +52,8 @@ use endpoint::Endpoint;
 
 use crate::gpio::{AFType, Speed};
 use crate::interrupt::typelevel::Interrupt;
-use crate::usb::{Dir, EndpointBufferAllocator, EndpointData, EndpointDataBuffer, In, Out};
+use crate::usb::vbus::VbusDetect;
+use crate::usb::{Dir, EndpointBufferAllocator, EndpointData, EndpointDataBuffer, In, Out, ENDPOINT_DATA_BUFFER_SIZE};
 use crate::{interrupt, Peripheral};
 
 pub mod control;
@@ -53,6 +61,8 @@ mod endpoint;
 
 const MAX_NR_EP: usize = 16;
 const EP_MAX_PACKET_SIZE: u16 = 64;
+/// EXTI line of the USBHS wakeup interrupt.
+const WAKEUP_EXTI_LINE: usize = 20;
 
 const NEW_AW: AtomicWaker = AtomicWaker::new();
 static BUS_WAKER: AtomicWaker = NEW_AW;
@@ -119,17 +129,28 @@ pub struct WakeupInterruptHandler<T: Instance> {
 
 impl<T: Instance> interrupt::typelevel::Handler<T::WakeupInterrupt> for WakeupInterruptHandler<T> {
     unsafe fn on_interrupt() {
-        todo!()
+        crate::pac::EXTI.intfr().write(|w| w.0 = 1 << WAKEUP_EXTI_LINE);
+
+        // Resume signaling on the bus, bring the PHY back so the SIE sees it.
+        T::hregs().ctrl().modify(|w| w.set_phy_suspendm(true));
+        BUS_WAKER.wake();
     }
 }
 
-pub struct Driver<'d, T: Instance, const NR_EP: usize> {
+/// USB driver.
+///
+/// Endpoints transfer by DMA from one of the [`EndpointDataBuffer`]s given to [`Driver::new`],
+/// the control pipe takes the one left after the class endpoints. High-speed bulk endpoints
+/// need 512-byte buffers, e.g. `[EndpointDataBuffer<512>; 3]`.
+pub struct Driver<'d, T: Instance, const NR_EP: usize, const N: usize = ENDPOINT_DATA_BUFFER_SIZE> {
     phantom: PhantomData<&'d T>,
-    allocator: EndpointBufferAllocator<'d, NR_EP>,
+    allocator: EndpointBufferAllocator<'d, NR_EP, N>,
     next_ep_addr: u8,
+    vbus: Option<VbusDetect<'d>>,
 }
 
-impl<'d, T: Instance, const NR_EP: usize> Driver<'d, T, NR_EP> {
+impl<'d, T: Instance, const NR_EP: usize, const N: usize> Driver<'d, T, NR_EP, N> {
+    /// Create a new USB driver, with one buffer for each endpoint and one for the control pipe.
     pub fn new(
         _peri: impl Peripheral<P = T> + 'd,
         _irqs: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>>
@@ -137,7 +158,7 @@ impl<'d, T: Instance, const NR_EP: usize> Driver<'d, T, NR_EP> {
             + 'd,
         dp: impl Peripheral<P = impl DpPin<T, 0> + 'd>,
         dm: impl Peripheral<P = impl DmPin<T, 0> + 'd>,
-        ep_buffer: &'d mut [EndpointDataBuffer; NR_EP],
+        ep_buffer: &'d mut [EndpointDataBuffer<N>; NR_EP],
     ) -> Self {
         assert!(ep_buffer.len() > 0);
         let dp = dp.into_ref();
@@ -186,9 +207,17 @@ impl<'d, T: Instance, const NR_EP: usize> Driver<'d, T, NR_EP> {
             allocator,
             // 0 is reserved for the control endpoint
             next_ep_addr: 1,
+            vbus: None,
         }
     }
 
+    /// Sense VBUS on `vbus`, connecting to the host only while it powers the bus.
+    ///
+    /// Without it, the bus is taken as powered from the start.
+    pub fn set_vbus_detect(&mut self, vbus: VbusDetect<'d>) {
+        self.vbus = Some(vbus);
+    }
+
     fn alloc_ep_address(&mut self) -> u8 {
         if self.next_ep_addr as usize >= MAX_NR_EP {
             panic!("ep addr overflow")
@@ -222,7 +251,7 @@ impl<'d, T: Instance, const NR_EP: usize> Driver<'d, T, NR_EP> {
     }
 }
 
-impl<'d, T: Instance, const NR_EP: usize> embassy_usb_driver::Driver<'d> for Driver<'d, T, NR_EP> {
+impl<'d, T: Instance, const NR_EP: usize, const N: usize> embassy_usb_driver::Driver<'d> for Driver<'d, T, NR_EP, N> {
     type EndpointOut = Endpoint<'d, T, Out>;
     type EndpointIn = Endpoint<'d, T, In>;
     type ControlPipe = ControlPipe<'d, T>;
@@ -274,6 +303,7 @@ impl<'d, T: Instance, const NR_EP: usize> embassy_usb_driver::Driver<'d> for Dri
         (
             Bus {
                 _phantom: PhantomData,
+                vbus: self.vbus,
                 fake_power_on: false,
             },
             ControlPipe::new(ep0),
@@ -283,11 +313,12 @@ impl<'d, T: Instance, const NR_EP: usize> embassy_usb_driver::Driver<'d> for Dri
 
 pub struct Bus<'d, T: Instance> {
     _phantom: PhantomData<&'d mut T>,
+    vbus: Option<VbusDetect<'d>>,
     fake_power_on: bool,
 }
 
 impl<'d, T: Instance> Bus<'d, T> {
-    fn bus_reset(&mut self) {
+    fn bus_reset() {
         let regs = T::regs();
         let d = T::dregs();
 
@@ -307,9 +338,8 @@ impl<'d, T: Instance> Bus<'d, T> {
             d.ep_rx_ctrl(i).write(|v| v.set_mask_uep_r_res(EpRxResponse::NAK));
         }
 
-        // Disable all endpoints [1, 15]
+        // Disable all endpoints [1, 15], their types are kept from allocation
         d.ep_config().write_value(EpConfig::default());
-        d.ep_type().write_value(EpType::default());
         d.ep_buf_mod().write_value(EpBufMod::default());
     }
 }
@@ -319,22 +349,23 @@ impl<'d, T: Instance> embassy_usb_driver::Bus for Bus<'d, T> {
         critical_section::with(|_| T::regs().ctrl().modify(|v| v.set_dev_pu_en(true)));
     }
 
-    async fn disable(&mut self) {}
+    async fn disable(&mut self) {
+        critical_section::with(|_| T::regs().ctrl().modify(|v| v.set_dev_pu_en(false)));
+    }
 
     async fn poll(&mut self) -> Event {
-        // TODO: VBUS detection
-        if !self.fake_power_on {
+        if self.vbus.is_none() && !self.fake_power_on {
             self.fake_power_on = true;
             return Event::PowerDetected;
         }
-        poll_fn(|cx| {
+        let bus = poll_fn(|cx| {
             BUS_WAKER.register(cx.waker());
 
             let r = T::regs();
             let flag = r.int_fg().read();
 
             if flag.bus_rst() {
-                self.bus_reset();
+                Self::bus_reset();
                 r.int_fg().write(|w| w.set_bus_rst(true));
                 critical_section::with(|_| {
                     r.int_en().modify(|w| w.set_bus_rst(true));
@@ -347,15 +378,21 @@ impl<'d, T: Instance> embassy_usb_driver::Bus for Bus<'d, T> {
                     r.int_en().modify(|w| w.set_suspend(true));
                 });
                 if mis_st.suspend() {
+                    suspend_phy::<T>();
                     Poll::Ready(Event::Suspend)
                 } else {
+                    resume_phy::<T>();
                     Poll::Ready(Event::Resume)
                 }
             } else {
                 Poll::Pending
             }
-        })
-        .await
+        });
+
+        match &mut self.vbus {
+            Some(vbus) => vbus.poll(bus).await,
+            None => bus.await,
+        }
     }
 
     fn endpoint_set_enabled(&mut self, ep_addr: EndpointAddress, enabled: bool) {
@@ -391,10 +428,37 @@ impl<'d, T: Instance> embassy_usb_driver::Bus for Bus<'d, T> {
     }
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        todo!()
+        resume_phy::<T>();
+
+        // The SIE drives resume signaling while the bit is set, for 1 to 15 ms.
+        let r = T::regs();
+        critical_section::with(|_| r.suspend().modify(|w| w.set_dev_remote_wakeup(true)));
+        embassy_time::Timer::after_millis(10).await;
+        critical_section::with(|_| r.suspend().modify(|w| w.set_dev_remote_wakeup(false)));
+        Ok(())
     }
 }
 
+/// Put the PHY in low-power mode, with the wakeup interrupt armed for the host resuming.
+fn suspend_phy<T: Instance>() {
+    let exti = crate::pac::EXTI;
+    critical_section::with(|_| {
+        exti.rtenr().modify(|w| w.set_tr(WAKEUP_EXTI_LINE, true));
+        exti.intfr().write(|w| w.0 = 1 << WAKEUP_EXTI_LINE);
+        exti.intenr().modify(|w| w.set_mr(WAKEUP_EXTI_LINE, true));
+        T::hregs().ctrl().modify(|w| w.set_phy_suspendm(false));
+    });
+    T::WakeupInterrupt::unpend();
+    unsafe { T::WakeupInterrupt::enable() };
+}
+
+fn resume_phy<T: Instance>() {
+    critical_section::with(|_| {
+        crate::pac::EXTI.intenr().modify(|w| w.set_mr(WAKEUP_EXTI_LINE, false));
+        T::hregs().ctrl().modify(|w| w.set_phy_suspendm(true));
+    });
+}
+
 /// USB endpoint.
 trait SealedInstance: crate::peripheral::RccPeripheral {
     fn regs() -> crate::pac::usbhs::Usb;
@@ -0,0 +1,296 @@
+//! USB PD messages and power data objects
+
+/// Control message types.
+pub mod control {
+    pub const GOOD_CRC: u8 = 0x01;
+    pub const ACCEPT: u8 = 0x03;
+    pub const REJECT: u8 = 0x04;
+    pub const PING: u8 = 0x05;
+    pub const PS_RDY: u8 = 0x06;
+    pub const GET_SOURCE_CAP: u8 = 0x07;
+    pub const GET_SINK_CAP: u8 = 0x08;
+    pub const DR_SWAP: u8 = 0x09;
+    pub const PR_SWAP: u8 = 0x0A;
+    pub const WAIT: u8 = 0x0C;
+    pub const SOFT_RESET: u8 = 0x0D;
+    pub const NOT_SUPPORTED: u8 = 0x10;
+}
+
+/// Data message types.
+pub mod data {
+    pub const SOURCE_CAPABILITIES: u8 = 0x01;
+    pub const REQUEST: u8 = 0x02;
+    pub const SINK_CAPABILITIES: u8 = 0x04;
+}
+
+/// Spec revision field value of PD 3.0.
+pub(crate) const REVISION_3_0: u8 = 0b10;
+
+/// Most data objects in a message.
+pub const MAX_DATA_OBJECTS: usize = 7;
+
+/// A received or outgoing message, header and data objects.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Message {
+    pub header: u16,
+    pub objects: [u32; MAX_DATA_OBJECTS],
+}
+
+impl Message {
+    pub(crate) fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < 2 {
+            return None;
+        }
+        let header = u16::from_le_bytes([buf[0], buf[1]]);
+        let mut message = Self {
+            header,
+            objects: [0; MAX_DATA_OBJECTS],
+        };
+
+        let n = message.num_objects();
+        if buf.len() < 2 + n * 4 {
+            return None;
+        }
+        for (i, object) in message.objects[..n].iter_mut().enumerate() {
+            let at = 2 + i * 4;
+            *object = u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]]);
+        }
+        Some(message)
+    }
+
+    /// Serialize into `buf`, returning the length, without the CRC the PHY appends.
+    pub(crate) fn write(&self, buf: &mut [u8]) -> usize {
+        buf[..2].copy_from_slice(&self.header.to_le_bytes());
+        let n = self.num_objects();
+        for (i, object) in self.objects[..n].iter().enumerate() {
+            buf[2 + i * 4..][..4].copy_from_slice(&object.to_le_bytes());
+        }
+        2 + n * 4
+    }
+
+    pub fn message_type(&self) -> u8 {
+        (self.header & 0x1F) as u8
+    }
+
+    pub fn num_objects(&self) -> usize {
+        ((self.header >> 12) & 0x7) as usize
+    }
+
+    pub fn message_id(&self) -> u8 {
+        ((self.header >> 9) & 0x7) as u8
+    }
+
+    pub fn spec_revision(&self) -> u8 {
+        ((self.header >> 6) & 0x3) as u8
+    }
+
+    /// Whether the message is an extended message, which is not supported.
+    pub fn is_extended(&self) -> bool {
+        self.header & 0x8000 != 0
+    }
+
+    /// Whether the message is the control message `message_type`.
+    pub fn is_control(&self, message_type: u8) -> bool {
+        !self.is_extended() && self.num_objects() == 0 && self.message_type() == message_type
+    }
+
+    /// Whether the message is the data message `message_type`.
+    pub fn is_data(&self, message_type: u8) -> bool {
+        !self.is_extended() && self.num_objects() > 0 && self.message_type() == message_type
+    }
+
+    /// The data objects.
+    pub fn data(&self) -> &[u32] {
+        &self.objects[..self.num_objects()]
+    }
+}
+
+/// A power data object, one supply offered by a source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerDataObject {
+    /// Fixed voltage supply.
+    Fixed { voltage_mv: u32, max_current_ma: u32 },
+    /// Battery supply, in a voltage range, with a power limit.
+    Battery {
+        min_voltage_mv: u32,
+        max_voltage_mv: u32,
+        max_power_mw: u32,
+    },
+    /// Variable, non-battery, supply in a voltage range.
+    Variable {
+        min_voltage_mv: u32,
+        max_voltage_mv: u32,
+        max_current_ma: u32,
+    },
+    /// Programmable power supply (PPS), set in 20 mV steps.
+    Programmable {
+        min_voltage_mv: u32,
+        max_voltage_mv: u32,
+        max_current_ma: u32,
+    },
+    /// An augmented PDO type not known here, e.g. EPR.
+    Unknown(u32),
+}
+
+impl PowerDataObject {
+    pub fn from_raw(raw: u32) -> Self {
+        match raw >> 30 {
+            0b00 => Self::Fixed {
+                voltage_mv: ((raw >> 10) & 0x3FF) * 50,
+                max_current_ma: (raw & 0x3FF) * 10,
+            },
+            0b01 => Self::Battery {
+                max_voltage_mv: ((raw >> 20) & 0x3FF) * 50,
+                min_voltage_mv: ((raw >> 10) & 0x3FF) * 50,
+                max_power_mw: (raw & 0x3FF) * 250,
+            },
+            0b10 => Self::Variable {
+                max_voltage_mv: ((raw >> 20) & 0x3FF) * 50,
+                min_voltage_mv: ((raw >> 10) & 0x3FF) * 50,
+                max_current_ma: (raw & 0x3FF) * 10,
+            },
+            _ if (raw >> 28) & 0x3 == 0 => Self::Programmable {
+                max_voltage_mv: ((raw >> 17) & 0xFF) * 100,
+                min_voltage_mv: ((raw >> 8) & 0xFF) * 100,
+                max_current_ma: (raw & 0x7F) * 50,
+            },
+            _ => Self::Unknown(raw),
+        }
+    }
+
+    /// Encode as a PDO, as a source advertises it.
+    pub fn to_raw(&self) -> u32 {
+        match *self {
+            Self::Fixed {
+                voltage_mv,
+                max_current_ma,
+            } => ((voltage_mv / 50) << 10) | (max_current_ma / 10),
+            Self::Battery {
+                min_voltage_mv,
+                max_voltage_mv,
+                max_power_mw,
+            } => (0b01 << 30) | ((max_voltage_mv / 50) << 20) | ((min_voltage_mv / 50) << 10) | (max_power_mw / 250),
+            Self::Variable {
+                min_voltage_mv,
+                max_voltage_mv,
+                max_current_ma,
+            } => (0b10 << 30) | ((max_voltage_mv / 50) << 20) | ((min_voltage_mv / 50) << 10) | (max_current_ma / 10),
+            Self::Programmable {
+                min_voltage_mv,
+                max_voltage_mv,
+                max_current_ma,
+            } => (0b11 << 30) | ((max_voltage_mv / 100) << 17) | ((min_voltage_mv / 100) << 8) | (max_current_ma / 50),
+            Self::Unknown(raw) => raw,
+        }
+    }
+}
+
+/// The supplies offered by a source, from its Source_Capabilities message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceCapabilities {
+    pdos: [u32; MAX_DATA_OBJECTS],
+    len: usize,
+}
+
+impl SourceCapabilities {
+    pub(crate) fn from_message(message: &Message) -> Self {
+        Self {
+            pdos: message.objects,
+            len: message.num_objects(),
+        }
+    }
+
+    /// The offered supplies, the first being vSafe5V.
+    pub fn iter(&self) -> impl Iterator<Item = PowerDataObject> + '_ {
+        self.pdos[..self.len].iter().map(|raw| PowerDataObject::from_raw(*raw))
+    }
+
+    /// Supply at object position `position`, starting at 1 as in requests.
+    pub fn get(&self, position: usize) -> Option<PowerDataObject> {
+        (1..=self.len)
+            .contains(&position)
+            .then(|| PowerDataObject::from_raw(self.pdos[position - 1]))
+    }
+
+    /// Whether the source supports USB communications, bit 26 of the first PDO.
+    pub fn usb_communications_capable(&self) -> bool {
+        self.pdos[0] & (1 << 26) != 0
+    }
+
+    /// Whether the source can also sink, bit 29 of the first PDO.
+    pub fn dual_role_power(&self) -> bool {
+        self.pdos[0] & (1 << 29) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_pdo() {
+        let pdo = PowerDataObject::Fixed {
+            voltage_mv: 5000,
+            max_current_ma: 3000,
+        };
+        assert_eq!(PowerDataObject::from_raw(0x0001_912C), pdo);
+        assert_eq!(pdo.to_raw(), 0x0001_912C);
+    }
+
+    #[test]
+    fn programmable_pdo() {
+        let pdo = PowerDataObject::Programmable {
+            min_voltage_mv: 3300,
+            max_voltage_mv: 21000,
+            max_current_ma: 3000,
+        };
+        assert_eq!(PowerDataObject::from_raw(0xC1A4_213C), pdo);
+        assert_eq!(pdo.to_raw(), 0xC1A4_213C);
+    }
+
+    #[test]
+    fn round_trip() {
+        let pdos = [
+            PowerDataObject::Battery {
+                min_voltage_mv: 3000,
+                max_voltage_mv: 4200,
+                max_power_mw: 15000,
+            },
+            PowerDataObject::Variable {
+                min_voltage_mv: 5000,
+                max_voltage_mv: 12000,
+                max_current_ma: 1500,
+            },
+        ];
+        for pdo in pdos {
+            assert_eq!(PowerDataObject::from_raw(pdo.to_raw()), pdo);
+        }
+
+        let epr = (0b11 << 30) | (1 << 28);
+        assert_eq!(PowerDataObject::from_raw(epr), PowerDataObject::Unknown(epr));
+        assert_eq!(PowerDataObject::Unknown(epr).to_raw(), epr);
+    }
+
+    #[test]
+    fn message() {
+        let message = Message {
+            header: (2 << 12) | (5 << 9) | ((REVISION_3_0 as u16) << 6) | data::SOURCE_CAPABILITIES as u16,
+            objects: [0x0001_912C, 0xC1A4_213C, 0, 0, 0, 0, 0],
+        };
+        assert_eq!(message.num_objects(), 2);
+        assert_eq!(message.message_id(), 5);
+        assert_eq!(message.spec_revision(), REVISION_3_0);
+        assert!(message.is_data(data::SOURCE_CAPABILITIES));
+        assert!(!message.is_control(data::SOURCE_CAPABILITIES));
+
+        let mut buf = [0; 2 + MAX_DATA_OBJECTS * 4];
+        let len = message.write(&mut buf);
+        assert_eq!(len, 10);
+        assert_eq!(buf[2..6], [0x2C, 0x91, 0x01, 0x00]);
+        assert_eq!(Message::parse(&buf[..len]), Some(message));
+        assert_eq!(Message::parse(&buf[..len - 1]), None);
+    }
+}
@@ -5,8 +5,8 @@
 //! - CC Pins:
 //! - UsbPdPhy: USBPD PHY layer
 //! - UsbPdSniffer: USBPD Sniffer based on PHY layer, no transmit support
-//! - [ ] UsbPdSink: USBPD Sink layer
-//! - [ ] UsbPdSource: USBPD Source layer
+//! - [x] UsbPdSink: USBPD Sink layer, see [`sink`]
+//! - [x] UsbPdSource and dual role: a pluggable policy engine, see [`policy`]
 
 use core::future::poll_fn;
 use core::marker::PhantomData;
@@ -18,9 +18,21 @@ use embedded_hal::delay::DelayNs;
 use pac::InterruptNumber;
 
 use crate::gpio::Pull;
+use crate::interrupt::typelevel::Interrupt;
 use crate::pac::usbpd::vals;
 use crate::{interrupt, into_ref, pac, println, Peripheral, RccPeripheral};
 
+pub mod message;
+pub mod policy;
+mod protocol;
+pub mod sink;
+
+/// TX_SEL for SOP messages.
+const TX_SEL_SOP0: u8 = 0;
+
+/// CC_PU of PORT_CC, the Rp pull-up current: 330uA, advertising 3.0A.
+const CC_PU_330: u8 = 0b01;
+
 #[derive(Debug)]
 pub enum Error {
     Rejected,
@@ -44,10 +56,9 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
 
         let status = usbpd.status().read();
 
-        println!("irq 0x{:02x}", status.0);
+        trace!("irq 0x{:02x}", status.0);
 
         if status.if_tx_end() {
-            println!(">");
             //         T::REGS.port_cc1().modify(|w| w.set_cc_lve(false));
             // T::REGS.port_cc2().modify(|w| w.set_cc_lve(false));
 
@@ -62,11 +73,11 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
 
         if status.if_rx_reset() {
             T::REGS.config().modify(|w| w.set_ie_rx_reset(false));
-            crate::println!("TODO: reset");
+            trace!("hard reset received");
         }
 
         if status.buf_err() {
-            crate::println!("TODO: buf_err");
+            warn!("receive buffer error");
         }
 
         T::REGS.status().write_value(status);
@@ -80,14 +91,38 @@ pub struct UsbPdPhy<'d, T: Instance> {
     _marker: PhantomData<&'d mut T>,
     cc1: vals::CcSel,
     cc2: vals::CcSel,
+    pull_up: bool,
 }
 
 impl<'d, T: Instance> UsbPdPhy<'d, T> {
-    /// Create a new SPI driver.
+    /// Create a new PHY for a sink, detecting the CC line pulled up by the source.
     pub fn new(
+        peri: impl Peripheral<P = T> + 'd,
+        cc1: impl Peripheral<P = impl CcPin<T>> + 'd,
+        cc2: impl Peripheral<P = impl CcPin<T>> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+    ) -> Result<Self, Error> {
+        Self::new_inner(peri, cc1, cc2, false)
+    }
+
+    /// Create a new PHY for a source, presenting Rp on both CC lines and detecting the one
+    /// pulled down by the sink.
+    ///
+    /// VBUS is switched by the application, see [`policy::Policy::transition_supply`].
+    pub fn new_source(
+        peri: impl Peripheral<P = T> + 'd,
+        cc1: impl Peripheral<P = impl CcPin<T>> + 'd,
+        cc2: impl Peripheral<P = impl CcPin<T>> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+    ) -> Result<Self, Error> {
+        Self::new_inner(peri, cc1, cc2, true)
+    }
+
+    fn new_inner(
         _peri: impl Peripheral<P = T> + 'd,
         cc1: impl Peripheral<P = impl CcPin<T>> + 'd,
         cc2: impl Peripheral<P = impl CcPin<T>> + 'd,
+        source: bool,
     ) -> Result<Self, Error> {
         into_ref!(cc1, cc2);
 
@@ -129,9 +164,14 @@ impl<'d, T: Instance> UsbPdPhy<'d, T> {
             _marker: PhantomData,
             cc1: cc1.port_sel(),
             cc2: cc2.port_sel(),
+            pull_up: source,
         };
+        this.set_pull_up(source);
         this.detect_cc()?;
 
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
         Ok(this)
     }
 
@@ -147,15 +187,28 @@ impl<'d, T: Instance> UsbPdPhy<'d, T> {
         T::port_cc_reg(self.cc1).modify(|w| w.set_cc_lve(false));
         T::port_cc_reg(self.cc2).modify(|w| w.set_cc_lve(false));
 
+        self.set_pull_up(self.pull_up);
         self.detect_cc()?;
 
         // pd_phy_reset
-        T::port_cc_reg(self.cc1).write(|w| w.set_cc_ce(vals::PortCcCe::V0_66));
-        T::port_cc_reg(self.cc2).write(|w| w.set_cc_ce(vals::PortCcCe::V0_66));
+        T::port_cc_reg(self.cc1).modify(|w| w.set_cc_ce(vals::PortCcCe::V0_66));
+        T::port_cc_reg(self.cc2).modify(|w| w.set_cc_ce(vals::PortCcCe::V0_66));
 
         Ok(())
     }
 
+    /// Present Rp on both CC lines as a source, or remove it as a sink.
+    ///
+    /// The sink's Rd is external on the CH32X035.
+    pub(crate) fn set_pull_up(&mut self, enabled: bool) {
+        self.pull_up = enabled;
+        let pull_up = if enabled { CC_PU_330 } else { 0 };
+        for cc in [self.cc1, self.cc2] {
+            T::port_cc_reg(cc).modify(|w| w.set_cc_pu(pull_up));
+        }
+        crate::delay::Delay.delay_us(2);
+    }
+
     fn detect_cc(&mut self) -> Result<(), Error> {
         // CH32X035 has no internal CC pull down support
         // The detection voltage is 0.22V, sufficient to detect the default power(500mA/900mA)
@@ -167,7 +220,7 @@ impl<'d, T: Instance> UsbPdPhy<'d, T> {
             // CC1 is connected
             T::REGS.config().modify(|w| w.set_cc_sel(vals::CcSel::CC1));
 
-            crate::println!("CC1 connected");
+            trace!("CC1 connected");
             Ok(())
         } else {
             T::port_cc_reg(self.cc2).modify(|w| w.set_cc_ce(vals::PortCcCe::V0_22));
@@ -176,10 +229,10 @@ impl<'d, T: Instance> UsbPdPhy<'d, T> {
             if T::port_cc_reg(self.cc2).read().pa_cc_ai() {
                 // CC2 is connected
                 T::REGS.config().modify(|w| w.set_cc_sel(vals::CcSel::CC2));
-                crate::println!("CC2 connected");
+                trace!("CC2 connected");
                 Ok(())
             } else {
-                crate::println!("CC not connected");
+                trace!("CC not connected");
 
                 Err(Error::CCNotConnected)
             }
@@ -311,6 +364,27 @@ impl<'d, T: Instance> UsbPdPhy<'d, T> {
         Ok(())
     }
 
+    /// Transmit a SOP message, waiting until it's sent.
+    ///
+    /// `buf` holds the header and data objects, the PHY appends the CRC.
+    pub async fn transmit_message(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.enable_tx_interrupt();
+        self.transmit(TX_SEL_SOP0, buf)?;
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+            if T::REGS.config().read().ie_tx_end() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+
+        T::port_cc_reg(T::REGS.config().read().cc_sel()).modify(|w| w.set_cc_lve(false));
+        Ok(())
+    }
+
     /// Transmit a hard reset.
     pub async fn transmit_hardreset(&mut self) {
         const TX_SEL_HARD_RESET: u8 = 0b10_10_10_01;
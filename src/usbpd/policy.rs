@@ -0,0 +1,533 @@
+//! USB PD policy engine, for sources, sinks and dual-role ports
+//!
+//! [`PolicyEngine`] runs the protocol for either power role and handles power and data role
+//! swaps, while the decisions and the VBUS switching are left to a [`Policy`]:
+//!
+//! ```rust,ignore
+//! struct Charger {
+//!     vbus: Output<'static>,
+//! }
+//!
+//! impl Policy for Charger {
+//!     fn source_capabilities(&self) -> &[PowerDataObject] {
+//!         &[PowerDataObject::Fixed { voltage_mv: 5000, max_current_ma: 3000 }]
+//!     }
+//!
+//!     async fn transition_supply(&mut self, supply: Option<PowerDataObject>) {
+//!         self.vbus.set_level(supply.is_some().into());
+//!         Timer::after_millis(20).await;
+//!     }
+//! }
+//!
+//! let phy = UsbPdPhy::new_source(p.USBPD, p.PC14, p.PC15, Irqs)?;
+//! let mut engine = PolicyEngine::new(phy, Charger { vbus }, PowerRole::Source);
+//! let error = engine.run().await;
+//! ```
+
+use embassy_futures::select::{select, Either};
+use embassy_time::{with_timeout, Duration, Timer};
+
+use super::message::{control, data, Message, PowerDataObject, SourceCapabilities, MAX_DATA_OBJECTS, REVISION_3_0};
+use super::protocol::Protocol;
+use super::sink::{self, Contract, Profile};
+use super::{Error, Instance, UsbPdPhy};
+
+/// nCapsCount
+const CAPS_COUNT: usize = 50;
+/// tTypeCSendSourceCap
+const SEND_SOURCE_CAP: Duration = Duration::from_millis(150);
+/// tSenderResponse
+const SENDER_RESPONSE: Duration = Duration::from_millis(30);
+/// tSrcTransition
+const SRC_TRANSITION: Duration = Duration::from_millis(25);
+/// tPSTransition
+const PS_TRANSITION: Duration = Duration::from_millis(550);
+/// tPSSourceOff
+const PS_SOURCE_OFF: Duration = Duration::from_millis(920);
+/// tPSSourceOn
+const PS_SOURCE_ON: Duration = Duration::from_millis(480);
+/// tSrcRecover
+const SRC_RECOVER: Duration = Duration::from_millis(660);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerRole {
+    Source,
+    Sink,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataRole {
+    /// Upstream facing port, a USB device.
+    Ufp,
+    /// Downstream facing port, a USB host.
+    Dfp,
+}
+
+/// A swap initiated by the local policy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    PowerRoleSwap,
+    DataRoleSwap,
+}
+
+/// A sink's request for one of the advertised supplies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Request {
+    /// Object position of the supply, starting at 1.
+    pub position: u8,
+    /// The requested supply, as advertised.
+    pub supply: PowerDataObject,
+    /// The request data object.
+    pub raw: u32,
+}
+
+impl Request {
+    /// Operating current for fixed and variable supplies, in mA.
+    pub fn operating_current_ma(&self) -> u32 {
+        ((self.raw >> 10) & 0x3FF) * 10
+    }
+
+    /// Output voltage asked for from a programmable supply, in mV.
+    pub fn programmable_voltage_mv(&self) -> u32 {
+        ((self.raw >> 9) & 0xFFF) * 20
+    }
+
+    /// Whether the sink can't get what it needs from the advertised supplies.
+    pub fn capability_mismatch(&self) -> bool {
+        self.raw & (1 << 26) != 0
+    }
+}
+
+/// Local decisions of a [`PolicyEngine`].
+///
+/// Only the source side has to be provided, the rest defaults to a port that stays in its
+/// role and sinks vSafe5V.
+#[allow(async_fn_in_trait)]
+pub trait Policy {
+    /// Supplies advertised as a source, vSafe5V first, at most 7.
+    fn source_capabilities(&self) -> &[PowerDataObject];
+
+    /// Whether to advertise the port as dual-role power, able to swap to a sink.
+    fn dual_role_power(&self) -> bool {
+        false
+    }
+
+    /// Whether to grant a sink's request, once it's checked to be within the advertised
+    /// supply.
+    fn evaluate_request(&mut self, request: &Request) -> bool {
+        let _ = request;
+        true
+    }
+
+    /// Switch VBUS to `supply`, or off for `None`, returning once it's within range.
+    async fn transition_supply(&mut self, supply: Option<PowerDataObject>);
+
+    /// The supply to request as a sink.
+    fn sink_profile(&mut self, capabilities: &SourceCapabilities) -> Profile {
+        let _ = capabilities;
+        Profile::Fixed {
+            voltage_mv: 5000,
+            current_ma: 500,
+        }
+    }
+
+    /// A new contract as a sink is in place.
+    fn sink_contract(&mut self, contract: &Contract) {
+        let _ = contract;
+    }
+
+    /// Whether to accept the partner's power role swap request.
+    fn accept_power_role_swap(&mut self) -> bool {
+        false
+    }
+
+    /// Whether to accept the partner's data role swap request.
+    fn accept_data_role_swap(&mut self) -> bool {
+        false
+    }
+
+    /// The power or data role changed after a swap.
+    fn role_changed(&mut self, power_role: PowerRole, data_role: DataRole) {
+        let _ = (power_role, data_role);
+    }
+
+    /// Wait for a swap to initiate. Never returns by default.
+    async fn command(&mut self) -> Command {
+        core::future::pending().await
+    }
+}
+
+/// USB PD policy engine.
+pub struct PolicyEngine<'d, T: Instance, P: Policy> {
+    protocol: Protocol<'d, T>,
+    policy: P,
+    power_role: PowerRole,
+}
+
+impl<'d, T: Instance, P: Policy> PolicyEngine<'d, T, P> {
+    /// Create a policy engine, starting in `power_role`, as a DFP when it's the source.
+    pub fn new(mut phy: UsbPdPhy<'d, T>, policy: P, power_role: PowerRole) -> Self {
+        let source = power_role == PowerRole::Source;
+        phy.set_pull_up(source);
+        Self {
+            protocol: Protocol::new(phy, source),
+            policy,
+            power_role,
+        }
+    }
+
+    pub fn power_role(&self) -> PowerRole {
+        self.power_role
+    }
+
+    pub fn data_role(&self) -> DataRole {
+        if self.protocol.dfp {
+            DataRole::Dfp
+        } else {
+            DataRole::Ufp
+        }
+    }
+
+    pub fn policy(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    /// Run the port, recovering from hard resets, until an error it can't recover from.
+    pub async fn run(&mut self) -> Error {
+        loop {
+            let result = match self.power_role {
+                PowerRole::Source => self.run_source().await,
+                PowerRole::Sink => self.run_sink().await,
+            };
+
+            match result {
+                // Role swapped, start over in the new role.
+                Ok(()) => {}
+                Err(Error::HardReset) => self.hard_reset().await,
+                Err(e) => return e,
+            }
+        }
+    }
+
+    async fn hard_reset(&mut self) {
+        self.protocol.reset();
+        self.protocol.dfp = self.power_role == PowerRole::Source;
+        if self.power_role == PowerRole::Source {
+            self.policy.transition_supply(None).await;
+            Timer::after(SRC_RECOVER).await;
+        }
+        self.policy.role_changed(self.power_role, self.data_role());
+    }
+
+    async fn run_source(&mut self) -> Result<(), Error> {
+        self.policy.transition_supply(Some(self.vsafe_5v())).await;
+        self.send_capabilities().await?;
+
+        loop {
+            let message = match select(self.protocol.receive(), self.policy.command()).await {
+                Either::First(message) => message?,
+                Either::Second(command) => {
+                    if self.command(command).await? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            };
+
+            if message.is_data(data::REQUEST) {
+                self.evaluate_request(message.objects[0]).await?;
+            } else if message.is_control(control::GET_SOURCE_CAP) {
+                self.send_capabilities().await?;
+            } else if message.is_control(control::SOFT_RESET) {
+                self.protocol.send(control::ACCEPT, &[]).await?;
+                self.send_capabilities().await?;
+            } else if !self.handle_swap(&message).await? {
+                self.not_supported().await?;
+            } else if self.power_role == PowerRole::Sink {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Advertise the source capabilities until a sink answers with GoodCRC.
+    async fn send_capabilities(&mut self) -> Result<(), Error> {
+        let mut pdos = [0; MAX_DATA_OBJECTS];
+        let capabilities = self.policy.source_capabilities();
+        assert!(!capabilities.is_empty() && capabilities.len() <= MAX_DATA_OBJECTS);
+        for (raw, pdo) in pdos.iter_mut().zip(capabilities) {
+            *raw = pdo.to_raw();
+        }
+        if self.policy.dual_role_power() {
+            pdos[0] |= 1 << 29;
+        }
+        let len = capabilities.len();
+
+        for _ in 0..CAPS_COUNT {
+            match self.protocol.send(data::SOURCE_CAPABILITIES, &pdos[..len]).await {
+                Ok(()) => return Ok(()),
+                Err(Error::MaxRetry) => Timer::after(SEND_SOURCE_CAP).await,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    fn vsafe_5v(&self) -> PowerDataObject {
+        self.policy.source_capabilities()[0]
+    }
+
+    async fn evaluate_request(&mut self, rdo: u32) -> Result<(), Error> {
+        let position = (rdo >> 28) as u8 & 0xF;
+        let supply = (position as usize)
+            .checked_sub(1)
+            .and_then(|i| self.policy.source_capabilities().get(i).copied());
+
+        let request = supply.map(|supply| Request {
+            position,
+            supply,
+            raw: rdo,
+        });
+        let accepted = match request {
+            Some(request) => within(&request) && self.policy.evaluate_request(&request),
+            None => false,
+        };
+
+        if !accepted {
+            return self.protocol.send(control::REJECT, &[]).await;
+        }
+
+        self.protocol.send(control::ACCEPT, &[]).await?;
+        Timer::after(SRC_TRANSITION).await;
+        self.policy.transition_supply(supply).await;
+        self.protocol.send(control::PS_RDY, &[]).await
+    }
+
+    async fn run_sink(&mut self) -> Result<(), Error> {
+        loop {
+            let message = match select(self.protocol.receive(), self.policy.command()).await {
+                Either::First(message) => message?,
+                Either::Second(command) => {
+                    if self.command(command).await? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            };
+
+            if message.is_data(data::SOURCE_CAPABILITIES) {
+                self.request(&message).await?;
+            } else if message.is_control(control::SOFT_RESET) || message.is_control(control::PING) {
+                // The source sends its capabilities again after a soft reset.
+            } else if !self.handle_swap(&message).await? {
+                self.not_supported().await?;
+            } else if self.power_role == PowerRole::Source {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn request(&mut self, message: &Message) -> Result<(), Error> {
+        self.protocol.revision = message.spec_revision().min(REVISION_3_0);
+        let capabilities = SourceCapabilities::from_message(message);
+        let profile = self.policy.sink_profile(&capabilities);
+        let (rdo, contract, _) = sink::select(&capabilities, profile);
+        self.protocol.send(data::REQUEST, &[rdo]).await?;
+
+        let reply = self.receive_within(SENDER_RESPONSE).await?;
+        if reply.is_control(control::REJECT) || reply.is_control(control::WAIT) {
+            return Ok(());
+        }
+        if !reply.is_control(control::ACCEPT) {
+            return Err(Error::Protocol(reply.message_type()));
+        }
+
+        self.expect_ps_ready(PS_TRANSITION).await?;
+        self.policy.sink_contract(&contract);
+        Ok(())
+    }
+
+    /// Answer a swap request from the partner, returning whether `message` was one.
+    async fn handle_swap(&mut self, message: &Message) -> Result<bool, Error> {
+        if message.is_control(control::PR_SWAP) {
+            if self.policy.accept_power_role_swap() {
+                self.protocol.send(control::ACCEPT, &[]).await?;
+                self.swap_power_role().await?;
+            } else {
+                self.protocol.send(control::REJECT, &[]).await?;
+            }
+            Ok(true)
+        } else if message.is_control(control::DR_SWAP) {
+            if self.policy.accept_data_role_swap() {
+                self.protocol.send(control::ACCEPT, &[]).await?;
+                self.swap_data_role();
+            } else {
+                self.protocol.send(control::REJECT, &[]).await?;
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Initiate a swap, returning whether the power role changed.
+    async fn command(&mut self, command: Command) -> Result<bool, Error> {
+        let message_type = match command {
+            Command::PowerRoleSwap => control::PR_SWAP,
+            Command::DataRoleSwap => control::DR_SWAP,
+        };
+        self.protocol.send(message_type, &[]).await?;
+
+        let reply = self.receive_within(SENDER_RESPONSE).await?;
+        if !reply.is_control(control::ACCEPT) {
+            trace!("swap refused: {}", reply.message_type());
+            return Ok(false);
+        }
+
+        match command {
+            Command::PowerRoleSwap => {
+                self.swap_power_role().await?;
+                Ok(true)
+            }
+            Command::DataRoleSwap => {
+                self.swap_data_role();
+                Ok(false)
+            }
+        }
+    }
+
+    /// Swap power roles once the swap is accepted, by either side.
+    async fn swap_power_role(&mut self) -> Result<(), Error> {
+        match self.power_role {
+            PowerRole::Source => {
+                Timer::after(SRC_TRANSITION).await;
+                self.policy.transition_supply(None).await;
+                self.protocol.phy.set_pull_up(false);
+                self.protocol.source = false;
+                self.power_role = PowerRole::Sink;
+                self.protocol.send(control::PS_RDY, &[]).await?;
+                self.expect_ps_ready(PS_SOURCE_ON).await?;
+            }
+            PowerRole::Sink => {
+                self.expect_ps_ready(PS_SOURCE_OFF).await?;
+                self.protocol.phy.set_pull_up(true);
+                self.protocol.source = true;
+                self.power_role = PowerRole::Source;
+                self.policy.transition_supply(Some(self.vsafe_5v())).await;
+                self.protocol.send(control::PS_RDY, &[]).await?;
+            }
+        }
+        self.policy.role_changed(self.power_role, self.data_role());
+        Ok(())
+    }
+
+    fn swap_data_role(&mut self) {
+        self.protocol.dfp = !self.protocol.dfp;
+        self.policy.role_changed(self.power_role, self.data_role());
+    }
+
+    async fn not_supported(&mut self) -> Result<(), Error> {
+        if self.protocol.revision >= REVISION_3_0 {
+            self.protocol.send(control::NOT_SUPPORTED, &[]).await
+        } else {
+            self.protocol.send(control::REJECT, &[]).await
+        }
+    }
+
+    async fn expect_ps_ready(&mut self, timeout: Duration) -> Result<(), Error> {
+        let ready = self.receive_within(timeout).await?;
+        if ready.is_control(control::PS_RDY) {
+            Ok(())
+        } else {
+            Err(Error::Protocol(ready.message_type()))
+        }
+    }
+
+    async fn receive_within(&mut self, timeout: Duration) -> Result<Message, Error> {
+        with_timeout(timeout, self.protocol.receive())
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+}
+
+/// Whether a request stays within the supply it's for.
+fn within(request: &Request) -> bool {
+    match request.supply {
+        PowerDataObject::Fixed { max_current_ma, .. } | PowerDataObject::Variable { max_current_ma, .. } => {
+            request.operating_current_ma() <= max_current_ma
+        }
+        PowerDataObject::Programmable {
+            min_voltage_mv,
+            max_voltage_mv,
+            max_current_ma,
+        } => {
+            (min_voltage_mv..=max_voltage_mv).contains(&request.programmable_voltage_mv())
+                && (request.raw & 0x7F) * 50 <= max_current_ma
+        }
+        PowerDataObject::Battery { .. } | PowerDataObject::Unknown(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PPS: PowerDataObject = PowerDataObject::Programmable {
+        min_voltage_mv: 3300,
+        max_voltage_mv: 11000,
+        max_current_ma: 3000,
+    };
+
+    fn request(supply: PowerDataObject, raw: u32) -> Request {
+        Request {
+            position: (raw >> 28) as u8,
+            supply,
+            raw,
+        }
+    }
+
+    #[test]
+    fn fixed_request() {
+        let supply = PowerDataObject::Fixed {
+            voltage_mv: 5000,
+            max_current_ma: 3000,
+        };
+
+        let ok = request(supply, (1 << 28) | (300 << 10) | 300);
+        assert_eq!(ok.operating_current_ma(), 3000);
+        assert!(!ok.capability_mismatch());
+        assert!(within(&ok));
+
+        let mismatch = request(supply, (1 << 28) | (1 << 26) | (300 << 10) | 300);
+        assert!(mismatch.capability_mismatch());
+        assert!(within(&mismatch));
+
+        assert!(!within(&request(supply, (1 << 28) | (310 << 10) | 310)));
+    }
+
+    #[test]
+    fn programmable_request() {
+        let ok = request(PPS, (4 << 28) | (250 << 9) | 60);
+        assert_eq!(ok.programmable_voltage_mv(), 5000);
+        assert!(within(&ok));
+
+        // Over the current limit, then above and below the voltage range.
+        assert!(!within(&request(PPS, (4 << 28) | (250 << 9) | 61)));
+        assert!(!within(&request(PPS, (4 << 28) | (560 << 9) | 60)));
+        assert!(!within(&request(PPS, (4 << 28) | (160 << 9) | 60)));
+    }
+
+    #[test]
+    fn unsupported_request() {
+        let battery = PowerDataObject::Battery {
+            min_voltage_mv: 3000,
+            max_voltage_mv: 4200,
+            max_power_mw: 15000,
+        };
+        assert!(!within(&request(battery, 1 << 28)));
+        assert!(!within(&request(PowerDataObject::Unknown(0xD000_0000), 1 << 28)));
+    }
+}
@@ -0,0 +1,111 @@
+//! Protocol layer: message IDs, GoodCRC and retries.
+
+use embassy_time::{with_timeout, Duration};
+
+use super::message::{control, Message, MAX_DATA_OBJECTS, REVISION_3_0};
+use super::{Error, Instance, UsbPdPhy};
+
+/// tReceive, with some margin for the executor.
+const GOOD_CRC_TIMEOUT: Duration = Duration::from_millis(2);
+/// nRetryCount
+const RETRIES: usize = 2;
+
+pub(crate) struct Protocol<'d, T: Instance> {
+    pub(crate) phy: UsbPdPhy<'d, T>,
+    message_id: u8,
+    last_rx_id: Option<u8>,
+    pub(crate) revision: u8,
+    pub(crate) source: bool,
+    pub(crate) dfp: bool,
+}
+
+impl<'d, T: Instance> Protocol<'d, T> {
+    pub(crate) fn new(phy: UsbPdPhy<'d, T>, source: bool) -> Self {
+        Self {
+            phy,
+            message_id: 0,
+            last_rx_id: None,
+            revision: REVISION_3_0,
+            source,
+            dfp: source,
+        }
+    }
+
+    /// Start over after a soft or hard reset.
+    pub(crate) fn reset(&mut self) {
+        self.message_id = 0;
+        self.last_rx_id = None;
+    }
+
+    fn header(&self, message_type: u8, num_objects: usize, message_id: u8) -> u16 {
+        message_type as u16
+            | ((self.dfp as u16) << 5)
+            | ((self.revision as u16) << 6)
+            | ((self.source as u16) << 8)
+            | ((message_id as u16) << 9)
+            | ((num_objects as u16) << 12)
+    }
+
+    /// Send a message and wait for its GoodCRC.
+    pub(crate) async fn send(&mut self, message_type: u8, objects: &[u32]) -> Result<(), Error> {
+        assert!(objects.len() <= MAX_DATA_OBJECTS);
+
+        let mut message = Message {
+            header: self.header(message_type, objects.len(), self.message_id),
+            objects: [0; MAX_DATA_OBJECTS],
+        };
+        message.objects[..objects.len()].copy_from_slice(objects);
+
+        let mut buf = [0; 2 + 4 * MAX_DATA_OBJECTS];
+        let len = message.write(&mut buf);
+
+        for _ in 0..=RETRIES {
+            self.phy.transmit_message(&buf[..len]).await?;
+
+            match with_timeout(GOOD_CRC_TIMEOUT, self.receive_raw()).await {
+                Ok(Ok(reply)) if reply.is_control(control::GOOD_CRC) && reply.message_id() == self.message_id => {
+                    self.message_id = (self.message_id + 1) & 0x7;
+                    return Ok(());
+                }
+                Ok(Err(Error::HardReset)) => return Err(Error::HardReset),
+                _ => {}
+            }
+        }
+        Err(Error::MaxRetry)
+    }
+
+    /// Receive the next message, acknowledging it with GoodCRC.
+    pub(crate) async fn receive(&mut self) -> Result<Message, Error> {
+        loop {
+            let message = self.receive_raw().await?;
+            if message.is_control(control::GOOD_CRC) {
+                // Stray, for a message we gave up on.
+                continue;
+            }
+
+            let header = self.header(control::GOOD_CRC, 0, message.message_id());
+            self.phy.transmit_message(&header.to_le_bytes()).await?;
+
+            if message.is_control(control::SOFT_RESET) {
+                self.reset();
+            } else if self.last_rx_id == Some(message.message_id()) {
+                // A retry of a message whose GoodCRC got lost.
+                continue;
+            }
+            self.last_rx_id = Some(message.message_id());
+
+            return Ok(message);
+        }
+    }
+
+    async fn receive_raw(&mut self) -> Result<Message, Error> {
+        loop {
+            let mut buf = [0; 2 + 4 * MAX_DATA_OBJECTS + 4];
+            let len = self.phy.receive(&mut buf).await?;
+            match Message::parse(&buf[..len.min(buf.len())]) {
+                Some(message) => return Ok(message),
+                None => trace!("dropping a short message"),
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! USB PD sink
+//!
+//! Waits for the source capabilities, picks the supply matching a [`Profile`] and requests
+//! it, then keeps the contract with [`UsbPdSink::serve`]:
+//!
+//! ```rust,ignore
+//! bind_interrupts!(struct Irqs {
+//!     USBPD => usbpd::InterruptHandler<peripherals::USBPD>;
+//! });
+//!
+//! let phy = UsbPdPhy::new(p.USBPD, p.PC14, p.PC15, Irqs)?;
+//! let mut sink = UsbPdSink::new(phy);
+//! let contract = sink.negotiate(Profile::Fixed { voltage_mv: 9000, current_ma: 2000 }).await?;
+//! loop {
+//!     sink.serve().await?;
+//! }
+//! ```
+
+use embassy_time::{with_timeout, Duration};
+
+use super::message::{control, data, Message, PowerDataObject, SourceCapabilities, REVISION_3_0};
+use super::protocol::Protocol;
+use super::{Error, Instance, UsbPdPhy};
+
+/// tTypeCSinkWaitCap
+const SINK_WAIT_CAP: Duration = Duration::from_millis(620);
+/// tSenderResponse
+const SENDER_RESPONSE: Duration = Duration::from_millis(30);
+/// tPSTransition
+const PS_TRANSITION: Duration = Duration::from_millis(550);
+/// A PPS contract expires after 15 s without a request, tPPSRequest is 10 s.
+const PPS_REQUEST: Duration = Duration::from_secs(8);
+
+/// The supply to ask for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Profile {
+    /// A fixed supply of exactly `voltage_mv`, providing at least `current_ma`.
+    Fixed { voltage_mv: u32, current_ma: u32 },
+    /// A programmable supply (PPS) set to `voltage_mv`, in 20 mV steps, with a current limit
+    /// of at least `current_ma`.
+    Programmable { voltage_mv: u32, current_ma: u32 },
+    /// The fixed supply with the highest voltage, at its full current.
+    HighestVoltage,
+}
+
+/// The supply agreed on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Contract {
+    /// Object position of the supply in the source capabilities, starting at 1.
+    pub position: u8,
+    pub voltage_mv: u32,
+    pub current_ma: u32,
+    /// Whether this is a PPS contract, refreshed by [`UsbPdSink::serve`].
+    pub programmable: bool,
+}
+
+/// USB PD sink policy.
+pub struct UsbPdSink<'d, T: Instance> {
+    protocol: Protocol<'d, T>,
+    capabilities: Option<SourceCapabilities>,
+    profile: Option<Profile>,
+    contract: Option<Contract>,
+}
+
+impl<'d, T: Instance> UsbPdSink<'d, T> {
+    pub fn new(phy: UsbPdPhy<'d, T>) -> Self {
+        Self {
+            protocol: Protocol::new(phy, false),
+            capabilities: None,
+            profile: None,
+            contract: None,
+        }
+    }
+
+    /// The last capabilities received from the source.
+    pub fn source_capabilities(&self) -> Option<&SourceCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// The contract in place, if any.
+    pub fn contract(&self) -> Option<Contract> {
+        self.contract
+    }
+
+    /// Get a contract for `profile`.
+    ///
+    /// When the source doesn't offer it, vSafe5V is requested instead, flagging the
+    /// capability mismatch, and [`Error::NotSupported`] is returned once it's in place.
+    pub async fn negotiate(&mut self, profile: Profile) -> Result<Contract, Error> {
+        self.profile = Some(profile);
+
+        if self.capabilities.is_some() {
+            self.protocol.send(control::GET_SOURCE_CAP, &[]).await?;
+        }
+        let capabilities = self.wait_for_source_capabilities().await?;
+        self.request(&capabilities, profile).await
+    }
+
+    /// Answer the source and keep the contract up, until something needs attention.
+    ///
+    /// New source capabilities are answered by requesting the profile again, and PPS
+    /// contracts are refreshed in time. Returns `Ok` after a soft reset, once a new contract
+    /// is in place, and [`Error::HardReset`] after a hard reset, when negotiation starts over.
+    pub async fn serve(&mut self) -> Result<(), Error> {
+        loop {
+            let refresh = self.contract.is_some_and(|c| c.programmable);
+            let received = if refresh {
+                with_timeout(PPS_REQUEST, self.protocol.receive()).await.ok()
+            } else {
+                Some(self.protocol.receive().await)
+            };
+
+            let message = match received {
+                None => {
+                    self.renegotiate().await?;
+                    continue;
+                }
+                Some(Err(Error::HardReset)) => {
+                    self.hard_reset();
+                    return Err(Error::HardReset);
+                }
+                Some(message) => message?,
+            };
+
+            if message.is_data(data::SOURCE_CAPABILITIES) {
+                self.store_capabilities(&message);
+                self.renegotiate().await?;
+            } else if message.is_control(control::SOFT_RESET) {
+                self.protocol.send(control::ACCEPT, &[]).await?;
+                self.contract = None;
+                let capabilities = self.wait_for_source_capabilities().await?;
+                if let Some(profile) = self.profile {
+                    self.request(&capabilities, profile).await?;
+                }
+                return Ok(());
+            } else if message.is_control(control::PING) {
+                // Nothing to do.
+            } else {
+                self.not_supported().await?;
+            }
+        }
+    }
+
+    async fn renegotiate(&mut self) -> Result<(), Error> {
+        if let (Some(capabilities), Some(profile)) = (self.capabilities, self.profile) {
+            match self.request(&capabilities, profile).await {
+                Ok(_) | Err(Error::NotSupported) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn hard_reset(&mut self) {
+        self.protocol.reset();
+        self.capabilities = None;
+        self.contract = None;
+    }
+
+    async fn not_supported(&mut self) -> Result<(), Error> {
+        if self.protocol.revision >= REVISION_3_0 {
+            self.protocol.send(control::NOT_SUPPORTED, &[]).await
+        } else {
+            self.protocol.send(control::REJECT, &[]).await
+        }
+    }
+
+    fn store_capabilities(&mut self, message: &Message) -> SourceCapabilities {
+        // Talk the highest revision both sides support.
+        self.protocol.revision = message.spec_revision().min(REVISION_3_0);
+        let capabilities = SourceCapabilities::from_message(message);
+        self.capabilities = Some(capabilities);
+        capabilities
+    }
+
+    async fn wait_for_source_capabilities(&mut self) -> Result<SourceCapabilities, Error> {
+        let message = with_timeout(SINK_WAIT_CAP, async {
+            loop {
+                let message = self.protocol.receive().await?;
+                if message.is_data(data::SOURCE_CAPABILITIES) {
+                    return Ok(message);
+                }
+            }
+        })
+        .await
+        .map_err(|_| Error::Timeout)?;
+
+        let message = match message {
+            Err(Error::HardReset) => {
+                self.hard_reset();
+                return Err(Error::HardReset);
+            }
+            message => message?,
+        };
+        Ok(self.store_capabilities(&message))
+    }
+
+    async fn request(&mut self, capabilities: &SourceCapabilities, profile: Profile) -> Result<Contract, Error> {
+        let (rdo, contract, matched) = select(capabilities, profile);
+        self.protocol.send(data::REQUEST, &[rdo]).await?;
+
+        let reply = self.receive_within(SENDER_RESPONSE).await?;
+        if reply.is_control(control::REJECT) || reply.is_control(control::WAIT) {
+            return Err(Error::Rejected);
+        }
+        if !reply.is_control(control::ACCEPT) {
+            return Err(Error::Protocol(reply.message_type()));
+        }
+
+        let ready = self.receive_within(PS_TRANSITION).await?;
+        if !ready.is_control(control::PS_RDY) {
+            return Err(Error::Protocol(ready.message_type()));
+        }
+
+        self.contract = Some(contract);
+        if matched {
+            Ok(contract)
+        } else {
+            Err(Error::NotSupported)
+        }
+    }
+
+    async fn receive_within(&mut self, timeout: Duration) -> Result<Message, Error> {
+        match with_timeout(timeout, self.protocol.receive()).await {
+            Ok(Err(Error::HardReset)) => {
+                self.hard_reset();
+                Err(Error::HardReset)
+            }
+            Ok(message) => message,
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+}
+
+/// Pick the supply for `profile`, returning the request data object, the contract it leads
+/// to, and whether it matches the profile.
+pub(super) fn select(capabilities: &SourceCapabilities, profile: Profile) -> (u32, Contract, bool) {
+    let fixed = |position: usize, voltage_mv: u32, current_ma: u32| {
+        let rdo = ((position as u32) << 28) | (1 << 24) | ((current_ma / 10) << 10) | (current_ma / 10);
+        let contract = Contract {
+            position: position as u8,
+            voltage_mv,
+            current_ma,
+            programmable: false,
+        };
+        (rdo, contract)
+    };
+
+    let found = capabilities
+        .iter()
+        .enumerate()
+        .filter_map(|(i, pdo)| match (pdo, profile) {
+            (
+                PowerDataObject::Fixed {
+                    voltage_mv,
+                    max_current_ma,
+                },
+                Profile::Fixed {
+                    voltage_mv: want_mv,
+                    current_ma,
+                },
+            ) if voltage_mv == want_mv && max_current_ma >= current_ma => Some(fixed(i + 1, voltage_mv, current_ma)),
+            (
+                PowerDataObject::Fixed {
+                    voltage_mv,
+                    max_current_ma,
+                },
+                Profile::HighestVoltage,
+            ) => Some(fixed(i + 1, voltage_mv, max_current_ma)),
+            (
+                PowerDataObject::Programmable {
+                    min_voltage_mv,
+                    max_voltage_mv,
+                    max_current_ma,
+                },
+                Profile::Programmable { voltage_mv, current_ma },
+            ) if (min_voltage_mv..=max_voltage_mv).contains(&voltage_mv) && max_current_ma >= current_ma => {
+                let rdo = (((i + 1) as u32) << 28) | (1 << 24) | ((voltage_mv / 20) << 9) | (current_ma / 50);
+                let contract = Contract {
+                    position: (i + 1) as u8,
+                    voltage_mv: voltage_mv / 20 * 20,
+                    current_ma: current_ma / 50 * 50,
+                    programmable: true,
+                };
+                Some((rdo, contract))
+            }
+            _ => None,
+        })
+        .max_by_key(|(_, contract)| contract.voltage_mv);
+
+    match found {
+        Some((rdo, contract)) => (rdo, contract, true),
+        None => {
+            // vSafe5V is always the first one.
+            let current_ma = match capabilities.get(1) {
+                Some(PowerDataObject::Fixed { max_current_ma, .. }) => max_current_ma,
+                _ => 100,
+            };
+            let (rdo, contract) = fixed(1, 5000, current_ma);
+            // Capability mismatch
+            (rdo | (1 << 26), contract, false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbpd::message::MAX_DATA_OBJECTS;
+
+    fn capabilities(pdos: &[PowerDataObject]) -> SourceCapabilities {
+        let mut objects = [0; MAX_DATA_OBJECTS];
+        for (object, pdo) in objects.iter_mut().zip(pdos) {
+            *object = pdo.to_raw();
+        }
+        SourceCapabilities::from_message(&Message {
+            header: ((pdos.len() as u16) << 12) | data::SOURCE_CAPABILITIES as u16,
+            objects,
+        })
+    }
+
+    fn fixed(voltage_mv: u32, max_current_ma: u32) -> PowerDataObject {
+        PowerDataObject::Fixed {
+            voltage_mv,
+            max_current_ma,
+        }
+    }
+
+    #[test]
+    fn select_fixed() {
+        let caps = capabilities(&[fixed(5000, 3000), fixed(9000, 3000), fixed(15000, 3000)]);
+
+        let (rdo, contract, found) = select(
+            &caps,
+            Profile::Fixed {
+                voltage_mv: 9000,
+                current_ma: 2000,
+            },
+        );
+        assert!(found);
+        assert_eq!(rdo, (2 << 28) | (1 << 24) | (200 << 10) | 200);
+        assert_eq!(
+            contract,
+            Contract {
+                position: 2,
+                voltage_mv: 9000,
+                current_ma: 2000,
+                programmable: false,
+            }
+        );
+
+        let (_, contract, found) = select(&caps, Profile::HighestVoltage);
+        assert!(found);
+        assert_eq!(
+            (contract.position, contract.voltage_mv, contract.current_ma),
+            (3, 15000, 3000)
+        );
+    }
+
+    #[test]
+    fn select_mismatch() {
+        let caps = capabilities(&[fixed(5000, 3000), fixed(9000, 3000)]);
+
+        for profile in [
+            Profile::Fixed {
+                voltage_mv: 20000,
+                current_ma: 1000,
+            },
+            Profile::Fixed {
+                voltage_mv: 9000,
+                current_ma: 5000,
+            },
+        ] {
+            let (rdo, contract, found) = select(&caps, profile);
+            assert!(!found);
+            assert_eq!(rdo, (1 << 28) | (1 << 26) | (1 << 24) | (300 << 10) | 300);
+            assert_eq!((contract.position, contract.voltage_mv), (1, 5000));
+        }
+    }
+
+    #[test]
+    fn select_programmable() {
+        let caps = capabilities(&[
+            fixed(5000, 3000),
+            fixed(9000, 3000),
+            fixed(15000, 3000),
+            PowerDataObject::Programmable {
+                min_voltage_mv: 3300,
+                max_voltage_mv: 11000,
+                max_current_ma: 3000,
+            },
+        ]);
+
+        let (rdo, contract, found) = select(
+            &caps,
+            Profile::Programmable {
+                voltage_mv: 5010,
+                current_ma: 2000,
+            },
+        );
+        assert!(found);
+        assert_eq!(rdo, (4 << 28) | (1 << 24) | (250 << 9) | 40);
+        assert_eq!(
+            contract,
+            Contract {
+                position: 4,
+                voltage_mv: 5000,
+                current_ma: 2000,
+                programmable: true,
+            }
+        );
+
+        let (_, _, found) = select(
+            &caps,
+            Profile::Programmable {
+                voltage_mv: 12000,
+                current_ma: 2000,
+            },
+        );
+        assert!(!found);
+    }
+}